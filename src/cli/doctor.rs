@@ -0,0 +1,311 @@
+use crate::config::{Config, Environment, PingBackendKind};
+use crate::db::{Database, SCHEMA_VERSION};
+use crate::detect_gateway;
+use crate::monitor::PingCapability;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    fn symbol(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "✓",
+            CheckStatus::Warn => "!",
+            CheckStatus::Fail => "✗",
+        }
+    }
+}
+
+/// The outcome of one check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagnosticResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl DiagnosticResult {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// What the checks look at, gathered up front so they can run against a
+/// controlled environment in tests
+pub struct Probes {
+    pub config_path: PathBuf,
+    /// None when there is no config file
+    pub config: Option<Result<Config, String>>,
+    pub db_path: PathBuf,
+    pub ping_binary: Option<PathBuf>,
+    pub traceroute_binary: Option<PathBuf>,
+    pub capability: PingCapability,
+    pub gateway: Option<String>,
+}
+
+impl Probes {
+    /// Look at the real system
+    pub fn gather(env: &Environment) -> Result<Self, Box<dyn std::error::Error>> {
+        let config_path = env.config_path()?;
+        let config = config_path
+            .exists()
+            .then(|| Config::load_for_env(env).map_err(|e| e.to_string()));
+        let db_path = match &config {
+            Some(Ok(config)) => config.database_path_for_env(env)?,
+            _ => env.database_path()?,
+        };
+
+        Ok(Self {
+            config_path,
+            config,
+            db_path,
+            ping_binary: find_in_path("ping"),
+            traceroute_binary: find_in_path("traceroute"),
+            capability: PingCapability::probe(),
+            gateway: detect_gateway(),
+        })
+    }
+}
+
+/// Run every check; the human and JSON output both come from this
+pub fn diagnose(probes: &Probes) -> Vec<DiagnosticResult> {
+    let config = match &probes.config {
+        Some(Ok(config)) => Some(config),
+        _ => None,
+    };
+    let native = config.is_some_and(|c| c.monitor.ping_backend == PingBackendKind::Native);
+    let traceroute = config.is_none_or(|c| c.monitor.traceroute_enabled);
+
+    vec![
+        check_config(&probes.config_path, probes.config.as_ref()),
+        check_database(&probes.db_path),
+        check_ping(probes.ping_binary.as_deref(), probes.capability, native),
+        check_traceroute(probes.traceroute_binary.as_deref(), traceroute),
+        check_gateway(probes.gateway.as_deref()),
+    ]
+}
+
+fn check_config(path: &Path, config: Option<&Result<Config, String>>) -> DiagnosticResult {
+    match config {
+        None => DiagnosticResult::new(
+            "config",
+            CheckStatus::Warn,
+            format!("{} not found, using defaults", path.display()),
+        ),
+        Some(Ok(_)) => {
+            DiagnosticResult::new("config", CheckStatus::Pass, path.display().to_string())
+        }
+        Some(Err(e)) => DiagnosticResult::new("config", CheckStatus::Fail, e.clone()),
+    }
+}
+
+fn check_database(path: &Path) -> DiagnosticResult {
+    let name = "database";
+    if !path.exists() {
+        return DiagnosticResult::new(
+            name,
+            CheckStatus::Warn,
+            format!("{} not created yet (run 'vigil init')", path.display()),
+        );
+    }
+    match Database::stored_version(path) {
+        Ok(version) if version == SCHEMA_VERSION => DiagnosticResult::new(
+            name,
+            CheckStatus::Pass,
+            format!("{} (schema v{})", path.display(), version),
+        ),
+        Ok(version) if version < SCHEMA_VERSION => DiagnosticResult::new(
+            name,
+            CheckStatus::Warn,
+            format!(
+                "schema v{} is older than v{} (run 'vigil upgrade')",
+                version, SCHEMA_VERSION
+            ),
+        ),
+        Ok(version) => DiagnosticResult::new(
+            name,
+            CheckStatus::Fail,
+            format!(
+                "schema v{} is newer than this build supports (v{})",
+                version, SCHEMA_VERSION
+            ),
+        ),
+        Err(e) => DiagnosticResult::new(name, CheckStatus::Fail, e.to_string()),
+    }
+}
+
+fn check_ping(binary: Option<&Path>, capability: PingCapability, native: bool) -> DiagnosticResult {
+    let name = "ping";
+    let in_process = native && capability != PingCapability::SystemOnly;
+    match binary {
+        Some(path) if native && !in_process => DiagnosticResult::new(
+            name,
+            CheckStatus::Warn,
+            format!(
+                "native backend configured but {}, using {}",
+                capability,
+                path.display()
+            ),
+        ),
+        Some(path) => DiagnosticResult::new(
+            name,
+            CheckStatus::Pass,
+            format!("{} ({})", path.display(), capability),
+        ),
+        None if in_process => DiagnosticResult::new(
+            name,
+            CheckStatus::Pass,
+            format!("native backend ({}); no ping binary", capability),
+        ),
+        None if capability != PingCapability::SystemOnly => DiagnosticResult::new(
+            name,
+            CheckStatus::Fail,
+            format!(
+                "ping not found in PATH; {}, set monitor.ping_backend = \"native\"",
+                capability
+            ),
+        ),
+        None => DiagnosticResult::new(
+            name,
+            CheckStatus::Fail,
+            "ping not found in PATH and no ICMP socket access",
+        ),
+    }
+}
+
+fn check_traceroute(binary: Option<&Path>, enabled: bool) -> DiagnosticResult {
+    let name = "traceroute";
+    match binary {
+        _ if !enabled => DiagnosticResult::new(name, CheckStatus::Pass, "disabled in config"),
+        Some(path) => DiagnosticResult::new(name, CheckStatus::Pass, path.display().to_string()),
+        None => DiagnosticResult::new(
+            name,
+            CheckStatus::Warn,
+            "traceroute not found in PATH; outages are recorded without hop info",
+        ),
+    }
+}
+
+fn check_gateway(gateway: Option<&str>) -> DiagnosticResult {
+    match gateway {
+        Some(ip) => DiagnosticResult::new("gateway", CheckStatus::Pass, ip),
+        None => DiagnosticResult::new(
+            "gateway",
+            CheckStatus::Warn,
+            "could not detect the default gateway; set targets.gateway",
+        ),
+    }
+}
+
+/// First executable named `name` on PATH
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+pub fn run(env: &Environment, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let results = diagnose(&Probes::gather(env)?);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        println!("Vigil doctor ({})\n", env);
+        for result in &results {
+            println!(
+                "  {} {:<11} {}",
+                result.status.symbol(),
+                result.name,
+                result.detail
+            );
+        }
+    }
+
+    let failed = results
+        .iter()
+        .filter(|r| r.status == CheckStatus::Fail)
+        .count();
+    if failed > 0 {
+        return Err(format!("{} check(s) failed", failed).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doctor_json_has_entry_per_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("monitor.db");
+        drop(Database::open(&db_path).unwrap());
+
+        let probes = Probes {
+            config_path: dir.path().join("config.toml"),
+            config: Some(Ok(Config::default())),
+            db_path,
+            ping_binary: Some(PathBuf::from("/sbin/ping")),
+            traceroute_binary: None,
+            capability: PingCapability::SystemOnly,
+            gateway: None,
+        };
+
+        let json = serde_json::to_value(diagnose(&probes)).unwrap();
+        let checks = json.as_array().unwrap();
+        let status = |name: &str| {
+            checks
+                .iter()
+                .find(|c| c["name"] == name)
+                .map(|c| c["status"].as_str().unwrap().to_string())
+        };
+
+        assert_eq!(checks.len(), 5);
+        assert_eq!(status("config").as_deref(), Some("pass"));
+        assert_eq!(status("database").as_deref(), Some("pass"));
+        assert_eq!(status("ping").as_deref(), Some("pass"));
+        assert_eq!(status("traceroute").as_deref(), Some("warn"));
+        assert_eq!(status("gateway").as_deref(), Some("warn"));
+        assert!(checks.iter().all(|c| c["detail"].is_string()));
+    }
+
+    #[test]
+    fn test_doctor_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let probes = Probes {
+            config_path: dir.path().join("config.toml"),
+            config: Some(Err("invalid type: string \"x\"".to_string())),
+            db_path: dir.path().join("missing.db"),
+            ping_binary: None,
+            traceroute_binary: None,
+            capability: PingCapability::SystemOnly,
+            gateway: Some("192.168.1.1".to_string()),
+        };
+
+        let statuses: Vec<(&str, CheckStatus)> = diagnose(&probes)
+            .iter()
+            .map(|r| (r.name, r.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("config", CheckStatus::Fail),
+                ("database", CheckStatus::Warn),
+                ("ping", CheckStatus::Fail),
+                ("traceroute", CheckStatus::Warn),
+                ("gateway", CheckStatus::Pass),
+            ]
+        );
+    }
+}
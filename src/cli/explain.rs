@@ -0,0 +1,213 @@
+use crate::config::DisplayConfig;
+use crate::models::Outage;
+use crate::monitor::annotate_ip;
+use crate::App;
+use chrono::{DateTime, Utc};
+
+pub fn run(app: &App, id: i64) -> Result<(), Box<dyn std::error::Error>> {
+    let mut outage = app
+        .db
+        .get_outage(id)?
+        .ok_or_else(|| format!("No outage with ID {}", id))?;
+
+    // Housemates know "Google DNS", not "8.8.8.8"
+    let targets = app.config.all_targets();
+    for affected in &mut outage.affected_targets {
+        if let Some(target) = targets.iter().find(|t| &t.key() == affected) {
+            *affected = target.name.clone();
+        }
+    }
+
+    println!(
+        "{}",
+        explain_outage(&outage, &app.config.display, Utc::now())
+    );
+    Ok(())
+}
+
+/// A plain-English account of an outage, e.g. "Your internet was down for 12
+/// minutes starting at 3:04 PM on October 14. The problem was at your ISP
+/// (hop 3, Comcast). It recovered on its own."
+pub fn explain_outage(outage: &Outage, display: &DisplayConfig, now: DateTime<Utc>) -> String {
+    let started = display.format_time(outage.start_time, "%-I:%M %p on %B %-d");
+    let duration = spoken_duration(outage.elapsed_secs(now));
+
+    let mut sentences = vec![if outage.end_time.is_some() {
+        format!(
+            "Your internet was down for {} starting at {}.",
+            duration, started
+        )
+    } else {
+        format!(
+            "Your internet has been down for {} since {}, and is still down.",
+            duration, started
+        )
+    }];
+
+    sentences.push(match outage.failing_hop {
+        Some(hop) => {
+            let owner = outage.failing_hop_ip.as_deref().and_then(annotate_ip);
+            let detail = match owner {
+                Some(owner) => format!("hop {}, {}", hop, owner),
+                None => format!("hop {}", hop),
+            };
+            match hop {
+                1 => format!(
+                    "The problem was inside your home network ({}, your router).",
+                    detail
+                ),
+                2 => format!(
+                    "The problem was at your modem or where it connects to your ISP ({}).",
+                    detail
+                ),
+                _ => format!("The problem was at your ISP ({}).", detail),
+            }
+        }
+        None => "It isn't clear where the problem was.".to_string(),
+    });
+
+    if !outage.affected_targets.is_empty() {
+        sentences.push(format!(
+            "{} could not be reached.",
+            spoken_list(&outage.affected_targets)
+        ));
+    }
+
+    if outage.planned {
+        sentences.push("It happened during planned maintenance.".to_string());
+    }
+    if outage.system_sleep {
+        sentences.push(
+            "This computer was asleep for most of it, so much of that time may not be real downtime."
+                .to_string(),
+        );
+    }
+    if outage.ended_by_shutdown() {
+        sentences.push(
+            "The monitor stopped while it was still down, so it may have lasted longer."
+                .to_string(),
+        );
+    } else if outage.end_time.is_some() {
+        sentences.push("It recovered on its own.".to_string());
+    }
+
+    sentences.join(" ")
+}
+
+/// "45 seconds", "12 minutes", "1 hour and 5 minutes"
+fn spoken_duration(secs: f64) -> String {
+    let secs = secs.max(0.0).round() as u64;
+    let unit = |n: u64, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+
+    match secs {
+        0..=59 => unit(secs, "second"),
+        60..=3599 => unit(secs / 60, "minute"),
+        _ => {
+            let (hours, minutes) = (secs / 3600, (secs % 3600) / 60);
+            if minutes == 0 {
+                unit(hours, "hour")
+            } else {
+                format!("{} and {}", unit(hours, "hour"), unit(minutes, "minute"))
+            }
+        }
+    }
+}
+
+/// "A", "A and B", "A, B and C"
+fn spoken_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DisplayTimezone;
+    use chrono::TimeZone;
+
+    fn display() -> DisplayConfig {
+        DisplayConfig {
+            timezone: DisplayTimezone::Utc,
+            ..DisplayConfig::default()
+        }
+    }
+
+    fn outage_at(start: DateTime<Utc>) -> Outage {
+        Outage {
+            start_time: start,
+            ..Outage::new(vec!["Google DNS".to_string(), "Cloudflare".to_string()])
+        }
+    }
+
+    #[test]
+    fn test_explain_isp_outage() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 14, 15, 4, 0).unwrap();
+        let mut outage = outage_at(start);
+        outage.end_at(start + chrono::Duration::minutes(12));
+        outage.failing_hop = Some(3);
+        outage.failing_hop_ip = Some("96.120.18.1".to_string());
+
+        assert_eq!(
+            explain_outage(&outage, &display(), start + chrono::Duration::hours(1)),
+            "Your internet was down for 12 minutes starting at 3:04 PM on October 14. \
+             The problem was at your ISP (hop 3, Comcast). \
+             Google DNS and Cloudflare could not be reached. \
+             It recovered on its own."
+        );
+    }
+
+    #[test]
+    fn test_explain_ongoing_outage() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 14, 9, 30, 0).unwrap();
+        let mut outage = outage_at(start);
+        outage.failing_hop = Some(1);
+        outage.failing_hop_ip = Some("192.168.1.1".to_string());
+
+        let text = explain_outage(&outage, &display(), start + chrono::Duration::seconds(45));
+        assert!(text.starts_with(
+            "Your internet has been down for 45 seconds since 9:30 AM on October 14, and is still down."
+        ));
+        assert!(text.contains("inside your home network (hop 1, your router)"));
+        assert!(!text.contains("recovered"));
+    }
+
+    #[test]
+    fn test_explain_undiagnosed_outage() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 14, 23, 0, 0).unwrap();
+        let mut outage = outage_at(start);
+        outage.affected_targets = vec![];
+        outage.end_at(start + chrono::Duration::minutes(65));
+        outage.system_sleep = true;
+
+        let text = explain_outage(&outage, &display(), start + chrono::Duration::hours(2));
+        assert!(text.contains("down for 1 hour and 5 minutes starting at 11:00 PM"));
+        assert!(text.contains("It isn't clear where the problem was."));
+        assert!(text.contains("asleep"));
+        assert!(!text.contains("could not be reached"));
+    }
+
+    #[test]
+    fn test_explain_outage_closed_at_shutdown() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 14, 15, 4, 0).unwrap();
+        let mut outage = outage_at(start);
+        outage.end_at(start + chrono::Duration::minutes(3));
+        outage.notes = Some(crate::models::SHUTDOWN_NOTE.to_string());
+
+        let text = explain_outage(&outage, &display(), start + chrono::Duration::hours(1));
+        assert!(text.ends_with(
+            "The monitor stopped while it was still down, so it may have lasted longer."
+        ));
+        assert!(!text.contains("recovered"));
+    }
+
+    #[test]
+    fn test_spoken_list() {
+        let items: Vec<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(spoken_list(&items[..1]), "a");
+        assert_eq!(spoken_list(&items[..2]), "a and b");
+        assert_eq!(spoken_list(&items), "a, b and c");
+    }
+}
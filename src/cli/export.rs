@@ -0,0 +1,301 @@
+use crate::cli::helpers::parse_duration;
+use crate::db::ExportCursor;
+use crate::models::{Outage, PingResult};
+use crate::App;
+use chrono::{DateTime, Utc};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Rows written between progress checkpoints
+const CHECKPOINT_EVERY: usize = 1000;
+
+const PING_HEADER: &str = "timestamp,target,target_name,latency_ms,success";
+
+const OUTAGE_HEADER: &str = "id,start_time,end_time,duration_secs,affected_targets,failing_hop,failing_hop_ip,notes,planned,acknowledged,system_sleep";
+
+pub fn run(
+    app: &App,
+    what: &str,
+    period: &str,
+    out: Option<&Path>,
+    resume: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if what != "pings" && what != "outages" {
+        return Err(format!("Unknown export type '{}'. Use: pings, outages", what).into());
+    }
+
+    let duration = parse_duration(period).map_err(|e| format!("Invalid duration: {}", e))?;
+    let until = Utc::now();
+    let since = until - duration;
+
+    if what == "outages" {
+        if resume {
+            return Err("--resume only applies to ping exports".into());
+        }
+        return export_outages(app, since, until, out);
+    }
+
+    match out {
+        Some(path) => export_pings_to_file(app, since, until, path, resume),
+        None if resume => Err("--resume requires --output".into()),
+        None => {
+            let stdout = std::io::stdout();
+            let mut writer = BufWriter::new(stdout.lock());
+            writeln!(writer, "{}", PING_HEADER)?;
+            app.db.export_pings(
+                since,
+                until,
+                None,
+                |ping, _| -> Result<(), Box<dyn std::error::Error>> {
+                    write_ping_row(&mut writer, &ping)?;
+                    Ok(())
+                },
+            )?;
+            writer.flush()?;
+            Ok(())
+        }
+    }
+}
+
+/// Export pings to a file, checkpointing progress to a sidecar file
+fn export_pings_to_file(
+    app: &App,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    path: &Path,
+    resume: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let progress_file = progress_path(path);
+
+    let checkpoint = if resume {
+        let checkpoint = read_progress(&progress_file)?;
+        if checkpoint.is_none() {
+            eprintln!("No export progress found, starting from the beginning.");
+        }
+        checkpoint
+    } else {
+        None
+    };
+
+    let file = match &checkpoint {
+        Some(progress) => {
+            // Drop anything written after the last checkpoint so rows aren't duplicated
+            let file = OpenOptions::new().write(true).open(path)?;
+            file.set_len(progress.bytes)?;
+            OpenOptions::new().append(true).open(path)?
+        }
+        None => File::create(path)?,
+    };
+
+    let mut bytes = checkpoint.as_ref().map(|p| p.bytes).unwrap_or(0);
+    let mut writer = BufWriter::new(file);
+    if checkpoint.is_none() {
+        bytes += write_line(&mut writer, PING_HEADER)?;
+    }
+
+    let mut since_checkpoint = 0;
+    let exported = app.db.export_pings(
+        since,
+        until,
+        checkpoint.as_ref().map(|p| &p.cursor),
+        |ping, cursor| -> Result<(), Box<dyn std::error::Error>> {
+            bytes += write_ping_row(&mut writer, &ping)?;
+            since_checkpoint += 1;
+
+            if since_checkpoint >= CHECKPOINT_EVERY {
+                writer.flush()?;
+                write_progress(&progress_file, &Progress { cursor, bytes })?;
+                since_checkpoint = 0;
+            }
+            Ok(())
+        },
+    )?;
+
+    writer.flush()?;
+    if progress_file.exists() {
+        std::fs::remove_file(&progress_file)?;
+    }
+
+    eprintln!("Exported {} pings to {}", exported, path.display());
+    Ok(())
+}
+
+/// Export outages oldest first; small enough to write in one go
+fn export_outages(
+    app: &App,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    out: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut outages = app.db.get_outages(since, until)?;
+    outages.reverse();
+
+    match out {
+        Some(path) => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            write_outages(&mut writer, &outages)?;
+            writer.flush()?;
+            eprintln!("Exported {} outages to {}", outages.len(), path.display());
+        }
+        None => {
+            let stdout = std::io::stdout();
+            let mut writer = BufWriter::new(stdout.lock());
+            write_outages(&mut writer, &outages)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn write_outages(writer: &mut impl Write, outages: &[Outage]) -> std::io::Result<()> {
+    write_line(writer, OUTAGE_HEADER)?;
+    for outage in outages {
+        let line = format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            outage.id.map(|id| id.to_string()).unwrap_or_default(),
+            outage.start_time.to_rfc3339(),
+            outage.end_time.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            outage
+                .duration_secs
+                .map(|d| d.to_string())
+                .unwrap_or_default(),
+            csv_field(&outage.affected_targets.join(";")),
+            outage
+                .failing_hop
+                .map(|h| h.to_string())
+                .unwrap_or_default(),
+            csv_field(outage.failing_hop_ip.as_deref().unwrap_or("")),
+            csv_field(outage.notes.as_deref().unwrap_or("")),
+            outage.planned,
+            outage.acknowledged,
+            outage.system_sleep
+        );
+        write_line(writer, &line)?;
+    }
+    Ok(())
+}
+
+/// Checkpointed export position: the last written row and the file length after it
+#[derive(Debug, PartialEq, Eq)]
+struct Progress {
+    cursor: ExportCursor,
+    bytes: u64,
+}
+
+/// Sidecar file recording export progress, e.g. `pings.csv.progress`
+fn progress_path(out: &Path) -> PathBuf {
+    let mut name = out.as_os_str().to_owned();
+    name.push(".progress");
+    PathBuf::from(name)
+}
+
+fn write_progress(path: &Path, progress: &Progress) -> std::io::Result<()> {
+    std::fs::write(
+        path,
+        format!(
+            "{}\t{}\t{}\n",
+            progress.cursor.timestamp, progress.cursor.id, progress.bytes
+        ),
+    )
+}
+
+fn read_progress(path: &Path) -> Result<Option<Progress>, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    let parts: Vec<&str> = content.trim().split('\t').collect();
+    match parts.as_slice() {
+        [timestamp, id, bytes] => Ok(Some(Progress {
+            cursor: ExportCursor {
+                timestamp: timestamp.to_string(),
+                id: id.parse()?,
+            },
+            bytes: bytes.parse()?,
+        })),
+        _ => Err(format!("Corrupt export progress file: {}", path.display()).into()),
+    }
+}
+
+/// Write a line and return the number of bytes written
+fn write_line(writer: &mut impl Write, line: &str) -> std::io::Result<u64> {
+    writeln!(writer, "{}", line)?;
+    Ok(line.len() as u64 + 1)
+}
+
+fn write_ping_row(writer: &mut impl Write, ping: &PingResult) -> std::io::Result<u64> {
+    let line = format!(
+        "{},{},{},{},{}",
+        ping.timestamp.to_rfc3339(),
+        csv_field(&ping.target),
+        csv_field(&ping.target_name),
+        ping.latency_ms.map(|l| l.to_string()).unwrap_or_default(),
+        ping.success
+    );
+    write_line(writer, &line)
+}
+
+/// Quote a CSV field if it contains separators, quotes, or newlines
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("pings.csv");
+        let progress_file = progress_path(&out);
+        assert!(progress_file
+            .to_string_lossy()
+            .ends_with("pings.csv.progress"));
+
+        assert!(read_progress(&progress_file).unwrap().is_none());
+
+        let progress = Progress {
+            cursor: ExportCursor {
+                timestamp: "2024-01-15T14:23:05+00:00".to_string(),
+                id: 42,
+            },
+            bytes: 1234,
+        };
+        write_progress(&progress_file, &progress).unwrap();
+        assert_eq!(read_progress(&progress_file).unwrap(), Some(progress));
+    }
+
+    #[test]
+    fn test_write_outages() {
+        let mut buf = Vec::new();
+        write_outages(&mut buf, &[]).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            format!("{}\n", OUTAGE_HEADER)
+        );
+
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string(), "1.1.1.1".to_string()]);
+        outage.id = Some(7);
+        outage.notes = Some("router, again".to_string());
+        let mut buf = Vec::new();
+        write_outages(&mut buf, &[outage]).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        let row = text.lines().nth(1).unwrap();
+        assert!(row.starts_with("7,"));
+        assert!(row.contains(",,8.8.8.8;1.1.1.1,,,\"router, again\",false,false,false"));
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("Google DNS"), "Google DNS");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -1,3 +1,5 @@
+pub use crate::format::{format_duration_secs, format_latency};
+use crate::models::Target;
 use chrono::Duration;
 
 /// Parse a duration string like "24h", "7d", "30d" into a chrono::Duration
@@ -25,29 +27,6 @@ pub fn parse_duration(s: &str) -> Result<Duration, String> {
     }
 }
 
-/// Format a duration in seconds to a human-readable string
-pub fn format_duration_secs(secs: f64) -> String {
-    if secs < 60.0 {
-        format!("{:.1}s", secs)
-    } else if secs < 3600.0 {
-        let mins = (secs / 60.0).floor();
-        let remaining_secs = secs % 60.0;
-        if remaining_secs < 1.0 {
-            format!("{}m", mins as i64)
-        } else {
-            format!("{}m {}s", mins as i64, remaining_secs as i64)
-        }
-    } else {
-        let hours = (secs / 3600.0).floor();
-        let remaining_mins = ((secs % 3600.0) / 60.0).floor();
-        if remaining_mins < 1.0 {
-            format!("{}h", hours as i64)
-        } else {
-            format!("{}h {}m", hours as i64, remaining_mins as i64)
-        }
-    }
-}
-
 /// Format a chrono Duration to a human-readable string
 pub fn format_duration(duration: Duration) -> String {
     format_duration_secs(duration.num_seconds() as f64)
@@ -72,10 +51,89 @@ pub fn truncate(s: &str, max_len: usize) -> String {
     }
 }
 
+/// Match text against a glob pattern (`*` = any run of characters, `?` = one character).
+/// Matching is case-insensitive.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it is currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` swallow one more character
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Find the targets whose name or IP matches a glob pattern
+pub fn resolve_targets(pattern: &str, targets: &[Target]) -> Vec<Target> {
+    targets
+        .iter()
+        .filter(|t| glob_match(pattern, &t.ip) || glob_match(pattern, &t.name))
+        .cloned()
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn sample_targets() -> Vec<Target> {
+        vec![
+            Target::new("Gateway", "192.168.1.1"),
+            Target::new("Gateway Backup", "192.168.2.1"),
+            Target::new("Google DNS", "8.8.8.8"),
+            Target::new("Cloudflare DNS", "1.1.1.1"),
+        ]
+    }
+
+    fn resolved_ips(pattern: &str) -> Vec<String> {
+        resolve_targets(pattern, &sample_targets())
+            .into_iter()
+            .map(|t| t.ip)
+            .collect()
+    }
+
+    #[test]
+    fn test_glob_prefix_suffix_middle() {
+        assert_eq!(
+            resolved_ips("192.168.*"),
+            vec!["192.168.1.1", "192.168.2.1"]
+        );
+        assert_eq!(resolved_ips("Gateway*"), vec!["192.168.1.1", "192.168.2.1"]);
+        assert_eq!(resolved_ips("*DNS"), vec!["8.8.8.8", "1.1.1.1"]);
+        assert_eq!(resolved_ips("192.*.1"), vec!["192.168.1.1", "192.168.2.1"]);
+        assert_eq!(resolved_ips("G*DNS"), vec!["8.8.8.8"]);
+        assert_eq!(resolved_ips("8.8.8.8"), vec!["8.8.8.8"]);
+        assert!(resolved_ips("10.*").is_empty());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*a*b", "xxaxxbxx"));
+        assert!(glob_match("gateway", "Gateway"));
+    }
+
     #[test]
     fn test_parse_duration() {
         assert_eq!(parse_duration("24h").unwrap(), Duration::hours(24));
@@ -93,14 +151,6 @@ mod tests {
         assert!(parse_duration("24x").is_err());
     }
 
-    #[test]
-    fn test_format_duration_secs() {
-        assert_eq!(format_duration_secs(5.0), "5.0s");
-        assert_eq!(format_duration_secs(65.0), "1m 5s");
-        assert_eq!(format_duration_secs(3665.0), "1h 1m");
-        assert_eq!(format_duration_secs(7200.0), "2h");
-    }
-
     #[test]
     fn test_progress_bar() {
         assert_eq!(progress_bar(100.0, 10), "██████████");
@@ -0,0 +1,90 @@
+use crate::models::{PingResult, Target};
+use crate::monitor::PingMonitor;
+use std::time::Duration;
+
+/// Ping timeout for the init reachability check
+const PROBE_TIMEOUT_MS: u64 = 1000;
+
+/// Ping each target once, concurrently, with a short timeout
+pub async fn probe_targets(targets: &[Target]) -> Vec<PingResult> {
+    let monitor = PingMonitor::with_settings(targets.to_vec(), Duration::ZERO, PROBE_TIMEOUT_MS);
+    futures::future::join_all(targets.iter().map(|t| monitor.ping(t))).await
+}
+
+/// Describe which probed targets answered, with advice for those that didn't
+pub fn reachability_summary(results: &[PingResult]) -> Vec<String> {
+    let mut lines: Vec<String> = results
+        .iter()
+        .map(|r| {
+            if r.success {
+                format!("  ✓ {} ({}) reachable", r.target_name, r.target)
+            } else {
+                format!(
+                    "  ✗ {} ({}) no reply - {}",
+                    r.target_name,
+                    r.target,
+                    r.error.as_deref().unwrap_or("timeout")
+                )
+            }
+        })
+        .collect();
+
+    let blocked = results.iter().filter(|r| !r.success).count();
+    if blocked > 0 {
+        lines.push(String::new());
+        lines.push(format!(
+            "{} target{} did not answer ICMP from this network.",
+            blocked,
+            if blocked == 1 { "" } else { "s" }
+        ));
+        lines.push(
+            "  Consider removing them from the config or using a TCP/HTTP check instead,"
+                .to_string(),
+        );
+        lines.push("  otherwise they will be reported as failing.".to_string());
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn result(ip: &str, name: &str, success: bool) -> PingResult {
+        PingResult {
+            target: ip.to_string(),
+            target_name: name.to_string(),
+            timestamp: Utc::now(),
+            success,
+            latency_ms: success.then_some(12.0),
+            jitter_ms: None,
+            loss: None,
+            error: (!success).then(|| "Request timeout".to_string()),
+            error_kind: None,
+            resolved_ip: None,
+        }
+    }
+
+    #[test]
+    fn test_reachability_summary() {
+        let lines = reachability_summary(&[
+            result("8.8.8.8", "Google DNS", true),
+            result("1.1.1.1", "Cloudflare", false),
+        ]);
+
+        assert_eq!(lines[0], "  ✓ Google DNS (8.8.8.8) reachable");
+        assert_eq!(
+            lines[1],
+            "  ✗ Cloudflare (1.1.1.1) no reply - Request timeout"
+        );
+        assert!(lines[3].starts_with("1 target did not answer"));
+    }
+
+    #[test]
+    fn test_reachability_summary_all_reachable() {
+        let lines = reachability_summary(&[result("8.8.8.8", "Google DNS", true)]);
+        assert_eq!(lines.len(), 1);
+    }
+}
@@ -1,7 +1,15 @@
 // CLI module
+pub mod doctor;
+pub mod explain;
+pub mod export;
 pub mod helpers;
+pub mod init;
+pub mod outage_detail;
 pub mod outages;
+pub mod serve;
 pub mod service;
+pub mod source;
 pub mod start;
 pub mod stats;
 pub mod status;
+pub mod trends;
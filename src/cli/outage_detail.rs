@@ -0,0 +1,206 @@
+use crate::cli::helpers::format_duration_secs;
+use crate::config::DisplayConfig;
+use crate::db::TracerouteWithMeta;
+use crate::models::{Marker, Outage};
+use crate::monitor::{describe_ip, format_traceroute};
+use crate::App;
+use chrono::{DateTime, Utc};
+
+pub fn run(app: &App, id: i64) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(outage) = app.db.get_outage(id)? else {
+        println!(
+            "No outage with ID {}. Run 'vigil outages' to list them.",
+            id
+        );
+        return Ok(());
+    };
+    let traces = app.db.get_traceroutes_for_outage(id)?;
+    let mut markers = app
+        .db
+        .get_markers_in_range(outage.start_time, outage.end_time.unwrap_or_else(Utc::now))?;
+    // Stored newest first; show them in the order they happened, like the traces
+    markers.reverse();
+
+    let hop_owner = outage
+        .failing_hop_ip
+        .as_deref()
+        .map(|ip| describe_ip(ip, app.config.display.reverse_dns));
+    print!(
+        "{}",
+        render_outage_detail(
+            &outage,
+            hop_owner.as_deref(),
+            &traces,
+            &markers,
+            &app.config.display,
+            Utc::now()
+        )
+    );
+    Ok(())
+}
+
+/// Everything stored about one outage, the markers placed during it, and
+/// its traceroutes in the order they ran
+pub fn render_outage_detail(
+    outage: &Outage,
+    hop_owner: Option<&str>,
+    traces: &[TracerouteWithMeta],
+    markers: &[Marker],
+    display: &DisplayConfig,
+    now: DateTime<Utc>,
+) -> String {
+    let mut out = String::new();
+    let time = |t: DateTime<Utc>| display.format_time(t, "%Y-%m-%d %H:%M:%S");
+
+    out.push_str(&format!(
+        "Outage {}\n",
+        outage
+            .id
+            .map_or_else(|| "-".to_string(), |id| id.to_string())
+    ));
+    out.push_str("═══════════════════════════════════════════════════════════\n\n");
+    out.push_str(&format!("  Started:   {}\n", time(outage.start_time)));
+    match outage.end_time {
+        Some(end) => out.push_str(&format!("  Ended:     {}\n", time(end))),
+        None => out.push_str("  Ended:     ongoing\n"),
+    }
+    out.push_str(&format!(
+        "  Duration:  {}\n",
+        format_duration_secs(outage.elapsed_secs(now))
+    ));
+    if !outage.affected_targets.is_empty() {
+        out.push_str(&format!(
+            "  Targets:   {}\n",
+            outage.affected_targets.join(", ")
+        ));
+    }
+    if let Some(hop) = outage.failing_hop {
+        let ip = hop_owner
+            .or(outage.failing_hop_ip.as_deref())
+            .unwrap_or("unknown");
+        out.push_str(&format!("  Failing hop: {} — {}\n", hop, ip));
+    }
+
+    let flags: Vec<&str> = [
+        (outage.planned, "planned"),
+        (outage.acknowledged, "acknowledged"),
+        (outage.system_sleep, "system sleep"),
+    ]
+    .into_iter()
+    .filter_map(|(set, label)| set.then_some(label))
+    .collect();
+    if !flags.is_empty() {
+        out.push_str(&format!("  Flags:     {}\n", flags.join(", ")));
+    }
+    if let Some(notes) = &outage.notes {
+        out.push_str(&format!("  Notes:     {}\n", notes));
+    }
+
+    if !markers.is_empty() {
+        out.push_str("\nMarkers:\n");
+        for marker in markers {
+            out.push_str(&format!("  {}  {}\n", time(marker.timestamp), marker.label));
+        }
+    }
+
+    if traces.is_empty() {
+        out.push_str("\nNo traceroutes recorded.\n");
+    }
+    for trace in traces {
+        out.push_str(&format!(
+            "\n{} ({})\n",
+            time(trace.result.timestamp),
+            trace.trigger
+        ));
+        out.push_str(&format_traceroute(&trace.result, display.latency_precision));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DisplayTimezone;
+    use crate::models::{TraceTrigger, TracerouteHop, TracerouteResult};
+    use chrono::TimeZone;
+
+    fn display() -> DisplayConfig {
+        DisplayConfig {
+            timezone: DisplayTimezone::Utc,
+            ..DisplayConfig::default()
+        }
+    }
+
+    fn trace(at: DateTime<Utc>, trigger: TraceTrigger) -> TracerouteWithMeta {
+        TracerouteWithMeta {
+            id: 1,
+            result: TracerouteResult {
+                target: "8.8.8.8".to_string(),
+                timestamp: at,
+                hops: vec![TracerouteHop {
+                    hop_number: 1,
+                    ip: Some("192.168.1.1".to_string()),
+                    hostname: None,
+                    latency_ms: Some(1.2),
+                    timeout: false,
+                }],
+                success: false,
+            },
+            trigger,
+        }
+    }
+
+    #[test]
+    fn test_render_outage_detail() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 14, 15, 4, 0).unwrap();
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string(), "1.1.1.1".to_string()]);
+        outage.id = Some(42);
+        outage.start_time = start;
+        outage.end_at(start + chrono::Duration::minutes(12));
+        outage.failing_hop = Some(2);
+        outage.failing_hop_ip = Some("10.0.0.1".to_string());
+        outage.acknowledged = true;
+
+        let traces = [
+            trace(start, TraceTrigger::StateChange),
+            trace(start + chrono::Duration::minutes(5), TraceTrigger::Periodic),
+        ];
+        let text = render_outage_detail(
+            &outage,
+            Some("10.0.0.1 (ISP)"),
+            &traces,
+            &[Marker {
+                id: 1,
+                timestamp: start + chrono::Duration::minutes(3),
+                label: "rebooted router".to_string(),
+            }],
+            &display(),
+            start + chrono::Duration::hours(1),
+        );
+
+        assert!(text.starts_with("Outage 42\n"));
+        assert!(text.contains("Started:   2026-10-14 15:04:00"));
+        assert!(text.contains("Ended:     2026-10-14 15:16:00"));
+        assert!(text.contains("Targets:   8.8.8.8, 1.1.1.1"));
+        assert!(text.contains("Failing hop: 2 — 10.0.0.1 (ISP)"));
+        assert!(text.contains("Flags:     acknowledged\n"));
+        assert!(text.contains("Markers:\n  2026-10-14 15:07:00  rebooted router\n"));
+        let first = text.find("2026-10-14 15:04:00 (state_change)").unwrap();
+        let second = text.find("2026-10-14 15:09:00 (periodic)").unwrap();
+        assert!(first < second);
+    }
+
+    #[test]
+    fn test_render_ongoing_outage_without_traces() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 14, 9, 0, 0).unwrap();
+        let mut outage = Outage::new(vec![]);
+        outage.start_time = start;
+
+        let text = render_outage_detail(&outage, None, &[], &[], &display(), start);
+        assert!(text.contains("Ended:     ongoing"));
+        assert!(text.contains("No traceroutes recorded."));
+        assert!(!text.contains("Flags:"));
+        assert!(!text.contains("Markers:"));
+    }
+}
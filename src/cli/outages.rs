@@ -1,46 +1,271 @@
 use crate::cli::helpers::{format_duration_secs, parse_duration, truncate};
-use crate::models::Outage;
+use crate::cli::source::{LocalDb, ReportSource};
+use crate::config::DisplayConfig;
+use crate::models::{Marker, Outage};
 use crate::App;
 use chrono::Utc;
 use std::collections::HashMap;
+use std::fmt::Write;
 
-pub fn run(app: &App, last: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// Columns shown when `--columns` is not given
+pub const DEFAULT_COLUMNS: &str = "start,duration,hop,targets";
+
+/// A selectable column in the outages table
+pub struct Column {
+    pub name: &'static str,
+    header: &'static str,
+    width: usize,
+    right_align: bool,
+    render: fn(&Outage, &DisplayConfig) -> String,
+}
+
+/// All columns available to `--columns`
+const COLUMNS: &[Column] = &[
+    Column {
+        name: "id",
+        header: "ID",
+        width: 5,
+        right_align: true,
+        render: render_id,
+    },
+    Column {
+        name: "start",
+        header: "Start Time",
+        width: 19,
+        right_align: false,
+        render: render_start,
+    },
+    Column {
+        name: "duration",
+        header: "Duration",
+        width: 8,
+        right_align: true,
+        render: render_duration,
+    },
+    Column {
+        name: "hop",
+        header: "Failing Hop",
+        width: 12,
+        right_align: true,
+        render: render_hop,
+    },
+    Column {
+        name: "severity",
+        header: "Severity",
+        width: 8,
+        right_align: false,
+        render: render_severity,
+    },
+    Column {
+        name: "targets",
+        header: "Affected Targets",
+        width: 20,
+        right_align: false,
+        render: render_targets,
+    },
+];
+
+/// Parse a comma-separated column list like "start,duration,hop"
+pub fn parse_columns(spec: &str) -> Result<Vec<&'static Column>, String> {
+    let columns: Vec<&'static Column> = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            COLUMNS.iter().find(|c| c.name == name).ok_or_else(|| {
+                let available: Vec<&str> = COLUMNS.iter().map(|c| c.name).collect();
+                format!(
+                    "Unknown column '{}'. Available: {}",
+                    name,
+                    available.join(", ")
+                )
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    if columns.is_empty() {
+        return Err("No columns selected".to_string());
+    }
+
+    Ok(columns)
+}
+
+/// Outages that started on the same (displayed) day
+pub struct DayGroup<'a> {
+    pub date: String,
+    pub outages: Vec<&'a Outage>,
+    pub downtime_secs: f64,
+}
+
+/// Group outages by the date they started, keeping the input order
+pub fn group_by_day<'a>(outages: &'a [Outage], display: &DisplayConfig) -> Vec<DayGroup<'a>> {
+    let mut groups: Vec<DayGroup<'a>> = Vec::new();
+
+    for outage in outages {
+        let date = display.format_time(outage.start_time, "%Y-%m-%d");
+        let group = match groups.iter_mut().position(|g| g.date == date) {
+            Some(index) => &mut groups[index],
+            None => {
+                groups.push(DayGroup {
+                    date,
+                    outages: Vec::new(),
+                    downtime_secs: 0.0,
+                });
+                groups.last_mut().expect("just pushed")
+            }
+        };
+        group.outages.push(outage);
+        group.downtime_secs += outage.duration_secs.unwrap_or(0.0);
+    }
+
+    groups
+}
+
+/// An outage or user marker, in display order
+#[derive(Debug)]
+pub enum TimelineEntry<'a> {
+    Outage(&'a Outage),
+    Marker(&'a Marker),
+}
+
+impl TimelineEntry<'_> {
+    fn time(&self) -> chrono::DateTime<Utc> {
+        match self {
+            TimelineEntry::Outage(o) => o.start_time,
+            TimelineEntry::Marker(m) => m.timestamp,
+        }
+    }
+}
+
+/// Merge outages and markers newest first, so markers appear at their point in time
+pub fn timeline<'a>(outages: &[&'a Outage], markers: &[&'a Marker]) -> Vec<TimelineEntry<'a>> {
+    let mut entries: Vec<TimelineEntry<'a>> = outages
+        .iter()
+        .map(|o| TimelineEntry::Outage(o))
+        .chain(markers.iter().map(|m| TimelineEntry::Marker(m)))
+        .collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.time()));
+    entries
+}
+
+fn render_marker(marker: &Marker, display: &DisplayConfig) -> String {
+    format!(
+        "▸ {}  {}",
+        display.format_time(marker.timestamp, "%Y-%m-%d %H:%M:%S"),
+        marker.label
+    )
+}
+
+fn render_entry(entry: &TimelineEntry, columns: &[&Column], display: &DisplayConfig) -> String {
+    match entry {
+        TimelineEntry::Outage(outage) => render_row(columns, outage, display),
+        TimelineEntry::Marker(marker) => render_marker(marker, display),
+    }
+}
+
+pub fn run(
+    app: &App,
+    last: &str,
+    columns: &str,
+    group_by: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    run_from(
+        &LocalDb(&app.db),
+        &app.config.display,
+        last,
+        columns,
+        group_by,
+    )
+}
+
+/// Print the outages report from any source (local database or a remote `vigil serve`)
+pub fn run_from(
+    source: &dyn ReportSource,
+    display: &DisplayConfig,
+    last: &str,
+    columns: &str,
+    group_by: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    print!("{}", report(source, display, last, columns, group_by)?);
+    Ok(())
+}
+
+/// Render the outages report
+pub fn report(
+    source: &dyn ReportSource,
+    display: &DisplayConfig,
+    last: &str,
+    columns: &str,
+    group_by: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let columns = parse_columns(columns)?;
+    match group_by {
+        None | Some("day") => {}
+        Some(other) => return Err(format!("Unknown grouping '{}'. Use: day", other).into()),
+    }
     let duration = parse_duration(last).map_err(|e| format!("Invalid duration: {}", e))?;
     let since = Utc::now() - duration;
     let until = Utc::now();
 
-    let outages = app.db.get_outages(since, until)?;
+    let outages = source.outages(since, until)?;
+    let markers = source.markers(since, until)?;
+    let mut out = String::new();
 
-    println!("Recent Outages (last {})", last);
-    println!("═══════════════════════════════════════════════════════════\n");
+    writeln!(out, "Recent Outages (last {})", last)?;
+    writeln!(
+        out,
+        "═══════════════════════════════════════════════════════════\n"
+    )?;
 
     if outages.is_empty() {
-        println!("No outages recorded in this period.");
-        return Ok(());
+        writeln!(out, "No outages recorded in this period.")?;
+        return Ok(out);
     }
 
-    // Print table header
-    println!(
-        "{:<19}  {:>8}  {:>12}  Affected Targets",
-        "Start Time", "Duration", "Failing Hop"
-    );
-    println!("{}", "─".repeat(65));
+    // Print table header, with a rule as wide as it
+    let header = render_header(&columns);
+    let rule = "─".repeat(header.chars().count());
+    writeln!(out, "{}", header)?;
+    writeln!(out, "{}", rule)?;
 
-    // Print each outage
-    for outage in &outages {
-        print_outage_row(outage);
+    if group_by.is_some() {
+        for group in group_by_day(&outages, display) {
+            writeln!(
+                out,
+                "{}  ({} outage{}, {})",
+                group.date,
+                group.outages.len(),
+                if group.outages.len() == 1 { "" } else { "s" },
+                format_duration_secs(group.downtime_secs)
+            )?;
+            let day_markers: Vec<&Marker> = markers
+                .iter()
+                .filter(|m| display.format_time(m.timestamp, "%Y-%m-%d") == group.date)
+                .collect();
+            for entry in timeline(&group.outages, &day_markers) {
+                writeln!(out, "  {}", render_entry(&entry, &columns, display))?;
+            }
+        }
+    } else {
+        // Print each outage, with markers at their point in time
+        let outage_refs: Vec<&Outage> = outages.iter().collect();
+        let marker_refs: Vec<&Marker> = markers.iter().collect();
+        for entry in timeline(&outage_refs, &marker_refs) {
+            writeln!(out, "{}", render_entry(&entry, &columns, display))?;
+        }
     }
 
-    println!("{}", "─".repeat(65));
+    writeln!(out, "{}", rule)?;
 
     // Summary
     let total_downtime: f64 = outages.iter().filter_map(|o| o.duration_secs).sum();
-    println!(
+    writeln!(
+        out,
         "\nSummary: {} outage{}, {} total downtime",
         outages.len(),
         if outages.len() == 1 { "" } else { "s" },
         format_duration_secs(total_downtime)
-    );
+    )?;
 
     // Most common failing hop
     let mut hop_counts: HashMap<u8, u32> = HashMap::new();
@@ -56,32 +281,123 @@ pub fn run(app: &App, last: &str) -> Result<(), Box<dyn std::error::Error>> {
             2 => "ISP Modem",
             _ => "ISP Backbone",
         };
-        println!(
+        writeln!(
+            out,
             "Most common failing hop: {} ({}) - {} occurrence{}",
             hop,
             hop_name,
             count,
             if count == 1 { "" } else { "s" }
-        );
+        )?;
     }
 
-    Ok(())
+    Ok(out)
+}
+
+/// Show only ongoing outages with their live duration; returns whether any are ongoing
+pub fn run_ongoing(app: &App) -> Result<bool, Box<dyn std::error::Error>> {
+    run_ongoing_from(&LocalDb(&app.db), &app.config.display)
 }
 
-fn print_outage_row(outage: &Outage) {
-    let start_time = outage.start_time.format("%Y-%m-%d %H:%M:%S").to_string();
+/// `run_ongoing` against any report source
+pub fn run_ongoing_from(
+    source: &dyn ReportSource,
+    display: &DisplayConfig,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let outages = source.ongoing_outages()?;
 
-    let duration = outage
+    if outages.is_empty() {
+        println!("No ongoing outages.");
+        return Ok(false);
+    }
+
+    let now = Utc::now();
+    println!("Ongoing Outages");
+    println!("═══════════════════════════════════════════════════════════\n");
+    for outage in &outages {
+        println!("{}", render_ongoing(outage, now, display));
+    }
+
+    Ok(true)
+}
+
+fn render_ongoing(outage: &Outage, now: chrono::DateTime<Utc>, display: &DisplayConfig) -> String {
+    format!(
+        "{:>5}  since {}  down for {}  {}",
+        render_id(outage, display),
+        render_start(outage, display),
+        format_duration_secs(outage.elapsed_secs(now)),
+        render_targets(outage, display)
+    )
+}
+
+/// Pad a cell to the column width (the last column is never padded)
+fn pad_cell(column: &Column, value: &str, is_last: bool) -> String {
+    if is_last {
+        value.to_string()
+    } else if column.right_align {
+        format!("{:>width$}", value, width = column.width)
+    } else {
+        format!("{:<width$}", value, width = column.width)
+    }
+}
+
+/// Render the table header for the selected columns
+fn render_header(columns: &[&Column]) -> String {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| pad_cell(c, c.header, i == columns.len() - 1))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+/// Render a single outage row for the selected columns
+fn render_row(columns: &[&Column], outage: &Outage, display: &DisplayConfig) -> String {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| pad_cell(c, &(c.render)(outage, display), i == columns.len() - 1))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+fn render_id(outage: &Outage, _display: &DisplayConfig) -> String {
+    let id = outage
+        .id
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| "-".to_string());
+    if outage.acknowledged {
+        format!("{}*", id)
+    } else {
+        id
+    }
+}
+
+fn render_start(outage: &Outage, display: &DisplayConfig) -> String {
+    display.format_time(outage.start_time, "%Y-%m-%d %H:%M:%S")
+}
+
+fn render_duration(outage: &Outage, _display: &DisplayConfig) -> String {
+    outage
         .duration_secs
         .map(format_duration_secs)
-        .unwrap_or_else(|| "ongoing".to_string());
+        .unwrap_or_else(|| "ongoing".to_string())
+}
 
-    let failing_hop = match (outage.failing_hop, &outage.failing_hop_ip) {
+fn render_hop(outage: &Outage, _display: &DisplayConfig) -> String {
+    match (outage.failing_hop, &outage.failing_hop_ip) {
         (Some(hop), Some(ip)) => format!("{} ({})", hop, truncate(ip, 8)),
         (Some(hop), None) => format!("{}", hop),
         (None, _) => "-".to_string(),
-    };
+    }
+}
+
+fn render_severity(outage: &Outage, _display: &DisplayConfig) -> String {
+    outage.severity().to_string()
+}
 
+fn render_targets(outage: &Outage, _display: &DisplayConfig) -> String {
     let affected = if outage.affected_targets.is_empty() {
         "-".to_string()
     } else if outage.affected_targets.len() <= 2 {
@@ -94,11 +410,147 @@ fn print_outage_row(outage: &Outage) {
         )
     };
 
-    println!(
-        "{:<19}  {:>8}  {:>12}  {}",
-        start_time,
-        duration,
-        failing_hop,
-        truncate(&affected, 20)
-    );
+    truncate(&affected, 20)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_outage() -> Outage {
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.duration_secs = Some(42.0);
+        outage.failing_hop = Some(2);
+        outage.failing_hop_ip = Some("10.0.0.1".to_string());
+        outage
+    }
+
+    #[test]
+    fn test_default_columns_match_original_layout() {
+        let columns = parse_columns(DEFAULT_COLUMNS).unwrap();
+        assert_eq!(
+            render_header(&columns),
+            format!(
+                "{:<19}  {:>8}  {:>12}  Affected Targets",
+                "Start Time", "Duration", "Failing Hop"
+            )
+        );
+    }
+
+    #[test]
+    fn test_report_rules_match_header_width() {
+        let db = crate::db::Database::in_memory().unwrap();
+        let mut outage = sample_outage();
+        outage.start_time = Utc::now() - chrono::Duration::hours(1);
+        db.insert_outage(&outage).unwrap();
+
+        for columns in [DEFAULT_COLUMNS, "severity,duration"] {
+            let text = report(
+                &LocalDb(&db),
+                &DisplayConfig::default(),
+                "1d",
+                columns,
+                None,
+            )
+            .unwrap();
+            let header = render_header(&parse_columns(columns).unwrap());
+            let rule = "─".repeat(header.chars().count());
+            assert!(text.contains(&format!("{}\n{}\n", header, rule)));
+            assert_eq!(text.matches(&format!("{}\n", rule)).count(), 2);
+        }
+    }
+
+    #[test]
+    fn test_custom_column_subset_in_order() {
+        let columns = parse_columns("severity,duration").unwrap();
+        let outage = sample_outage();
+
+        assert_eq!(render_header(&columns), "Severity  Duration");
+        assert_eq!(
+            render_row(&columns, &outage, &DisplayConfig::default()),
+            "moderate  42.0s"
+        );
+    }
+
+    #[test]
+    fn test_render_ongoing_uses_live_duration() {
+        let display = DisplayConfig {
+            timezone: crate::config::DisplayTimezone::Utc,
+            ..DisplayConfig::default()
+        };
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.id = Some(7);
+        outage.start_time = "2024-01-15T14:23:05Z".parse().unwrap();
+        let now = "2024-01-15T14:25:47Z".parse().unwrap();
+
+        assert_eq!(
+            render_ongoing(&outage, now, &display),
+            "    7  since 2024-01-15 14:23:05  down for 2m 42s  8.8.8.8"
+        );
+    }
+
+    #[test]
+    fn test_group_by_day() {
+        let display = DisplayConfig {
+            timezone: crate::config::DisplayTimezone::Utc,
+            ..DisplayConfig::default()
+        };
+        let outages: Vec<Outage> = [
+            ("2024-01-17T09:00:00Z", 30.0),
+            ("2024-01-16T23:59:00Z", 60.0),
+            ("2024-01-16T08:00:00Z", 15.0),
+            ("2024-01-15T12:00:00Z", 5.0),
+        ]
+        .iter()
+        .map(|(start, duration)| {
+            let mut outage = sample_outage();
+            outage.start_time = start.parse().unwrap();
+            outage.duration_secs = Some(*duration);
+            outage
+        })
+        .collect();
+
+        let groups = group_by_day(&outages, &display);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].date, "2024-01-17");
+        assert_eq!(groups[1].date, "2024-01-16");
+        assert_eq!(groups[1].outages.len(), 2);
+        assert_eq!(groups[1].downtime_secs, 75.0);
+        assert_eq!(groups[2].outages.len(), 1);
+        assert_eq!(groups[2].downtime_secs, 5.0);
+    }
+
+    #[test]
+    fn test_timeline_places_markers_by_time() {
+        let mut older = sample_outage();
+        older.start_time = "2024-01-15T10:00:00Z".parse().unwrap();
+        let mut newer = sample_outage();
+        newer.start_time = "2024-01-15T12:00:00Z".parse().unwrap();
+        let marker = Marker {
+            id: 1,
+            timestamp: "2024-01-15T11:00:00Z".parse().unwrap(),
+            label: "unplugged router".to_string(),
+        };
+
+        let entries = timeline(&[&newer, &older], &[&marker]);
+        assert!(matches!(entries[0], TimelineEntry::Outage(o) if o.start_time == newer.start_time));
+        assert!(matches!(entries[1], TimelineEntry::Marker(m) if m.label == "unplugged router"));
+        assert!(matches!(entries[2], TimelineEntry::Outage(o) if o.start_time == older.start_time));
+
+        let display = DisplayConfig {
+            timezone: crate::config::DisplayTimezone::Utc,
+            ..DisplayConfig::default()
+        };
+        assert_eq!(
+            render_marker(&marker, &display),
+            "▸ 2024-01-15 11:00:00  unplugged router"
+        );
+    }
+
+    #[test]
+    fn test_unknown_column_rejected() {
+        let err = parse_columns("start,bogus").err().unwrap();
+        assert!(err.contains("bogus"));
+        assert!(parse_columns(" , ").is_err());
+    }
 }
@@ -0,0 +1,273 @@
+use crate::cli::helpers::parse_duration;
+use crate::db::Database;
+use crate::models::{ConnectivityState, Outage, SessionSummary, Stats};
+use crate::App;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::json;
+use tiny_http::{Header, Method, Response, Server};
+
+/// Current state as reported by `/api/status`, derived from the database only
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    /// Unknown when no monitor session is running
+    pub state: ConnectivityState,
+    pub ongoing_outage: Option<Outage>,
+    pub sessions: SessionSummary,
+    pub last_24h: Stats,
+}
+
+/// A routed response: HTTP status code and JSON body
+#[derive(Debug)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: serde_json::Value,
+}
+
+impl ApiResponse {
+    fn ok(body: impl Serialize) -> Self {
+        match serde_json::to_value(body) {
+            Ok(body) => Self { status: 200, body },
+            Err(e) => Self::error(500, &e.to_string()),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+pub fn run(app: &App, bind: &str, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let server = Server::http((bind, port)).map_err(|e| format!("Failed to bind: {}", e))?;
+
+    println!("Serving read-only API on http://{}:{}", bind, port);
+    println!(
+        "Endpoints: /api/status, /api/outages?last=7d, /api/outages/ongoing, /api/markers?last=7d, /api/stats?period=30d, /api/outage/<id>"
+    );
+    println!("Press Ctrl+C to stop\n");
+
+    serve(&server, &app.db);
+    Ok(())
+}
+
+/// Handle requests until the server is closed
+pub fn serve(server: &Server, db: &Database) {
+    for request in server.incoming_requests() {
+        let response = if *request.method() == Method::Get {
+            route(db, request.url())
+        } else {
+            ApiResponse::error(405, "Only GET is supported")
+        };
+
+        tracing::debug!(
+            "{} {} -> {}",
+            request.method(),
+            request.url(),
+            response.status
+        );
+
+        let header =
+            Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+        let reply = Response::from_string(response.body.to_string())
+            .with_status_code(response.status)
+            .with_header(header);
+        if let Err(e) = request.respond(reply) {
+            tracing::warn!("Failed to send API response: {}", e);
+        }
+    }
+}
+
+/// Map a request path (with optional query string) to a response
+pub fn route(db: &Database, url: &str) -> ApiResponse {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let result = match segments.as_slice() {
+        ["api", "status"] => status(db),
+        ["api", "outages"] => outages(db, query),
+        ["api", "outages", "ongoing"] => ongoing_outages(db),
+        ["api", "markers"] => markers(db, query),
+        ["api", "stats"] => stats(db, query_param(query, "period").unwrap_or("24h")),
+        ["api", "outage", id] => match id.parse::<i64>() {
+            Ok(id) => outage(db, id),
+            Err(_) => Ok(ApiResponse::error(400, "Outage ID must be a number")),
+        },
+        _ => Ok(ApiResponse::error(404, "Not found")),
+    };
+
+    result.unwrap_or_else(|e| ApiResponse::error(500, &e.to_string()))
+}
+
+type RouteResult = Result<ApiResponse, Box<dyn std::error::Error>>;
+
+fn status(db: &Database) -> RouteResult {
+    let now = Utc::now();
+    let sessions = db.get_session_summary(now)?;
+    let ongoing_outage = db.get_ongoing_outage()?;
+
+    let state = match (&sessions.current_start, &ongoing_outage) {
+        (None, _) => ConnectivityState::Unknown,
+        (Some(_), Some(_)) => ConnectivityState::Offline,
+        (Some(_), None) => ConnectivityState::Online,
+    };
+
+    Ok(ApiResponse::ok(StatusReport {
+        state,
+        ongoing_outage,
+        sessions,
+        last_24h: db.get_stats(now - Duration::hours(24), now)?,
+    }))
+}
+
+fn outages(db: &Database, query: &str) -> RouteResult {
+    Ok(match time_range(query) {
+        Ok((since, until)) => ApiResponse::ok(db.get_outages(since, until)?),
+        Err(response) => response,
+    })
+}
+
+fn ongoing_outages(db: &Database) -> RouteResult {
+    Ok(ApiResponse::ok(db.get_ongoing_outages()?))
+}
+
+fn markers(db: &Database, query: &str) -> RouteResult {
+    Ok(match time_range(query) {
+        Ok((since, until)) => ApiResponse::ok(db.get_markers_in_range(since, until)?),
+        Err(response) => response,
+    })
+}
+
+/// `since`/`until` (RFC 3339) when given, otherwise the trailing `last` period (default 24h)
+fn time_range(query: &str) -> Result<(DateTime<Utc>, DateTime<Utc>), ApiResponse> {
+    let timestamp = |key| {
+        query_param(query, key)
+            .map(|v| {
+                DateTime::parse_from_rfc3339(v)
+                    .map(|t| t.with_timezone(&Utc))
+                    .map_err(|_| ApiResponse::error(400, "Invalid timestamp"))
+            })
+            .transpose()
+    };
+
+    let until = timestamp("until")?.unwrap_or_else(Utc::now);
+    match timestamp("since")? {
+        Some(since) => Ok((since, until)),
+        None => {
+            let last = query_param(query, "last").unwrap_or("24h");
+            let duration =
+                parse_duration(last).map_err(|_| ApiResponse::error(400, "Invalid duration"))?;
+            Ok((until - duration, until))
+        }
+    }
+}
+
+fn stats(db: &Database, period: &str) -> RouteResult {
+    let Ok(duration) = parse_duration(period) else {
+        return Ok(ApiResponse::error(400, "Invalid duration"));
+    };
+    let until = Utc::now();
+    Ok(ApiResponse::ok(db.get_stats(until - duration, until)?))
+}
+
+fn outage(db: &Database, id: i64) -> RouteResult {
+    Ok(match db.get_outage(id)? {
+        Some(outage) => ApiResponse::ok(outage),
+        None => ApiResponse::error(404, "Outage not found"),
+    })
+}
+
+/// Look up a query string parameter, e.g. `last` in `last=7d&x=1`
+fn query_param<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded_db() -> (Database, i64) {
+        let db = Database::in_memory().unwrap();
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.start_time = Utc::now() - Duration::hours(2);
+        outage.end_time = Some(outage.start_time + Duration::seconds(42));
+        outage.duration_secs = Some(42.0);
+        outage.failing_hop = Some(2);
+        let id = db.insert_outage(&outage).unwrap();
+        (db, id)
+    }
+
+    #[test]
+    fn test_route_endpoints() {
+        let (db, id) = seeded_db();
+
+        let status = route(&db, "/api/status");
+        assert_eq!(status.status, 200);
+        assert_eq!(status.body["state"], "Unknown");
+        assert!(status.body["ongoing_outage"].is_null());
+        assert_eq!(status.body["last_24h"]["total_outages"], 1);
+
+        let outages = route(&db, "/api/outages?last=7d");
+        assert_eq!(outages.status, 200);
+        assert_eq!(outages.body.as_array().unwrap().len(), 1);
+        assert_eq!(outages.body[0]["failing_hop"], 2);
+        assert_eq!(route(&db, "/api/outages?last=1h").body, json!([]));
+        let since =
+            (Utc::now() - Duration::hours(3)).to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let ranged = route(&db, &format!("/api/outages?since={}", since));
+        assert_eq!(ranged.body.as_array().unwrap().len(), 1);
+        assert_eq!(route(&db, "/api/outages/ongoing").body, json!([]));
+        assert_eq!(route(&db, "/api/markers?last=7d").body, json!([]));
+
+        let stats = route(&db, "/api/stats?period=30d");
+        assert_eq!(stats.status, 200);
+        assert_eq!(stats.body["total_outages"], 1);
+        assert_eq!(stats.body["total_downtime_secs"], 42.0);
+
+        let outage = route(&db, &format!("/api/outage/{}", id));
+        assert_eq!(outage.status, 200);
+        assert_eq!(outage.body["id"], id);
+        assert_eq!(outage.body["affected_targets"], json!(["8.8.8.8"]));
+    }
+
+    #[test]
+    fn test_route_errors() {
+        let (db, _) = seeded_db();
+
+        assert_eq!(route(&db, "/api/outage/999").status, 404);
+        assert_eq!(route(&db, "/api/outage/abc").status, 400);
+        assert_eq!(route(&db, "/api/stats?period=forever").status, 400);
+        assert_eq!(route(&db, "/api/outages?since=yesterday").status, 400);
+        assert_eq!(route(&db, "/nope").status, 404);
+    }
+
+    #[test]
+    fn test_serve_over_http() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+
+        // The server thread runs until the test process exits
+        std::thread::spawn(move || {
+            let (db, _) = seeded_db();
+            serve(&server, &db);
+        });
+
+        let body: serde_json::Value = ureq::get(&format!("http://{}/api/stats?period=7d", addr))
+            .call()
+            .unwrap()
+            .into_json()
+            .unwrap();
+        assert_eq!(body["total_outages"], 1);
+
+        let err = ureq::get(&format!("http://{}/api/outage/999", addr))
+            .call()
+            .unwrap_err();
+        assert!(matches!(err, ureq::Error::Status(404, _)));
+    }
+}
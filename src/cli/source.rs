@@ -0,0 +1,138 @@
+use crate::db::Database;
+use crate::models::{Marker, Outage};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+/// Timeout for a single request to a remote `vigil serve`
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type SourceResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Where report commands read their data from
+pub trait ReportSource {
+    /// Outages that started within `[since, until]`, newest first
+    fn outages(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> SourceResult<Vec<Outage>>;
+
+    /// Outages without an end time, newest first
+    fn ongoing_outages(&self) -> SourceResult<Vec<Outage>>;
+
+    /// Timeline markers within `[since, until]`, newest first
+    fn markers(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> SourceResult<Vec<Marker>>;
+}
+
+/// Reads from this machine's database
+pub struct LocalDb<'a>(pub &'a Database);
+
+impl ReportSource for LocalDb<'_> {
+    fn outages(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> SourceResult<Vec<Outage>> {
+        Ok(self.0.get_outages(since, until)?)
+    }
+
+    fn ongoing_outages(&self) -> SourceResult<Vec<Outage>> {
+        Ok(self.0.get_ongoing_outages()?)
+    }
+
+    fn markers(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> SourceResult<Vec<Marker>> {
+        Ok(self.0.get_markers_in_range(since, until)?)
+    }
+}
+
+/// Reads from another machine's `vigil serve` API, e.g. "http://server:8080"
+pub struct HttpRemote {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl HttpRemote {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().timeout(REMOTE_TIMEOUT).build(),
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> SourceResult<T> {
+        let url = format!("{}{}", self.base_url, path);
+        let response = self
+            .agent
+            .get(&url)
+            .call()
+            .map_err(|e| format!("Remote request to {} failed: {}", url, e))?;
+        Ok(response.into_json()?)
+    }
+}
+
+/// `since=...&until=...` with UTC timestamps that need no URL escaping
+fn range_query(since: DateTime<Utc>, until: DateTime<Utc>) -> String {
+    format!(
+        "since={}&until={}",
+        since.to_rfc3339_opts(SecondsFormat::Micros, true),
+        until.to_rfc3339_opts(SecondsFormat::Micros, true)
+    )
+}
+
+impl ReportSource for HttpRemote {
+    fn outages(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> SourceResult<Vec<Outage>> {
+        self.get(&format!("/api/outages?{}", range_query(since, until)))
+    }
+
+    fn ongoing_outages(&self) -> SourceResult<Vec<Outage>> {
+        self.get("/api/outages/ongoing")
+    }
+
+    fn markers(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> SourceResult<Vec<Marker>> {
+        self.get(&format!("/api/markers?{}", range_query(since, until)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::outages;
+    use crate::config::{DisplayConfig, DisplayTimezone};
+
+    fn seeded_db(start: DateTime<Utc>) -> Database {
+        let db = Database::in_memory().unwrap();
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string(), "1.1.1.1".to_string()]);
+        outage.start_time = start;
+        outage.end_time = Some(start + chrono::Duration::seconds(42));
+        outage.duration_secs = Some(42.0);
+        outage.failing_hop = Some(2);
+        outage.failing_hop_ip = Some("10.0.0.1".to_string());
+        db.insert_outage(&outage).unwrap();
+        db.insert_marker(start - chrono::Duration::minutes(5), "moved router")
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_remote_renders_like_local() {
+        let start = Utc::now() - chrono::Duration::hours(2);
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+
+        // The server thread runs until the test process exits
+        std::thread::spawn(move || {
+            let db = seeded_db(start);
+            crate::cli::serve::serve(&server, &db);
+        });
+
+        let local_db = seeded_db(start);
+        let local = LocalDb(&local_db);
+        let remote = HttpRemote::new(&format!("http://{}/", addr));
+        let display = DisplayConfig {
+            timezone: DisplayTimezone::Utc,
+            ..DisplayConfig::default()
+        };
+
+        for group_by in [None, Some("day")] {
+            let columns = "id,start,duration,hop,targets";
+            let expected = outages::report(&local, &display, "24h", columns, group_by).unwrap();
+            let actual = outages::report(&remote, &display, "24h", columns, group_by).unwrap();
+            assert!(expected.contains("moved router"));
+            assert_eq!(actual, expected);
+        }
+        assert!(remote.ongoing_outages().unwrap().is_empty());
+    }
+}
@@ -1 +1,478 @@
 // Start command implementation - Phase 2+
+
+use crate::cli::helpers::format_duration_secs;
+use crate::config::DisplayConfig;
+use crate::models::Outage;
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/// How long per-target changes are held so near-simultaneous ones can be merged
+pub const COALESCE_WINDOW: Duration = Duration::from_millis(200);
+
+/// Pings buffered before one transaction writes them (`monitor.log_all_pings`)
+pub const PING_BATCH_SIZE: usize = 50;
+
+/// Longest buffered pings wait before being written, however few there are
+pub const PING_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Shortest gap between "still offline" notifications; heartbeats in between
+/// are only logged
+pub const HEARTBEAT_NOTIFY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// What kind of change a printed ping result represents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The target stopped answering (or its error changed)
+    Failed,
+    /// The target answered after failing
+    Recovered,
+    /// First result, or the latency moved
+    Latency,
+}
+
+/// A per-target status line waiting to be printed
+#[derive(Debug, Clone)]
+pub struct StatusChange {
+    pub time: DateTime<Utc>,
+    pub status_char: &'static str,
+    pub target_name: String,
+    pub target: String,
+    /// Latency or error text
+    pub detail: String,
+    pub kind: ChangeKind,
+}
+
+impl StatusChange {
+    fn render(&self, display: &DisplayConfig) -> String {
+        format!(
+            "[{}] {} {} ({}) - {}",
+            display.format_time(self.time, "%H:%M:%S"),
+            self.status_char,
+            self.target_name,
+            self.target,
+            self.detail
+        )
+    }
+}
+
+/// Buffers status changes for a short window, then merges failures and
+/// recoveries from several targets into one line each
+pub struct ChangeCoalescer {
+    window: Duration,
+    pending: Vec<StatusChange>,
+    deadline: Option<Instant>,
+}
+
+impl ChangeCoalescer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: Vec::new(),
+            deadline: None,
+        }
+    }
+
+    /// Buffer a change; the window starts with the first change after a flush
+    pub fn push(&mut self, change: StatusChange, now: Instant) {
+        self.deadline.get_or_insert(now + self.window);
+        self.pending.push(change);
+    }
+
+    /// When the buffered changes should be flushed, if any are pending
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Render and clear the buffered changes
+    pub fn flush(&mut self, display: &DisplayConfig) -> Vec<String> {
+        self.deadline = None;
+        let pending = std::mem::take(&mut self.pending);
+
+        let count = |kind| pending.iter().filter(|c| c.kind == kind).count();
+        let merge_failed = count(ChangeKind::Failed) > 1;
+        let merge_recovered = count(ChangeKind::Recovered) > 1;
+
+        let mut lines = Vec::new();
+        let mut merged_printed = Vec::new();
+        for change in &pending {
+            let merge = match change.kind {
+                ChangeKind::Failed => merge_failed,
+                ChangeKind::Recovered => merge_recovered,
+                ChangeKind::Latency => false,
+            };
+            if !merge {
+                lines.push(change.render(display));
+                continue;
+            }
+
+            // Per-target detail still goes to the log
+            tracing::info!(
+                "{} ({}) - {}",
+                change.target_name,
+                change.target,
+                change.detail
+            );
+
+            // The summary goes where the first change of its kind would have been
+            if merged_printed.contains(&change.kind) {
+                continue;
+            }
+            merged_printed.push(change.kind);
+            lines.push(summarize(&pending, change, display));
+        }
+
+        lines
+    }
+}
+
+/// One line for every buffered change of the same kind as `first`
+fn summarize(pending: &[StatusChange], first: &StatusChange, display: &DisplayConfig) -> String {
+    let names: Vec<&str> = pending
+        .iter()
+        .filter(|c| c.kind == first.kind)
+        .map(|c| c.target_name.as_str())
+        .collect();
+    let verb = match first.kind {
+        ChangeKind::Failed => "failed",
+        _ => "recovered",
+    };
+
+    format!(
+        "[{}] {} {} targets {}: {}",
+        display.format_time(first.time, "%H:%M:%S"),
+        first.status_char,
+        names.len(),
+        verb,
+        names.join(", ")
+    )
+}
+
+/// Collects each target's first result for a short while after startup, so a
+/// healthy start prints one line instead of one per target
+pub struct StartupSummary {
+    expected: usize,
+    deadline: Option<Instant>,
+    healthy: Vec<String>,
+    reported: usize,
+}
+
+impl StartupSummary {
+    /// A zero window disables the summary; every first result prints as usual
+    pub fn new(expected: usize, window: Duration, now: Instant) -> Self {
+        Self {
+            expected,
+            deadline: (!window.is_zero() && expected > 0).then(|| now + window),
+            healthy: Vec::new(),
+            reported: 0,
+        }
+    }
+
+    /// Take a target's first result. Returns true when it was absorbed into
+    /// the summary; failures are never absorbed, so they print right away.
+    pub fn absorb(&mut self, target_name: &str, success: bool) -> bool {
+        if self.deadline.is_none() {
+            return false;
+        }
+        self.reported += 1;
+        if success {
+            self.healthy.push(target_name.to_string());
+        }
+        success
+    }
+
+    /// When the summary is due, while it is still being collected
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Every target has reported, so there's nothing left to wait for
+    pub fn is_complete(&self) -> bool {
+        self.deadline.is_some() && self.reported >= self.expected
+    }
+
+    /// Stop collecting and render the summary line, if anything was absorbed
+    pub fn finish(&mut self, time: DateTime<Utc>, display: &DisplayConfig) -> Option<String> {
+        self.deadline.take()?;
+        if self.healthy.is_empty() {
+            return None;
+        }
+
+        let time = display.format_time(time, "%H:%M:%S");
+        let healthy = std::mem::take(&mut self.healthy);
+        Some(if healthy.len() == self.expected {
+            format!(
+                "[{}] ✓ All {} targets reporting healthy",
+                time, self.expected
+            )
+        } else {
+            format!(
+                "[{}] ✓ {}/{} targets reporting healthy: {}",
+                time,
+                healthy.len(),
+                self.expected,
+                healthy.join(", ")
+            )
+        })
+    }
+}
+
+/// Paces the "still offline" heartbeat while an outage lasts
+pub struct OutageHeartbeat {
+    every: Duration,
+    next: Option<Instant>,
+    last_notified: Option<Instant>,
+}
+
+impl OutageHeartbeat {
+    /// A zero interval never beats
+    pub fn new(every: Duration) -> Self {
+        Self {
+            every,
+            next: None,
+            last_notified: None,
+        }
+    }
+
+    /// Begin beating for an outage whose start was just notified
+    pub fn start(&mut self, now: Instant) {
+        if self.every.is_zero() {
+            return;
+        }
+        self.next = Some(now + self.every);
+        self.last_notified = Some(now);
+    }
+
+    /// The outage is over
+    pub fn stop(&mut self) {
+        self.next = None;
+        self.last_notified = None;
+    }
+
+    /// When the next heartbeat is due, during an outage
+    pub fn deadline(&self) -> Option<Instant> {
+        self.next
+    }
+
+    /// Move on to the next beat. Returns true when this beat should also
+    /// notify, at most once per `HEARTBEAT_NOTIFY_INTERVAL`.
+    pub fn beat(&mut self, now: Instant) -> bool {
+        let Some(next) = self.next else {
+            return false;
+        };
+        // Beats missed while busy (a long traceroute) are skipped, not replayed
+        self.next = Some(next.max(now) + self.every);
+
+        let throttle = HEARTBEAT_NOTIFY_INTERVAL.max(self.every);
+        let notify = self
+            .last_notified
+            .is_none_or(|last| now.saturating_duration_since(last) >= throttle);
+        if notify {
+            self.last_notified = Some(now);
+        }
+        notify
+    }
+}
+
+/// "Still offline (12m, failing hop 3 — 10.0.0.1 (ISP))"
+pub fn heartbeat_message(outage: &Outage, hop_owner: Option<&str>, now: DateTime<Utc>) -> String {
+    let hop = match outage.failing_hop {
+        Some(hop) => format!(
+            "{} — {}",
+            hop,
+            hop_owner
+                .or(outage.failing_hop_ip.as_deref())
+                .unwrap_or("unknown")
+        ),
+        None => "unknown".to_string(),
+    };
+    format!(
+        "Still offline ({}, failing hop {})",
+        format_duration_secs(outage.elapsed_secs(now)),
+        hop
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(name: &str, kind: ChangeKind) -> StatusChange {
+        StatusChange {
+            time: "2024-01-15T14:23:05Z".parse().unwrap(),
+            status_char: if kind == ChangeKind::Failed {
+                "✗"
+            } else {
+                "✓"
+            },
+            target_name: name.to_string(),
+            target: format!("ip-of-{}", name),
+            detail: if kind == ChangeKind::Failed {
+                "timeout".to_string()
+            } else {
+                "12.0ms".to_string()
+            },
+            kind,
+        }
+    }
+
+    fn utc() -> DisplayConfig {
+        DisplayConfig {
+            timezone: crate::config::DisplayTimezone::Utc,
+            ..DisplayConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_coalesces_near_simultaneous_failures() {
+        let mut coalescer = ChangeCoalescer::new(COALESCE_WINDOW);
+        let start = Instant::now();
+
+        coalescer.push(change("Gateway", ChangeKind::Failed), start);
+        coalescer.push(
+            change("Google DNS", ChangeKind::Failed),
+            start + Duration::from_millis(5),
+        );
+        coalescer.push(
+            change("Quad9", ChangeKind::Latency),
+            start + Duration::from_millis(8),
+        );
+        coalescer.push(
+            change("Cloudflare", ChangeKind::Failed),
+            start + Duration::from_millis(10),
+        );
+
+        // The window is measured from the first change
+        assert_eq!(coalescer.deadline(), Some(start + COALESCE_WINDOW));
+
+        assert_eq!(
+            coalescer.flush(&utc()),
+            vec![
+                "[14:23:05] ✗ 3 targets failed: Gateway, Google DNS, Cloudflare",
+                "[14:23:05] ✓ Quad9 (ip-of-Quad9) - 12.0ms",
+            ]
+        );
+        assert!(coalescer.deadline().is_none());
+        assert!(coalescer.flush(&utc()).is_empty());
+    }
+
+    #[test]
+    fn test_single_change_printed_as_is() {
+        let mut coalescer = ChangeCoalescer::new(COALESCE_WINDOW);
+        let now = Instant::now();
+
+        coalescer.push(change("Gateway", ChangeKind::Failed), now);
+        coalescer.push(change("Cloudflare", ChangeKind::Recovered), now);
+
+        assert_eq!(
+            coalescer.flush(&utc()),
+            vec![
+                "[14:23:05] ✗ Gateway (ip-of-Gateway) - timeout",
+                "[14:23:05] ✓ Cloudflare (ip-of-Cloudflare) - 12.0ms",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_startup_summary_all_healthy() {
+        let now = Instant::now();
+        let mut summary = StartupSummary::new(3, Duration::from_secs(3), now);
+        assert_eq!(summary.deadline(), Some(now + Duration::from_secs(3)));
+
+        assert!(summary.absorb("Gateway", true));
+        assert!(summary.absorb("Google DNS", true));
+        assert!(!summary.is_complete());
+        assert!(summary.absorb("Cloudflare", true));
+        assert!(summary.is_complete());
+
+        let time = "2024-01-15T14:23:05Z".parse().unwrap();
+        assert_eq!(
+            summary.finish(time, &utc()).as_deref(),
+            Some("[14:23:05] ✓ All 3 targets reporting healthy")
+        );
+        // Later first results (e.g. after a config reload) print normally
+        assert!(summary.deadline().is_none());
+        assert!(!summary.absorb("Quad9", true));
+        assert!(summary.finish(time, &utc()).is_none());
+    }
+
+    #[test]
+    fn test_startup_summary_with_failures_and_stragglers() {
+        let time = "2024-01-15T14:23:05Z".parse().unwrap();
+        let mut summary = StartupSummary::new(4, Duration::from_secs(3), Instant::now());
+
+        assert!(summary.absorb("Gateway", true));
+        // Failures print on their own
+        assert!(!summary.absorb("Google DNS", false));
+        assert!(summary.absorb("Cloudflare", true));
+        // One target never answered before the deadline
+        assert!(!summary.is_complete());
+        assert_eq!(
+            summary.finish(time, &utc()).as_deref(),
+            Some("[14:23:05] ✓ 2/4 targets reporting healthy: Gateway, Cloudflare")
+        );
+
+        let mut none_healthy = StartupSummary::new(1, Duration::from_secs(3), Instant::now());
+        assert!(!none_healthy.absorb("Gateway", false));
+        assert!(none_healthy.finish(time, &utc()).is_none());
+
+        let mut disabled = StartupSummary::new(2, Duration::ZERO, Instant::now());
+        assert!(disabled.deadline().is_none());
+        assert!(!disabled.absorb("Gateway", true));
+    }
+
+    #[test]
+    fn test_heartbeat_cadence_and_throttle() {
+        let start = Instant::now();
+        let mut heartbeat = OutageHeartbeat::new(Duration::from_secs(300));
+        assert!(heartbeat.deadline().is_none());
+
+        heartbeat.start(start);
+        let mut notified = Vec::new();
+        for beat in 1..=6 {
+            let now = start + Duration::from_secs(300 * beat);
+            assert_eq!(heartbeat.deadline(), Some(now));
+            if heartbeat.beat(now) {
+                notified.push(beat);
+            }
+        }
+        // Logged every 5 minutes, notified every 15
+        assert_eq!(notified, vec![3, 6]);
+
+        // A late beat schedules the next one from now
+        let late = start + Duration::from_secs(300 * 6 + 1000);
+        heartbeat.beat(late);
+        assert_eq!(heartbeat.deadline(), Some(late + Duration::from_secs(300)));
+
+        heartbeat.stop();
+        assert!(heartbeat.deadline().is_none());
+        assert!(!heartbeat.beat(late));
+
+        let mut off = OutageHeartbeat::new(Duration::ZERO);
+        off.start(start);
+        assert!(off.deadline().is_none());
+    }
+
+    #[test]
+    fn test_heartbeat_message() {
+        let start: DateTime<Utc> = "2024-01-15T14:23:05Z".parse().unwrap();
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.start_time = start;
+        let now = start + chrono::Duration::minutes(12);
+
+        assert_eq!(
+            heartbeat_message(&outage, None, now),
+            "Still offline (12m, failing hop unknown)"
+        );
+
+        outage.failing_hop = Some(3);
+        outage.failing_hop_ip = Some("10.0.0.1".to_string());
+        assert_eq!(
+            heartbeat_message(&outage, None, now),
+            "Still offline (12m, failing hop 3 — 10.0.0.1)"
+        );
+        assert_eq!(
+            heartbeat_message(&outage, Some("10.0.0.1 (Swisscom)"), now),
+            "Still offline (12m, failing hop 3 — 10.0.0.1 (Swisscom))"
+        );
+    }
+}
@@ -1,119 +1,531 @@
-use crate::cli::helpers::{format_duration_secs, parse_duration, progress_bar};
+use crate::cli::helpers::{
+    format_duration_secs, format_latency, parse_duration, progress_bar, resolve_targets,
+};
+use crate::config::DisplayConfig;
+use crate::models::{Outage, Stats, Target};
 use crate::App;
-use chrono::{Timelike, Utc};
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 
-pub fn run(app: &App, period: &str) -> Result<(), Box<dyn std::error::Error>> {
+const TIME_BLOCK_NAMES: [&str; 4] = ["00:00-06:00", "06:00-12:00", "12:00-18:00", "18:00-24:00"];
+
+/// One bar of a chart: outages and downtime under a label
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChartBucket {
+    pub label: String,
+    pub outages: u32,
+    pub downtime_secs: f64,
+}
+
+/// The series behind the stats charts, for plotting elsewhere
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChartData {
+    /// Outages by the 6-hour block (display time) they started in
+    pub time_blocks: Vec<ChartBucket>,
+    /// Per display-day totals, oldest first
+    pub daily_downtime: Vec<ChartBucket>,
+    /// Per failing hop, most downtime first
+    pub failing_hops: Vec<ChartBucket>,
+}
+
+impl ChartData {
+    pub fn from_outages(outages: &[Outage], display: &DisplayConfig) -> Self {
+        Self {
+            time_blocks: time_distribution(outages, display),
+            daily_downtime: daily_downtime(outages, display),
+            failing_hops: failing_hops(outages)
+                .into_iter()
+                .map(|(hop, outages, downtime_secs)| ChartBucket {
+                    label: format!("hop {}", hop),
+                    outages,
+                    downtime_secs,
+                })
+                .collect(),
+        }
+    }
+
+    /// One row per bucket: `series,label,outages,downtime_secs`
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("series,label,outages,downtime_secs\n");
+        for (series, buckets) in [
+            ("time_block", &self.time_blocks),
+            ("day", &self.daily_downtime),
+            ("failing_hop", &self.failing_hops),
+        ] {
+            for bucket in buckets {
+                csv.push_str(&format!(
+                    "{},{},{},{:.1}\n",
+                    series, bucket.label, bucket.outages, bucket.downtime_secs
+                ));
+            }
+        }
+        csv
+    }
+}
+
+/// Everything the stats view shows, for `--json`
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsReport {
+    pub period: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    /// Targets the report is limited to (`--target`); empty means all
+    pub targets: Vec<Target>,
+    pub availability_percent: f64,
+    pub latency: LatencySummary,
+    pub outages: OutageSummary,
+    /// Per failing hop, most downtime first
+    pub failing_hops: Vec<HopSummary>,
+    /// Outages by the 6-hour block (display time) they started in
+    pub time_distribution: Vec<ChartBucket>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub avg_jitter_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutageSummary {
+    pub total: u32,
+    /// DEGRADED periods that recovered without an outage
+    pub near_misses: usize,
+    /// DEGRADED periods where only the targets failed and the canaries didn't
+    pub target_specific: usize,
+    pub total_downtime_secs: f64,
+    pub avg_duration_secs: Option<f64>,
+    pub longest_secs: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HopSummary {
+    pub hop: u8,
+    /// Where that hop usually is ("Gateway/Router", "ISP Modem", "ISP Backbone")
+    pub location: &'static str,
+    pub outages: u32,
+    pub downtime_secs: f64,
+}
+
+impl StatsReport {
+    /// The report for `stats` and the outages behind it. Latency and near
+    /// misses come from other tables and start out empty.
+    pub fn from_outages(
+        period: &str,
+        stats: &Stats,
+        outages: &[Outage],
+        display: &DisplayConfig,
+    ) -> Self {
+        Self {
+            period: period.to_string(),
+            period_start: stats.period_start,
+            period_end: stats.period_end,
+            targets: Vec::new(),
+            availability_percent: stats.availability_percent,
+            latency: LatencySummary {
+                avg_jitter_ms: None,
+            },
+            outages: OutageSummary {
+                total: stats.total_outages,
+                near_misses: 0,
+                target_specific: 0,
+                total_downtime_secs: stats.total_downtime_secs,
+                avg_duration_secs: stats.avg_outage_duration_secs,
+                longest_secs: outages
+                    .iter()
+                    .filter_map(|o| o.duration_secs)
+                    .max_by(f64::total_cmp),
+            },
+            failing_hops: failing_hops(outages)
+                .into_iter()
+                .map(|(hop, outages, downtime_secs)| HopSummary {
+                    hop,
+                    location: hop_location(hop),
+                    outages,
+                    downtime_secs,
+                })
+                .collect(),
+            time_distribution: if outages.is_empty() {
+                Vec::new()
+            } else {
+                time_distribution(outages, display)
+            },
+        }
+    }
+}
+
+pub fn run(
+    app: &App,
+    period: &str,
+    target: Option<&str>,
+    chart_data: Option<&str>,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     let duration = parse_duration(period).map_err(|e| format!("Invalid duration: {}", e))?;
     let since = Utc::now() - duration;
     let until = Utc::now();
 
-    let stats = app.db.get_stats(since, until)?;
-    let outages = app.db.get_outages(since, until)?;
+    let mut stats = app.db.get_stats(since, until)?;
+    let mut outages = app.db.get_outages(since, until)?;
+
+    let matches = match target {
+        Some(pattern) => {
+            let matches = resolve_targets(pattern, &app.config.all_targets());
+            if matches.is_empty() {
+                return Err(format!("No targets match '{}'", pattern).into());
+            }
+            Some(matches)
+        }
+        None => None,
+    };
+
+    if let Some(matches) = &matches {
+        outages.retain(|o| {
+            o.counts_against_availability()
+                && o.affected_targets
+                    .iter()
+                    .any(|key| matches.iter().any(|t| &t.key() == key))
+        });
+        stats = Stats::from_outages(&outages, since, until);
+    }
+
+    if let Some(format) = chart_data {
+        let data = ChartData::from_outages(&outages, &app.config.display);
+        match format {
+            "json" => println!("{}", serde_json::to_string_pretty(&data)?),
+            "csv" => print!("{}", data.to_csv()),
+            other => {
+                return Err(format!("Unknown chart data format '{}'. Use: json, csv", other).into())
+            }
+        }
+        return Ok(());
+    }
+
+    let mut report = StatsReport::from_outages(period, &stats, &outages, &app.config.display);
+    report.targets = matches.unwrap_or_default();
+    report.latency.avg_jitter_ms = app.db.get_average_jitter(since, until)?;
+    let degraded = app.db.get_degraded_events(since, until)?;
+    report.outages.near_misses = degraded.iter().filter(|d| d.is_near_miss()).count();
+    report.outages.target_specific = degraded.iter().filter(|d| d.target_specific).count();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report, &app.config.display);
+    }
+    Ok(())
+}
 
-    println!("Statistics (last {})", period);
+fn print_report(report: &StatsReport, display: &DisplayConfig) {
+    println!("Statistics (last {})", report.period);
     println!("═══════════════════════════════════════════════════════════\n");
 
+    if !report.targets.is_empty() {
+        let names: Vec<String> = report
+            .targets
+            .iter()
+            .map(|t| format!("{} ({})", t.name, t.ip))
+            .collect();
+        println!("Targets: {}", names.join(", "));
+    }
+
     println!(
         "Period: {} → {}",
-        since.format("%Y-%m-%d %H:%M"),
-        until.format("%Y-%m-%d %H:%M")
+        display.format_time(report.period_start, "%Y-%m-%d %H:%M"),
+        display.format_time(report.period_end, "%Y-%m-%d %H:%M")
     );
 
     // Availability bar
     println!("\nAvailability:");
     println!(
         "  {} {:.3}%",
-        progress_bar(stats.availability_percent, 40),
-        stats.availability_percent
+        progress_bar(report.availability_percent, 40),
+        report.availability_percent
     );
 
+    if let Some(jitter) = report.latency.avg_jitter_ms {
+        println!(
+            "  Avg jitter: {}",
+            format_latency(jitter, display.latency_precision)
+        );
+    }
+
     // Outage statistics
+    let outages = &report.outages;
     println!("\nOutages:");
-    println!("  Total: {}", stats.total_outages);
+    println!("  Total: {}", outages.total);
 
-    if stats.total_downtime_secs > 0.0 {
+    if outages.near_misses > 0 {
+        println!(
+            "  Near misses: {} (degraded, recovered without an outage)",
+            outages.near_misses
+        );
+    }
+
+    if outages.target_specific > 0 {
+        println!(
+            "  Target-specific: {} (targets failed, canaries reachable)",
+            outages.target_specific
+        );
+    }
+
+    if outages.total_downtime_secs > 0.0 {
         println!(
             "  Total downtime: {}",
-            format_duration_secs(stats.total_downtime_secs)
+            format_duration_secs(outages.total_downtime_secs)
         );
     }
 
-    if let Some(avg) = stats.avg_outage_duration_secs {
+    if let Some(avg) = outages.avg_duration_secs {
         println!("  Average duration: {}", format_duration_secs(avg));
     }
 
-    // Find longest outage
-    if let Some(longest) = outages
-        .iter()
-        .filter_map(|o| o.duration_secs)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-    {
+    if let Some(longest) = outages.longest_secs {
         println!("  Longest: {}", format_duration_secs(longest));
     }
 
-    // Failing hop analysis
-    if !outages.is_empty() {
+    // Failing hop analysis and time distribution (by 6-hour blocks)
+    if !report.time_distribution.is_empty() {
         println!("\nFailing Hop Analysis:");
 
-        let mut hop_stats: HashMap<u8, (u32, f64)> = HashMap::new();
-        for outage in &outages {
-            if let Some(hop) = outage.failing_hop {
-                let entry = hop_stats.entry(hop).or_insert((0, 0.0));
-                entry.0 += 1;
-                entry.1 += outage.duration_secs.unwrap_or(0.0);
-            }
-        }
-
-        let mut hop_list: Vec<_> = hop_stats.into_iter().collect();
-        hop_list.sort_by(|a, b| b.1 .1.partial_cmp(&a.1 .1).unwrap());
-
-        for (hop, (count, total_time)) in hop_list {
-            let hop_name = match hop {
-                1 => "Gateway/Router",
-                2 => "ISP Modem",
-                _ => "ISP Backbone",
-            };
+        for hop in &report.failing_hops {
             println!(
                 "  Hop {}: {} outage{} ({} total)",
-                hop,
-                count,
-                if count == 1 { "" } else { "s" },
-                format_duration_secs(total_time)
+                hop.hop,
+                hop.outages,
+                if hop.outages == 1 { "" } else { "s" },
+                format_duration_secs(hop.downtime_secs)
             );
-            println!("    └─ {}", hop_name);
+            println!("    └─ {}", hop.location);
         }
-    }
 
-    // Time distribution (by 6-hour blocks)
-    if !outages.is_empty() {
         println!("\nTime Distribution:");
+        for line in time_distribution_lines(&report.time_distribution) {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Where a failing hop usually sits on a home connection
+fn hop_location(hop: u8) -> &'static str {
+    match hop {
+        1 => "Gateway/Router",
+        2 => "ISP Modem",
+        _ => "ISP Backbone",
+    }
+}
 
-        let mut time_blocks = [0u32; 4]; // 00-06, 06-12, 12-18, 18-24
-        for outage in &outages {
-            let hour = outage.start_time.hour();
-            let block = (hour / 6) as usize;
-            time_blocks[block] += 1;
+/// Outage count and total downtime per failing hop, most downtime first
+fn failing_hops(outages: &[Outage]) -> Vec<(u8, u32, f64)> {
+    let mut hop_stats: HashMap<u8, (u32, f64)> = HashMap::new();
+    for outage in outages {
+        if let Some(hop) = outage.failing_hop {
+            let entry = hop_stats.entry(hop).or_insert((0, 0.0));
+            entry.0 += 1;
+            entry.1 += outage.duration_secs.unwrap_or(0.0);
         }
+    }
+
+    let mut hop_list: Vec<_> = hop_stats
+        .into_iter()
+        .map(|(hop, (count, total_time))| (hop, count, total_time))
+        .collect();
+    hop_list.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.0.cmp(&b.0)));
+    hop_list
+}
+
+/// Outages by the 6-hour block they started in: 00-06, 06-12, 12-18, 18-24
+fn time_distribution(outages: &[Outage], display: &DisplayConfig) -> Vec<ChartBucket> {
+    let mut blocks: Vec<ChartBucket> = TIME_BLOCK_NAMES
+        .iter()
+        .map(|name| ChartBucket {
+            label: name.to_string(),
+            outages: 0,
+            downtime_secs: 0.0,
+        })
+        .collect();
+
+    for outage in outages {
+        // Bucket by the hour as displayed, not the stored UTC hour
+        let hour = display.timezone.hour(outage.start_time);
+        let block = &mut blocks[(hour / 6) as usize];
+        block.outages += 1;
+        block.downtime_secs += outage.duration_secs.unwrap_or(0.0);
+    }
+    blocks
+}
 
-        let max_count = *time_blocks.iter().max().unwrap_or(&1);
-        let block_names = ["00:00-06:00", "06:00-12:00", "12:00-18:00", "18:00-24:00"];
+/// ASCII bars for the time distribution
+fn time_distribution_lines(blocks: &[ChartBucket]) -> Vec<String> {
+    let max_count = blocks.iter().map(|b| b.outages).max().unwrap_or(1);
 
-        for (name, count) in block_names.iter().zip(time_blocks.iter()) {
+    blocks
+        .iter()
+        .map(|block| {
             let bar_width = if max_count > 0 {
-                (*count as f64 / max_count as f64 * 12.0).round() as usize
+                (block.outages as f64 / max_count as f64 * 12.0).round() as usize
             } else {
                 0
             };
-            println!(
+            format!(
                 "  {}  {}  {} outage{}",
-                name,
+                block.label,
                 "█".repeat(bar_width) + &"░".repeat(12 - bar_width),
-                count,
-                if *count == 1 { "" } else { "s" }
-            );
+                block.outages,
+                if block.outages == 1 { "" } else { "s" }
+            )
+        })
+        .collect()
+}
+
+/// Outages and downtime per day they started on (display time)
+fn daily_downtime(outages: &[Outage], display: &DisplayConfig) -> Vec<ChartBucket> {
+    let mut days: BTreeMap<String, (u32, f64)> = BTreeMap::new();
+    for outage in outages {
+        let day = display.format_time(outage.start_time, "%Y-%m-%d");
+        let entry = days.entry(day).or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 += outage.duration_secs.unwrap_or(0.0);
+    }
+
+    days.into_iter()
+        .map(|(label, (outages, downtime_secs))| ChartBucket {
+            label,
+            outages,
+            downtime_secs,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DisplayTimezone;
+    use chrono::TimeZone;
+
+    fn outage(start: DateTime<Utc>, secs: f64, hop: Option<u8>) -> Outage {
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.start_time = start;
+        outage.end_at(start + chrono::Duration::seconds(secs as i64));
+        outage.failing_hop = hop;
+        outage
+    }
+
+    #[test]
+    fn test_chart_data_matches_ascii_chart() {
+        let display = DisplayConfig {
+            timezone: DisplayTimezone::Utc,
+            ..DisplayConfig::default()
+        };
+        let outages = vec![
+            outage(
+                Utc.with_ymd_and_hms(2026, 10, 13, 3, 0, 0).unwrap(),
+                60.0,
+                Some(3),
+            ),
+            outage(
+                Utc.with_ymd_and_hms(2026, 10, 13, 14, 0, 0).unwrap(),
+                120.0,
+                Some(1),
+            ),
+            outage(
+                Utc.with_ymd_and_hms(2026, 10, 14, 15, 0, 0).unwrap(),
+                30.0,
+                Some(3),
+            ),
+        ];
+
+        let data = ChartData::from_outages(&outages, &display);
+        let ascii = time_distribution_lines(&time_distribution(&outages, &display));
+        for (bucket, line) in data.time_blocks.iter().zip(&ascii) {
+            assert!(line.starts_with(&format!("  {}", bucket.label)));
+            assert!(line.contains(&format!("  {} outage", bucket.outages)));
         }
+        let counts: Vec<u32> = data.time_blocks.iter().map(|b| b.outages).collect();
+        assert_eq!(counts, vec![1, 0, 2, 0]);
+
+        assert_eq!(
+            data.daily_downtime,
+            vec![
+                ChartBucket {
+                    label: "2026-10-13".to_string(),
+                    outages: 2,
+                    downtime_secs: 180.0
+                },
+                ChartBucket {
+                    label: "2026-10-14".to_string(),
+                    outages: 1,
+                    downtime_secs: 30.0
+                },
+            ]
+        );
+        let hops: Vec<(&str, u32)> = data
+            .failing_hops
+            .iter()
+            .map(|b| (b.label.as_str(), b.outages))
+            .collect();
+        assert_eq!(hops, vec![("hop 1", 1), ("hop 3", 2)]);
+
+        let csv = data.to_csv();
+        assert!(csv.starts_with("series,label,outages,downtime_secs\n"));
+        assert!(csv.contains("time_block,12:00-18:00,2,150.0\n"));
+        assert!(csv.contains("day,2026-10-14,1,30.0\n"));
     }
 
-    Ok(())
+    #[test]
+    fn test_stats_report_json() {
+        let display = DisplayConfig {
+            timezone: DisplayTimezone::Utc,
+            ..DisplayConfig::default()
+        };
+        let outages = vec![
+            outage(
+                Utc.with_ymd_and_hms(2026, 10, 13, 3, 0, 0).unwrap(),
+                60.0,
+                Some(3),
+            ),
+            outage(
+                Utc.with_ymd_and_hms(2026, 10, 13, 14, 0, 0).unwrap(),
+                120.0,
+                Some(1),
+            ),
+            outage(
+                Utc.with_ymd_and_hms(2026, 10, 14, 15, 0, 0).unwrap(),
+                30.0,
+                None,
+            ),
+        ];
+        let since = Utc.with_ymd_and_hms(2026, 10, 8, 0, 0, 0).unwrap();
+        let until = Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap();
+        let stats = Stats::from_outages(&outages, since, until);
+
+        let report = StatsReport::from_outages("7d", &stats, &outages, &display);
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["outages"]["total"], 3);
+        assert_eq!(json["outages"]["longest_secs"], 120.0);
+        assert_eq!(
+            json["failing_hops"],
+            serde_json::json!([
+                {"hop": 1, "location": "Gateway/Router", "outages": 1, "downtime_secs": 120.0},
+                {"hop": 3, "location": "ISP Backbone", "outages": 1, "downtime_secs": 60.0},
+            ])
+        );
+        let blocks: Vec<(&str, u64)> = json["time_distribution"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| (b["label"].as_str().unwrap(), b["outages"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(
+            blocks,
+            vec![
+                ("00:00-06:00", 1),
+                ("06:00-12:00", 0),
+                ("12:00-18:00", 2),
+                ("18:00-24:00", 0)
+            ]
+        );
+        assert!(json["latency"]["avg_jitter_ms"].is_null());
+    }
 }
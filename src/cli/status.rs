@@ -1,30 +1,243 @@
-use crate::cli::helpers::{format_duration_secs, progress_bar};
-use crate::monitor::PingMonitor;
+use crate::cli::helpers::{
+    format_duration_secs, format_latency, parse_duration, progress_bar, resolve_targets,
+};
+use crate::config::PingBackendKind;
+use crate::models::{ConnectivityState, PingErrorKind, PingResult, PingStats, Target};
+use crate::monitor::{describe_ip, LiveSnapshot, PingCapability, PingMonitor, Streak};
 use crate::App;
 use chrono::{Duration, Utc};
 
-pub async fn run(app: &App) -> Result<(), Box<dyn std::error::Error>> {
+/// Live state older than this is from a monitor that is no longer running
+pub const LIVE_MAX_AGE_SECS: i64 = 30;
+
+/// Describe a target's current run, e.g. "✓ 1240 in a row"
+pub fn render_streak(streak: Streak) -> String {
+    match streak {
+        Streak::Successes(n) => format!("✓ {} in a row", n),
+        Streak::Failures(1) => "✗ 1 consecutive failure".to_string(),
+        Streak::Failures(n) => format!("✗ {} consecutive failures", n),
+        Streak::None => "· no results yet".to_string(),
+    }
+}
+
+/// Rolling stats line shown under a target, e.g.
+/// "1h: min 9.8ms / avg 14.2ms / max 41.0ms / p95 30.1ms, variation 2.3ms, 0.5% loss (200 pings)".
+/// Without stored losses it ends in "(199 replies)" instead.
+pub fn render_ping_stats(stats: &PingStats, window: &str, precision: usize) -> Option<String> {
+    if stats.samples == 0 {
+        return None;
+    }
+    let latency = |ms: Option<f64>| {
+        ms.map(|ms| format_latency(ms, precision))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let variation = stats
+        .variation_ms
+        .map(|ms| format!(", variation {}", format_latency(ms, precision)))
+        .unwrap_or_default();
+    let plural = if stats.samples == 1 { "" } else { "s" };
+    let count = match stats.loss_percent {
+        Some(loss) => format!(", {:.1}% loss ({} ping{})", loss, stats.samples, plural),
+        None => format!(
+            " ({} repl{})",
+            stats.samples,
+            if stats.samples == 1 { "y" } else { "ies" }
+        ),
+    };
+    Some(format!(
+        "{}: min {} / avg {} / max {} / p95 {}{}{}",
+        window,
+        latency(stats.min_ms),
+        latency(stats.avg_ms),
+        latency(stats.max_ms),
+        latency(stats.p95_ms),
+        variation,
+        count
+    ))
+}
+
+/// Status mark and summary for a target's probes, e.g. ("✓", "3/3 ok, avg 18ms"),
+/// ("~", "2/3 ok, 1/3 loss, avg 20ms") or ("✗", "3/3 loss"). A single probe
+/// reads just "18ms" or "timeout".
+pub fn render_probes(results: &[PingResult], precision: usize) -> (&'static str, String) {
+    let total = results.len();
+    let latencies: Vec<f64> = results
+        .iter()
+        .filter(|r| r.success)
+        .filter_map(|r| r.latency_ms)
+        .collect();
+    let ok = results.iter().filter(|r| r.success).count();
+    let avg = (!latencies.is_empty()).then(|| {
+        format_latency(
+            latencies.iter().sum::<f64>() / latencies.len() as f64,
+            precision,
+        )
+    });
+
+    // Why the last probe failed; timeouts are the unremarkable case
+    let reason = results
+        .iter()
+        .rev()
+        .find(|r| !r.success)
+        .map(|r| match r.error_kind {
+            Some(PingErrorKind::Other) | None => {
+                r.error.clone().unwrap_or_else(|| "timeout".to_string())
+            }
+            Some(kind) => kind.to_string(),
+        })
+        .filter(|reason| reason != "timeout");
+
+    let status = match ok {
+        0 => "✗",
+        n if n == total => "✓",
+        _ => "~",
+    };
+    let summary = match (total, ok, avg) {
+        (1, _, Some(avg)) => avg,
+        (1, _, None) => reason.unwrap_or_else(|| "timeout".to_string()),
+        (_, 0, _) => match reason {
+            Some(reason) => format!("{}/{} loss ({})", total, total, reason),
+            None => format!("{}/{} loss", total, total),
+        },
+        (_, ok, avg) => {
+            let mut parts = vec![format!("{}/{} ok", ok, total)];
+            if ok < total {
+                parts.push(format!("{}/{} loss", total - ok, total));
+            }
+            if let Some(avg) = avg {
+                parts.push(format!("avg {}", avg));
+            }
+            parts.join(", ")
+        }
+    };
+    (status, summary)
+}
+
+/// `probe_count` pings per target when there is no running monitor to ask
+pub async fn run(
+    app: &App,
+    brief: bool,
+    target: Option<&str>,
+    window: &str,
+    probe_count: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let window_duration = parse_duration(window).map_err(|e| format!("Invalid window: {}", e))?;
+
+    let targets = match target {
+        Some(pattern) => {
+            let matches = resolve_targets(pattern, &app.config.all_targets());
+            if matches.is_empty() {
+                return Err(format!("No targets match '{}'", pattern).into());
+            }
+            matches
+        }
+        None => app.config.all_targets(),
+    };
+
+    if brief {
+        println!("{}", render_brief(&brief_status(app, &targets).await?));
+        return Ok(());
+    }
+
     println!("Network Monitor Status");
     println!("═══════════════════════════════════════════════════════════\n");
 
-    // Check current connectivity by pinging targets
-    let targets = app.config.all_targets();
-    let monitor = PingMonitor::new(&app.config);
+    let now = Utc::now();
+    // Hidden unless every ping (or every reply) is stored; change-only rows would mislead
+    let rolling = |ip: &str| -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let stats = app.db.get_ping_stats(
+            ip,
+            now - window_duration,
+            now,
+            app.config.monitor.log_all_pings,
+        )?;
+        Ok(stats.and_then(|stats| {
+            render_ping_stats(&stats, window, app.config.display.latency_precision)
+        }))
+    };
 
-    println!("Target Health:");
-    for target in &targets {
-        let result = monitor.ping(target).await;
-        let status = if result.success { "✓" } else { "✗" };
-        let latency = result
-            .latency_ms
-            .map(|l| format!("{:.1}ms", l))
-            .unwrap_or_else(|| "timeout".to_string());
+    // Prefer the running monitor's streaks; otherwise ping each target
+    let live = match app.live_state_path() {
+        Ok(path) => {
+            LiveSnapshot::read_fresh(&path, Utc::now(), Duration::seconds(LIVE_MAX_AGE_SECS))
+        }
+        Err(_) => None,
+    };
+
+    if let Some(live) = live {
+        println!("Target Health (live, state {}):", live.state);
+        for snapshot in live
+            .targets
+            .iter()
+            .filter(|s| targets.iter().any(|t| t.key() == s.key))
+        {
+            let latency = snapshot
+                .latency_ms
+                .map(|l| {
+                    format!(
+                        " - {}",
+                        format_latency(l, app.config.display.latency_precision)
+                    )
+                })
+                .unwrap_or_default();
+            let quarantined = if snapshot.quarantined {
+                " (quarantined)"
+            } else {
+                ""
+            };
+            println!(
+                "  {} ({}) {}{}{}",
+                snapshot.name,
+                snapshot.ip,
+                render_streak(snapshot.streak),
+                latency,
+                quarantined
+            );
+            if let Some(line) = rolling(&snapshot.key)? {
+                println!("    {}", line);
+            }
+        }
+    } else {
+        let monitor = PingMonitor::new(&app.config);
 
-        println!("  {} {} ({}) - {}", status, target.name, target.ip, latency);
+        println!("Target Health:");
+        for target in &targets {
+            let mut results = Vec::with_capacity(probe_count as usize);
+            for _ in 0..probe_count.max(1) {
+                results.push(monitor.ping(target).await);
+            }
+            let (status, summary) = render_probes(&results, app.config.display.latency_precision);
+
+            println!("  {} {} ({}) - {}", status, target.name, target.ip, summary);
+            if let Some(line) = rolling(&target.key())? {
+                println!("    {}", line);
+            }
+        }
+    }
+
+    let capability = PingCapability::probe();
+    println!(
+        "\nPing method: {} ({})",
+        capability.ping_method(app.config.monitor.ping_backend == PingBackendKind::Native),
+        capability
+    );
+
+    // Monitor uptime
+    let sessions = app.db.get_session_summary(now)?;
+    match sessions.current_start {
+        Some(start) => println!(
+            "\nMonitoring since {} (uptime {}), total observed {}",
+            app.config.display.format_time(start, "%H:%M"),
+            format_duration_secs((now - start).num_seconds() as f64),
+            format_duration_secs(sessions.total_observed_secs)
+        ),
+        None => println!(
+            "\nMonitor not running (total observed {})",
+            format_duration_secs(sessions.total_observed_secs)
+        ),
     }
 
     // Get today's statistics
-    let now = Utc::now();
     let today_start = now - Duration::hours(24);
     let stats = app.db.get_stats(today_start, now)?;
 
@@ -47,20 +260,345 @@ pub async fn run(app: &App) -> Result<(), Box<dyn std::error::Error>> {
         println!("  Avg outage duration: {}", format_duration_secs(avg));
     }
 
+    if let Some(jitter) = app.db.get_average_jitter(today_start, now)? {
+        println!(
+            "  Avg jitter: {}",
+            format_latency(jitter, app.config.display.latency_precision)
+        );
+    }
+
     // Check for ongoing outage
     if let Some(outage) = app.db.get_ongoing_outage()? {
         let duration = (Utc::now() - outage.start_time).num_seconds() as f64;
         println!("\n⚠️  ONGOING OUTAGE:");
         println!(
             "  Started: {}",
-            outage.start_time.format("%Y-%m-%d %H:%M:%S")
+            app.config
+                .display
+                .format_time(outage.start_time, "%Y-%m-%d %H:%M:%S")
         );
         println!("  Duration: {}", format_duration_secs(duration));
         if let Some(hop) = outage.failing_hop {
-            let hop_ip = outage.failing_hop_ip.as_deref().unwrap_or("unknown");
-            println!("  Failing hop: {} ({})", hop, hop_ip);
+            let hop_ip = outage
+                .failing_hop_ip
+                .as_deref()
+                .map(|ip| describe_ip(ip, app.config.display.reverse_dns))
+                .unwrap_or_else(|| "unknown".to_string());
+            println!("  Failing hop: {} — {}", hop, hop_ip);
         }
     }
 
     Ok(())
 }
+
+/// Iteration control for `status --refresh`: runs forever without a count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefreshCount {
+    remaining: Option<u32>,
+}
+
+impl RefreshCount {
+    /// `count` is the total number of status blocks to print
+    pub fn new(count: Option<u32>) -> Self {
+        Self {
+            remaining: count.map(|n| n.saturating_sub(1)),
+        }
+    }
+
+    /// Called after each block; whether another one should follow
+    pub fn another(&mut self) -> bool {
+        match &mut self.remaining {
+            None => true,
+            Some(0) => false,
+            Some(n) => {
+                *n -= 1;
+                true
+            }
+        }
+    }
+}
+
+/// Reprint the status every `every`, `count` times (until interrupted without one)
+pub async fn run_refreshing(
+    app: &App,
+    brief: bool,
+    target: Option<&str>,
+    window: &str,
+    probe_count: u32,
+    every: std::time::Duration,
+    count: Option<u32>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut iterations = RefreshCount::new(count);
+    loop {
+        if !brief {
+            println!(
+                "── {} ──",
+                app.config
+                    .display
+                    .format_time(Utc::now(), "%Y-%m-%d %H:%M:%S")
+            );
+        }
+        run(app, brief, target, window, probe_count).await?;
+
+        if !iterations.another() {
+            return Ok(());
+        }
+        if !brief {
+            println!();
+        }
+        tokio::time::sleep(every).await;
+    }
+}
+
+/// Connectivity summary shown by `status --brief`
+#[derive(Debug, Clone, PartialEq)]
+pub enum BriefStatus {
+    Online { latency_ms: Option<f64> },
+    Degraded,
+    Offline { duration_secs: f64 },
+    Unknown,
+}
+
+/// Determine the brief status cheaply: trust the running daemon's ongoing outage
+/// and live state, otherwise send a single ping to the first target
+async fn brief_status(
+    app: &App,
+    targets: &[Target],
+) -> Result<BriefStatus, Box<dyn std::error::Error>> {
+    let now = Utc::now();
+    let daemon_running = app.db.get_session_summary(now)?.current_start.is_some();
+
+    if daemon_running {
+        if let Some(outage) = app.db.get_ongoing_outage()? {
+            return Ok(BriefStatus::Offline {
+                duration_secs: (now - outage.start_time).num_seconds() as f64,
+            });
+        }
+        let live = app.live_state_path().ok().and_then(|path| {
+            LiveSnapshot::read_fresh(&path, now, Duration::seconds(LIVE_MAX_AGE_SECS))
+        });
+        if let Some(live) = live {
+            return Ok(brief_from_live(&live, targets));
+        }
+    }
+
+    let Some(target) = targets.first() else {
+        return Ok(BriefStatus::Unknown);
+    };
+
+    // One lost ping says nothing about the connection as a whole
+    let result = PingMonitor::new(&app.config).ping(target).await;
+    Ok(if result.success {
+        BriefStatus::Online {
+            latency_ms: result.latency_ms,
+        }
+    } else {
+        BriefStatus::Unknown
+    })
+}
+
+/// The running monitor's state, with the latest latency among `targets`
+fn brief_from_live(live: &LiveSnapshot, targets: &[Target]) -> BriefStatus {
+    match live.state {
+        ConnectivityState::Online => BriefStatus::Online {
+            latency_ms: live
+                .targets
+                .iter()
+                .filter(|s| targets.iter().any(|t| t.key() == s.key))
+                .find_map(|s| s.latency_ms),
+        },
+        ConnectivityState::Degraded => BriefStatus::Degraded,
+        // Only until the new outage is saved, which gives its start
+        ConnectivityState::Offline => BriefStatus::Offline { duration_secs: 0.0 },
+        ConnectivityState::Unknown => BriefStatus::Unknown,
+    }
+}
+
+/// Render a brief status as a single line, e.g. "ONLINE 18ms" or "OFFLINE 2m"
+pub fn render_brief(status: &BriefStatus) -> String {
+    match status {
+        BriefStatus::Online {
+            latency_ms: Some(ms),
+        } => format!("ONLINE {}", format_latency(*ms, 0)),
+        BriefStatus::Online { latency_ms: None } => "ONLINE".to_string(),
+        BriefStatus::Degraded => "DEGRADED".to_string(),
+        BriefStatus::Offline { duration_secs } => {
+            format!("OFFLINE {}", compact_duration(*duration_secs))
+        }
+        BriefStatus::Unknown => "UNKNOWN".to_string(),
+    }
+}
+
+/// Largest whole unit only: "45s", "2m", "3h", "1d"
+fn compact_duration(secs: f64) -> String {
+    let secs = secs.max(0.0) as u64;
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_count() {
+        let mut three = RefreshCount::new(Some(3));
+        assert!(three.another());
+        assert!(three.another());
+        assert!(!three.another());
+        assert!(!three.another());
+
+        // A single block exits straight away
+        assert!(!RefreshCount::new(Some(1)).another());
+
+        let mut forever = RefreshCount::new(None);
+        assert!((0..1000).all(|_| forever.another()));
+    }
+
+    #[test]
+    fn test_render_probes() {
+        let probe = |latency_ms: Option<f64>| PingResult {
+            target: "8.8.8.8".to_string(),
+            target_name: "Google DNS".to_string(),
+            timestamp: Utc::now(),
+            success: latency_ms.is_some(),
+            latency_ms,
+            jitter_ms: None,
+            loss: None,
+            error: latency_ms.is_none().then(|| "Request timeout".to_string()),
+            error_kind: latency_ms.is_none().then_some(PingErrorKind::Timeout),
+            resolved_ip: None,
+        };
+
+        let all_ok = [probe(Some(16.0)), probe(Some(18.0)), probe(Some(20.0))];
+        assert_eq!(
+            render_probes(&all_ok, 0),
+            ("✓", "3/3 ok, avg 18ms".to_string())
+        );
+
+        let partial = [probe(Some(18.0)), probe(None), probe(Some(22.0))];
+        assert_eq!(
+            render_probes(&partial, 0),
+            ("~", "2/3 ok, 1/3 loss, avg 20ms".to_string())
+        );
+
+        let none = [probe(None), probe(None), probe(None)];
+        assert_eq!(render_probes(&none, 0), ("✗", "3/3 loss".to_string()));
+
+        // A single probe keeps the plain latency / timeout display
+        assert_eq!(
+            render_probes(&[probe(Some(18.4))], 0),
+            ("✓", "18ms".to_string())
+        );
+        assert_eq!(
+            render_probes(&[probe(None)], 0),
+            ("✗", "timeout".to_string())
+        );
+
+        // Other failures say what went wrong
+        let unresolved = PingResult {
+            error: Some("DNS resolution failed".to_string()),
+            error_kind: Some(PingErrorKind::DnsFailure),
+            ..probe(None)
+        };
+        assert_eq!(
+            render_probes(std::slice::from_ref(&unresolved), 0),
+            ("✗", "DNS failure".to_string())
+        );
+        assert_eq!(
+            render_probes(&[unresolved.clone(), unresolved], 0),
+            ("✗", "2/2 loss (DNS failure)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_render_streak() {
+        assert_eq!(render_streak(Streak::Successes(1240)), "✓ 1240 in a row");
+        assert_eq!(
+            render_streak(Streak::Failures(8)),
+            "✗ 8 consecutive failures"
+        );
+        assert_eq!(
+            render_streak(Streak::Failures(1)),
+            "✗ 1 consecutive failure"
+        );
+        assert_eq!(render_streak(Streak::None), "· no results yet");
+    }
+
+    #[test]
+    fn test_render_brief_is_single_line() {
+        let cases = [
+            (
+                BriefStatus::Online {
+                    latency_ms: Some(18.4),
+                },
+                "ONLINE 18ms",
+            ),
+            (BriefStatus::Online { latency_ms: None }, "ONLINE"),
+            (BriefStatus::Degraded, "DEGRADED"),
+            (
+                BriefStatus::Offline {
+                    duration_secs: 125.0,
+                },
+                "OFFLINE 2m",
+            ),
+            (BriefStatus::Offline { duration_secs: 7.0 }, "OFFLINE 7s"),
+            (BriefStatus::Unknown, "UNKNOWN"),
+        ];
+
+        for (status, expected) in cases {
+            let line = render_brief(&status);
+            assert_eq!(line, expected);
+            assert!(!line.contains('\n'));
+        }
+    }
+
+    #[test]
+    fn test_brief_from_live() {
+        use crate::monitor::TargetSnapshot;
+
+        let targets = vec![
+            Target::new("Google DNS", "8.8.8.8"),
+            Target::new("Cloudflare", "1.1.1.1"),
+        ];
+        let snapshot = |ip: &str, latency_ms: Option<f64>| TargetSnapshot {
+            name: ip.to_string(),
+            ip: ip.to_string(),
+            key: ip.to_string(),
+            streak: Streak::Successes(3),
+            latency_ms,
+            quarantined: false,
+        };
+        let mut live = LiveSnapshot {
+            updated_at: Utc::now(),
+            state: ConnectivityState::Online,
+            targets: vec![
+                snapshot("9.9.9.9", Some(5.0)),
+                snapshot("8.8.8.8", None),
+                snapshot("1.1.1.1", Some(12.0)),
+            ],
+        };
+        assert_eq!(
+            brief_from_live(&live, &targets),
+            BriefStatus::Online {
+                latency_ms: Some(12.0)
+            }
+        );
+
+        live.state = ConnectivityState::Degraded;
+        assert_eq!(brief_from_live(&live, &targets), BriefStatus::Degraded);
+        live.state = ConnectivityState::Unknown;
+        assert_eq!(brief_from_live(&live, &targets), BriefStatus::Unknown);
+    }
+
+    #[test]
+    fn test_compact_duration() {
+        assert_eq!(compact_duration(0.0), "0s");
+        assert_eq!(compact_duration(3600.0), "1h");
+        assert_eq!(compact_duration(90000.0), "1d");
+    }
+}
@@ -0,0 +1,114 @@
+use crate::cli::helpers::{format_duration_secs, parse_duration};
+use crate::config::{DisplayConfig, DisplayTimezone};
+use crate::models::{Rollup, TrendGranularity};
+use crate::App;
+use chrono::{Local, Utc};
+
+/// Width of the bar chart in characters
+const BAR_WIDTH: usize = 12;
+
+pub fn run(app: &App, period: &str, by: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let duration = parse_duration(period).map_err(|e| format!("Invalid duration: {}", e))?;
+    let granularity: TrendGranularity = by.parse()?;
+    let until = Utc::now();
+    let since = until - duration;
+
+    // Days and weeks start at midnight where the user reads them
+    let rollups = match app.config.display.timezone {
+        DisplayTimezone::Local => app
+            .db
+            .get_outage_trends(since, until, granularity, &Local)?,
+        DisplayTimezone::Utc => app.db.get_outage_trends(since, until, granularity, &Utc)?,
+        DisplayTimezone::Named(tz) => app.db.get_outage_trends(since, until, granularity, &tz)?,
+    };
+
+    println!("Outage Trends (last {}, by {})", period, by);
+    println!("═══════════════════════════════════════════════════════════\n");
+
+    if rollups.iter().all(|r| r.outages == 0) {
+        println!("No outages recorded in this period.");
+        return Ok(());
+    }
+
+    let max_count = rollups.iter().map(|r| r.outages).max().unwrap_or(0);
+    for rollup in &rollups {
+        println!(
+            "  {}",
+            render_row(rollup, granularity, max_count, &app.config.display)
+        );
+    }
+
+    Ok(())
+}
+
+fn bucket_label(rollup: &Rollup, granularity: TrendGranularity, display: &DisplayConfig) -> String {
+    match granularity {
+        TrendGranularity::Day => display.format_time(rollup.bucket_start, "%a %Y-%m-%d"),
+        TrendGranularity::Week => display.format_time(rollup.bucket_start, "Wk %Y-%m-%d"),
+    }
+}
+
+fn render_row(
+    rollup: &Rollup,
+    granularity: TrendGranularity,
+    max_count: u32,
+    display: &DisplayConfig,
+) -> String {
+    let bar_width = if max_count > 0 {
+        (rollup.outages as f64 / max_count as f64 * BAR_WIDTH as f64).round() as usize
+    } else {
+        0
+    };
+
+    let mut row = format!(
+        "{}  {}  {} outage{}",
+        bucket_label(rollup, granularity, display),
+        "█".repeat(bar_width) + &"░".repeat(BAR_WIDTH - bar_width),
+        rollup.outages,
+        if rollup.outages == 1 { "" } else { "s" }
+    );
+    if rollup.downtime_secs > 0.0 {
+        row.push_str(&format!(
+            " ({})",
+            format_duration_secs(rollup.downtime_secs)
+        ));
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_row() {
+        let display = DisplayConfig {
+            timezone: DisplayTimezone::Utc,
+            ..DisplayConfig::default()
+        };
+        let rollup = Rollup {
+            bucket_start: "2024-01-19T00:00:00Z".parse().unwrap(),
+            outages: 2,
+            downtime_secs: 0.0,
+        };
+        assert_eq!(
+            render_row(&rollup, TrendGranularity::Day, 4, &display),
+            "Fri 2024-01-19  ██████░░░░░░  2 outages"
+        );
+        assert!(render_row(&rollup, TrendGranularity::Week, 2, &display)
+            .starts_with("Wk 2024-01-19  ████████████"));
+
+        // A bucket starting at midnight in Los Angeles is labelled with that day
+        let display = DisplayConfig {
+            timezone: "America/Los_Angeles".parse().unwrap(),
+            ..DisplayConfig::default()
+        };
+        let rollup = Rollup {
+            bucket_start: "2024-01-19T08:00:00Z".parse().unwrap(),
+            ..rollup
+        };
+        assert!(
+            render_row(&rollup, TrendGranularity::Day, 2, &display).starts_with("Fri 2024-01-19")
+        );
+    }
+}
@@ -1,8 +1,10 @@
-use crate::models::Target;
+use crate::models::{CheckKind, Target};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, Timelike, Utc, Weekday};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 /// Runtime environment for Vigil
@@ -17,10 +19,12 @@ pub enum Environment {
 impl Environment {
     /// Determine environment from VIGIL_ENV variable
     pub fn from_env() -> Self {
-        match std::env::var("VIGIL_ENV").as_deref() {
-            Ok("development") | Ok("dev") => Environment::Development,
-            Ok("test") => Environment::Test,
-            _ => Environment::Production,
+        match std::env::var("VIGIL_ENV") {
+            Ok(value) => value.parse().unwrap_or_else(|e| {
+                tracing::warn!("{} - using production", e);
+                Environment::Production
+            }),
+            Err(_) => Environment::Production,
         }
     }
 
@@ -59,6 +63,19 @@ impl Environment {
     }
 }
 
+impl std::str::FromStr for Environment {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "production" | "prod" => Ok(Environment::Production),
+            "development" | "dev" => Ok(Environment::Development),
+            "test" => Ok(Environment::Test),
+            _ => Err(ConfigError::UnknownEnvironment(s.to_string())),
+        }
+    }
+}
+
 impl fmt::Display for Environment {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -79,6 +96,16 @@ pub enum ConfigError {
     SerializeError(#[from] toml::ser::Error),
     #[error("Could not determine config directory")]
     NoConfigDir,
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+    #[error("Unknown environment \"{0}\" (expected production, development or test)")]
+    UnknownEnvironment(String),
+    #[error("Unknown config key: {0}")]
+    UnknownKey(String),
+    #[error("Invalid value for {key}: {reason}")]
+    InvalidValue { key: String, reason: String },
+    #[error("No last known good config at {}", .0.display())]
+    NoBackup(PathBuf),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +114,11 @@ pub struct MonitorConfig {
     #[serde(default = "default_ping_interval")]
     pub ping_interval_ms: u64,
 
+    /// Start each sweep up to this many milliseconds early or late, at random,
+    /// so instances that share targets don't ping them in lockstep (0 = off)
+    #[serde(default)]
+    pub interval_jitter_ms: u64,
+
     /// Ping timeout in milliseconds
     #[serde(default = "default_ping_timeout")]
     pub ping_timeout_ms: u64,
@@ -99,23 +131,260 @@ pub struct MonitorConfig {
     #[serde(default = "default_offline_threshold")]
     pub offline_threshold: u32,
 
+    /// Count failures within the last this many samples instead of in a row,
+    /// so that e.g. 50% loss still trips the thresholds above (at least
+    /// `offline_threshold`). Unset: consecutive failures.
+    #[serde(default)]
+    pub window_samples: Option<u32>,
+
     /// Consecutive successes to recover to ONLINE
     #[serde(default = "default_recovery_threshold")]
     pub recovery_threshold: u32,
+
+    /// Seconds of continued success after the recovery threshold before an
+    /// outage is closed (0 closes it immediately)
+    #[serde(default)]
+    pub recovery_grace_secs: u64,
+
+    /// Minimum seconds spent in DEGRADED or OFFLINE before recovering, even
+    /// once the recovery threshold is met (0 = no hold-down)
+    #[serde(default)]
+    pub min_state_duration_secs: u64,
+
+    /// How many targets must be failing at once before a sample counts as a
+    /// failure: "any", "all", or a number (capped at the number of targets).
+    /// Fewer failing targets count as healthy.
+    #[serde(default)]
+    pub quorum: Quorum,
+
+    /// Don't count a hostname target failing to resolve as a connectivity
+    /// failure (IP targets never resolve, so they're unaffected)
+    #[serde(default)]
+    pub ignore_dns_failures: bool,
+
+    /// Enter DEGRADED when a target's round trips vary by more than this many
+    /// ms on average over its last 10 replies, even though none are lost
+    #[serde(default)]
+    pub jitter_threshold_ms: Option<f64>,
+
+    /// Leave a target out of the state after this many failed samples in a row
+    /// while other targets still reply, until it replies again (for a
+    /// decommissioned or mistyped target). Unset: never.
+    #[serde(default)]
+    pub quarantine_after: Option<u32>,
+
+    /// Seconds a hostname target's resolved address is reused (0 = resolve
+    /// before every check)
+    #[serde(default = "default_dns_cache_ttl_secs")]
+    pub dns_cache_ttl_secs: u64,
+
+    /// Distinct targets that must be failing at once to enter DEGRADED
+    /// (capped at the number of targets)
+    #[serde(default = "default_degraded_min_targets")]
+    pub degraded_min_targets: usize,
+
+    /// Distinct targets that must be failing at once to go OFFLINE; fewer is
+    /// a target-specific problem and stays DEGRADED (capped at the number of targets)
+    #[serde(default = "default_offline_min_targets")]
+    pub offline_min_targets: usize,
+
+    /// Well-known targets that must also fail before an outage is declared
+    #[serde(default)]
+    pub canary_targets: Vec<Target>,
+
+    /// Run traceroutes when an outage starts (disable where traceroute is unavailable)
+    #[serde(default = "default_traceroute_enabled")]
+    pub traceroute_enabled: bool,
+
+    /// Maximum traceroutes run when an outage starts (one per failing target)
+    #[serde(default = "default_max_outage_traces")]
+    pub max_outage_traces: usize,
+
+    /// Traced first when an outage starts, ahead of the failing targets (which
+    /// may begin with the gateway). Set to "" for the first public monitored
+    /// target instead (leaving it out means the 8.8.8.8 default).
+    #[serde(default = "default_trace_target")]
+    pub trace_target: Option<String>,
+
+    /// Trace the failing targets again every this many seconds while OFFLINE.
+    /// Unset: only at the start of an outage.
+    #[serde(default)]
+    pub trace_interval_secs: Option<u64>,
+
+    /// Log a "still offline" heartbeat every this many seconds while OFFLINE;
+    /// notifications repeat it at most every 15 minutes. Unset: no heartbeat.
+    #[serde(default)]
+    pub ongoing_outage_heartbeat_secs: Option<u64>,
+
+    /// A trace whose last hop is in the same IPv4 /N as the target still counts
+    /// as reaching it (anycast answers from a neighbouring address). 32 = exact.
+    #[serde(default = "default_traceroute_match_prefix")]
+    pub traceroute_match_prefix: u8,
+
+    /// Ping all targets at once ("concurrent") or one after another ("sequential")
+    #[serde(default)]
+    pub ping_mode: PingMode,
+
+    /// Send pings with the system binary ("system") or from this process ("native")
+    #[serde(default)]
+    pub ping_backend: PingBackendKind,
+
+    /// Echo requests per sample; with more than one, partial loss is measurable
+    #[serde(default = "default_ping_count")]
+    pub ping_count: u32,
+
+    /// Times a failed check is retried straight away before it counts as lost,
+    /// so one dropped packet doesn't fail a sample at long intervals
+    #[serde(default)]
+    pub ping_retries: u32,
+
+    /// Packet loss fraction (0.0-1.0) above which a sample counts as a failure
+    /// even though some replies arrived. Unset: only total loss fails.
+    #[serde(default)]
+    pub max_acceptable_loss: Option<f64>,
+
+    /// Serve Prometheus metrics on this address (e.g. "127.0.0.1:9184") while
+    /// monitoring. Unset: no metrics server.
+    #[serde(default)]
+    pub metrics_addr: Option<String>,
+
+    /// Store every ping result rather than only those where a target's
+    /// status or rounded latency changed
+    #[serde(default)]
+    pub log_all_pings: bool,
+}
+
+/// How each ping is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PingBackendKind {
+    #[default]
+    System,
+    /// In-process ICMP echo; falls back to system when ICMP sockets are unavailable
+    Native,
+}
+
+/// How a sweep over all targets is issued
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PingMode {
+    #[default]
+    Concurrent,
+    /// Avoids bursts on constrained devices; each ping waits for the previous one
+    Sequential,
+}
+
+/// How many failing targets make a failing sample (`monitor.quorum`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "QuorumValue", into = "QuorumValue")]
+pub enum Quorum {
+    #[default]
+    Any,
+    All,
+    /// At least this many (never 0)
+    Count(usize),
+}
+
+impl Quorum {
+    /// Failing targets needed out of `targets` monitored
+    pub fn required(self, targets: usize) -> usize {
+        let targets = targets.max(1);
+        match self {
+            Quorum::Any => 1,
+            Quorum::All => targets,
+            Quorum::Count(n) => n.clamp(1, targets),
+        }
+    }
+}
+
+impl std::str::FromStr for Quorum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "any" => Ok(Quorum::Any),
+            "all" => Ok(Quorum::All),
+            n => match n.parse::<usize>() {
+                Ok(0) => Err("quorum must be at least 1".to_string()),
+                Ok(n) => Ok(Quorum::Count(n)),
+                Err(_) => Err(format!(
+                    "invalid quorum '{}': use \"any\", \"all\", or a number",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// `quorum = "all"` or `quorum = 2` in TOML
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum QuorumValue {
+    Count(usize),
+    Name(String),
+}
+
+impl TryFrom<QuorumValue> for Quorum {
+    type Error = String;
+
+    fn try_from(value: QuorumValue) -> Result<Self, Self::Error> {
+        match value {
+            QuorumValue::Count(n) => n.to_string().parse(),
+            QuorumValue::Name(name) => name.parse(),
+        }
+    }
+}
+
+impl From<Quorum> for QuorumValue {
+    fn from(quorum: Quorum) -> Self {
+        match quorum {
+            Quorum::Any => QuorumValue::Name("any".to_string()),
+            Quorum::All => QuorumValue::Name("all".to_string()),
+            Quorum::Count(n) => QuorumValue::Count(n),
+        }
+    }
 }
 
 impl Default for MonitorConfig {
     fn default() -> Self {
         Self {
             ping_interval_ms: default_ping_interval(),
+            interval_jitter_ms: 0,
             ping_timeout_ms: default_ping_timeout(),
             degraded_threshold: default_degraded_threshold(),
             offline_threshold: default_offline_threshold(),
+            window_samples: None,
             recovery_threshold: default_recovery_threshold(),
+            recovery_grace_secs: 0,
+            min_state_duration_secs: 0,
+            quorum: Quorum::default(),
+            ignore_dns_failures: false,
+            jitter_threshold_ms: None,
+            quarantine_after: None,
+            dns_cache_ttl_secs: default_dns_cache_ttl_secs(),
+            degraded_min_targets: default_degraded_min_targets(),
+            offline_min_targets: default_offline_min_targets(),
+            canary_targets: Vec::new(),
+            traceroute_enabled: default_traceroute_enabled(),
+            max_outage_traces: default_max_outage_traces(),
+            trace_target: default_trace_target(),
+            trace_interval_secs: None,
+            ongoing_outage_heartbeat_secs: None,
+            traceroute_match_prefix: default_traceroute_match_prefix(),
+            ping_mode: PingMode::default(),
+            ping_backend: PingBackendKind::default(),
+            ping_count: default_ping_count(),
+            ping_retries: 0,
+            max_acceptable_loss: None,
+            metrics_addr: None,
+            log_all_pings: false,
         }
     }
 }
 
+fn default_dns_cache_ttl_secs() -> u64 {
+    300
+}
 fn default_ping_interval() -> u64 {
     1000
 }
@@ -131,6 +400,27 @@ fn default_offline_threshold() -> u32 {
 fn default_recovery_threshold() -> u32 {
     2
 }
+fn default_degraded_min_targets() -> usize {
+    1
+}
+fn default_offline_min_targets() -> usize {
+    1
+}
+fn default_traceroute_enabled() -> bool {
+    true
+}
+fn default_max_outage_traces() -> usize {
+    2
+}
+fn default_trace_target() -> Option<String> {
+    Some("8.8.8.8".to_string())
+}
+fn default_traceroute_match_prefix() -> u8 {
+    24
+}
+fn default_ping_count() -> u32 {
+    1
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TargetsConfig {
@@ -166,6 +456,33 @@ pub struct DatabaseConfig {
     /// Number of days to retain data
     #[serde(default = "default_retention_days")]
     pub retention_days: u32,
+
+    /// Seconds to cache computed stats (0 disables caching)
+    #[serde(default = "default_stats_cache_ttl")]
+    pub stats_cache_ttl_secs: u64,
+
+    /// Keep at most this many ping samples per target, regardless of age
+    #[serde(default)]
+    pub max_pings_per_target: Option<u32>,
+
+    /// Pings older than this many days are deleted unless they fall within
+    /// `outage_context_mins` of an outage (unset: keep every ping until retention)
+    #[serde(default)]
+    pub compact_after_days: Option<u32>,
+
+    /// Minutes of pings kept on either side of an outage when compacting
+    #[serde(default = "default_outage_context_mins")]
+    pub outage_context_mins: u64,
+
+    /// Also store every successful ping's latency in a narrow table, for cheap
+    /// charting and the rolling line in `status`
+    #[serde(default)]
+    pub latency_table: bool,
+
+    /// Fail instead of warning when a stored outage's affected targets are
+    /// not valid JSON
+    #[serde(default)]
+    pub strict_json: bool,
 }
 
 impl Default for DatabaseConfig {
@@ -173,6 +490,12 @@ impl Default for DatabaseConfig {
         Self {
             path: None,
             retention_days: default_retention_days(),
+            stats_cache_ttl_secs: default_stats_cache_ttl(),
+            max_pings_per_target: None,
+            compact_after_days: None,
+            outage_context_mins: default_outage_context_mins(),
+            latency_table: false,
+            strict_json: false,
         }
     }
 }
@@ -180,6 +503,12 @@ impl Default for DatabaseConfig {
 fn default_retention_days() -> u32 {
     90
 }
+fn default_stats_cache_ttl() -> u64 {
+    5
+}
+fn default_outage_context_mins() -> u64 {
+    60
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggingConfig {
@@ -204,8 +533,279 @@ fn default_log_level() -> String {
     "info".to_string()
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisplayConfig {
+    /// Decimal places shown for latencies
+    #[serde(default = "default_latency_precision")]
+    pub latency_precision: usize,
+
+    /// Time zone used when displaying timestamps ("local", "utc", or e.g. "Europe/Zurich")
+    #[serde(default)]
+    pub timezone: DisplayTimezone,
+
+    /// Look up reverse DNS for failing hops the built-in owner table doesn't know
+    #[serde(default)]
+    pub reverse_dns: bool,
+
+    /// Seconds after startup during which healthy first results are summarized
+    /// in one line instead of printed per target (0 = print each)
+    #[serde(default = "default_startup_settle_secs")]
+    pub startup_settle_secs: u64,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            latency_precision: default_latency_precision(),
+            timezone: DisplayTimezone::default(),
+            reverse_dns: false,
+            startup_settle_secs: default_startup_settle_secs(),
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// Format a stored UTC timestamp in the configured display time zone
+    pub fn format_time(&self, time: DateTime<Utc>, fmt: &str) -> String {
+        self.timezone.format(time, fmt)
+    }
+}
+
+fn default_latency_precision() -> usize {
+    1
+}
+
+fn default_startup_settle_secs() -> u64 {
+    3
+}
+
+/// Time zone for displayed timestamps. Stored data is always UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum DisplayTimezone {
+    /// The system's local time zone
+    #[default]
+    Local,
+    Utc,
+    /// A named IANA zone, e.g. "America/New_York"
+    Named(chrono_tz::Tz),
+}
+
+impl DisplayTimezone {
+    pub fn format(&self, time: DateTime<Utc>, fmt: &str) -> String {
+        match self {
+            DisplayTimezone::Local => time.with_timezone(&Local).format(fmt).to_string(),
+            DisplayTimezone::Utc => time.format(fmt).to_string(),
+            DisplayTimezone::Named(tz) => time.with_timezone(tz).format(fmt).to_string(),
+        }
+    }
+
+    /// Hour of the day (0-23) on the clock in this zone
+    pub fn hour(&self, time: DateTime<Utc>) -> u32 {
+        match self {
+            DisplayTimezone::Local => time.with_timezone(&Local).hour(),
+            DisplayTimezone::Utc => time.hour(),
+            DisplayTimezone::Named(tz) => time.with_timezone(tz).hour(),
+        }
+    }
+}
+
+impl std::str::FromStr for DisplayTimezone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "local" => Ok(DisplayTimezone::Local),
+            "utc" => Ok(DisplayTimezone::Utc),
+            _ => s
+                .parse::<chrono_tz::Tz>()
+                .map(DisplayTimezone::Named)
+                .map_err(|_| {
+                    format!(
+                    "Unknown time zone '{}'. Use local, utc, or an IANA name like Europe/Zurich",
+                    s
+                )
+                }),
+        }
+    }
+}
+
+impl TryFrom<String> for DisplayTimezone {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<DisplayTimezone> for String {
+    fn from(tz: DisplayTimezone) -> Self {
+        match tz {
+            DisplayTimezone::Local => "local".to_string(),
+            DisplayTimezone::Utc => "utc".to_string(),
+            DisplayTimezone::Named(tz) => tz.name().to_string(),
+        }
+    }
+}
+
+/// A recurring weekly maintenance window in local time, e.g. Sunday 02:00-04:00.
+/// Windows whose end is before their start wrap past midnight into the next day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    /// Day the window starts ("mon", "tue", ... or full names)
+    pub day: Weekday,
+    /// Start time (inclusive), e.g. "02:00"
+    pub start: NaiveTime,
+    /// End time (exclusive), e.g. "04:00"
+    pub end: NaiveTime,
+}
+
+impl MaintenanceWindow {
+    /// Check whether a local timestamp falls inside this window
+    pub fn contains(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let day = now.weekday();
+
+        if self.start <= self.end {
+            day == self.day && time >= self.start && time < self.end
+        } else {
+            (day == self.day && time >= self.start) || (day == self.day.succ() && time < self.end)
+        }
+    }
+}
+
+/// Check whether a local timestamp falls inside any maintenance window
+pub fn is_in_maintenance_window(now: NaiveDateTime, windows: &[MaintenanceWindow]) -> bool {
+    windows.iter().any(|w| w.contains(now))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MaintenanceConfig {
+    /// Weekly windows during which outages are marked as planned
+    #[serde(default)]
+    pub windows: Vec<MaintenanceWindow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct NotificationConfig {
+    /// URL that receives a JSON POST on OFFLINE and recovery
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+
+    /// Email a summary when an outage ends
+    #[serde(default)]
+    pub email: Option<EmailConfig>,
+
+    /// Show a macOS notification on OFFLINE and recovery
+    #[serde(default)]
+    pub desktop: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+
+    /// Upgrade the connection with STARTTLS (disable only for local relays)
+    #[serde(default = "default_starttls")]
+    pub starttls: bool,
+
+    #[serde(default)]
+    pub username: Option<String>,
+
+    #[serde(default)]
+    pub password: Option<String>,
+
+    pub from: String,
+
+    pub to: String,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_starttls() -> bool {
+    true
+}
+
+/// Shell commands run on state changes, with event details in `VIGIL_*` variables
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HooksConfig {
+    #[serde(default)]
+    pub on_degraded: Option<String>,
+
+    /// Run when the targets fail but the canaries are still reachable
+    #[serde(default)]
+    pub on_target_specific: Option<String>,
+
+    #[serde(default)]
+    pub on_offline: Option<String>,
+
+    #[serde(default)]
+    pub on_recovered: Option<String>,
+
+    /// Seconds before a hook that is still running gets killed
+    #[serde(default = "default_hook_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            on_degraded: None,
+            on_target_specific: None,
+            on_offline: None,
+            on_recovered: None,
+            timeout_secs: default_hook_timeout_secs(),
+        }
+    }
+}
+
+fn default_hook_timeout_secs() -> u64 {
+    30
+}
+
+/// Stops calling a failing notification endpoint for a while
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures before the circuit opens
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// Seconds to wait while open before trying the endpoint again
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_failure_threshold(),
+            cooldown_secs: default_cooldown_secs(),
+        }
+    }
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+fn default_cooldown_secs() -> u64 {
+    300
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
+    /// Refuse to load a questionable config (e.g. duplicate targets) instead of warning
+    #[serde(default)]
+    pub strict: bool,
+
     #[serde(default)]
     pub monitor: MonitorConfig,
 
@@ -217,6 +817,18 @@ pub struct Config {
 
     #[serde(default)]
     pub logging: LoggingConfig,
+
+    #[serde(default)]
+    pub display: DisplayConfig,
+
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+
+    #[serde(default)]
+    pub hooks: HooksConfig,
 }
 
 impl Config {
@@ -230,14 +842,265 @@ impl Config {
         let config_path = env.config_path()?;
 
         if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)?;
-            let config: Config = toml::from_str(&content)?;
-            Ok(config)
+            Self::load_remembering(&config_path)
         } else {
             Ok(Config::default())
         }
     }
 
+    /// Load configuration from an explicit file path
+    pub fn load_from_path(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_toml(&content)
+    }
+
+    fn from_toml(content: &str) -> Result<Self, ConfigError> {
+        let config: Config = toml::from_str(content)?;
+        if config.strict {
+            config.validate()?;
+        }
+        Ok(config)
+    }
+
+    /// Load `path` and, if it also validates, keep a copy of it as the last
+    /// known good config for `config rollback`
+    fn load_remembering(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)?;
+        let config = Self::from_toml(&content)?;
+        if config.validate().is_ok() {
+            let backup = backup_path(path);
+            let unchanged = std::fs::read_to_string(&backup).is_ok_and(|old| old == content);
+            if !unchanged {
+                if let Err(e) = std::fs::write(&backup, &content) {
+                    tracing::warn!("Failed to update {}: {}", backup.display(), e);
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Restore this environment's last known good config (returns the config path)
+    pub fn rollback_for_env(env: &Environment) -> Result<PathBuf, ConfigError> {
+        let config_path = env.config_path()?;
+        Self::rollback(&config_path)?;
+        Ok(config_path)
+    }
+
+    /// Replace `path` with the last copy of it that loaded and validated
+    fn rollback(path: &Path) -> Result<(), ConfigError> {
+        let backup = backup_path(path);
+        if !backup.exists() {
+            return Err(ConfigError::NoBackup(backup));
+        }
+        std::fs::copy(&backup, path)?;
+        Ok(())
+    }
+
+    /// Problems that are only warnings unless `strict` is set
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let (targets, mut problems) = dedupe_targets(self.configured_targets());
+        problems.extend(ignored_sources(&targets));
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems.join("; ")))
+        }
+    }
+
+    /// Set one value by dotted key (e.g. "monitor.ping_interval_ms"), parsed as
+    /// the field's type. An empty value clears an optional setting.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        match key {
+            "strict" => self.strict = parse_value(key, value)?,
+            "monitor.ping_interval_ms" => {
+                self.monitor.ping_interval_ms = parse_nonzero(key, value)?
+            }
+            "monitor.interval_jitter_ms" => {
+                self.monitor.interval_jitter_ms = parse_value(key, value)?
+            }
+            "monitor.ping_timeout_ms" => self.monitor.ping_timeout_ms = parse_nonzero(key, value)?,
+            "monitor.degraded_threshold" => {
+                self.monitor.degraded_threshold = parse_nonzero(key, value)?
+            }
+            "monitor.offline_threshold" => {
+                self.monitor.offline_threshold = parse_nonzero(key, value)?
+            }
+            "monitor.window_samples" => {
+                let samples: Option<u32> = parse_optional(key, value)?;
+                if samples == Some(0) {
+                    return Err(invalid_value(key, "must be greater than 0"));
+                }
+                self.monitor.window_samples = samples;
+            }
+            "monitor.recovery_threshold" => {
+                self.monitor.recovery_threshold = parse_nonzero(key, value)?
+            }
+            "monitor.recovery_grace_secs" => {
+                self.monitor.recovery_grace_secs = parse_value(key, value)?
+            }
+            "monitor.min_state_duration_secs" => {
+                self.monitor.min_state_duration_secs = parse_value(key, value)?
+            }
+            "monitor.degraded_min_targets" => {
+                self.monitor.degraded_min_targets = parse_nonzero(key, value)?
+            }
+            "monitor.offline_min_targets" => {
+                self.monitor.offline_min_targets = parse_nonzero(key, value)?
+            }
+            "monitor.traceroute_enabled" => {
+                self.monitor.traceroute_enabled = parse_value(key, value)?
+            }
+            "monitor.max_outage_traces" => {
+                self.monitor.max_outage_traces = parse_value(key, value)?
+            }
+            // Kept as "" rather than None, which would save as the 8.8.8.8 default
+            "monitor.trace_target" => self.monitor.trace_target = Some(value.trim().to_string()),
+            "monitor.trace_interval_secs" => {
+                let secs: Option<u64> = parse_optional(key, value)?;
+                if secs == Some(0) {
+                    return Err(invalid_value(key, "must be greater than 0"));
+                }
+                self.monitor.trace_interval_secs = secs;
+            }
+            "monitor.ongoing_outage_heartbeat_secs" => {
+                let secs: Option<u64> = parse_optional(key, value)?;
+                if secs == Some(0) {
+                    return Err(invalid_value(key, "must be greater than 0"));
+                }
+                self.monitor.ongoing_outage_heartbeat_secs = secs;
+            }
+            "monitor.traceroute_match_prefix" => {
+                let prefix: u8 = parse_value(key, value)?;
+                if prefix > 32 {
+                    return Err(invalid_value(key, "must be between 0 and 32"));
+                }
+                self.monitor.traceroute_match_prefix = prefix;
+            }
+            "monitor.ping_mode" => self.monitor.ping_mode = parse_serde(key, value)?,
+            "monitor.quorum" => self.monitor.quorum = parse_serde(key, value)?,
+            "monitor.ignore_dns_failures" => {
+                self.monitor.ignore_dns_failures = parse_value(key, value)?
+            }
+            "monitor.jitter_threshold_ms" => {
+                let ms: Option<f64> = parse_optional(key, value)?;
+                if ms.is_some_and(|ms| ms <= 0.0) {
+                    return Err(invalid_value(key, "must be greater than 0"));
+                }
+                self.monitor.jitter_threshold_ms = ms;
+            }
+            "monitor.quarantine_after" => {
+                let samples: Option<u32> = parse_optional(key, value)?;
+                if samples == Some(0) {
+                    return Err(invalid_value(key, "must be greater than 0"));
+                }
+                self.monitor.quarantine_after = samples;
+            }
+            "monitor.dns_cache_ttl_secs" => {
+                self.monitor.dns_cache_ttl_secs = parse_value(key, value)?
+            }
+            "monitor.ping_backend" => self.monitor.ping_backend = parse_serde(key, value)?,
+            "monitor.ping_count" => self.monitor.ping_count = parse_nonzero(key, value)?,
+            "monitor.ping_retries" => self.monitor.ping_retries = parse_value(key, value)?,
+            "monitor.max_acceptable_loss" => {
+                let loss: Option<f64> = parse_optional(key, value)?;
+                if loss.is_some_and(|loss| !(0.0..=1.0).contains(&loss)) {
+                    return Err(invalid_value(key, "must be between 0.0 and 1.0"));
+                }
+                self.monitor.max_acceptable_loss = loss;
+            }
+            "monitor.metrics_addr" => self.monitor.metrics_addr = parse_optional(key, value)?,
+            "monitor.log_all_pings" => self.monitor.log_all_pings = parse_value(key, value)?,
+            "targets.gateway" => self.targets.gateway = parse_optional(key, value)?,
+            "database.path" => self.database.path = parse_optional(key, value)?,
+            "database.retention_days" => self.database.retention_days = parse_value(key, value)?,
+            "database.stats_cache_ttl_secs" => {
+                self.database.stats_cache_ttl_secs = parse_value(key, value)?
+            }
+            "database.max_pings_per_target" => {
+                self.database.max_pings_per_target = parse_optional(key, value)?
+            }
+            "database.compact_after_days" => {
+                self.database.compact_after_days = parse_optional(key, value)?
+            }
+            "database.outage_context_mins" => {
+                self.database.outage_context_mins = parse_value(key, value)?
+            }
+            "database.latency_table" => self.database.latency_table = parse_value(key, value)?,
+            "database.strict_json" => self.database.strict_json = parse_value(key, value)?,
+            "logging.level" => self.logging.level = parse_value(key, value)?,
+            "logging.file" => self.logging.file = parse_optional(key, value)?,
+            "display.latency_precision" => {
+                self.display.latency_precision = parse_value(key, value)?
+            }
+            "display.timezone" => self.display.timezone = parse_serde(key, value)?,
+            "display.reverse_dns" => self.display.reverse_dns = parse_value(key, value)?,
+            "display.startup_settle_secs" => {
+                self.display.startup_settle_secs = parse_value(key, value)?
+            }
+            "notifications.webhook_url" => {
+                self.notifications.webhook_url = parse_optional(key, value)?
+            }
+            "notifications.circuit_breaker.failure_threshold" => {
+                self.notifications.circuit_breaker.failure_threshold = parse_nonzero(key, value)?
+            }
+            "notifications.circuit_breaker.cooldown_secs" => {
+                self.notifications.circuit_breaker.cooldown_secs = parse_value(key, value)?
+            }
+            "notifications.desktop" => self.notifications.desktop = parse_value(key, value)?,
+            "hooks.on_degraded" => self.hooks.on_degraded = parse_optional(key, value)?,
+            "hooks.on_target_specific" => {
+                self.hooks.on_target_specific = parse_optional(key, value)?
+            }
+            "hooks.on_offline" => self.hooks.on_offline = parse_optional(key, value)?,
+            "hooks.on_recovered" => self.hooks.on_recovered = parse_optional(key, value)?,
+            "hooks.timeout_secs" => self.hooks.timeout_secs = parse_nonzero(key, value)?,
+            _ => return Err(ConfigError::UnknownKey(key.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Flatten the configuration into dotted keys (e.g. "monitor.ping_interval_ms")
+    pub fn flatten(&self) -> Result<BTreeMap<String, String>, ConfigError> {
+        let value = toml::Value::try_from(self)?;
+        let mut keys = BTreeMap::new();
+        flatten_value("", &value, &mut keys);
+        Ok(keys)
+    }
+
+    /// Compare this configuration with another, key by key
+    pub fn diff(&self, other: &Config) -> Result<Vec<ConfigChange>, ConfigError> {
+        let ours = self.flatten()?;
+        let theirs = other.flatten()?;
+        let mut changes = Vec::new();
+
+        for (key, old) in &ours {
+            match theirs.get(key) {
+                None => changes.push(ConfigChange::Removed {
+                    key: key.clone(),
+                    value: old.clone(),
+                }),
+                Some(new) if new != old => changes.push(ConfigChange::Changed {
+                    key: key.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for (key, new) in &theirs {
+            if !ours.contains_key(key) {
+                changes.push(ConfigChange::Added {
+                    key: key.clone(),
+                    value: new.clone(),
+                });
+            }
+        }
+
+        changes.sort_by(|a, b| a.key().cmp(b.key()));
+        Ok(changes)
+    }
+
     /// Save configuration to the default location
     pub fn save(&self) -> Result<(), ConfigError> {
         self.save_for_env(&Environment::Production)
@@ -251,6 +1114,11 @@ impl Config {
             std::fs::create_dir_all(parent)?;
         }
 
+        // The file about to be replaced becomes the rollback point if it's still good
+        if config_path.exists() {
+            let _ = Self::load_remembering(&config_path);
+        }
+
         let content = toml::to_string_pretty(self)?;
         std::fs::write(&config_path, content)?;
         Ok(())
@@ -294,8 +1162,34 @@ impl Config {
         self.log_path_for_env(&Environment::Production)
     }
 
-    /// Get all targets to monitor (including gateway if configured)
+    /// The configuration as the monitor applies it: defaults filled in, data
+    /// and log paths resolved for `env`, and duplicate targets dropped
+    pub fn effective_for_env(&self, env: &Environment) -> Result<Config, ConfigError> {
+        let mut effective = self.clone();
+        effective.database.path = Some(self.database_path_for_env(env)?);
+        effective.logging.file = self.log_path_for_env(env)?;
+
+        let mut targets = self.all_targets();
+        if self.targets.gateway.is_some() && !targets.is_empty() {
+            // The gateway is listed first and stays in its own setting
+            targets.remove(0);
+        }
+        effective.targets.targets = targets;
+        Ok(effective)
+    }
+
+    /// Get all targets to monitor (including gateway if configured).
+    /// An address listed more than once is monitored once, under its first entry.
     pub fn all_targets(&self) -> Vec<Target> {
+        let (targets, duplicates) = dedupe_targets(self.configured_targets());
+        for problem in duplicates.iter().chain(&ignored_sources(&targets)) {
+            tracing::warn!("{}", problem);
+        }
+        targets
+    }
+
+    /// The gateway followed by the target list, as written
+    fn configured_targets(&self) -> Vec<Target> {
         let mut targets = Vec::new();
 
         if let Some(ref gateway) = self.targets.gateway {
@@ -307,9 +1201,449 @@ impl Config {
     }
 }
 
+/// Comparable form of a target address: IPs in canonical form (so IPv6
+/// spellings like "2001:DB8:0::1" and "2001:db8::1" match), hostnames lowercased
+fn normalize_address(address: &str) -> String {
+    let address = address.trim();
+    match address.parse::<std::net::IpAddr>() {
+        Ok(ip) => ip.to_string(),
+        Err(_) => address.to_ascii_lowercase(),
+    }
+}
+
+/// Drop targets already listed with the same address, check and source,
+/// describing each one dropped. The same host checked another way or over
+/// another uplink is a target of its own.
+fn dedupe_targets(targets: Vec<Target>) -> (Vec<Target>, Vec<String>) {
+    let key = |t: &Target| {
+        (
+            normalize_address(&t.ip),
+            t.check.clone().unwrap_or(CheckKind::Icmp),
+            t.source.as_deref().map(str::trim).unwrap_or("").to_string(),
+        )
+    };
+    let mut kept: Vec<Target> = Vec::new();
+    let mut duplicates = Vec::new();
+
+    for target in targets {
+        let target_key = key(&target);
+        match kept.iter().find(|t| key(t) == target_key) {
+            Some(first) => duplicates.push(format!(
+                "Target \"{}\" ({}) duplicates \"{}\" ({}) and is monitored once",
+                target.name, target.ip, first.name, first.ip
+            )),
+            None => kept.push(target),
+        }
+    }
+    (kept, duplicates)
+}
+
+/// Targets with a `source` their check can't honor: only the system ping
+/// picks the interface, TCP and HTTP checks leave from the default route
+fn ignored_sources(targets: &[Target]) -> Vec<String> {
+    targets
+        .iter()
+        .filter(|t| t.source.is_some() && !matches!(t.check, None | Some(CheckKind::Icmp)))
+        .map(|t| {
+            format!(
+                "Target \"{}\" ({}) sets a source, which only ICMP checks use",
+                t.name, t.ip
+            )
+        })
+        .collect()
+}
+
+/// A single difference between two configurations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigChange {
+    /// Key only present in the other config
+    Added { key: String, value: String },
+    /// Key only present in this config
+    Removed { key: String, value: String },
+    /// Key present in both with different values
+    Changed {
+        key: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl ConfigChange {
+    /// The dotted key this change refers to
+    pub fn key(&self) -> &str {
+        match self {
+            ConfigChange::Added { key, .. }
+            | ConfigChange::Removed { key, .. }
+            | ConfigChange::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigChange::Added { key, value } => write!(f, "+ {} = {}", key, value),
+            ConfigChange::Removed { key, value } => write!(f, "- {} = {}", key, value),
+            ConfigChange::Changed { key, old, new } => write!(f, "~ {}: {} -> {}", key, old, new),
+        }
+    }
+}
+
+fn invalid_value(key: &str, reason: impl fmt::Display) -> ConfigError {
+    ConfigError::InvalidValue {
+        key: key.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, ConfigError> {
+    let type_name = std::any::type_name::<T>()
+        .rsplit("::")
+        .next()
+        .unwrap_or("value");
+    value
+        .trim()
+        .parse()
+        .map_err(|_| invalid_value(key, format!("\"{}\" is not a valid {}", value, type_name)))
+}
+
+/// Counts and intervals where zero would stall or disable the monitor
+fn parse_nonzero<T: std::str::FromStr + Default + PartialEq>(
+    key: &str,
+    value: &str,
+) -> Result<T, ConfigError> {
+    let parsed = parse_value(key, value)?;
+    if parsed == T::default() {
+        return Err(invalid_value(key, "must be greater than 0"));
+    }
+    Ok(parsed)
+}
+
+fn parse_optional<T: std::str::FromStr>(key: &str, value: &str) -> Result<Option<T>, ConfigError> {
+    if value.trim().is_empty() {
+        Ok(None)
+    } else {
+        parse_value(key, value).map(Some)
+    }
+}
+
+/// Enum-like settings, spelled as they are in the config file
+fn parse_serde<T: serde::de::DeserializeOwned>(key: &str, value: &str) -> Result<T, ConfigError> {
+    toml::Value::String(value.trim().to_string())
+        .try_into()
+        .map_err(|e: toml::de::Error| invalid_value(key, e.message()))
+}
+
+/// Recursively flatten a TOML value into dotted keys, indexing arrays of tables
+fn flatten_value(prefix: &str, value: &toml::Value, keys: &mut BTreeMap<String, String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (k, v) in table {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{}.{}", prefix, k)
+                };
+                flatten_value(&key, v, keys);
+            }
+        }
+        toml::Value::Array(items) if items.iter().any(|v| v.is_table()) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten_value(&format!("{}[{}]", prefix, i), item, keys);
+            }
+        }
+        other => {
+            keys.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+/// What `vigil config migrate` changed, and what it could not handle
+#[derive(Debug, Default)]
+pub struct ConfigMigration {
+    pub changes: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl ConfigMigration {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.warnings.is_empty()
+    }
+}
+
+/// A config-file migration step; steps run in order, like the database migrations
+type ConfigMigrationStep = fn(&mut toml::Table, &mut ConfigMigration);
+
+/// Known transformations from older config shapes, oldest first
+const CONFIG_MIGRATIONS: &[ConfigMigrationStep] = &[
+    // Early notification design: [notifications] enabled = true
+    remove_notifications_enabled,
+    // Early notification design: [notifications.webhook] url = "..."
+    flatten_webhook_table,
+];
+
+/// Where the last known good copy of a config file is kept (`config.toml.bak`)
+pub fn backup_path(config_path: &Path) -> PathBuf {
+    config_path.with_extension("toml.bak")
+}
+
+/// Apply every known migration to a config file's contents.
+/// The result is checked to load as the current `Config`.
+pub fn migrate_config_toml(text: &str) -> Result<(toml::Table, ConfigMigration), ConfigError> {
+    let mut table: toml::Table = toml::from_str(text)?;
+    let mut report = ConfigMigration::default();
+
+    for step in CONFIG_MIGRATIONS {
+        step(&mut table, &mut report);
+    }
+
+    let _: Config = toml::Value::Table(table.clone()).try_into()?;
+    Ok((table, report))
+}
+
+fn section<'a>(table: &'a mut toml::Table, name: &str) -> Option<&'a mut toml::Table> {
+    table.get_mut(name).and_then(toml::Value::as_table_mut)
+}
+
+fn remove_notifications_enabled(table: &mut toml::Table, report: &mut ConfigMigration) {
+    let Some(notifications) = section(table, "notifications") else {
+        return;
+    };
+    if notifications.remove("enabled").is_some() {
+        report.changes.push(
+            "Removed notifications.enabled (each sink is enabled by configuring it)".to_string(),
+        );
+    }
+}
+
+fn flatten_webhook_table(table: &mut toml::Table, report: &mut ConfigMigration) {
+    let Some(notifications) = section(table, "notifications") else {
+        return;
+    };
+    let Some(toml::Value::Table(mut webhook)) = notifications.remove("webhook") else {
+        return;
+    };
+
+    let enabled = webhook
+        .remove("enabled")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let url = webhook.remove("url");
+
+    match url {
+        Some(toml::Value::String(url)) if enabled => {
+            if notifications.contains_key("webhook_url") {
+                report.warnings.push(
+                    "notifications.webhook.url dropped: notifications.webhook_url is already set"
+                        .to_string(),
+                );
+            } else {
+                notifications.insert("webhook_url".to_string(), toml::Value::String(url));
+                report.changes.push(
+                    "Moved notifications.webhook.url to notifications.webhook_url".to_string(),
+                );
+            }
+        }
+        _ => report
+            .changes
+            .push("Removed disabled [notifications.webhook] section".to_string()),
+    }
+
+    for key in webhook.keys() {
+        report.warnings.push(format!(
+            "notifications.webhook.{} is no longer supported and was removed",
+            key
+        ));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::CheckKind;
+
+    #[test]
+    fn test_environment_from_str() {
+        for (alias, env) in [
+            ("production", Environment::Production),
+            ("prod", Environment::Production),
+            ("development", Environment::Development),
+            ("dev", Environment::Development),
+            ("Test", Environment::Test),
+        ] {
+            assert_eq!(alias.parse::<Environment>().unwrap(), env);
+        }
+        assert_eq!(
+            Environment::Development
+                .to_string()
+                .parse::<Environment>()
+                .unwrap(),
+            Environment::Development
+        );
+
+        let err = "prdo".parse::<Environment>().unwrap_err();
+        assert!(matches!(err, ConfigError::UnknownEnvironment(ref v) if v == "prdo"));
+    }
+
+    #[test]
+    fn test_effective_config_fills_defaults() {
+        let on_disk = "[monitor]\nping_interval_ms = 500\n\n[[targets.targets]]\nname = \"A\"\nip = \"1.1.1.1\"\n\n[[targets.targets]]\nname = \"B\"\nip = \"1.1.1.1\"\n";
+        assert!(!on_disk.contains("ping_timeout_ms"));
+        let config: Config = toml::from_str(on_disk).unwrap();
+
+        let effective = config.effective_for_env(&Environment::Test).unwrap();
+        let text = toml::to_string_pretty(&effective).unwrap();
+        assert!(text.contains("ping_interval_ms = 500"));
+        assert!(text.contains("ping_timeout_ms = 2000"));
+        assert_eq!(
+            effective.database.path,
+            Some(Environment::Test.database_path().unwrap())
+        );
+        assert_eq!(effective.targets.targets.len(), 1);
+    }
+
+    #[test]
+    fn test_successful_load_updates_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let good = "[monitor]\nping_interval_ms = 500\n";
+        std::fs::write(&path, good).unwrap();
+
+        Config::load_remembering(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), good);
+
+        // Broken or invalid files never replace the backup
+        std::fs::write(&path, "[monitor\nping_interval_ms = 500").unwrap();
+        assert!(Config::load_remembering(&path).is_err());
+        std::fs::write(
+            &path,
+            "[[targets.targets]]\nname = \"A\"\nip = \"1.1.1.1\"\n\n[[targets.targets]]\nname = \"B\"\nip = \"1.1.1.1\"\n",
+        )
+        .unwrap();
+        Config::load_remembering(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), good);
+    }
+
+    #[test]
+    fn test_rollback_restores_last_good() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        assert!(matches!(
+            Config::rollback(&path),
+            Err(ConfigError::NoBackup(_))
+        ));
+
+        let good = "[monitor]\nping_interval_ms = 750\n";
+        std::fs::write(&path, good).unwrap();
+        Config::load_remembering(&path).unwrap();
+        std::fs::write(&path, "ping_interval_ms = ").unwrap();
+
+        Config::rollback(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), good);
+        assert_eq!(
+            Config::load_from_path(&path)
+                .unwrap()
+                .monitor
+                .ping_interval_ms,
+            750
+        );
+    }
+
+    #[test]
+    fn test_set_numeric_value() {
+        let mut config = Config::default();
+        config.set("monitor.ping_interval_ms", "500").unwrap();
+        assert_eq!(config.monitor.ping_interval_ms, 500);
+
+        let err = config.set("monitor.ping_interval_ms", "abc").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid value for monitor.ping_interval_ms: \"abc\" is not a valid u64"
+        );
+        assert!(config.set("monitor.ping_interval_ms", "0").is_err());
+        assert_eq!(config.monitor.ping_interval_ms, 500);
+    }
+
+    #[test]
+    fn test_set_string_value() {
+        let mut config = Config::default();
+        config.set("targets.gateway", "192.168.1.1").unwrap();
+        assert_eq!(config.targets.gateway.as_deref(), Some("192.168.1.1"));
+
+        // Empty clears it back to auto-detection
+        config.set("targets.gateway", "").unwrap();
+        assert_eq!(config.targets.gateway, None);
+
+        // An empty trace target survives a save rather than reverting to 8.8.8.8
+        config.set("monitor.trace_target", "").unwrap();
+        let saved: Config = toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(saved.monitor.trace_target.as_deref(), Some(""));
+
+        config.set("monitor.ping_mode", "sequential").unwrap();
+        assert_eq!(config.monitor.ping_mode, PingMode::Sequential);
+        assert!(config.set("monitor.ping_mode", "sometimes").is_err());
+
+        config.set("monitor.quorum", "2").unwrap();
+        assert_eq!(config.monitor.quorum, Quorum::Count(2));
+        config.set("monitor.quorum", "all").unwrap();
+        assert_eq!(config.monitor.quorum, Quorum::All);
+        assert!(config.set("monitor.quorum", "0").is_err());
+        assert!(config.set("monitor.quorum", "most").is_err());
+        let parsed: Config = toml::from_str("[monitor]\nquorum = 2").unwrap();
+        assert_eq!(parsed.monitor.quorum, Quorum::Count(2));
+        let parsed: Config = toml::from_str("[monitor]\nquorum = \"any\"").unwrap();
+        assert_eq!(parsed.monitor.quorum, Quorum::Any);
+    }
+
+    #[test]
+    fn test_set_unknown_key() {
+        let mut config = Config::default();
+        let err = config.set("monitor.ping_intervall_ms", "500").unwrap_err();
+        assert!(
+            matches!(err, ConfigError::UnknownKey(ref key) if key == "monitor.ping_intervall_ms")
+        );
+    }
+
+    #[test]
+    fn test_migrate_old_config() {
+        let old = r#"
+[monitor]
+ping_interval_ms = 500
+
+[notifications]
+enabled = true
+
+[notifications.webhook]
+enabled = true
+url = "https://hooks.example.com/vigil"
+on_degraded = true
+"#;
+
+        let (table, report) = migrate_config_toml(old).unwrap();
+        assert_eq!(report.changes.len(), 2);
+        assert_eq!(
+            report.warnings,
+            vec!["notifications.webhook.on_degraded is no longer supported and was removed"]
+        );
+
+        let config: Config = toml::Value::Table(table).try_into().unwrap();
+        assert_eq!(config.monitor.ping_interval_ms, 500);
+        assert_eq!(
+            config.notifications.webhook_url.as_deref(),
+            Some("https://hooks.example.com/vigil")
+        );
+
+        // Migrating again is a no-op
+        let rewritten = toml::to_string_pretty(&migrate_config_toml(old).unwrap().0).unwrap();
+        assert!(migrate_config_toml(&rewritten).unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_current_config_is_noop() {
+        let current = toml::to_string_pretty(&Config::default()).unwrap();
+        let (_, report) = migrate_config_toml(&current).unwrap();
+        assert!(report.is_empty());
+    }
 
     #[test]
     fn test_default_config() {
@@ -337,6 +1671,243 @@ targets = [
         assert_eq!(config.monitor.degraded_threshold, 5);
         assert_eq!(config.targets.gateway, Some("192.168.1.1".to_string()));
         assert_eq!(config.targets.targets.len(), 1);
+        assert!(config.monitor.canary_targets.is_empty());
+    }
+
+    #[test]
+    fn test_parse_canary_targets() {
+        let toml_str = r#"
+[monitor]
+canary_targets = [
+    { name = "Quad9", ip = "9.9.9.9" }
+]
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.monitor.canary_targets.len(), 1);
+        assert_eq!(config.monitor.canary_targets[0].ip, "9.9.9.9");
+    }
+
+    #[test]
+    fn test_parse_target_check_kind() {
+        let toml_str = r#"
+[[targets.targets]]
+name = "Google DNS"
+ip = "8.8.8.8"
+
+[[targets.targets]]
+name = "Web"
+ip = "example.com"
+check = { kind = "tcp_connect", port = 443 }
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let targets = &config.targets.targets;
+        assert_eq!(targets[0].check, None);
+        assert_eq!(targets[1].check, Some(CheckKind::TcpConnect { port: 443 }));
+
+        // Unset checks stay out of saved configs
+        let saved = toml::to_string(&config).unwrap();
+        assert_eq!(saved.matches("check").count(), 1);
+    }
+
+    #[test]
+    fn test_duplicate_targets_collapse() {
+        let toml_str = r#"
+[targets]
+gateway = "192.168.1.1"
+
+[[targets.targets]]
+name = "Router"
+ip = "192.168.1.1"
+
+[[targets.targets]]
+name = "Google DNS"
+ip = "8.8.8.8"
+
+[[targets.targets]]
+name = "Google DNS again"
+ip = " 8.8.8.8"
+
+[[targets.targets]]
+name = "Cloudflare v6"
+ip = "2606:4700:4700::1111"
+
+[[targets.targets]]
+name = "Cloudflare v6 long form"
+ip = "2606:4700:4700:0:0:0:0:1111"
+
+[[targets.targets]]
+name = "Google DNS over TCP"
+ip = "8.8.8.8"
+check = { kind = "tcp_connect", port = 53 }
+
+[[targets.targets]]
+name = "Google DNS via LTE"
+ip = "8.8.8.8"
+source = "en1"
+
+[[targets.targets]]
+name = "Google DNS over TCP via LTE"
+ip = "8.8.8.8"
+check = { kind = "tcp_connect", port = 53 }
+source = "en1"
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let names: Vec<String> = config.all_targets().into_iter().map(|t| t.name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Gateway",
+                "Google DNS",
+                "Cloudflare v6",
+                "Google DNS over TCP",
+                "Google DNS via LTE",
+                "Google DNS over TCP via LTE"
+            ]
+        );
+        let err = config.validate().unwrap_err();
+        assert!(
+            matches!(err, ConfigError::Invalid(ref msg) if msg.contains("sets a source, which only ICMP checks use"))
+        );
+
+        // Strict mode refuses to load it
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, toml_str).unwrap();
+        assert!(Config::load_from_path(&path).is_ok());
+        std::fs::write(&path, format!("strict = true\n{}", toml_str)).unwrap();
+        let err = Config::load_from_path(&path).unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid(ref msg) if msg.contains("Router")));
+    }
+
+    #[test]
+    fn test_config_diff() {
+        let current = Config::default();
+        let other: Config = toml::from_str(
+            r#"
+[monitor]
+ping_interval_ms = 500
+
+[targets]
+gateway = "192.168.1.1"
+targets = [
+    { name = "Google DNS", ip = "8.8.8.8" }
+]
+"#,
+        )
+        .unwrap();
+
+        let changes = current.diff(&other).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                ConfigChange::Changed {
+                    key: "monitor.ping_interval_ms".to_string(),
+                    old: "1000".to_string(),
+                    new: "500".to_string(),
+                },
+                ConfigChange::Added {
+                    key: "targets.gateway".to_string(),
+                    value: "\"192.168.1.1\"".to_string(),
+                },
+                ConfigChange::Removed {
+                    key: "targets.targets[1].ip".to_string(),
+                    value: "\"1.1.1.1\"".to_string(),
+                },
+                ConfigChange::Removed {
+                    key: "targets.targets[1].name".to_string(),
+                    value: "\"Cloudflare\"".to_string(),
+                },
+            ]
+        );
+
+        assert!(current.diff(&Config::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_display_timezone() {
+        let config: Config = toml::from_str(
+            r#"
+[display]
+timezone = "America/New_York"
+"#,
+        )
+        .unwrap();
+        let instant = "2024-01-15T14:23:05Z".parse::<DateTime<Utc>>().unwrap();
+
+        assert_eq!(
+            config.display.format_time(instant, "%Y-%m-%d %H:%M"),
+            "2024-01-15 09:23"
+        );
+        assert_eq!(
+            DisplayTimezone::Utc.format(instant, "%Y-%m-%d %H:%M"),
+            "2024-01-15 14:23"
+        );
+        assert_eq!(
+            "Asia/Tokyo"
+                .parse::<DisplayTimezone>()
+                .unwrap()
+                .format(instant, "%H:%M"),
+            "23:23"
+        );
+        assert_eq!(config.display.timezone.hour(instant), 9);
+        assert_eq!(DisplayTimezone::Utc.hour(instant), 14);
+
+        assert_eq!(Config::default().display.timezone, DisplayTimezone::Local);
+        assert!("Mars/Olympus".parse::<DisplayTimezone>().is_err());
+        assert_eq!(
+            String::from("Europe/Zurich".parse::<DisplayTimezone>().unwrap()),
+            "Europe/Zurich"
+        );
+    }
+
+    fn local(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_maintenance_window() {
+        let config: Config = toml::from_str(
+            r#"
+[maintenance]
+windows = [{ day = "sun", start = "02:00", end = "04:00" }]
+"#,
+        )
+        .unwrap();
+        let windows = &config.maintenance.windows;
+
+        // 2024-01-14 is a Sunday
+        assert!(is_in_maintenance_window(local("2024-01-14 03:00"), windows));
+        assert!(is_in_maintenance_window(local("2024-01-14 02:00"), windows));
+        assert!(is_in_maintenance_window(local("2024-01-14 03:59"), windows));
+        assert!(!is_in_maintenance_window(
+            local("2024-01-14 04:00"),
+            windows
+        ));
+        assert!(!is_in_maintenance_window(
+            local("2024-01-14 01:59"),
+            windows
+        ));
+        // Same time on Monday
+        assert!(!is_in_maintenance_window(
+            local("2024-01-15 03:00"),
+            windows
+        ));
+        assert!(!is_in_maintenance_window(local("2024-01-14 03:00"), &[]));
+    }
+
+    #[test]
+    fn test_maintenance_window_past_midnight() {
+        let window = MaintenanceWindow {
+            day: Weekday::Sat,
+            start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(1, 0, 0).unwrap(),
+        };
+
+        // 2024-01-13 is a Saturday
+        assert!(window.contains(local("2024-01-13 23:30")));
+        assert!(window.contains(local("2024-01-14 00:59")));
+        assert!(!window.contains(local("2024-01-14 01:00")));
+        assert!(!window.contains(local("2024-01-13 00:30")));
     }
 
     #[test]
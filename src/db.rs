@@ -1,12 +1,65 @@
-use crate::models::{Outage, PingResult, Stats, TracerouteResult};
-use chrono::{DateTime, Duration, Utc};
-use rusqlite::{params, Connection};
-use std::path::Path;
+use crate::models::{
+    DaemonSession, DegradedEvent, HopFrequency, Marker, Outage, PingResult, PingStats, Rollup,
+    SessionSummary, Stats, TraceTrigger, TracerouteHop, TracerouteResult, TrendGranularity,
+};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration as StdDuration, Instant};
 use thiserror::Error;
 
-/// Current schema version - increment when adding migrations
-#[allow(dead_code)]
-const SCHEMA_VERSION: i32 = 1;
+/// Default time-to-live for cached stats
+const DEFAULT_STATS_CACHE_TTL: StdDuration = StdDuration::from_secs(5);
+
+/// Ranges at least this long read whole days from the `daily_stats` rollup
+const ROLLUP_MIN_RANGE_DAYS: i64 = 2;
+
+/// Rebuild `daily_stats` rows from `outages`, for one day (`?1`) or all days (`?1` NULL).
+/// Days are UTC dates of the outage start; failing_hop 0 means none was identified.
+const REFRESH_DAILY_STATS: &str = r#"
+    INSERT INTO daily_stats (day, failing_hop, outages, downtime_secs)
+    SELECT substr(start_time, 1, 10), COALESCE(failing_hop, 0), COUNT(*), COALESCE(SUM(duration_secs), 0)
+    FROM outages
+    WHERE planned = 0 AND system_sleep = 0
+      AND (?1 IS NULL OR substr(start_time, 1, 10) = ?1)
+    GROUP BY 1, 2
+"#;
+
+/// Current schema version - the last entry in `MIGRATIONS`
+pub const SCHEMA_VERSION: i32 = 12;
+
+type Migration = fn(&Database) -> Result<(), DbError>;
+
+/// Every schema change in order; each runs once, in its own transaction
+const MIGRATIONS: &[(i32, &str, Migration)] = &[
+    (
+        1,
+        "Initial schema: outages, ping_log, traceroutes",
+        Database::migrate_v1,
+    ),
+    (2, "Daemon sessions", Database::migrate_v2),
+    (3, "Planned outages", Database::migrate_v3),
+    (4, "Timeline markers", Database::migrate_v4),
+    (5, "Acknowledged outages", Database::migrate_v5),
+    (6, "System sleep outages", Database::migrate_v6),
+    (7, "Normalized traceroute hops", Database::migrate_v7),
+    (8, "Ping jitter", Database::migrate_v8),
+    (9, "Daily stats rollup", Database::migrate_v9),
+    (10, "Degraded events", Database::migrate_v10),
+    (11, "Latency samples", Database::migrate_v11),
+    (12, "Traceroute trigger", Database::migrate_v12),
+];
+
+/// Migrations a database at schema `version` still needs, as (version, description)
+pub fn pending_migrations(version: i32) -> Vec<(i32, &'static str)> {
+    MIGRATIONS
+        .iter()
+        .filter(|(v, _, _)| *v > version)
+        .map(|&(v, description, _)| (v, description))
+        .collect()
+}
 
 #[derive(Error, Debug)]
 pub enum DbError {
@@ -18,34 +71,206 @@ pub enum DbError {
     Json(#[from] serde_json::Error),
     #[error("Migration error: {0}")]
     Migration(String),
+    #[error("Invalid database path {}: {reason}", path.display())]
+    InvalidPath { path: PathBuf, reason: String },
+    #[error("Outage not found: {}", .0.map_or_else(|| "it has no ID".to_string(), |id| format!("ID {}", id)))]
+    OutageNotFound(Option<i64>),
+    #[error("Corrupt affected_targets in outage {id}: {reason}")]
+    CorruptAffectedTargets { id: i64, reason: String },
+}
+
+/// Check that a database file can be created or opened at `path`, creating
+/// missing parent directories. Fails with the offending path if it is a
+/// directory or its directory can't be created or written.
+pub fn validate_path(path: &Path) -> Result<(), DbError> {
+    let invalid = |path: &Path, reason: String| DbError::InvalidPath {
+        path: path.to_path_buf(),
+        reason,
+    };
+
+    if path.is_dir() {
+        return Err(invalid(
+            path,
+            "is a directory, expected a file path".to_string(),
+        ));
+    }
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    if !parent.exists() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| invalid(parent, format!("cannot create directory: {}", e)))?;
+        tracing::info!("Created database directory {}", parent.display());
+    } else if !parent.is_dir() {
+        return Err(invalid(parent, "is not a directory".to_string()));
+    }
+
+    if path.exists() {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| invalid(path, format!("not writable: {}", e)))?;
+    } else {
+        let probe = parent.join(".vigil-write-test");
+        std::fs::write(&probe, b"")
+            .map_err(|e| invalid(parent, format!("directory not writable: {}", e)))?;
+        let _ = std::fs::remove_file(&probe);
+    }
+
+    Ok(())
+}
+
+/// Position of the last exported ping, used to resume an interrupted export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportCursor {
+    /// RFC3339 timestamp exactly as stored in `ping_log`
+    pub timestamp: String,
+    /// Row id, to break ties between pings with the same timestamp
+    pub id: i64,
+}
+
+/// A stored traceroute with its row ID and why it was run
+#[derive(Debug, Clone)]
+pub struct TracerouteWithMeta {
+    pub id: i64,
+    pub result: TracerouteResult,
+    pub trigger: TraceTrigger,
+}
+
+/// A cached stats result with the time it was computed
+struct CachedStats {
+    computed_at: Instant,
+    stats: Stats,
 }
 
+/// Cached stats keyed by the range they cover, with both ends in TTL-sized
+/// buckets: callers derive the range from `Utc::now()`, so exact bounds never repeat
+type StatsCache = HashMap<(i64, i64), CachedStats>;
+
 pub struct Database {
     conn: Connection,
+    stats_cache: RefCell<StatsCache>,
+    stats_cache_ttl: StdDuration,
+    stats_computations: Cell<u64>,
+    latency_table: bool,
+    strict_json: bool,
+    corrupt_rows: Cell<u64>,
 }
 
 impl Database {
     /// Open or create a database at the given path
     pub fn open(path: &Path) -> Result<Self, DbError> {
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
+        validate_path(path)?;
 
         let conn = Connection::open(path)?;
-        let db = Database { conn };
+        let db = Database::from_connection(conn);
         db.init_schema()?;
         Ok(db)
     }
 
+    /// Schema version of the database file at `path`, without migrating it
+    pub fn stored_version(path: &Path) -> Result<i32, DbError> {
+        let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let has_versions: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_versions {
+            return Ok(0);
+        }
+        Ok(conn.query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Write a consistent copy of the database to `path` with SQLite's online
+    /// backup, safe while the monitor keeps writing
+    pub fn backup_to(&self, path: &Path) -> Result<(), DbError> {
+        self.conn.backup(rusqlite::DatabaseName::Main, path, None)?;
+        Ok(())
+    }
+
+    /// Replace the database at `db_path` with the backup at `backup`. The backup is
+    /// checked to be a vigil database no newer than this build, copied next to
+    /// `db_path` and renamed into place, so a failure leaves the original intact.
+    pub fn restore(backup: &Path, db_path: &Path) -> Result<(), DbError> {
+        let version = Database::stored_version(backup)?;
+        if version == 0 || version > SCHEMA_VERSION {
+            return Err(DbError::InvalidPath {
+                path: backup.to_path_buf(),
+                reason: format!(
+                    "schema version {} is not a vigil database this build can read",
+                    version
+                ),
+            });
+        }
+
+        let staging = db_path.with_extension("db.restoring");
+        std::fs::copy(backup, &staging)?;
+        std::fs::rename(&staging, db_path)?;
+        Ok(())
+    }
+
     /// Create an in-memory database (useful for testing)
     #[allow(dead_code)]
     pub fn in_memory() -> Result<Self, DbError> {
         let conn = Connection::open_in_memory()?;
-        let db = Database { conn };
+        let db = Database::from_connection(conn);
         db.init_schema()?;
         Ok(db)
     }
 
+    fn from_connection(conn: Connection) -> Self {
+        Database {
+            conn,
+            stats_cache: RefCell::new(HashMap::new()),
+            stats_cache_ttl: DEFAULT_STATS_CACHE_TTL,
+            stats_computations: Cell::new(0),
+            latency_table: false,
+            strict_json: false,
+            corrupt_rows: Cell::new(0),
+        }
+    }
+
+    /// Set how long cached stats stay valid (zero disables caching)
+    pub fn set_stats_cache_ttl(&mut self, ttl: StdDuration) {
+        self.stats_cache_ttl = ttl;
+        self.invalidate_stats_cache();
+    }
+
+    /// Also write successful pings' latency to the narrow `latency_samples` table
+    pub fn set_latency_table(&mut self, enabled: bool) {
+        self.latency_table = enabled;
+    }
+
+    /// Fail reads of outages whose `affected_targets` isn't valid JSON instead
+    /// of logging a warning and reading them as empty
+    pub fn set_strict_json(&mut self, strict: bool) {
+        self.strict_json = strict;
+    }
+
+    /// Number of outage rows read with a corrupt `affected_targets` blob
+    pub fn corrupt_rows(&self) -> u64 {
+        self.corrupt_rows.get()
+    }
+
+    /// Number of times stats were computed from the database (cache misses)
+    pub fn stats_computations(&self) -> u64 {
+        self.stats_computations.get()
+    }
+
+    /// Drop all cached stats (called after any write that affects outages)
+    fn invalidate_stats_cache(&self) {
+        self.stats_cache.borrow_mut().clear();
+    }
+
     /// Initialize the database schema and run migrations
     fn init_schema(&self) -> Result<(), DbError> {
         // Create schema version table first
@@ -59,33 +284,37 @@ impl Database {
             "#,
         )?;
 
-        // Check current version
-        let current_version: i32 = self
-            .conn
-            .query_row(
-                "SELECT COALESCE(MAX(version), 0) FROM schema_version",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-
-        // Apply migrations
-        if current_version < 1 {
-            self.migrate_v1()?;
+        let current_version = self.current_version()?;
+        for &(version, description, migrate) in MIGRATIONS {
+            if version > current_version {
+                self.apply_migration(version, description, migrate)?;
+            }
         }
 
-        // Future migrations would go here:
-        // if current_version < 2 {
-        //     self.migrate_v2()?;
-        // }
+        Ok(())
+    }
+
+    /// Run one migration and record it, all or nothing
+    fn apply_migration(
+        &self,
+        version: i32,
+        description: &str,
+        migrate: Migration,
+    ) -> Result<(), DbError> {
+        tracing::info!("Applying database migration v{}: {}", version, description);
 
+        let tx = self.conn.unchecked_transaction()?;
+        migrate(self).map_err(|e| DbError::Migration(format!("v{}: {}", version, e)))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description) VALUES (?1, ?2)",
+            params![version, description],
+        )?;
+        tx.commit()?;
         Ok(())
     }
 
     /// V1: Initial schema
     fn migrate_v1(&self) -> Result<(), DbError> {
-        tracing::info!("Applying database migration v1");
-
         self.conn.execute_batch(
             r#"
             -- Outage events
@@ -127,19 +356,220 @@ impl Database {
             CREATE INDEX IF NOT EXISTS idx_ping_log_target ON ping_log(target);
             CREATE INDEX IF NOT EXISTS idx_traceroutes_outage_id ON traceroutes(outage_id);
             CREATE INDEX IF NOT EXISTS idx_traceroutes_timestamp ON traceroutes(timestamp);
+            "#,
+        )?;
+
+        Ok(())
+    }
+
+    /// V2: Daemon sessions
+    fn migrate_v2(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            -- Monitor daemon runs (start/stop)
+            CREATE TABLE IF NOT EXISTS daemon_sessions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                start_time TEXT NOT NULL,
+                end_time TEXT,
+                crashed INTEGER NOT NULL DEFAULT 0,
+                heartbeat_at TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_daemon_sessions_start_time ON daemon_sessions(start_time);
+            "#,
+        )?;
+
+        Ok(())
+    }
+
+    /// V3: Planned (maintenance window) outages
+    fn migrate_v3(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            ALTER TABLE outages ADD COLUMN planned INTEGER NOT NULL DEFAULT 0;
+            "#,
+        )?;
+
+        Ok(())
+    }
+
+    /// V4: User-entered timeline markers
+    fn migrate_v4(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS markers (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                label TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_markers_timestamp ON markers(timestamp);
+            "#,
+        )?;
+
+        Ok(())
+    }
+
+    /// V5: Acknowledged outages
+    fn migrate_v5(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            ALTER TABLE outages ADD COLUMN acknowledged INTEGER NOT NULL DEFAULT 0;
+            "#,
+        )?;
+
+        Ok(())
+    }
+
+    /// V6: Outages that overlapped system sleep
+    fn migrate_v6(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            ALTER TABLE outages ADD COLUMN system_sleep INTEGER NOT NULL DEFAULT 0;
+            "#,
+        )?;
+
+        Ok(())
+    }
+
+    /// V7: Normalized traceroute hops (the JSON blob in `traceroutes.hops` is kept)
+    fn migrate_v7(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS traceroute_hops (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                traceroute_id INTEGER NOT NULL REFERENCES traceroutes(id),
+                hop_number INTEGER NOT NULL,
+                ip TEXT,
+                latency_ms REAL,
+                timeout INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_traceroute_hops_traceroute_id ON traceroute_hops(traceroute_id);
+            CREATE INDEX IF NOT EXISTS idx_traceroute_hops_ip ON traceroute_hops(ip);
+            "#,
+        )?;
+
+        // Backfill from existing blobs
+        let traces: Vec<(i64, String)> = self
+            .conn
+            .prepare("SELECT id, hops FROM traceroutes")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        for (id, hops_json) in traces {
+            match serde_json::from_str::<Vec<TracerouteHop>>(&hops_json) {
+                Ok(hops) => self.insert_traceroute_hops(id, &hops)?,
+                Err(e) => tracing::warn!("Skipping traceroute {} with unreadable hops: {}", id, e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// V8: Per-sample ping jitter
+    fn migrate_v8(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            ALTER TABLE ping_log ADD COLUMN jitter_ms REAL;
+            "#,
+        )?;
+
+        Ok(())
+    }
+
+    /// V9: Daily outage rollup so long-range stats skip rescanning outages
+    fn migrate_v9(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS daily_stats (
+                day TEXT NOT NULL,
+                failing_hop INTEGER NOT NULL,
+                outages INTEGER NOT NULL,
+                downtime_secs REAL NOT NULL,
+                PRIMARY KEY (day, failing_hop)
+            );
+            "#,
+        )?;
+
+        // Backfill from existing outages
+        self.conn
+            .execute(REFRESH_DAILY_STATS, params![None::<String>])?;
+
+        Ok(())
+    }
+
+    /// V10: DEGRADED periods, including ones that recovered without an outage
+    fn migrate_v10(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS degraded_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                start_time TEXT NOT NULL,
+                end_time TEXT,
+                duration_secs REAL,
+                failing_targets TEXT NOT NULL,
+                outage_id INTEGER REFERENCES outages(id) ON DELETE SET NULL,
+                target_specific INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_degraded_events_start ON degraded_events(start_time);
+            "#,
+        )?;
+
+        Ok(())
+    }
+
+    /// V11: Narrow per-target latency table for charting
+    fn migrate_v11(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS latency_samples (
+                target TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                latency_ms REAL NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_latency_samples_target_timestamp
+                ON latency_samples(target, timestamp);
+            "#,
+        )?;
+
+        Ok(())
+    }
 
-            -- Record migration
-            INSERT INTO schema_version (version, description)
-            VALUES (1, 'Initial schema: outages, ping_log, traceroutes');
+    /// V12: Why each traceroute was run (earlier ones were all at OFFLINE)
+    fn migrate_v12(&self) -> Result<(), DbError> {
+        self.conn.execute_batch(
+            r#"
+            ALTER TABLE traceroutes ADD COLUMN trigger TEXT NOT NULL DEFAULT 'state_change';
             "#,
         )?;
 
         Ok(())
     }
 
+    /// Recompute the rollup rows for the day an outage started
+    fn refresh_daily_stats(&self, outage_id: i64) -> Result<(), DbError> {
+        let day: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT substr(start_time, 1, 10) FROM outages WHERE id = ?1",
+                params![outage_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        if let Some(day) = day {
+            self.conn
+                .execute("DELETE FROM daily_stats WHERE day = ?1", params![day])?;
+            self.conn.execute(REFRESH_DAILY_STATS, params![day])?;
+        }
+        Ok(())
+    }
+
     /// Get the current schema version
     #[allow(dead_code)]
-    pub fn schema_version(&self) -> Result<i32, DbError> {
+    pub fn current_version(&self) -> Result<i32, DbError> {
         let version: i32 = self.conn.query_row(
             "SELECT COALESCE(MAX(version), 0) FROM schema_version",
             [],
@@ -154,8 +584,8 @@ impl Database {
 
         self.conn.execute(
             r#"
-            INSERT INTO outages (start_time, end_time, duration_secs, affected_targets, failing_hop, failing_hop_ip, notes)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            INSERT INTO outages (start_time, end_time, duration_secs, affected_targets, failing_hop, failing_hop_ip, notes, planned, acknowledged, system_sleep)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
             "#,
             params![
                 outage.start_time.to_rfc3339(),
@@ -165,20 +595,37 @@ impl Database {
                 outage.failing_hop,
                 outage.failing_hop_ip,
                 outage.notes,
+                outage.planned as i32,
+                outage.acknowledged as i32,
+                outage.system_sleep as i32,
             ],
         )?;
 
-        Ok(self.conn.last_insert_rowid())
+        let id = self.conn.last_insert_rowid();
+        self.refresh_daily_stats(id)?;
+        self.invalidate_stats_cache();
+        Ok(id)
+    }
+
+    /// Mark an outage as acknowledged. Returns false if no such outage exists.
+    /// `update_outage` leaves the flag alone, so an ack made while the daemon is
+    /// running survives the recovery update.
+    pub fn acknowledge_outage(&self, id: i64) -> Result<bool, DbError> {
+        let updated = self.conn.execute(
+            "UPDATE outages SET acknowledged = 1 WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(updated > 0)
     }
 
     /// Update an existing outage (e.g., when it ends)
     pub fn update_outage(&self, outage: &Outage) -> Result<(), DbError> {
         let affected_targets_json = serde_json::to_string(&outage.affected_targets)?;
 
-        self.conn.execute(
+        let updated = self.conn.execute(
             r#"
             UPDATE outages
-            SET end_time = ?2, duration_secs = ?3, affected_targets = ?4, failing_hop = ?5, failing_hop_ip = ?6, notes = ?7
+            SET end_time = ?2, duration_secs = ?3, affected_targets = ?4, failing_hop = ?5, failing_hop_ip = ?6, notes = ?7, planned = ?8, system_sleep = ?9
             WHERE id = ?1
             "#,
             params![
@@ -189,25 +636,84 @@ impl Database {
                 outage.failing_hop,
                 outage.failing_hop_ip,
                 outage.notes,
+                outage.planned as i32,
+                outage.system_sleep as i32,
             ],
         )?;
 
+        // Zero rows means the outage was never saved or has been cleaned up
+        let Some(id) = outage.id.filter(|_| updated > 0) else {
+            return Err(DbError::OutageNotFound(outage.id));
+        };
+        self.refresh_daily_stats(id)?;
+        self.invalidate_stats_cache();
         Ok(())
     }
 
+    /// Persist an outage that just ended. Updates the row created when it started,
+    /// or inserts the complete outage if that insert never succeeded.
+    pub fn record_recovered_outage(
+        &self,
+        outage: &Outage,
+        known_id: Option<i64>,
+    ) -> Result<i64, DbError> {
+        if let Some(id) = known_id {
+            let mut updated = outage.clone();
+            updated.id = Some(id);
+            match self.update_outage(&updated) {
+                Ok(()) => return Ok(id),
+                Err(DbError::OutageNotFound(_)) => {
+                    tracing::warn!(
+                        "Outage {} is no longer in the database, saving it again",
+                        id
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        } else {
+            tracing::warn!("Outage was not recorded when it started, saving it now");
+        }
+
+        self.insert_outage(outage)
+    }
+
     /// Get the most recent ongoing outage (if any)
     pub fn get_ongoing_outage(&self) -> Result<Option<Outage>, DbError> {
+        Ok(self.get_ongoing_outages()?.into_iter().next())
+    }
+
+    /// Get every outage without an end time, newest first
+    pub fn get_ongoing_outages(&self) -> Result<Vec<Outage>, DbError> {
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT id, start_time, end_time, duration_secs, affected_targets, failing_hop, failing_hop_ip, notes
+            SELECT id, start_time, end_time, duration_secs, affected_targets, failing_hop, failing_hop_ip, notes, planned, acknowledged, system_sleep
             FROM outages
             WHERE end_time IS NULL
             ORDER BY start_time DESC
-            LIMIT 1
             "#,
         )?;
 
         let mut rows = stmt.query([])?;
+        let mut outages = Vec::new();
+
+        while let Some(row) = rows.next()? {
+            outages.push(self.row_to_outage(row)?);
+        }
+
+        Ok(outages)
+    }
+
+    /// Get a single outage by ID
+    pub fn get_outage(&self, id: i64) -> Result<Option<Outage>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, start_time, end_time, duration_secs, affected_targets, failing_hop, failing_hop_ip, notes, planned, acknowledged, system_sleep
+            FROM outages
+            WHERE id = ?1
+            "#,
+        )?;
+
+        let mut rows = stmt.query(params![id])?;
 
         if let Some(row) = rows.next()? {
             Ok(Some(self.row_to_outage(row)?))
@@ -224,7 +730,7 @@ impl Database {
     ) -> Result<Vec<Outage>, DbError> {
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT id, start_time, end_time, duration_secs, affected_targets, failing_hop, failing_hop_ip, notes
+            SELECT id, start_time, end_time, duration_secs, affected_targets, failing_hop, failing_hop_ip, notes, planned, acknowledged, system_sleep
             FROM outages
             WHERE start_time >= ?1 AND start_time <= ?2
             ORDER BY start_time DESC
@@ -244,10 +750,11 @@ impl Database {
     fn row_to_outage(&self, row: &rusqlite::Row) -> Result<Outage, DbError> {
         let start_time_str: String = row.get(1)?;
         let end_time_str: Option<String> = row.get(2)?;
+        let id: i64 = row.get(0)?;
         let affected_targets_json: String = row.get(4)?;
 
         Ok(Outage {
-            id: Some(row.get(0)?),
+            id: Some(id),
             start_time: DateTime::parse_from_rfc3339(&start_time_str)
                 .map(|dt| dt.with_timezone(&Utc))
                 .unwrap_or_else(|_| Utc::now()),
@@ -257,166 +764,2181 @@ impl Database {
                     .ok()
             }),
             duration_secs: row.get(3)?,
-            affected_targets: serde_json::from_str(&affected_targets_json).unwrap_or_default(),
+            affected_targets: self.parse_affected_targets(id, &affected_targets_json)?,
             failing_hop: row.get(5)?,
             failing_hop_ip: row.get(6)?,
             notes: row.get(7)?,
+            planned: row.get::<_, i32>(8)? != 0,
+            acknowledged: row.get::<_, i32>(9)? != 0,
+            system_sleep: row.get::<_, i32>(10)? != 0,
         })
     }
 
-    /// Insert a ping result
-    pub fn insert_ping(&self, ping: &PingResult) -> Result<(), DbError> {
+    /// Decode an outage's `affected_targets` blob. A corrupt one is an error in
+    /// strict mode; otherwise it is logged with the row ID and read as empty,
+    /// the raw text staying untouched in the database.
+    fn parse_affected_targets(&self, id: i64, json: &str) -> Result<Vec<String>, DbError> {
+        match serde_json::from_str(json) {
+            Ok(targets) => Ok(targets),
+            Err(e) if self.strict_json => Err(DbError::CorruptAffectedTargets {
+                id,
+                reason: e.to_string(),
+            }),
+            Err(e) => {
+                tracing::warn!(
+                    "Outage {} has corrupt affected_targets {:?} ({}); reading as empty",
+                    id,
+                    json,
+                    e
+                );
+                self.corrupt_rows.set(self.corrupt_rows.get() + 1);
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Insert a degraded period (returns its ID)
+    pub fn insert_degraded_event(&self, event: &DegradedEvent) -> Result<i64, DbError> {
         self.conn.execute(
             r#"
-            INSERT INTO ping_log (timestamp, target, target_name, latency_ms, success)
-            VALUES (?1, ?2, ?3, ?4, ?5)
+            INSERT INTO degraded_events (start_time, end_time, duration_secs, failing_targets, outage_id, target_specific)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
             "#,
             params![
-                ping.timestamp.to_rfc3339(),
-                ping.target,
-                ping.target_name,
-                ping.latency_ms,
-                ping.success as i32,
+                event.start_time.to_rfc3339(),
+                event.end_time.map(|t| t.to_rfc3339()),
+                event.duration_secs,
+                serde_json::to_string(&event.failing_targets)?,
+                event.outage_id,
+                event.target_specific,
             ],
         )?;
-        Ok(())
-    }
 
-    /// Insert a traceroute result
-    pub fn insert_traceroute(
-        &self,
-        outage_id: Option<i64>,
-        trace: &TracerouteResult,
-    ) -> Result<(), DbError> {
-        let hops_json = serde_json::to_string(&trace.hops)?;
+        Ok(self.conn.last_insert_rowid())
+    }
 
+    /// Update a stored degraded period when it ends or escalates
+    pub fn update_degraded_event(&self, event: &DegradedEvent) -> Result<(), DbError> {
         self.conn.execute(
             r#"
-            INSERT INTO traceroutes (outage_id, timestamp, target, hops, success)
-            VALUES (?1, ?2, ?3, ?4, ?5)
+            UPDATE degraded_events
+            SET end_time = ?2, duration_secs = ?3, failing_targets = ?4, outage_id = ?5,
+                target_specific = ?6
+            WHERE id = ?1
             "#,
             params![
-                outage_id,
-                trace.timestamp.to_rfc3339(),
-                trace.target,
-                hops_json,
-                trace.success as i32,
+                event.id,
+                event.end_time.map(|t| t.to_rfc3339()),
+                event.duration_secs,
+                serde_json::to_string(&event.failing_targets)?,
+                event.outage_id,
+                event.target_specific,
             ],
         )?;
+
         Ok(())
     }
 
-    /// Calculate statistics for a time period
-    pub fn get_stats(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Stats, DbError> {
-        let outages = self.get_outages(since, until)?;
-
-        let total_outages = outages.len() as u32;
-        let total_downtime_secs: f64 = outages.iter().filter_map(|o| o.duration_secs).sum();
-
-        let period_secs = (until - since).num_seconds() as f64;
-        let availability_percent = if period_secs > 0.0 {
-            ((period_secs - total_downtime_secs) / period_secs) * 100.0
+    /// Degraded periods that started within a time range, newest first
+    pub fn get_degraded_events(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<DegradedEvent>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, start_time, end_time, duration_secs, failing_targets, outage_id, target_specific
+            FROM degraded_events
+            WHERE start_time >= ?1 AND start_time <= ?2
+            ORDER BY start_time DESC
+            "#,
+        )?;
+
+        let events = stmt
+            .query_map(params![since.to_rfc3339(), until.to_rfc3339()], |row| {
+                let start_time: String = row.get(1)?;
+                let end_time: Option<String> = row.get(2)?;
+                let failing_targets: String = row.get(4)?;
+                Ok(DegradedEvent {
+                    id: Some(row.get(0)?),
+                    start_time: DateTime::parse_from_rfc3339(&start_time)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                    end_time: end_time.and_then(|s| {
+                        DateTime::parse_from_rfc3339(&s)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .ok()
+                    }),
+                    duration_secs: row.get(3)?,
+                    failing_targets: serde_json::from_str(&failing_targets).unwrap_or_default(),
+                    outage_id: row.get(5)?,
+                    target_specific: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(events)
+    }
+
+    /// Insert a sampled ping result (only into `ping_log`; see `insert_latency_sample`)
+    pub fn insert_ping(&self, ping: &PingResult) -> Result<(), DbError> {
+        self.insert_pings(std::slice::from_ref(ping), false)?;
+        Ok(())
+    }
+
+    /// Record a successful ping's latency in `latency_samples`, if that table is on.
+    /// Called for every ping when `ping_log` only gets the changes.
+    pub fn insert_latency_sample(&self, ping: &PingResult) -> Result<(), DbError> {
+        if let (true, true, Some(latency)) = (self.latency_table, ping.success, ping.latency_ms) {
+            self.conn.execute(
+                "INSERT INTO latency_samples (target, timestamp, latency_ms) VALUES (?1, ?2, ?3)",
+                params![ping.target, ping.timestamp.to_rfc3339(), latency],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Insert several ping results in one transaction, reusing the prepared
+    /// statements across rows (returns the number inserted).
+    ///
+    /// Used by `monitor.log_all_pings`: storing every ping instead of on-change
+    /// samples grows `ping_log` by one row per target per interval - roughly
+    /// 250k rows a day for three targets at 1s - so `database.retention_days`
+    /// matters far more with it enabled.
+    pub fn insert_pings_batch(&self, pings: &[PingResult]) -> Result<usize, DbError> {
+        self.insert_pings(pings, self.latency_table)
+    }
+
+    fn insert_pings(&self, pings: &[PingResult], with_samples: bool) -> Result<usize, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut ping_stmt = tx.prepare_cached(
+                r#"
+                INSERT INTO ping_log (timestamp, target, target_name, latency_ms, success, jitter_ms)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+            )?;
+            let mut sample_stmt = tx.prepare_cached(
+                "INSERT INTO latency_samples (target, timestamp, latency_ms) VALUES (?1, ?2, ?3)",
+            )?;
+
+            for ping in pings {
+                let timestamp = ping.timestamp.to_rfc3339();
+                ping_stmt.execute(params![
+                    timestamp,
+                    ping.target,
+                    ping.target_name,
+                    ping.latency_ms,
+                    ping.success as i32,
+                    ping.jitter_ms,
+                ])?;
+
+                if let (true, true, Some(latency)) = (with_samples, ping.success, ping.latency_ms) {
+                    sample_stmt.execute(params![ping.target, timestamp, latency])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(pings.len())
+    }
+
+    /// Latency samples for one target in a time range, oldest first
+    /// (empty unless `database.latency_table` was enabled while monitoring)
+    pub fn get_latency_series(
+        &self,
+        target: &str,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, f64)>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT timestamp, latency_ms FROM latency_samples
+            WHERE target = ?1 AND timestamp >= ?2 AND timestamp <= ?3
+            ORDER BY timestamp
+            "#,
+        )?;
+
+        let samples = stmt
+            .query_map(
+                params![target, since.to_rfc3339(), until.to_rfc3339()],
+                |row| {
+                    let timestamp: String = row.get(0)?;
+                    Ok((timestamp, row.get::<_, f64>(1)?))
+                },
+            )?
+            .filter_map(|row| row.ok())
+            .filter_map(|(timestamp, latency)| {
+                let timestamp = DateTime::parse_from_rfc3339(&timestamp).ok()?;
+                Some((timestamp.with_timezone(&Utc), latency))
+            })
+            .collect();
+
+        Ok(samples)
+    }
+
+    /// Latency spread and loss of one target's pings in a time range.
+    ///
+    /// Change-only `ping_log` rows say nothing about the pings in between, so
+    /// they are only used when `all_pings_logged` (`monitor.log_all_pings`).
+    /// Otherwise latency comes from `latency_samples`, without loss, and there
+    /// are no stats at all when that table is off.
+    pub fn get_ping_stats(
+        &self,
+        target: &str,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        all_pings_logged: bool,
+    ) -> Result<Option<PingStats>, DbError> {
+        if !all_pings_logged {
+            if !self.latency_table {
+                return Ok(None);
+            }
+            let latencies = self
+                .get_latency_series(target, since, until)?
+                .into_iter()
+                .map(|(_, latency)| latency)
+                .collect();
+            return Ok(Some(PingStats::from_replies(latencies)));
+        }
+
+        let range = params![target, since.to_rfc3339(), until.to_rfc3339()];
+        let samples: u32 = self.conn.query_row(
+            "SELECT COUNT(*) FROM ping_log WHERE target = ?1 AND timestamp >= ?2 AND timestamp <= ?3",
+            range,
+            |row| row.get(0),
+        )?;
+
+        // Percentiles need every value, so fetch latencies and summarize in Rust;
+        // time order is kept for the jitter between consecutive pings
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT latency_ms FROM ping_log
+            WHERE target = ?1 AND timestamp >= ?2 AND timestamp <= ?3
+                AND success = 1 AND latency_ms IS NOT NULL
+            ORDER BY timestamp
+            "#,
+        )?;
+        let latencies = stmt
+            .query_map(range, |row| row.get::<_, f64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(PingStats::from_latencies(latencies, samples)))
+    }
+
+    /// Average jitter of logged pings in a time range (None if no ping recorded jitter)
+    pub fn get_average_jitter(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Option<f64>, DbError> {
+        Ok(self.conn.query_row(
+            r#"
+            SELECT AVG(jitter_ms) FROM ping_log
+            WHERE timestamp >= ?1 AND timestamp <= ?2 AND jitter_ms IS NOT NULL
+            "#,
+            params![since.to_rfc3339(), until.to_rfc3339()],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Stream pings in a time range ordered by timestamp, starting after `after` if given.
+    /// Each row is passed to `f` with its cursor; returning an error stops the export.
+    pub fn export_pings<F, E>(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        after: Option<&ExportCursor>,
+        mut f: F,
+    ) -> Result<usize, E>
+    where
+        F: FnMut(PingResult, ExportCursor) -> Result<(), E>,
+        E: From<DbError>,
+    {
+        let mut stmt = self
+            .conn
+            .prepare(
+                r#"
+            SELECT id, timestamp, target, target_name, latency_ms, success, jitter_ms
+            FROM ping_log
+            WHERE timestamp >= ?1 AND timestamp <= ?2
+              AND (?3 IS NULL OR timestamp > ?3 OR (timestamp = ?3 AND id > ?4))
+            ORDER BY timestamp, id
+            "#,
+            )
+            .map_err(DbError::from)?;
+
+        let mut rows = stmt
+            .query(params![
+                since.to_rfc3339(),
+                until.to_rfc3339(),
+                after.map(|c| c.timestamp.as_str()),
+                after.map(|c| c.id).unwrap_or(0),
+            ])
+            .map_err(DbError::from)?;
+
+        let mut exported = 0;
+        while let Some(row) = rows.next().map_err(DbError::from)? {
+            let (cursor, ping) = Self::row_to_ping(row).map_err(DbError::from)?;
+            f(ping, cursor)?;
+            exported += 1;
+        }
+
+        Ok(exported)
+    }
+
+    fn row_to_ping(row: &rusqlite::Row) -> Result<(ExportCursor, PingResult), rusqlite::Error> {
+        let timestamp_str: String = row.get(1)?;
+        let cursor = ExportCursor {
+            timestamp: timestamp_str.clone(),
+            id: row.get(0)?,
+        };
+
+        let ping = PingResult {
+            target: row.get(2)?,
+            target_name: row.get(3)?,
+            timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+            success: row.get::<_, i32>(5)? != 0,
+            latency_ms: row.get(4)?,
+            jitter_ms: row.get(6)?,
+            loss: None,
+            error: None,
+            error_kind: None,
+            resolved_ip: None,
+        };
+
+        Ok((cursor, ping))
+    }
+
+    /// Insert a traceroute result
+    pub fn insert_traceroute(
+        &self,
+        outage_id: Option<i64>,
+        trace: &TracerouteResult,
+        trigger: TraceTrigger,
+    ) -> Result<(), DbError> {
+        let hops_json = serde_json::to_string(&trace.hops)?;
+
+        // The blob and the normalized rows are written together
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            r#"
+            INSERT INTO traceroutes (outage_id, timestamp, target, hops, success, trigger)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+            params![
+                outage_id,
+                trace.timestamp.to_rfc3339(),
+                trace.target,
+                hops_json,
+                trace.success as i32,
+                trigger.as_str(),
+            ],
+        )?;
+        self.insert_traceroute_hops(tx.last_insert_rowid(), &trace.hops)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Traceroutes linked to an outage, oldest first
+    pub fn get_traceroutes_for_outage(
+        &self,
+        outage_id: i64,
+    ) -> Result<Vec<TracerouteWithMeta>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, timestamp, target, hops, success, trigger
+            FROM traceroutes
+            WHERE outage_id = ?1
+            ORDER BY timestamp ASC, id ASC
+            "#,
+        )?;
+
+        let rows = stmt
+            .query_map(params![outage_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i32>(4)? != 0,
+                    row.get::<_, String>(5)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(id, timestamp, target, hops, success, trigger)| {
+                let trigger = trigger.parse().unwrap_or_else(|e| {
+                    tracing::warn!("Traceroute {}: {}", id, e);
+                    TraceTrigger::StateChange
+                });
+                Ok(TracerouteWithMeta {
+                    id,
+                    result: TracerouteResult {
+                        target,
+                        timestamp: DateTime::parse_from_rfc3339(&timestamp)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .unwrap_or_else(|_| Utc::now()),
+                        hops: serde_json::from_str(&hops)?,
+                        success,
+                    },
+                    trigger,
+                })
+            })
+            .collect()
+    }
+
+    fn insert_traceroute_hops(
+        &self,
+        traceroute_id: i64,
+        hops: &[TracerouteHop],
+    ) -> Result<(), DbError> {
+        let mut stmt = self.conn.prepare_cached(
+            r#"
+            INSERT INTO traceroute_hops (traceroute_id, hop_number, ip, latency_ms, timeout)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+        )?;
+        for hop in hops {
+            stmt.execute(params![
+                traceroute_id,
+                hop.hop_number,
+                hop.ip,
+                hop.latency_ms,
+                hop.timeout as i32,
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// IPs that were the last responding hop of failed traceroutes, most frequent first
+    pub fn get_frequent_failing_ips(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<HopFrequency>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT h.ip, COUNT(*) AS occurrences
+            FROM traceroute_hops h
+            JOIN traceroutes t ON t.id = h.traceroute_id
+            WHERE t.success = 0
+              AND t.timestamp >= ?1 AND t.timestamp <= ?2
+              AND h.timeout = 0 AND h.ip IS NOT NULL
+              AND h.hop_number = (
+                  SELECT MAX(last.hop_number) FROM traceroute_hops last
+                  WHERE last.traceroute_id = h.traceroute_id
+                    AND last.timeout = 0 AND last.ip IS NOT NULL
+              )
+            GROUP BY h.ip
+            ORDER BY occurrences DESC, h.ip
+            "#,
+        )?;
+
+        let rows = stmt.query_map(params![since.to_rfc3339(), until.to_rfc3339()], |row| {
+            Ok(HopFrequency {
+                ip: row.get(0)?,
+                occurrences: row.get(1)?,
+            })
+        })?;
+
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Record a timeline marker, e.g. "unplugged the router"
+    pub fn insert_marker(&self, timestamp: DateTime<Utc>, label: &str) -> Result<i64, DbError> {
+        self.conn.execute(
+            "INSERT INTO markers (timestamp, label) VALUES (?1, ?2)",
+            params![timestamp.to_rfc3339(), label],
+        )?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Get markers within a time range, newest first
+    pub fn get_markers_in_range(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Marker>, DbError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, timestamp, label
+            FROM markers
+            WHERE timestamp >= ?1 AND timestamp <= ?2
+            ORDER BY timestamp DESC
+            "#,
+        )?;
+
+        let markers = stmt
+            .query_map(params![since.to_rfc3339(), until.to_rfc3339()], |row| {
+                let timestamp: String = row.get(1)?;
+                Ok(Marker {
+                    id: row.get(0)?,
+                    timestamp: DateTime::parse_from_rfc3339(&timestamp)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                    label: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(markers)
+    }
+
+    /// Get statistics for a time period, served from a short-lived cache when possible
+    pub fn get_stats(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Stats, DbError> {
+        if self.stats_cache_ttl.is_zero() {
+            return self.get_stats_uncached(since, until);
+        }
+
+        let ttl_ms = self.stats_cache_ttl.as_millis().max(1) as i64;
+        let bucket = |at: DateTime<Utc>| at.timestamp_millis().div_euclid(ttl_ms);
+        let key = (bucket(since), bucket(until));
+        if let Some(cached) = self.stats_cache.borrow().get(&key) {
+            if cached.computed_at.elapsed() < self.stats_cache_ttl {
+                return Ok(cached.stats.clone());
+            }
+        }
+
+        let stats = self.get_stats_uncached(since, until)?;
+        self.stats_cache.borrow_mut().insert(
+            key,
+            CachedStats {
+                computed_at: Instant::now(),
+                stats: stats.clone(),
+            },
+        );
+        Ok(stats)
+    }
+
+    /// Record the start of a monitor session, returning its ID. Sessions left
+    /// open by a crash are closed at their last heartbeat or recorded ping.
+    pub fn start_session(&self, now: DateTime<Utc>) -> Result<i64, DbError> {
+        let crashed = self.conn.execute(
+            r#"
+            UPDATE daemon_sessions
+            SET end_time = MAX(
+                    COALESCE(heartbeat_at, start_time),
+                    COALESCE(
+                        (SELECT MAX(timestamp) FROM ping_log WHERE timestamp >= daemon_sessions.start_time),
+                        start_time
+                    )
+                ),
+                crashed = 1
+            WHERE end_time IS NULL
+            "#,
+            [],
+        )?;
+
+        if crashed > 0 {
+            tracing::warn!(
+                "Closed {} unfinished monitor session(s) from a previous run",
+                crashed
+            );
+        }
+
+        self.conn.execute(
+            "INSERT INTO daemon_sessions (start_time, heartbeat_at) VALUES (?1, ?1)",
+            params![now.to_rfc3339()],
+        )?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Record that a monitor session is still running
+    pub fn touch_session(&self, id: i64, now: DateTime<Utc>) -> Result<(), DbError> {
+        self.conn.execute(
+            "UPDATE daemon_sessions SET heartbeat_at = ?2 WHERE id = ?1",
+            params![id, now.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Record a clean end of a monitor session
+    pub fn end_session(&self, id: i64, now: DateTime<Utc>) -> Result<(), DbError> {
+        self.conn.execute(
+            "UPDATE daemon_sessions SET end_time = ?2 WHERE id = ?1",
+            params![id, now.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Get all monitor sessions, oldest first
+    pub fn get_sessions(&self) -> Result<Vec<DaemonSession>, DbError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, start_time, end_time, crashed, heartbeat_at FROM daemon_sessions ORDER BY start_time",
+        )?;
+
+        let sessions = stmt
+            .query_map([], |row| {
+                let start_time: String = row.get(1)?;
+                let end_time: Option<String> = row.get(2)?;
+                let heartbeat_at: Option<String> = row.get(4)?;
+                Ok(DaemonSession {
+                    id: row.get(0)?,
+                    start_time: DateTime::parse_from_rfc3339(&start_time)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                    end_time: end_time.and_then(|s| {
+                        DateTime::parse_from_rfc3339(&s)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .ok()
+                    }),
+                    crashed: row.get::<_, i32>(3)? != 0,
+                    heartbeat_at: heartbeat_at.and_then(|s| {
+                        DateTime::parse_from_rfc3339(&s)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .ok()
+                    }),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(sessions)
+    }
+
+    /// Summarize the current session and total observed time across all sessions
+    pub fn get_session_summary(&self, now: DateTime<Utc>) -> Result<SessionSummary, DbError> {
+        Ok(SessionSummary::from_sessions(&self.get_sessions()?, now))
+    }
+
+    /// Calculate statistics for a time period, bypassing the cache. Long ranges
+    /// sum whole days from the `daily_stats` rollup; short ones scan outages.
+    pub fn get_stats_uncached(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Stats, DbError> {
+        self.stats_computations
+            .set(self.stats_computations.get() + 1);
+
+        if until - since >= Duration::days(ROLLUP_MIN_RANGE_DAYS) {
+            self.get_stats_from_rollup(since, until)
         } else {
-            100.0
+            self.get_stats_full_scan(since, until)
+        }
+    }
+
+    /// Calculate statistics by scanning every outage in the range
+    pub fn get_stats_full_scan(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Stats, DbError> {
+        // Planned maintenance and system sleep don't count against availability
+        let outages: Vec<Outage> = self
+            .get_outages(since, until)?
+            .into_iter()
+            .filter(Outage::counts_against_availability)
+            .collect();
+
+        Ok(Stats::from_outages(&outages, since, until))
+    }
+
+    /// Whole UTC days from the rollup, plus the partial first and last days from outages
+    fn get_stats_from_rollup(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Stats, DbError> {
+        let midnight = |date: chrono::NaiveDate| date.and_time(chrono::NaiveTime::MIN).and_utc();
+        let first_full_day = if midnight(since.date_naive()) == since {
+            since
+        } else {
+            midnight(since.date_naive() + Duration::days(1))
+        };
+        let last_partial_day = midnight(until.date_naive());
+
+        let mut total_outages = 0u32;
+        let mut total_downtime_secs = 0.0;
+        let mut hop_counts: HashMap<u8, u32> = HashMap::new();
+
+        let mut stmt = self.conn.prepare_cached(
+            r#"
+            SELECT failing_hop, SUM(outages), SUM(downtime_secs)
+            FROM daily_stats
+            WHERE day >= ?1 AND day < ?2
+            GROUP BY failing_hop
+            "#,
+        )?;
+        let mut rows = stmt.query(params![
+            first_full_day.format("%Y-%m-%d").to_string(),
+            last_partial_day.format("%Y-%m-%d").to_string()
+        ])?;
+        while let Some(row) = rows.next()? {
+            let hop: u8 = row.get(0)?;
+            let outages: u32 = row.get(1)?;
+            total_outages += outages;
+            total_downtime_secs += row.get::<_, f64>(2)?;
+            if hop != 0 {
+                *hop_counts.entry(hop).or_insert(0) += outages;
+            }
+        }
+
+        // Partial days at either end
+        let mut edges = self.get_outages(since, first_full_day)?;
+        edges.retain(|o| o.start_time < first_full_day);
+        edges.extend(self.get_outages(last_partial_day, until)?);
+        for outage in edges.iter().filter(|o| o.counts_against_availability()) {
+            total_outages += 1;
+            total_downtime_secs += outage.duration_secs.unwrap_or(0.0);
+            if let Some(hop) = outage.failing_hop {
+                *hop_counts.entry(hop).or_insert(0) += 1;
+            }
+        }
+
+        Ok(Stats::from_totals(
+            total_outages,
+            total_downtime_secs,
+            &hop_counts,
+            since,
+            until,
+        ))
+    }
+
+    /// Roll up outage counts and downtime per day or week, cut at midnight in `tz`.
+    /// Every bucket in the range is returned, including empty ones; planned and sleep outages are skipped.
+    pub fn get_outage_trends<Tz: TimeZone>(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        granularity: TrendGranularity,
+        tz: &Tz,
+    ) -> Result<Vec<Rollup>, DbError> {
+        let mut rollups = Vec::new();
+        let mut bucket = granularity.bucket_start(since, tz);
+        while bucket < until {
+            rollups.push(Rollup {
+                bucket_start: bucket,
+                outages: 0,
+                downtime_secs: 0.0,
+            });
+            bucket = granularity.next_bucket(bucket, tz);
+        }
+
+        for outage in self.get_outages(since, until)? {
+            if !outage.counts_against_availability() {
+                continue;
+            }
+
+            let start = granularity.bucket_start(outage.start_time, tz);
+            if let Some(rollup) = rollups.iter_mut().find(|r| r.bucket_start == start) {
+                rollup.outages += 1;
+                rollup.downtime_secs += outage.duration_secs.unwrap_or_else(|| {
+                    (until - outage.start_time).num_milliseconds() as f64 / 1000.0
+                });
+            }
+        }
+
+        Ok(rollups)
+    }
+
+    /// Delete old data based on retention policy
+    pub fn cleanup(&self, retention_days: u32) -> Result<u64, DbError> {
+        let cutoff = Utc::now() - Duration::days(retention_days as i64);
+        let cutoff_str = cutoff.to_rfc3339();
+
+        let deleted_pings = self.conn.execute(
+            "DELETE FROM ping_log WHERE timestamp < ?1",
+            params![cutoff_str],
+        )?;
+
+        self.conn.execute(
+            "DELETE FROM traceroute_hops WHERE traceroute_id IN (SELECT id FROM traceroutes WHERE timestamp < ?1)",
+            params![cutoff_str],
+        )?;
+
+        let deleted_traceroutes = self.conn.execute(
+            "DELETE FROM traceroutes WHERE timestamp < ?1",
+            params![cutoff_str],
+        )?;
+
+        let deleted_outages = self.conn.execute(
+            "DELETE FROM outages WHERE start_time < ?1",
+            params![cutoff_str],
+        )?;
+
+        let deleted_degraded = self.conn.execute(
+            "DELETE FROM degraded_events WHERE start_time < ?1",
+            params![cutoff_str],
+        )?;
+
+        let deleted_samples = self.conn.execute(
+            "DELETE FROM latency_samples WHERE timestamp < ?1",
+            params![cutoff_str],
+        )?;
+
+        // The cutoff day may still have outages left, so rebuild it rather than drop it
+        let cutoff_day = cutoff.format("%Y-%m-%d").to_string();
+        self.conn.execute(
+            "DELETE FROM daily_stats WHERE day <= ?1",
+            params![cutoff_day],
+        )?;
+        self.conn
+            .execute(REFRESH_DAILY_STATS, params![cutoff_day])?;
+
+        self.invalidate_stats_cache();
+        Ok((deleted_pings
+            + deleted_traceroutes
+            + deleted_outages
+            + deleted_degraded
+            + deleted_samples) as u64)
+    }
+
+    /// Delete pings older than `older_than` unless they fall within `window`
+    /// of an outage, so long-term history keeps full detail only where it
+    /// matters. Returns the number of pings deleted.
+    pub fn compact_keeping_outage_context(
+        &self,
+        older_than: DateTime<Utc>,
+        window: Duration,
+    ) -> Result<u64, DbError> {
+        let now = Utc::now();
+        // Outages starting after the cutoff can still reach back across it
+        let keep: Vec<(String, String)> = {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT start_time, end_time FROM outages WHERE start_time < ?1")?;
+            let rows = stmt.query_map(params![(older_than + window).to_rfc3339()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+            })?;
+
+            let mut keep = Vec::new();
+            for row in rows {
+                let (start, end) = row?;
+                let parse = |s: &str| {
+                    DateTime::parse_from_rfc3339(s)
+                        .ok()
+                        .map(|dt| dt.with_timezone(&Utc))
+                };
+                let Some(start) = parse(&start) else {
+                    continue;
+                };
+                let end = end.as_deref().and_then(parse).unwrap_or(now);
+                keep.push(((start - window).to_rfc3339(), (end + window).to_rfc3339()));
+            }
+            keep
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute_batch(
+            "CREATE TEMP TABLE IF NOT EXISTS keep_ranges (start TEXT NOT NULL, end TEXT NOT NULL);
+             DELETE FROM temp.keep_ranges;",
+        )?;
+        {
+            let mut insert =
+                tx.prepare("INSERT INTO temp.keep_ranges (start, end) VALUES (?1, ?2)")?;
+            for (start, end) in &keep {
+                insert.execute(params![start, end])?;
+            }
+        }
+        let deleted = tx.execute(
+            r#"
+            DELETE FROM ping_log
+            WHERE timestamp < ?1
+              AND NOT EXISTS (
+                  SELECT 1 FROM temp.keep_ranges k
+                  WHERE ping_log.timestamp BETWEEN k.start AND k.end
+              )
+            "#,
+            params![older_than.to_rfc3339()],
+        )?;
+        tx.execute("DELETE FROM temp.keep_ranges", [])?;
+        tx.commit()?;
+
+        Ok(deleted as u64)
+    }
+
+    /// Delete all but the newest `max_per_target` ping samples of each target
+    pub fn cleanup_pings_beyond(&self, max_per_target: u32) -> Result<u64, DbError> {
+        let deleted = self.conn.execute(
+            r#"
+            DELETE FROM ping_log WHERE id IN (
+                SELECT id FROM (
+                    SELECT id, ROW_NUMBER() OVER (
+                        PARTITION BY target ORDER BY timestamp DESC, id DESC
+                    ) AS position
+                    FROM ping_log
+                )
+                WHERE position > ?1
+            )
+            "#,
+            params![max_per_target],
+        )?;
+
+        self.invalidate_stats_cache();
+        Ok(deleted as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_path_rejects_directory() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let err = Database::open(dir.path()).err().unwrap();
+        assert!(matches!(err, DbError::InvalidPath { ref path, .. } if path == dir.path()));
+        assert!(err.to_string().contains("is a directory"));
+    }
+
+    #[test]
+    fn test_validate_path_rejects_uncreatable_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("not-a-dir");
+        std::fs::write(&file, "").unwrap();
+
+        // The "directory" for the database is actually a file
+        let db_path = file.join("data").join("monitor.db");
+        let err = validate_path(&db_path).unwrap_err();
+        assert!(err.to_string().contains("cannot create directory"));
+        assert!(err.to_string().contains("not-a-dir"));
+
+        let err = validate_path(&file.join("monitor.db")).unwrap_err();
+        assert!(err.to_string().contains("is not a directory"));
+    }
+
+    #[test]
+    fn test_validate_path_creates_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("nested").join("monitor.db");
+
+        Database::open(&db_path).unwrap();
+        assert!(db_path.exists());
+        assert!(!dir.path().join("nested").join(".vigil-write-test").exists());
+    }
+
+    #[test]
+    fn test_create_database() {
+        let db = Database::in_memory().unwrap();
+        assert!(db.get_ongoing_outage().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_insert_and_get_outage() {
+        let db = Database::in_memory().unwrap();
+
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        let id = db.insert_outage(&outage).unwrap();
+        outage.id = Some(id);
+
+        let ongoing = db.get_ongoing_outage().unwrap();
+        assert!(ongoing.is_some());
+        assert_eq!(ongoing.unwrap().id, Some(id));
+
+        // End the outage
+        outage.end();
+        db.update_outage(&outage).unwrap();
+
+        let ongoing = db.get_ongoing_outage().unwrap();
+        assert!(ongoing.is_none());
+    }
+
+    #[test]
+    fn test_get_ongoing_outages_skips_closed() {
+        let db = Database::in_memory().unwrap();
+        let now = Utc::now();
+
+        let mut closed = Outage::new(vec!["8.8.8.8".to_string()]);
+        closed.start_time = now - Duration::minutes(30);
+        closed.end_time = Some(now - Duration::minutes(29));
+        closed.duration_secs = Some(60.0);
+        db.insert_outage(&closed).unwrap();
+
+        let mut older = Outage::new(vec!["1.1.1.1".to_string()]);
+        older.start_time = now - Duration::minutes(10);
+        let older_id = db.insert_outage(&older).unwrap();
+
+        let mut newer = Outage::new(vec!["9.9.9.9".to_string()]);
+        newer.start_time = now - Duration::minutes(2);
+        let newer_id = db.insert_outage(&newer).unwrap();
+
+        let ongoing = db.get_ongoing_outages().unwrap();
+        let ids: Vec<Option<i64>> = ongoing.iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![Some(newer_id), Some(older_id)]);
+        assert!(ongoing.iter().all(|o| o.end_time.is_none()));
+
+        assert_eq!(db.get_ongoing_outage().unwrap().unwrap().id, Some(newer_id));
+    }
+
+    #[test]
+    fn test_insert_ping() {
+        let db = Database::in_memory().unwrap();
+
+        let ping = PingResult {
+            target: "8.8.8.8".to_string(),
+            target_name: "Google DNS".to_string(),
+            timestamp: Utc::now(),
+            success: true,
+            latency_ms: Some(15.5),
+            jitter_ms: None,
+            loss: None,
+            error: None,
+            error_kind: None,
+            resolved_ip: None,
+        };
+
+        db.insert_ping(&ping).unwrap();
+    }
+
+    #[test]
+    fn test_export_pings_resume() {
+        let db = Database::in_memory().unwrap();
+        let base = Utc::now() - Duration::hours(1);
+
+        // Two pings share each timestamp to exercise the id tie-breaker
+        for i in 0..10 {
+            let ping = PingResult {
+                target: format!("10.0.0.{}", i),
+                target_name: "Test".to_string(),
+                timestamp: base + Duration::seconds(i / 2),
+                success: true,
+                latency_ms: Some(i as f64),
+                jitter_ms: None,
+                loss: None,
+                error: None,
+                error_kind: None,
+                resolved_ip: None,
+            };
+            db.insert_ping(&ping).unwrap();
+        }
+
+        let since = base - Duration::minutes(1);
+        let until = Utc::now();
+
+        // Simulate an interruption after 3 rows, keeping the last good cursor
+        let mut exported = Vec::new();
+        let mut checkpoint = None;
+        let result = db.export_pings(since, until, None, |ping, cursor| {
+            if exported.len() == 3 {
+                return Err(DbError::Migration("interrupted".to_string()));
+            }
+            exported.push(ping.target);
+            checkpoint = Some(cursor);
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(exported.len(), 3);
+
+        // Resume from the checkpoint
+        let resumed = db
+            .export_pings(since, until, checkpoint.as_ref(), |ping, _| {
+                exported.push(ping.target);
+                Ok::<(), DbError>(())
+            })
+            .unwrap();
+        assert_eq!(resumed, 7);
+
+        let expected: Vec<String> = (0..10).map(|i| format!("10.0.0.{}", i)).collect();
+        assert_eq!(exported, expected);
+    }
+
+    #[test]
+    fn test_session_open_close() {
+        let db = Database::in_memory().unwrap();
+        let start = Utc::now() - Duration::hours(2);
+
+        let id = db.start_session(start).unwrap();
+        db.touch_session(id, start + Duration::hours(1)).unwrap();
+        let summary = db.get_session_summary(start + Duration::hours(1)).unwrap();
+        assert_eq!(summary.current_start, Some(start));
+        assert_eq!(summary.total_observed_secs, 3600.0);
+
+        db.end_session(id, start + Duration::hours(2)).unwrap();
+        let summary = db.get_session_summary(start + Duration::hours(3)).unwrap();
+        assert!(summary.current_start.is_none());
+        assert_eq!(summary.total_observed_secs, 7200.0);
+    }
+
+    #[test]
+    fn test_session_crash_recovery() {
+        let db = Database::in_memory().unwrap();
+        let start = Utc::now() - Duration::hours(10);
+
+        // First session crashes after logging a ping one hour in
+        db.start_session(start).unwrap();
+        db.insert_ping(&PingResult {
+            target: "8.8.8.8".to_string(),
+            target_name: "Google DNS".to_string(),
+            timestamp: start + Duration::hours(1),
+            success: true,
+            latency_ms: Some(10.0),
+            jitter_ms: None,
+            loss: None,
+            error: None,
+            error_kind: None,
+            resolved_ip: None,
+        })
+        .unwrap();
+
+        // Without heartbeats it no longer counts as running
+        let summary = db.get_session_summary(start + Duration::hours(2)).unwrap();
+        assert!(summary.current_start.is_none());
+        assert_eq!(summary.total_observed_secs, 0.0);
+
+        // Next start closes it at the last ping
+        let restart = start + Duration::hours(5);
+        let id = db.start_session(restart).unwrap();
+        db.touch_session(id, restart + Duration::hours(2)).unwrap();
+
+        let sessions = db.get_sessions().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions[0].crashed);
+        assert_eq!(sessions[0].end_time, Some(start + Duration::hours(1)));
+        assert!(sessions[1].end_time.is_none());
+
+        // 1h from the crashed session + 2h of the current one
+        let summary = db
+            .get_session_summary(restart + Duration::hours(2))
+            .unwrap();
+        assert_eq!(summary.current_start, Some(restart));
+        assert_eq!(summary.total_observed_secs, 3.0 * 3600.0);
+    }
+
+    #[test]
+    fn test_session_crash_closed_at_heartbeat() {
+        let db = Database::in_memory().unwrap();
+        let start = Utc::now() - Duration::hours(10);
+
+        // Change-only logging: the last ping is long before the last heartbeat
+        let id = db.start_session(start).unwrap();
+        db.touch_session(id, start + Duration::hours(3)).unwrap();
+        let summary = db.get_session_summary(start + Duration::hours(4)).unwrap();
+        assert!(summary.current_start.is_none());
+        assert_eq!(summary.total_observed_secs, 3.0 * 3600.0);
+
+        db.start_session(start + Duration::hours(5)).unwrap();
+        let sessions = db.get_sessions().unwrap();
+        assert!(sessions[0].crashed);
+        assert_eq!(sessions[0].end_time, Some(start + Duration::hours(3)));
+    }
+
+    #[test]
+    fn test_planned_outages_excluded_from_stats() {
+        let db = Database::in_memory().unwrap();
+        let until = Utc::now();
+        let since = until - Duration::hours(24);
+
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.start_time = until - Duration::hours(1);
+        outage.duration_secs = Some(60.0);
+        outage.planned = true;
+        let id = db.insert_outage(&outage).unwrap();
+
+        let stored = db.get_outages(since, until).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id, Some(id));
+        assert!(stored[0].planned);
+
+        let stats = db.get_stats(since, until).unwrap();
+        assert_eq!(stats.total_outages, 0);
+        assert_eq!(stats.availability_percent, 100.0);
+    }
+
+    #[test]
+    fn test_sleep_outages_excluded_from_stats() {
+        let db = Database::in_memory().unwrap();
+        let until = Utc::now();
+        let since = until - Duration::hours(24);
+
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.start_time = until - Duration::hours(1);
+        let id = db.insert_outage(&outage).unwrap();
+
+        // Tagged when the outage closes
+        outage.id = Some(id);
+        outage.end_time = Some(outage.start_time + Duration::minutes(20));
+        outage.duration_secs = Some(1200.0);
+        outage.system_sleep = true;
+        db.update_outage(&outage).unwrap();
+
+        assert!(db.get_outage(id).unwrap().unwrap().system_sleep);
+        assert_eq!(db.get_stats(since, until).unwrap().total_outages, 0);
+    }
+
+    #[test]
+    fn test_outage_trends() {
+        let db = Database::in_memory().unwrap();
+        // Monday 2024-01-15 through Sunday 2024-01-28
+        let since = "2024-01-15T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let until = since + Duration::days(14);
+
+        for (start, duration) in [
+            ("2024-01-15T08:00:00Z", 30.0),
+            ("2024-01-15T20:00:00Z", 90.0),
+            ("2024-01-17T12:00:00Z", 10.0),
+            ("2024-01-23T03:00:00Z", 60.0),
+        ] {
+            let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+            outage.start_time = start.parse().unwrap();
+            outage.duration_secs = Some(duration);
+            db.insert_outage(&outage).unwrap();
+        }
+
+        let daily = db
+            .get_outage_trends(since, until, TrendGranularity::Day, &Utc)
+            .unwrap();
+        assert_eq!(daily.len(), 14);
+        assert_eq!(daily[0].bucket_start, since);
+        assert_eq!(daily[0].outages, 2);
+        assert_eq!(daily[0].downtime_secs, 120.0);
+        assert_eq!(daily[1].outages, 0);
+        assert_eq!(daily[2].outages, 1);
+        assert_eq!(daily[8].outages, 1);
+
+        let weekly = db
+            .get_outage_trends(since, until, TrendGranularity::Week, &Utc)
+            .unwrap();
+        assert_eq!(weekly.len(), 2);
+        assert_eq!(weekly[0].outages, 3);
+        assert_eq!(weekly[0].downtime_secs, 130.0);
+        assert_eq!(weekly[1].outages, 1);
+        assert_eq!(weekly[1].bucket_start, since + Duration::weeks(1));
+    }
+
+    #[test]
+    fn test_outage_trends_cut_at_local_midnight() {
+        let db = Database::in_memory().unwrap();
+        let tz = chrono_tz::America::Los_Angeles;
+        // Friday 2024-01-19 21:30 in Los Angeles, already Saturday in UTC
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.start_time = "2024-01-20T05:30:00Z".parse().unwrap();
+        outage.duration_secs = Some(60.0);
+        db.insert_outage(&outage).unwrap();
+
+        let since = "2024-01-18T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let until = since + Duration::days(3);
+
+        let utc = db
+            .get_outage_trends(since, until, TrendGranularity::Day, &Utc)
+            .unwrap();
+        let busy = utc.iter().find(|r| r.outages == 1).unwrap();
+        assert_eq!(busy.bucket_start.date_naive().to_string(), "2024-01-20");
+
+        let local = db
+            .get_outage_trends(since, until, TrendGranularity::Day, &tz)
+            .unwrap();
+        let busy = local.iter().find(|r| r.outages == 1).unwrap();
+        assert_eq!(
+            busy.bucket_start,
+            "2024-01-19T08:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert!(local
+            .windows(2)
+            .all(|w| w[1].bucket_start - w[0].bucket_start == Duration::days(1)));
+
+        // The day clocks spring forward is an hour short, and buckets follow it
+        let since = "2024-03-09T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let around_dst = db
+            .get_outage_trends(since, since + Duration::days(3), TrendGranularity::Day, &tz)
+            .unwrap();
+        let starts: Vec<String> = around_dst
+            .iter()
+            .map(|r| r.bucket_start.to_rfc3339())
+            .collect();
+        assert_eq!(
+            starts,
+            [
+                "2024-03-09T08:00:00+00:00",
+                "2024-03-10T08:00:00+00:00",
+                "2024-03-11T07:00:00+00:00",
+                "2024-03-12T07:00:00+00:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_recovered_outage_after_failed_insert() {
+        let db = Database::in_memory().unwrap();
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+
+        // Simulate the insert at OFFLINE failing
+        db.conn.execute_batch("PRAGMA query_only = ON").unwrap();
+        assert!(db.insert_outage(&outage).is_err());
+        db.conn.execute_batch("PRAGMA query_only = OFF").unwrap();
+
+        outage.end();
+        let id = db.record_recovered_outage(&outage, None).unwrap();
+
+        let stored = db.get_outage(id).unwrap().unwrap();
+        assert!(stored.end_time.is_some());
+        assert_eq!(stored.duration_secs, outage.duration_secs);
+        assert!(db.get_ongoing_outage().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_update_missing_outage_fails() {
+        let db = Database::in_memory().unwrap();
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.end();
+
+        let err = db.update_outage(&outage).unwrap_err();
+        assert!(matches!(err, DbError::OutageNotFound(None)));
+
+        outage.id = Some(42);
+        let err = db.update_outage(&outage).unwrap_err();
+        assert!(matches!(err, DbError::OutageNotFound(Some(42))));
+        assert_eq!(err.to_string(), "Outage not found: ID 42");
+
+        // Recovery saves it again rather than losing it
+        let id = db.record_recovered_outage(&outage, Some(42)).unwrap();
+        assert!(db.get_outage(id).unwrap().unwrap().end_time.is_some());
+    }
+
+    #[test]
+    fn test_record_recovered_outage_updates_existing() {
+        let db = Database::in_memory().unwrap();
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        let id = db.insert_outage(&outage).unwrap();
+
+        outage.end();
+        assert_eq!(db.record_recovered_outage(&outage, Some(id)).unwrap(), id);
+        assert!(db.get_ongoing_outage().unwrap().is_none());
+        let until = Utc::now() + Duration::seconds(1);
+        assert_eq!(
+            db.get_outages(until - Duration::hours(1), until)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_acknowledge_ongoing_outage() {
+        let db = Database::in_memory().unwrap();
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        let id = db.insert_outage(&outage).unwrap();
+        assert!(!db.get_ongoing_outage().unwrap().unwrap().acknowledged);
+
+        assert!(db.acknowledge_outage(id).unwrap());
+        let ongoing = db.get_ongoing_outage().unwrap().unwrap();
+        assert_eq!(ongoing.id, Some(id));
+        assert!(ongoing.acknowledged);
+
+        // The daemon's recovery update doesn't clear the ack
+        outage.id = Some(id);
+        outage.end();
+        db.update_outage(&outage).unwrap();
+        assert!(db.get_outage(id).unwrap().unwrap().acknowledged);
+
+        assert!(!db.acknowledge_outage(999).unwrap());
+    }
+
+    fn trace_ending_at(hops: &[Option<&str>], success: bool) -> TracerouteResult {
+        TracerouteResult {
+            target: "8.8.8.8".to_string(),
+            timestamp: Utc::now() - Duration::minutes(5),
+            hops: hops
+                .iter()
+                .enumerate()
+                .map(|(i, ip)| TracerouteHop {
+                    hop_number: i as u8 + 1,
+                    ip: ip.map(str::to_string),
+                    hostname: None,
+                    latency_ms: ip.map(|_| 5.0),
+                    timeout: ip.is_none(),
+                })
+                .collect(),
+            success,
+        }
+    }
+
+    fn hop_rows(db: &Database) -> i64 {
+        db.conn
+            .query_row("SELECT COUNT(*) FROM traceroute_hops", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_get_outage_with_traceroutes() {
+        let db = Database::in_memory().unwrap();
+        let id = db
+            .insert_outage(&Outage::new(vec!["8.8.8.8".to_string()]))
+            .unwrap();
+        let other = db
+            .insert_outage(&Outage::new(vec!["1.1.1.1".to_string()]))
+            .unwrap();
+
+        let mut first = trace_ending_at(&[Some("192.168.1.1"), None, None], false);
+        first.timestamp = Utc::now() - Duration::minutes(10);
+        let second = trace_ending_at(&[Some("192.168.1.1"), Some("10.0.0.1")], true);
+        db.insert_traceroute(Some(id), &second, TraceTrigger::Periodic)
+            .unwrap();
+        db.insert_traceroute(Some(id), &first, TraceTrigger::StateChange)
+            .unwrap();
+        db.insert_traceroute(Some(other), &first, TraceTrigger::StateChange)
+            .unwrap();
+
+        let outage = db.get_outage(id).unwrap().unwrap();
+        assert_eq!(outage.affected_targets, vec!["8.8.8.8"]);
+        assert!(db.get_outage(id + 100).unwrap().is_none());
+
+        // Only this outage's traces, oldest first, with hops decoded
+        let traces = db.get_traceroutes_for_outage(id).unwrap();
+        assert_eq!(traces.len(), 2);
+        assert!(!traces[0].result.success);
+        assert_eq!(traces[0].result.hops.len(), 3);
+        assert!(traces[0].result.hops[2].timeout);
+        assert!(traces[1].result.success);
+        assert_eq!(traces[1].result.hops[1].ip.as_deref(), Some("10.0.0.1"));
+        assert_ne!(traces[0].id, traces[1].id);
+        assert!(db.get_traceroutes_for_outage(id + 100).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_traceroute_trigger_round_trip() {
+        let db = Database::in_memory().unwrap();
+        let id = db
+            .insert_outage(&Outage::new(vec!["8.8.8.8".to_string()]))
+            .unwrap();
+        let triggers = [TraceTrigger::StateChange, TraceTrigger::Periodic];
+        for trigger in triggers {
+            db.insert_traceroute(
+                Some(id),
+                &trace_ending_at(&[Some("192.168.1.1"), None], false),
+                trigger,
+            )
+            .unwrap();
+        }
+
+        let stored = db.get_traceroutes_for_outage(id).unwrap();
+        let stored_triggers: Vec<TraceTrigger> = stored.iter().map(|t| t.trigger).collect();
+        assert_eq!(stored_triggers, triggers);
+        assert_eq!(stored[0].result.hops.len(), 2);
+
+        // Rows from before the column existed read as state_change
+        db.conn
+            .execute(
+                "INSERT INTO traceroutes (outage_id, timestamp, target, hops, success) VALUES (?1, ?2, '8.8.8.8', '[]', 0)",
+                params![id, Utc::now().to_rfc3339()],
+            )
+            .unwrap();
+        let stored = db.get_traceroutes_for_outage(id).unwrap();
+        assert_eq!(stored.last().unwrap().trigger, TraceTrigger::StateChange);
+    }
+
+    #[test]
+    fn test_frequent_failing_ips() {
+        let db = Database::in_memory().unwrap();
+        let until = Utc::now();
+        let since = until - Duration::hours(1);
+
+        let failed_at_modem =
+            trace_ending_at(&[Some("192.168.1.1"), Some("10.0.0.1"), None], false);
+        db.insert_traceroute(None, &failed_at_modem, TraceTrigger::StateChange)
+            .unwrap();
+        db.insert_traceroute(None, &failed_at_modem, TraceTrigger::StateChange)
+            .unwrap();
+        db.insert_traceroute(
+            None,
+            &trace_ending_at(&[Some("192.168.1.1"), None, None], false),
+            TraceTrigger::StateChange,
+        )
+        .unwrap();
+        // Successful traces never count
+        db.insert_traceroute(
+            None,
+            &trace_ending_at(&[Some("192.168.1.1"), Some("8.8.8.8")], true),
+            TraceTrigger::Periodic,
+        )
+        .unwrap();
+
+        assert_eq!(hop_rows(&db), 11);
+        assert_eq!(
+            db.get_frequent_failing_ips(since, until).unwrap(),
+            vec![
+                HopFrequency {
+                    ip: "10.0.0.1".to_string(),
+                    occurrences: 2,
+                },
+                HopFrequency {
+                    ip: "192.168.1.1".to_string(),
+                    occurrences: 1,
+                },
+            ]
+        );
+        assert!(db
+            .get_frequent_failing_ips(since - Duration::hours(2), since)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_migrate_v7_backfills_hops() {
+        let db = Database::in_memory().unwrap();
+        let trace = trace_ending_at(&[Some("192.168.1.1"), Some("10.0.0.1"), None], false);
+        db.insert_traceroute(None, &trace, TraceTrigger::StateChange)
+            .unwrap();
+
+        // Rewind to a v6 database that only has the blob
+        db.conn
+            .execute_batch(
+                "DROP TABLE traceroute_hops; DELETE FROM schema_version WHERE version >= 7;",
+            )
+            .unwrap();
+        db.apply_migration(7, "Normalized traceroute hops", Database::migrate_v7)
+            .unwrap();
+
+        assert_eq!(hop_rows(&db), 3);
+        assert_eq!(db.current_version().unwrap(), 7);
+        let until = Utc::now();
+        let frequent = db
+            .get_frequent_failing_ips(until - Duration::hours(1), until)
+            .unwrap();
+        assert_eq!(frequent[0].ip, "10.0.0.1");
+    }
+
+    #[test]
+    fn test_markers() {
+        let db = Database::in_memory().unwrap();
+        let now = Utc::now();
+
+        db.insert_marker(now - Duration::hours(3), "old").unwrap();
+        let id = db
+            .insert_marker(now - Duration::minutes(30), "unplugged router")
+            .unwrap();
+        db.insert_marker(now - Duration::minutes(10), "plugged back in")
+            .unwrap();
+
+        let markers = db
+            .get_markers_in_range(now - Duration::hours(1), now)
+            .unwrap();
+        assert_eq!(markers.len(), 2);
+        assert_eq!(markers[0].label, "plugged back in");
+        assert_eq!(markers[1].id, id);
+        assert_eq!(markers[1].label, "unplugged router");
+    }
+
+    #[test]
+    fn test_degraded_events() {
+        let db = Database::in_memory().unwrap();
+        let now = Utc::now();
+
+        let mut near_miss = DegradedEvent::new(vec!["8.8.8.8".to_string()]);
+        near_miss.start_time = now - Duration::minutes(30);
+        near_miss.id = Some(db.insert_degraded_event(&near_miss).unwrap());
+        near_miss.end_at(now - Duration::minutes(29));
+        db.update_degraded_event(&near_miss).unwrap();
+
+        let mut escalated = DegradedEvent::new(vec!["8.8.8.8".to_string(), "1.1.1.1".to_string()]);
+        escalated.start_time = now - Duration::minutes(10);
+        escalated.id = Some(db.insert_degraded_event(&escalated).unwrap());
+        let outage_id = db.insert_outage(&Outage::new(vec![])).unwrap();
+        escalated.end_at(now - Duration::minutes(9));
+        escalated.escalate(outage_id);
+        db.update_degraded_event(&escalated).unwrap();
+
+        let mut canaries_up = DegradedEvent::new(vec!["10.0.0.1".to_string()]);
+        canaries_up.start_time = now - Duration::minutes(5);
+        canaries_up.id = Some(db.insert_degraded_event(&canaries_up).unwrap());
+        canaries_up.target_specific = true;
+        db.update_degraded_event(&canaries_up).unwrap();
+
+        let events = db
+            .get_degraded_events(now - Duration::hours(1), now)
+            .unwrap();
+        assert_eq!(events, vec![canaries_up, escalated, near_miss]);
+        assert!(events[0].target_specific);
+        assert!(!events[1].is_near_miss());
+        assert!(events[2].is_near_miss());
+    }
+
+    #[test]
+    fn test_ping_stats_p95() {
+        let db = Database::in_memory().unwrap();
+        let now = Utc::now();
+        let ping = |secs_ago: i64, latency_ms: Option<f64>| PingResult {
+            target: "8.8.8.8".to_string(),
+            target_name: "Google DNS".to_string(),
+            timestamp: now - Duration::seconds(secs_ago),
+            success: latency_ms.is_some(),
+            latency_ms,
+            jitter_ms: None,
+            loss: None,
+            error: None,
+            error_kind: None,
+            resolved_ip: None,
         };
 
-        let avg_outage_duration_secs = if total_outages > 0 {
-            Some(total_downtime_secs / total_outages as f64)
-        } else {
+        // 1..=95 ms, five slow outliers, and four losses
+        for ms in 1..=95 {
+            db.insert_ping(&ping(ms, Some(ms as f64))).unwrap();
+        }
+        for ms in [200.0, 300.0, 400.0, 500.0, 600.0] {
+            db.insert_ping(&ping(100, Some(ms))).unwrap();
+        }
+        for _ in 0..4 {
+            db.insert_ping(&ping(100, None)).unwrap();
+        }
+        // Outside the window
+        db.insert_ping(&ping(7200, Some(5000.0))).unwrap();
+
+        let stats = db
+            .get_ping_stats("8.8.8.8", now - Duration::hours(1), now, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stats.samples, 104);
+        assert_eq!(stats.min_ms, Some(1.0));
+        assert_eq!(stats.max_ms, Some(600.0));
+        assert_eq!(stats.p95_ms, Some(95.0));
+        assert!((stats.loss_percent.unwrap() - 4.0 / 104.0 * 100.0).abs() < 1e-9);
+
+        let empty = db
+            .get_ping_stats("1.1.1.1", now - Duration::hours(1), now, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(empty.samples, 0);
+        assert_eq!(empty.p95_ms, None);
+
+        // Change-only ping_log rows are no basis for loss or percentiles
+        assert_eq!(
+            db.get_ping_stats("8.8.8.8", now - Duration::hours(1), now, false)
+                .unwrap(),
             None
+        );
+    }
+
+    #[test]
+    fn test_ping_stats_from_latency_samples() {
+        let mut db = Database::in_memory().unwrap();
+        db.set_latency_table(true);
+        let now = Utc::now();
+        for (secs_ago, latency_ms) in [(30, Some(10.0)), (20, None), (10, Some(20.0))] {
+            db.insert_latency_sample(&PingResult {
+                target: "8.8.8.8".to_string(),
+                target_name: "Google DNS".to_string(),
+                timestamp: now - Duration::seconds(secs_ago),
+                success: latency_ms.is_some(),
+                latency_ms,
+                jitter_ms: None,
+                loss: None,
+                error: None,
+                error_kind: None,
+                resolved_ip: None,
+            })
+            .unwrap();
+        }
+
+        let stats = db
+            .get_ping_stats("8.8.8.8", now - Duration::hours(1), now, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stats.samples, 2);
+        assert_eq!(stats.avg_ms, Some(15.0));
+        assert_eq!(stats.variation_ms, Some(10.0));
+        // Lost pings aren't stored there
+        assert_eq!(stats.loss_percent, None);
+    }
+
+    #[test]
+    fn test_ping_stats_variation() {
+        let db = Database::in_memory().unwrap();
+        let now = Utc::now();
+        let ping = |secs_ago: i64, latency_ms: Option<f64>| PingResult {
+            target: "8.8.8.8".to_string(),
+            target_name: "Google DNS".to_string(),
+            timestamp: now - Duration::seconds(secs_ago),
+            success: latency_ms.is_some(),
+            latency_ms,
+            jitter_ms: None,
+            loss: None,
+            error: None,
+            error_kind: None,
+            resolved_ip: None,
         };
 
-        // Find most common failing hop
-        let mut hop_counts: std::collections::HashMap<u8, u32> = std::collections::HashMap::new();
-        for outage in &outages {
-            if let Some(hop) = outage.failing_hop {
-                *hop_counts.entry(hop).or_insert(0) += 1;
-            }
+        // Inserted out of order; in time order the RTTs are 10, 14, 12, 20
+        // (the loss in between doesn't count)
+        for (secs_ago, latency) in [(30, Some(14.0)), (40, Some(10.0)), (10, Some(20.0))] {
+            db.insert_ping(&ping(secs_ago, latency)).unwrap();
         }
-        let most_common_failing_hop = hop_counts
-            .into_iter()
-            .max_by_key(|(_, count)| *count)
-            .map(|(hop, _)| hop);
+        db.insert_ping(&ping(25, None)).unwrap();
+        db.insert_ping(&ping(20, Some(12.0))).unwrap();
 
-        Ok(Stats {
-            period_start: since,
-            period_end: until,
-            total_outages,
-            total_downtime_secs,
-            availability_percent,
-            avg_outage_duration_secs,
-            most_common_failing_hop,
-        })
+        let stats = db
+            .get_ping_stats("8.8.8.8", now - Duration::hours(1), now, true)
+            .unwrap()
+            .unwrap();
+        // |14-10| + |12-14| + |20-12| = 14 over 3 pairs
+        assert!((stats.variation_ms.unwrap() - 14.0 / 3.0).abs() < 1e-9);
+
+        // One reply has nothing to vary against
+        assert_eq!(PingStats::from_latencies(vec![10.0], 1).variation_ms, None);
+        assert_eq!(
+            PingStats::from_latencies(vec![10.0, 10.0, 10.0], 3).variation_ms,
+            Some(0.0)
+        );
     }
 
-    /// Delete old data based on retention policy
-    pub fn cleanup(&self, retention_days: u32) -> Result<u64, DbError> {
-        let cutoff = Utc::now() - Duration::days(retention_days as i64);
-        let cutoff_str = cutoff.to_rfc3339();
+    #[test]
+    fn test_latency_samples() {
+        let mut db = Database::in_memory().unwrap();
+        let now = Utc::now();
+        let ping = |target: &str, mins_ago: i64, latency_ms: Option<f64>| PingResult {
+            target: target.to_string(),
+            target_name: target.to_string(),
+            timestamp: now - Duration::minutes(mins_ago),
+            success: latency_ms.is_some(),
+            latency_ms,
+            jitter_ms: None,
+            loss: None,
+            error: None,
+            error_kind: None,
+            resolved_ip: None,
+        };
 
-        let deleted_pings = self.conn.execute(
-            "DELETE FROM ping_log WHERE timestamp < ?1",
-            params![cutoff_str],
-        )?;
+        // Off by default: only ping_log is written
+        db.insert_pings_batch(&[ping("8.8.8.8", 50, Some(9.0))])
+            .unwrap();
+        db.insert_latency_sample(&ping("8.8.8.8", 50, Some(9.0)))
+            .unwrap();
+        assert!(db
+            .get_latency_series("8.8.8.8", now - Duration::hours(1), now)
+            .unwrap()
+            .is_empty());
 
-        let deleted_traceroutes = self.conn.execute(
-            "DELETE FROM traceroutes WHERE timestamp < ?1",
-            params![cutoff_str],
-        )?;
+        db.set_latency_table(true);
+        db.insert_pings_batch(&[ping("8.8.8.8", 30, Some(12.5)), ping("8.8.8.8", 20, None)])
+            .unwrap();
+        db.insert_latency_sample(&ping("1.1.1.1", 15, Some(4.0)))
+            .unwrap();
+        db.insert_latency_sample(&ping("8.8.8.8", 10, Some(14.0)))
+            .unwrap();
+        db.insert_latency_sample(&ping("8.8.8.8", 90, Some(20.0)))
+            .unwrap();
+        // Sampled pings go to ping_log alone, so each is stored once
+        db.insert_ping(&ping("8.8.8.8", 10, Some(14.0))).unwrap();
 
-        let deleted_outages = self.conn.execute(
-            "DELETE FROM outages WHERE start_time < ?1",
-            params![cutoff_str],
-        )?;
+        let series = db
+            .get_latency_series("8.8.8.8", now - Duration::hours(1), now)
+            .unwrap();
+        let latencies: Vec<f64> = series.iter().map(|(_, latency)| *latency).collect();
+        assert_eq!(latencies, vec![12.5, 14.0]);
+        assert!(series[0].0 < series[1].0);
 
-        Ok((deleted_pings + deleted_traceroutes + deleted_outages) as u64)
+        let recent = db
+            .get_latency_series("8.8.8.8", now - Duration::minutes(15), now)
+            .unwrap();
+        assert_eq!(recent.len(), 1);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_insert_10k_pings_batch() {
+        let db = Database::in_memory().unwrap();
+        let now = Utc::now();
+        let pings: Vec<PingResult> = (0..10_000)
+            .map(|i| PingResult {
+                target: format!("10.0.0.{}", i % 4),
+                target_name: "Bulk".to_string(),
+                timestamp: now - Duration::milliseconds(i),
+                success: true,
+                latency_ms: Some(1.0),
+                jitter_ms: None,
+                loss: None,
+                error: None,
+                error_kind: None,
+                resolved_ip: None,
+            })
+            .collect();
+
+        let started = Instant::now();
+        assert_eq!(db.insert_pings_batch(&pings).unwrap(), 10_000);
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM ping_log", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 10_000);
+        // One transaction; per-row commits would take far longer on disk
+        assert!(started.elapsed() < StdDuration::from_secs(10));
+    }
 
     #[test]
-    fn test_create_database() {
+    fn test_corrupt_affected_targets() {
+        let mut db = Database::in_memory().unwrap();
+        let id = db
+            .insert_outage(&Outage::new(vec!["8.8.8.8".to_string()]))
+            .unwrap();
+        db.conn
+            .execute(
+                "UPDATE outages SET affected_targets = '[\"8.8.8.8\",' WHERE id = ?1",
+                params![id],
+            )
+            .unwrap();
+
+        // Lenient by default: warned about, counted and read as empty
+        let outage = db.get_outage(id).unwrap().unwrap();
+        assert!(outage.affected_targets.is_empty());
+        assert_eq!(db.corrupt_rows(), 1);
+
+        db.set_strict_json(true);
+        match db.get_outage(id) {
+            Err(DbError::CorruptAffectedTargets { id: bad, .. }) => assert_eq!(bad, id),
+            other => panic!(
+                "expected CorruptAffectedTargets, got {:?}",
+                other.map(|_| ())
+            ),
+        }
+    }
+
+    #[test]
+    fn test_insert_pings_batch() {
+        let mut db = Database::in_memory().unwrap();
+        db.set_latency_table(true);
+        let now = Utc::now();
+        let pings: Vec<PingResult> = (0..5)
+            .map(|i| PingResult {
+                target: "8.8.8.8".to_string(),
+                target_name: "Google DNS".to_string(),
+                timestamp: now - Duration::seconds(i),
+                success: i != 2,
+                latency_ms: (i != 2).then_some(10.0 + i as f64),
+                jitter_ms: None,
+                loss: None,
+                error: None,
+                error_kind: None,
+                resolved_ip: None,
+            })
+            .collect();
+
+        assert_eq!(db.insert_pings_batch(&pings).unwrap(), 5);
+        assert_eq!(db.insert_pings_batch(&[]).unwrap(), 0);
+
+        let count: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM ping_log", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 5);
+        let series = db
+            .get_latency_series("8.8.8.8", now - Duration::minutes(1), now)
+            .unwrap();
+        assert_eq!(series.len(), 4);
+    }
+
+    fn assert_rollup_matches_full_scan(db: &Database, since: DateTime<Utc>, until: DateTime<Utc>) {
+        let rollup = db.get_stats_uncached(since, until).unwrap();
+        let full = db.get_stats_full_scan(since, until).unwrap();
+        assert_eq!(rollup.total_outages, full.total_outages);
+        assert_eq!(rollup.total_downtime_secs, full.total_downtime_secs);
+        assert_eq!(rollup.availability_percent, full.availability_percent);
+        assert_eq!(
+            rollup.avg_outage_duration_secs,
+            full.avg_outage_duration_secs
+        );
+        assert_eq!(rollup.most_common_failing_hop, full.most_common_failing_hop);
+    }
+
+    fn seed_across_days(db: &Database, now: DateTime<Utc>) -> Vec<i64> {
+        (0..30)
+            .map(|i| {
+                let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+                outage.start_time = now - Duration::hours(i * 7 + 1);
+                outage.end_time = Some(outage.start_time + Duration::seconds(i + 10));
+                outage.duration_secs = Some((i + 10) as f64);
+                outage.failing_hop = match i % 4 {
+                    0 => None,
+                    3 => Some(3),
+                    _ => Some(2),
+                };
+                outage.planned = i % 9 == 0;
+                db.insert_outage(&outage).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_daily_rollup_matches_full_scan() {
         let db = Database::in_memory().unwrap();
-        assert!(db.get_ongoing_outage().unwrap().is_none());
+        let now = Utc::now();
+        let ids = seed_across_days(&db, now);
+
+        // An ongoing outage counts, with no downtime yet
+        let mut ongoing = Outage::new(vec!["1.1.1.1".to_string()]);
+        ongoing.start_time = now - Duration::minutes(30);
+        db.insert_outage(&ongoing).unwrap();
+
+        for (since, until) in [
+            (now - Duration::days(7), now),
+            (now - Duration::days(3), now - Duration::hours(20)),
+            (now - Duration::days(30), now + Duration::hours(1)),
+        ] {
+            assert_rollup_matches_full_scan(&db, since, until);
+        }
+        let week = db.get_stats_uncached(now - Duration::days(7), now).unwrap();
+        // 24 outages in the week, 3 of them planned, plus the ongoing one
+        assert_eq!(week.total_outages, 22);
+        assert_eq!(week.most_common_failing_hop, Some(2));
+
+        // Closing and re-tagging outages keeps the rollup in step
+        let mut outage = db.get_outage(ids[5]).unwrap().unwrap();
+        outage.system_sleep = true;
+        db.update_outage(&outage).unwrap();
+        let mut outage = db.get_outage(ids[12]).unwrap().unwrap();
+        outage.duration_secs = Some(600.0);
+        db.update_outage(&outage).unwrap();
+        assert_rollup_matches_full_scan(&db, now - Duration::days(7), now);
+        assert_eq!(
+            db.get_stats_uncached(now - Duration::days(7), now)
+                .unwrap()
+                .total_outages,
+            21
+        );
     }
 
     #[test]
-    fn test_insert_and_get_outage() {
+    fn test_backup_restores_identical_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("monitor.db");
+        let backup_path = dir.path().join("monitor.db.backup");
+        let now = Utc::now();
+
+        let db = Database::open(&db_path).unwrap();
+        seed_across_days(&db, now);
+        db.insert_marker(now, "unplugged router").unwrap();
+        db.insert_traceroute(
+            None,
+            &trace_ending_at(&[Some("192.168.1.1"), None], false),
+            TraceTrigger::StateChange,
+        )
+        .unwrap();
+        db.backup_to(&backup_path).unwrap();
+
+        let counts = |db: &Database| -> Vec<i64> {
+            [
+                "outages",
+                "markers",
+                "traceroutes",
+                "traceroute_hops",
+                "daily_stats",
+            ]
+            .iter()
+            .map(|table| {
+                db.conn
+                    .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                        row.get(0)
+                    })
+                    .unwrap()
+            })
+            .collect()
+        };
+        let before = counts(&db);
+        assert!(before.iter().all(|&n| n > 0));
+
+        // Later writes are undone by restoring
+        db.insert_marker(now, "after backup").unwrap();
+        drop(db);
+        Database::restore(&backup_path, &db_path).unwrap();
+
+        let restored = Database::open(&db_path).unwrap();
+        assert_eq!(counts(&restored), before);
+        assert_eq!(restored.current_version().unwrap(), SCHEMA_VERSION);
+
+        // Anything that isn't a vigil database is refused
+        let junk = dir.path().join("junk.db");
+        std::fs::write(&junk, "not a database").unwrap();
+        assert!(Database::restore(&junk, &db_path).is_err());
+        assert_eq!(counts(&Database::open(&db_path).unwrap()), before);
+    }
+
+    #[test]
+    fn test_old_schema_is_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("monitor.db");
+
+        // A database as first released: v1 tables only, with an outage in it
+        {
+            let db = Database::from_connection(Connection::open(&path).unwrap());
+            db.conn
+                .execute_batch(
+                    "CREATE TABLE schema_version (version INTEGER PRIMARY KEY, applied_at TEXT NOT NULL DEFAULT (datetime('now')), description TEXT);",
+                )
+                .unwrap();
+            db.apply_migration(1, MIGRATIONS[0].1, MIGRATIONS[0].2)
+                .unwrap();
+            db.conn
+                .execute(
+                    "INSERT INTO outages (start_time, affected_targets) VALUES (?1, '[\"8.8.8.8\"]')",
+                    params![Utc::now().to_rfc3339()],
+                )
+                .unwrap();
+        }
+        assert_eq!(Database::stored_version(&path).unwrap(), 1);
+        assert_eq!(pending_migrations(1).len(), (SCHEMA_VERSION - 1) as usize);
+
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.current_version().unwrap(), SCHEMA_VERSION);
+        assert_eq!(Database::stored_version(&path).unwrap(), SCHEMA_VERSION);
+        assert!(pending_migrations(SCHEMA_VERSION).is_empty());
+
+        let outages = db
+            .get_outages(Utc::now() - Duration::hours(1), Utc::now())
+            .unwrap();
+        assert_eq!(outages.len(), 1);
+        assert!(!outages[0].acknowledged);
+    }
+
+    #[test]
+    fn test_migrate_v9_backfills_rollup() {
+        let db = Database::in_memory().unwrap();
+        let now = Utc::now();
+        seed_across_days(&db, now);
+
+        db.conn
+            .execute_batch("DROP TABLE daily_stats; DELETE FROM schema_version WHERE version >= 9;")
+            .unwrap();
+        db.apply_migration(9, "Daily stats rollup", Database::migrate_v9)
+            .unwrap();
+
+        assert_eq!(db.current_version().unwrap(), 9);
+        assert_rollup_matches_full_scan(&db, now - Duration::days(10), now);
+    }
+
+    #[test]
+    fn test_compact_keeps_pings_around_old_outages() {
         let db = Database::in_memory().unwrap();
+        let now = Utc::now();
+        let outage_start = now - Duration::days(30);
 
         let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
-        let id = db.insert_outage(&outage).unwrap();
-        outage.id = Some(id);
+        outage.start_time = outage_start;
+        outage.end_at(outage_start + Duration::minutes(10));
+        db.insert_outage(&outage).unwrap();
 
-        let ongoing = db.get_ongoing_outage().unwrap();
-        assert!(ongoing.is_some());
-        assert_eq!(ongoing.unwrap().id, Some(id));
+        let offsets = [
+            ("before", outage_start - Duration::minutes(30)),
+            ("during", outage_start + Duration::minutes(5)),
+            ("after", outage_start + Duration::minutes(40)),
+            ("distant", outage_start - Duration::hours(3)),
+            ("healthy", now - Duration::days(20)),
+            ("recent", now - Duration::days(2)),
+        ];
+        for (name, timestamp) in offsets {
+            db.insert_ping(&PingResult {
+                target: "8.8.8.8".to_string(),
+                target_name: name.to_string(),
+                timestamp,
+                success: true,
+                latency_ms: Some(12.0),
+                jitter_ms: None,
+                loss: None,
+                error: None,
+                error_kind: None,
+                resolved_ip: None,
+            })
+            .unwrap();
+        }
 
-        // End the outage
-        outage.end();
-        db.update_outage(&outage).unwrap();
+        let deleted = db
+            .compact_keeping_outage_context(now - Duration::days(14), Duration::hours(1))
+            .unwrap();
+        assert_eq!(deleted, 2);
 
-        let ongoing = db.get_ongoing_outage().unwrap();
-        assert!(ongoing.is_none());
+        let mut stmt = db
+            .conn
+            .prepare("SELECT target_name FROM ping_log ORDER BY timestamp")
+            .unwrap();
+        let kept: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(kept, vec!["before", "during", "after", "recent"]);
+
+        // Nothing left to compact on a second run
+        assert_eq!(
+            db.compact_keeping_outage_context(now - Duration::days(14), Duration::hours(1))
+                .unwrap(),
+            0
+        );
     }
 
     #[test]
-    fn test_insert_ping() {
+    fn test_cleanup_pings_beyond_count() {
         let db = Database::in_memory().unwrap();
+        let now = Utc::now();
+        for (target, count) in [("8.8.8.8", 7), ("1.1.1.1", 2)] {
+            for i in 0..count {
+                db.insert_ping(&PingResult {
+                    target: target.to_string(),
+                    target_name: target.to_string(),
+                    timestamp: now - Duration::minutes(i),
+                    success: true,
+                    latency_ms: Some(i as f64),
+                    jitter_ms: None,
+                    loss: None,
+                    error: None,
+                    error_kind: None,
+                    resolved_ip: None,
+                })
+                .unwrap();
+            }
+        }
 
-        let ping = PingResult {
-            target: "8.8.8.8".to_string(),
-            target_name: "Google DNS".to_string(),
-            timestamp: Utc::now(),
-            success: true,
-            latency_ms: Some(15.5),
-            error: None,
+        let day_ago = now - Duration::hours(24);
+        db.get_stats(day_ago, now).unwrap();
+        assert_eq!(db.cleanup_pings_beyond(3).unwrap(), 4);
+        // Cached stats don't outlive the deleted rows
+        db.get_stats(day_ago, now).unwrap();
+        assert_eq!(db.stats_computations(), 2);
+
+        let count = |target: &str| -> i64 {
+            db.conn
+                .query_row(
+                    "SELECT COUNT(*) FROM ping_log WHERE target = ?1",
+                    params![target],
+                    |row| row.get(0),
+                )
+                .unwrap()
         };
+        assert_eq!(count("8.8.8.8"), 3);
+        assert_eq!(count("1.1.1.1"), 2);
 
-        db.insert_ping(&ping).unwrap();
+        // The newest samples are the ones kept
+        let oldest_kept: f64 = db
+            .conn
+            .query_row(
+                "SELECT MAX(latency_ms) FROM ping_log WHERE target = '8.8.8.8'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(oldest_kept, 2.0);
+    }
+
+    #[test]
+    fn test_average_jitter() {
+        let db = Database::in_memory().unwrap();
+        let now = Utc::now();
+        assert!(db
+            .get_average_jitter(now - Duration::hours(1), now)
+            .unwrap()
+            .is_none());
+
+        for jitter in [Some(1.0), Some(3.0), None] {
+            db.insert_ping(&PingResult {
+                target: "8.8.8.8".to_string(),
+                target_name: "Google DNS".to_string(),
+                timestamp: now - Duration::minutes(1),
+                success: jitter.is_some(),
+                latency_ms: jitter.map(|_| 14.0),
+                jitter_ms: jitter,
+                loss: None,
+                error: None,
+                error_kind: None,
+                resolved_ip: None,
+            })
+            .unwrap();
+        }
+
+        assert_eq!(
+            db.get_average_jitter(now - Duration::hours(1), now)
+                .unwrap(),
+            Some(2.0)
+        );
     }
 
     #[test]
@@ -430,4 +2952,43 @@ mod tests {
         assert_eq!(stats.total_outages, 0);
         assert_eq!(stats.availability_percent, 100.0);
     }
+
+    #[test]
+    fn test_stats_cache() {
+        let mut db = Database::in_memory().unwrap();
+        db.set_stats_cache_ttl(StdDuration::from_millis(50));
+
+        let until = Utc::now();
+        let since = until - Duration::hours(24);
+
+        // Two quick calls within the TTL compute only once
+        db.get_stats(since, until).unwrap();
+        db.get_stats(since, until).unwrap();
+        assert_eq!(db.stats_computations(), 1);
+
+        // After expiry the stats are recomputed
+        std::thread::sleep(StdDuration::from_millis(60));
+        db.get_stats(since, until).unwrap();
+        assert_eq!(db.stats_computations(), 2);
+
+        // Writes invalidate the cache
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.start_time = until - Duration::hours(1);
+        db.insert_outage(&outage).unwrap();
+        let stats = db.get_stats(since, until).unwrap();
+        assert_eq!(db.stats_computations(), 3);
+        assert_eq!(stats.total_outages, 1);
+
+        // The uncached path always recomputes
+        db.get_stats_uncached(since, until).unwrap();
+        assert_eq!(db.stats_computations(), 4);
+
+        // Ranges a moment apart, as from `Utc::now()` on each call, share an entry
+        db.set_stats_cache_ttl(StdDuration::from_secs(60));
+        let until = DateTime::from_timestamp(1_700_000_040, 0).unwrap();
+        db.get_stats(until - Duration::hours(24), until).unwrap();
+        let later = until + Duration::seconds(5);
+        db.get_stats(later - Duration::hours(24), later).unwrap();
+        assert_eq!(db.stats_computations(), 5);
+    }
 }
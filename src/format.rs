@@ -0,0 +1,57 @@
+/// Format a duration in seconds to a human-readable string
+pub fn format_duration_secs(secs: f64) -> String {
+    if secs < 60.0 {
+        format!("{:.1}s", secs)
+    } else if secs < 3600.0 {
+        let mins = (secs / 60.0).floor();
+        let remaining_secs = secs % 60.0;
+        if remaining_secs < 1.0 {
+            format!("{}m", mins as i64)
+        } else {
+            format!("{}m {}s", mins as i64, remaining_secs as i64)
+        }
+    } else {
+        let hours = (secs / 3600.0).floor();
+        let remaining_mins = ((secs % 3600.0) / 60.0).floor();
+        if remaining_mins < 1.0 {
+            format!("{}h", hours as i64)
+        } else {
+            format!("{}h {}m", hours as i64, remaining_mins as i64)
+        }
+    }
+}
+
+/// Format a latency in milliseconds with the given number of decimals.
+/// Sub-millisecond values are shown in µs, values of a second or more in s.
+pub fn format_latency(ms: f64, precision: usize) -> String {
+    if ms < 1.0 {
+        format!("{:.0}µs", ms * 1000.0)
+    } else if ms < 1000.0 {
+        format!("{:.prec$}ms", ms, prec = precision)
+    } else {
+        format!("{:.prec$}s", ms / 1000.0, prec = precision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration_secs() {
+        assert_eq!(format_duration_secs(5.0), "5.0s");
+        assert_eq!(format_duration_secs(65.0), "1m 5s");
+        assert_eq!(format_duration_secs(3665.0), "1h 1m");
+        assert_eq!(format_duration_secs(7200.0), "2h");
+    }
+
+    #[test]
+    fn test_format_latency() {
+        assert_eq!(format_latency(0.042, 1), "42µs");
+        assert_eq!(format_latency(1.0, 1), "1.0ms");
+        assert_eq!(format_latency(14.123, 1), "14.1ms");
+        assert_eq!(format_latency(14.123, 2), "14.12ms");
+        assert_eq!(format_latency(14.5, 0), "14ms");
+        assert_eq!(format_latency(1234.0, 2), "1.23s");
+    }
+}
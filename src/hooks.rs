@@ -0,0 +1,180 @@
+use crate::config::HooksConfig;
+use crate::monitor::StateEvent;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// How often a running hook is checked for exit
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Error, Debug)]
+pub enum HookError {
+    #[error("Failed to start hook: {0}")]
+    Spawn(#[from] std::io::Error),
+    #[error("Hook killed after {0:?}")]
+    Timeout(Duration),
+    #[error("Hook exited with {0}")]
+    Failed(ExitStatus),
+}
+
+/// Event details handed to a hook command
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookInvocation {
+    /// "degraded", "target_specific", "offline" or "recovered"
+    pub event: &'static str,
+    pub targets: Vec<String>,
+    pub duration_secs: Option<f64>,
+}
+
+impl HookInvocation {
+    /// The invocation for a state event, if hooks run for it
+    pub fn from_state_event(event: &StateEvent) -> Option<Self> {
+        match event {
+            StateEvent::Degraded { failing_targets } => Some(Self {
+                event: "degraded",
+                targets: failing_targets.clone(),
+                duration_secs: None,
+            }),
+            StateEvent::TargetSpecific { failing_targets } => Some(Self {
+                event: "target_specific",
+                targets: failing_targets.clone(),
+                duration_secs: None,
+            }),
+            StateEvent::Offline { outage } => Some(Self {
+                event: "offline",
+                targets: outage.affected_targets.clone(),
+                duration_secs: None,
+            }),
+            StateEvent::Recovered { outage } => Some(Self {
+                event: "recovered",
+                targets: outage.affected_targets.clone(),
+                duration_secs: outage.duration_secs,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Environment variables passed to the hook
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("VIGIL_EVENT", self.event.to_string()),
+            ("VIGIL_TARGETS", self.targets.join(",")),
+            (
+                "VIGIL_DURATION",
+                self.duration_secs
+                    .map(|d| format!("{:.1}", d))
+                    .unwrap_or_default(),
+            ),
+        ]
+    }
+}
+
+/// Runs the configured `[hooks]` commands in the background
+pub struct HookRunner {
+    config: HooksConfig,
+}
+
+impl HookRunner {
+    pub fn new(config: &HooksConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    fn command_for(&self, event: &str) -> Option<&str> {
+        let command = match event {
+            "degraded" => &self.config.on_degraded,
+            "target_specific" => &self.config.on_target_specific,
+            "offline" => &self.config.on_offline,
+            "recovered" => &self.config.on_recovered,
+            _ => &None,
+        };
+        command.as_deref().filter(|c| !c.trim().is_empty())
+    }
+
+    /// Start the hook for `event` on its own thread; failures are only logged.
+    /// The handle can be ignored - the hook is killed if it outlives the timeout.
+    pub fn fire(&self, event: &StateEvent) -> Option<JoinHandle<()>> {
+        let invocation = HookInvocation::from_state_event(event)?;
+        let command = self.command_for(invocation.event)?.to_string();
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+
+        let handle = std::thread::spawn(move || {
+            let event = invocation.event;
+            match run_hook(&command, &invocation.env(), timeout) {
+                Ok(()) => tracing::info!("Hook for {} finished: {}", event, command),
+                Err(e) => tracing::warn!("Hook for {} failed ({}): {}", event, command, e),
+            }
+        });
+        Some(handle)
+    }
+}
+
+/// Run `command` through `sh -c`, killing it once `timeout` has passed
+fn run_hook(command: &str, env: &[(&str, String)], timeout: Duration) -> Result<(), HookError> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return if status.success() {
+                Ok(())
+            } else {
+                Err(HookError::Failed(status))
+            };
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(HookError::Timeout(timeout));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Outage;
+
+    #[test]
+    fn test_hook_receives_event_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("hook.out");
+        let config = HooksConfig {
+            on_recovered: Some(format!(
+                "printf '%s|%s|%s' \"$VIGIL_EVENT\" \"$VIGIL_TARGETS\" \"$VIGIL_DURATION\" > '{}'",
+                out.display()
+            )),
+            ..HooksConfig::default()
+        };
+        let runner = HookRunner::new(&config);
+
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string(), "1.1.1.1".to_string()]);
+        outage.duration_secs = Some(42.0);
+
+        // No hook configured for this event
+        let degraded = StateEvent::Degraded {
+            failing_targets: vec!["8.8.8.8".to_string()],
+        };
+        assert!(runner.fire(&degraded).is_none());
+
+        runner
+            .fire(&StateEvent::Recovered { outage })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "recovered|8.8.8.8,1.1.1.1|42.0"
+        );
+    }
+}
@@ -1,8 +1,11 @@
 pub mod cli;
 pub mod config;
 pub mod db;
+pub mod format;
+pub mod hooks;
 pub mod models;
 pub mod monitor;
+pub mod notifications;
 
 use config::{Config, Environment};
 use std::path::Path;
@@ -12,8 +15,8 @@ use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 /// Software version from Cargo.toml
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-/// Database schema version - increment when adding migrations
-pub const DB_SCHEMA_VERSION: u32 = 1;
+/// Database schema version this build migrates to
+pub const DB_SCHEMA_VERSION: u32 = db::SCHEMA_VERSION as u32;
 
 /// Initialize the logging framework with daily log rotation (for production)
 pub fn init_logging(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
@@ -25,6 +28,11 @@ pub fn init_logging_for_env(
     config: &Config,
     env: &Environment,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // The global subscriber can only be set once per process
+    if tracing::dispatcher::has_been_set() {
+        return Ok(());
+    }
+
     let filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.logging.level));
 
@@ -111,34 +119,16 @@ impl App {
 
     /// Create a new App for a specific environment
     pub fn with_env(env: Environment) -> Result<Self, Box<dyn std::error::Error>> {
-        let config = Config::load_for_env(&env)?;
-        init_logging_for_env(&config, &env)?;
-
-        let db_path = config.database_path_for_env(&env)?;
-        let db = db::Database::open(&db_path)?;
-
-        tracing::info!("Database opened at {:?}", db_path);
-
-        Ok(App {
-            config,
-            db,
-            environment: env,
-        })
+        AppBuilder::new().environment(env).logging(true).build()
     }
 
     /// Create app with a custom database path (for testing)
     #[allow(dead_code)]
     pub fn with_db_path(db_path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
-        let config = Config::load()?;
-        init_logging(&config)?;
-
-        let db = db::Database::open(db_path)?;
-
-        Ok(App {
-            config,
-            db,
-            environment: Environment::Production,
-        })
+        AppBuilder::new()
+            .database(db::Database::open(db_path)?)
+            .logging(true)
+            .build()
     }
 
     /// Get the database path for this app's environment
@@ -146,12 +136,107 @@ impl App {
         self.config.database_path_for_env(&self.environment)
     }
 
+    /// Where the running monitor publishes live target state (next to the database)
+    pub fn live_state_path(&self) -> Result<std::path::PathBuf, config::ConfigError> {
+        Ok(self.db_path()?.with_extension("live.json"))
+    }
+
     /// Get the config path for this app's environment
     pub fn config_path(&self) -> Result<std::path::PathBuf, config::ConfigError> {
         self.environment.config_path()
     }
 }
 
+/// Builds an `App`, loading from disk only what was not supplied
+///
+/// ```no_run
+/// # use vigil::{AppBuilder, config::Config, db::Database};
+/// let app = AppBuilder::new()
+///     .config(Config::default())
+///     .database(Database::in_memory().unwrap())
+///     .build()
+///     .unwrap();
+/// ```
+pub struct AppBuilder {
+    environment: Environment,
+    config: Option<Config>,
+    db: Option<db::Database>,
+    logging: bool,
+}
+
+impl AppBuilder {
+    pub fn new() -> Self {
+        Self {
+            environment: Environment::Production,
+            config: None,
+            db: None,
+            logging: false,
+        }
+    }
+
+    /// Environment used for default paths (config, database, logs)
+    pub fn environment(mut self, env: Environment) -> Self {
+        self.environment = env;
+        self
+    }
+
+    /// Use this config instead of loading the environment's config file
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Use this database instead of opening the configured path
+    pub fn database(mut self, db: db::Database) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// Initialize logging (skipped if a subscriber is already installed)
+    pub fn logging(mut self, enabled: bool) -> Self {
+        self.logging = enabled;
+        self
+    }
+
+    pub fn build(self) -> Result<App, Box<dyn std::error::Error>> {
+        let env = self.environment;
+        let config = match self.config {
+            Some(config) => config,
+            None => Config::load_for_env(&env)?,
+        };
+        if self.logging {
+            init_logging_for_env(&config, &env)?;
+        }
+
+        let mut db = match self.db {
+            Some(db) => db,
+            None => {
+                let db_path = config.database_path_for_env(&env)?;
+                let db = db::Database::open(&db_path)?;
+                tracing::info!("Database opened at {:?}", db_path);
+                db
+            }
+        };
+        db.set_stats_cache_ttl(std::time::Duration::from_secs(
+            config.database.stats_cache_ttl_secs,
+        ));
+        db.set_latency_table(config.database.latency_table);
+        db.set_strict_json(config.database.strict_json);
+
+        Ok(App {
+            config,
+            db,
+            environment: env,
+        })
+    }
+}
+
+impl Default for AppBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Detect the default gateway IP on macOS
 pub fn detect_gateway() -> Option<String> {
     use std::process::Command;
@@ -177,6 +262,36 @@ pub fn detect_gateway() -> Option<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_builder_with_injected_config_and_db() {
+        let mut config = Config::default();
+        config.targets.targets = vec![models::Target::new("Test", "192.0.2.1")];
+        config.database.stats_cache_ttl_secs = 0;
+
+        let app = AppBuilder::new()
+            .environment(Environment::Test)
+            .config(config)
+            .database(db::Database::in_memory().unwrap())
+            .build()
+            .unwrap();
+
+        assert_eq!(app.environment, Environment::Test);
+        assert_eq!(app.config.targets.targets[0].ip, "192.0.2.1");
+
+        let mut outage = models::Outage::new(vec!["192.0.2.1".to_string()]);
+        outage.duration_secs = Some(30.0);
+        app.db.insert_outage(&outage).unwrap();
+
+        // CLI commands run against the injected database
+        cli::outages::run(&app, "24h", cli::outages::DEFAULT_COLUMNS, None).unwrap();
+        let now = chrono::Utc::now();
+        let stats = app
+            .db
+            .get_stats(now - chrono::Duration::hours(1), now)
+            .unwrap();
+        assert_eq!(stats.total_outages, 1);
+    }
+
     #[test]
     fn test_detect_gateway() {
         // This test only works on macOS with a network connection
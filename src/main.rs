@@ -1,11 +1,20 @@
 use clap::{Parser, Subcommand};
 use tokio::signal;
 use vigil::{
-    cli,
-    config::{Config, Environment},
+    cli::{
+        self,
+        start::{ChangeCoalescer, ChangeKind, OutageHeartbeat, StartupSummary, StatusChange},
+    },
+    config::{Config, Environment, PingBackendKind},
     detect_gateway,
-    models::ConnectivityState,
-    monitor::{format_traceroute, ConnectivityTracker, HopAnalyzer, PingMonitor, StateEvent},
+    hooks::HookRunner,
+    models::{ConnectivityState, TraceTrigger, SESSION_HEARTBEAT_SECS, SHUTDOWN_NOTE},
+    monitor::{
+        check_source, describe_ip, detect_sleep_windows, format_traceroute, mostly_asleep,
+        outage_trace_targets, ConnectivityTracker, HopAnalyzer, LiveSnapshot, MetricsRegistry,
+        PingCapability, PingMonitor, StateEvent,
+    },
+    notifications::{self, NotificationEvent, NotificationQueue},
     App, VERSION,
 };
 
@@ -21,9 +30,9 @@ struct Cli {
     #[arg(long, global = true)]
     dev: bool,
 
-    /// Environment: production, development, test
+    /// Environment: production (prod), development (dev), test
     #[arg(long, short = 'e', global = true, env = "VIGIL_ENV")]
-    env: Option<String>,
+    env: Option<Environment>,
 
     #[command(subcommand)]
     command: Commands,
@@ -35,11 +44,7 @@ impl Cli {
         if self.dev {
             return Environment::Development;
         }
-        match self.env.as_deref() {
-            Some("dev") | Some("development") => Environment::Development,
-            Some("test") => Environment::Test,
-            _ => Environment::from_env(),
-        }
+        self.env.unwrap_or_default()
     }
 }
 
@@ -53,13 +58,53 @@ enum Commands {
     },
 
     /// Show current network status
-    Status,
+    Status {
+        /// Print a single line like "ONLINE 18ms" (for prompts and menu bars)
+        #[arg(long)]
+        brief: bool,
+
+        /// Only check targets whose name or IP matches this glob (e.g. "Gateway*")
+        #[arg(short, long)]
+        target: Option<String>,
+
+        /// Reprint the status every N seconds
+        #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+        refresh: Option<u64>,
+
+        /// With --refresh, exit after this many status blocks
+        #[arg(long, requires = "refresh", value_parser = clap::value_parser!(u32).range(1..))]
+        count: Option<u32>,
+
+        /// Window for each target's rolling latency and loss (e.g. "1h", "24h")
+        #[arg(short, long, default_value = "1h")]
+        window: String,
+
+        /// Ping each target this many times and show the aggregate (without a running monitor)
+        #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        probe_count: u32,
+    },
 
     /// List recent outages
     Outages {
         /// Time period (e.g., "24h", "7d", "30d")
         #[arg(short, long, default_value = "24h")]
         last: String,
+
+        /// Columns to show, in order (id, start, duration, hop, severity, targets)
+        #[arg(long, default_value = cli::outages::DEFAULT_COLUMNS)]
+        columns: String,
+
+        /// Group outages under daily headers with subtotals (day)
+        #[arg(long)]
+        group_by: Option<String>,
+
+        /// Only show outages still in progress (exits 1 if there are any)
+        #[arg(long, conflicts_with_all = ["last", "group_by"])]
+        ongoing: bool,
+
+        /// Read from another machine's `vigil serve` (e.g. http://server:8080)
+        #[arg(long)]
+        remote: Option<String>,
     },
 
     /// Show statistics
@@ -67,15 +112,92 @@ enum Commands {
         /// Time period (e.g., "24h", "7d", "30d")
         #[arg(short, long, default_value = "24h")]
         period: String,
+
+        /// Only count outages affecting targets matching this glob (e.g. "192.168.*")
+        #[arg(short, long)]
+        target: Option<String>,
+
+        /// Print the data behind the charts instead (json or csv)
+        #[arg(long, value_name = "FORMAT")]
+        chart_data: Option<String>,
+
+        /// Print the whole report as JSON
+        #[arg(long, conflicts_with = "chart_data")]
+        json: bool,
+    },
+
+    /// Acknowledge an outage so reminders stop (the outage keeps running)
+    Ack {
+        /// Outage ID (see `vigil outages`)
+        id: i64,
+    },
+
+    /// Show one outage in detail, with its traceroutes
+    Outage {
+        /// Outage ID (see `vigil outages`)
+        id: i64,
+    },
+
+    /// Explain an outage in plain English (for sharing)
+    Explain {
+        /// Outage ID (see `vigil outages`)
+        id: i64,
+    },
+
+    /// Add a timestamped note to the timeline (e.g. "unplugged router")
+    Mark {
+        /// Text of the marker
+        label: String,
+    },
+
+    /// Show outage counts and downtime per day or week
+    Trends {
+        /// Time period (e.g., "30d", "12w")
+        #[arg(short, long, default_value = "30d")]
+        period: String,
+
+        /// Bucket size (day, week)
+        #[arg(long, default_value = "day")]
+        by: String,
+    },
+
+    /// Serve a read-only JSON API for dashboards
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value = "8080")]
+        port: u16,
+
+        /// Address to bind (localhost only by default)
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
     },
 
     /// Run a manual traceroute
     Trace {
-        /// Target IP or hostname
+        /// Target IP or hostname, or a glob matching configured targets (e.g. "192.168.*")
         #[arg(default_value = "8.8.8.8")]
         target: String,
     },
 
+    /// Export recorded data as CSV
+    Export {
+        /// What to export (pings, outages)
+        #[arg(default_value = "pings")]
+        what: String,
+
+        /// Time period (e.g., "24h", "7d", "30d")
+        #[arg(short, long, default_value = "24h")]
+        period: String,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+
+        /// Continue an interrupted export into the same output file
+        #[arg(long)]
+        resume: bool,
+    },
+
     /// Manage configuration
     Config {
         #[command(subcommand)]
@@ -98,6 +220,13 @@ enum Commands {
     /// Initialize configuration and database
     Init,
 
+    /// Check the setup: config, database, ping/traceroute, gateway
+    Doctor {
+        /// Print the checks as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Show version and environment info
     Version {
         /// Show detailed version info
@@ -105,6 +234,19 @@ enum Commands {
         verbose: bool,
     },
 
+    /// Write a consistent copy of the database (safe while monitoring)
+    Backup {
+        /// Where to write it (default: monitor.db.backup_<time> next to the database)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Replace the database with a backup (the monitor must be stopped)
+    Restore {
+        /// Backup file written by `vigil backup`
+        path: std::path::PathBuf,
+    },
+
     /// Upgrade database schema
     Upgrade {
         /// Show what would be done without making changes
@@ -119,19 +261,39 @@ enum Commands {
 
 #[derive(Subcommand)]
 enum ConfigAction {
-    /// Show current configuration
-    Show,
+    /// Show the configuration file as written
+    Show {
+        /// Show the resolved configuration the monitor uses (defaults, paths, deduplicated targets)
+        #[arg(long)]
+        effective: bool,
+    },
 
     /// Show configuration file path
     Path,
 
-    /// Set a configuration value
+    /// Show differences between the current config and another file
+    Diff {
+        /// Config file to compare against
+        other: std::path::PathBuf,
+    },
+
+    /// Rewrite the config file for renamed or removed keys (keeps a .orig copy)
+    Migrate {
+        /// Show what would change without rewriting the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Set a configuration value (rewrites the config file)
     Set {
         /// Key to set (e.g., "monitor.ping_interval_ms")
         key: String,
-        /// Value to set
+        /// Value to set (empty clears an optional setting)
         value: String,
     },
+
+    /// Restore the last config that loaded and validated (config.toml.bak)
+    Rollback,
 }
 
 #[derive(Subcommand)]
@@ -163,23 +325,80 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let env = cli.environment();
 
     match cli.command {
-        Commands::Init => cmd_init(&env)?,
+        Commands::Init => cmd_init(&env).await?,
         Commands::Config { action } => cmd_config(action, &env)?,
         Commands::Start { foreground } => cmd_start(foreground, &env).await?,
-        Commands::Status => cmd_status(&env).await?,
-        Commands::Outages { last } => cmd_outages(&last, &env)?,
-        Commands::Stats { period } => cmd_stats(&period, &env)?,
-        Commands::Trace { target } => cmd_trace(&target).await?,
+        Commands::Status {
+            brief,
+            target,
+            refresh,
+            count,
+            window,
+            probe_count,
+        } => {
+            cmd_status(
+                brief,
+                target.as_deref(),
+                &window,
+                probe_count,
+                refresh,
+                count,
+                &env,
+            )
+            .await?
+        }
+        Commands::Outages {
+            last,
+            columns,
+            group_by,
+            ongoing,
+            remote,
+        } => cmd_outages(
+            &last,
+            &columns,
+            group_by.as_deref(),
+            ongoing,
+            remote.as_deref(),
+            &env,
+        )?,
+        Commands::Stats {
+            period,
+            target,
+            chart_data,
+            json,
+        } => cmd_stats(
+            &period,
+            target.as_deref(),
+            chart_data.as_deref(),
+            json,
+            &env,
+        )?,
+        Commands::Trends { period, by } => cmd_trends(&period, &by, &env)?,
+        Commands::Mark { label } => cmd_mark(&label, &env)?,
+        Commands::Ack { id } => cmd_ack(id, &env)?,
+        Commands::Outage { id } => cmd_outage(id, &env)?,
+        Commands::Explain { id } => cmd_explain(id, &env)?,
+        Commands::Serve { port, bind } => cmd_serve(&bind, port, &env)?,
+        Commands::Export {
+            what,
+            period,
+            output,
+            resume,
+        } => cmd_export(&what, &period, output.as_deref(), resume, &env)?,
+        Commands::Trace { target } => cmd_trace(&target, &env).await?,
         Commands::Service { action } => cmd_service(action)?,
         Commands::Cleanup { days } => cmd_cleanup(days, &env)?,
+        Commands::Doctor { json } => cli::doctor::run(&env, json)?,
         Commands::Version { verbose } => cmd_version(verbose, &env)?,
+        Commands::Backup { output } => cmd_backup(output, &env)?,
+        Commands::Restore { path } => cmd_restore(&path, &env)?,
         Commands::Upgrade { dry_run, no_backup } => cmd_upgrade(dry_run, no_backup, &env)?,
     }
 
     Ok(())
 }
 
-fn cmd_init(env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
+async fn cmd_init(env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
     println!("Initializing Vigil ({})...\n", env);
 
     // Create data directory
@@ -220,9 +439,10 @@ fn cmd_init(env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
         println!("  (Set manually with: vigil config set targets.gateway <IP>)\n");
     }
 
-    println!("Targets to monitor:");
-    for target in app.config.all_targets() {
-        println!("  - {} ({})", target.name, target.ip);
+    println!("Checking target reachability...");
+    let results = cli::init::probe_targets(&app.config.all_targets()).await;
+    for line in cli::init::reachability_summary(&results) {
+        println!("{}", line);
     }
 
     if env.is_dev() {
@@ -237,10 +457,18 @@ fn cmd_init(env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
 
 fn cmd_config(action: ConfigAction, env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
     match action {
-        ConfigAction::Show => {
-            let config = Config::load_for_env(env)?;
-            let toml_str = toml::to_string_pretty(&config)?;
-            println!("{}", toml_str);
+        ConfigAction::Show { effective: true } => {
+            let config = Config::load_for_env(env)?.effective_for_env(env)?;
+            println!("{}", toml::to_string_pretty(&config)?);
+        }
+        ConfigAction::Show { effective: false } => {
+            let path = env.config_path()?;
+            if path.exists() {
+                print!("{}", std::fs::read_to_string(&path)?);
+            } else {
+                println!("No config file at {} - using defaults.", path.display());
+                println!("Run 'vigil config show --effective' to see them.");
+            }
         }
         ConfigAction::Path => {
             println!("Environment: {}", env);
@@ -248,11 +476,70 @@ fn cmd_config(action: ConfigAction, env: &Environment) -> Result<(), Box<dyn std
             println!("Database:    {}", env.database_path()?.display());
             println!("Logs:        {}", env.log_path()?.display());
         }
-        ConfigAction::Set { key, value } => {
-            println!("Setting {} = {}", key, value);
-            println!("(Configuration editing not yet implemented - edit config file directly)");
+        ConfigAction::Diff { other } => {
+            let config = Config::load_for_env(env)?;
+            let other_config = Config::load_from_path(&other)?;
+            let changes = config.diff(&other_config)?;
+
+            println!("Comparing {} config with:", env);
+            println!("  {}\n", other.display());
+
+            if changes.is_empty() {
+                println!("No differences.");
+            } else {
+                for change in &changes {
+                    println!("  {}", change);
+                }
+                println!(
+                    "\n{} difference{}",
+                    changes.len(),
+                    if changes.len() == 1 { "" } else { "s" }
+                );
+            }
+        }
+        ConfigAction::Migrate { dry_run } => {
             let path = env.config_path()?;
-            println!("Config file: {}", path.display());
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let (table, report) = vigil::config::migrate_config_toml(&text)?;
+
+            if report.is_empty() {
+                println!("{} is up to date.", path.display());
+                return Ok(());
+            }
+            for change in &report.changes {
+                println!("  ✓ {}", change);
+            }
+            for warning in &report.warnings {
+                println!("  ⚠️  {}", warning);
+            }
+
+            if dry_run {
+                println!("\nDry run - {} not modified", path.display());
+            } else {
+                let backup = path.with_extension("toml.orig");
+                std::fs::copy(&path, &backup)?;
+                std::fs::write(&path, toml::to_string_pretty(&table)?)?;
+                println!("\nRewrote {}", path.display());
+                println!("Backup (with original comments): {}", backup.display());
+            }
+        }
+        ConfigAction::Set { key, value } => {
+            let mut config = Config::load_for_env(env)?;
+            config.set(&key, &value)?;
+            if config.strict {
+                config.validate()?;
+            }
+            config.save_for_env(env)?;
+
+            println!("Set {} = {}", key, value);
+            println!("Config file: {}", env.config_path()?.display());
+            println!("Restart the monitor for the change to take effect.");
+        }
+        ConfigAction::Rollback => {
+            let path = Config::rollback_for_env(env)?;
+            println!("Restored {} from its last known good copy", path.display());
+            println!("Restart the monitor for the change to take effect.");
         }
     }
     Ok(())
@@ -267,10 +554,36 @@ async fn cmd_start(_foreground: bool, env: &Environment) -> Result<(), Box<dyn s
     let targets = app.config.all_targets();
     println!("Monitoring targets:");
     for target in &targets {
-        println!("  • {} ({})", target.name, target.ip);
+        match &target.source {
+            Some(source) => println!("  • {} ({}) via {}", target.name, target.ip, source),
+            None => println!("  • {} ({})", target.name, target.ip),
+        }
     }
 
+    if !app.config.monitor.canary_targets.is_empty() {
+        println!("\nCanary targets (gate outage detection):");
+        for canary in &app.config.monitor.canary_targets {
+            println!("  • {} ({})", canary.name, canary.ip);
+        }
+    }
+
+    // A missing uplink would otherwise look like an outage on that target
+    for target in targets.iter().chain(&app.config.monitor.canary_targets) {
+        if let Some(source) = &target.source {
+            check_source(source).map_err(|e| format!("Target \"{}\": {}", target.name, e))?;
+        }
+    }
+
+    // Native pings need ICMP socket access; without it they fall back to the system binary
+    let capability = PingCapability::probe();
+    let wants_native = app.config.monitor.ping_backend == PingBackendKind::Native;
+
     println!("\nSettings:");
+    println!(
+        "  Ping method: {} ({})",
+        capability.ping_method(wants_native),
+        capability
+    );
     println!("  Ping interval: {}ms", app.config.monitor.ping_interval_ms);
     println!("  Ping timeout: {}ms", app.config.monitor.ping_timeout_ms);
     println!(
@@ -284,6 +597,15 @@ async fn cmd_start(_foreground: bool, env: &Environment) -> Result<(), Box<dyn s
 
     println!("\nStarting monitoring... Press Ctrl+C to stop.\n");
 
+    // Record this monitor session
+    let session_id = match app.db.start_session(chrono::Utc::now()) {
+        Ok(id) => Some(id),
+        Err(e) => {
+            tracing::error!("Failed to record monitor session: {}", e);
+            None
+        }
+    };
+
     // Create ping monitor and state tracker
     let monitor = PingMonitor::new(&app.config);
     let mut tracker = ConnectivityTracker::new(&app.config.monitor, &targets);
@@ -293,24 +615,175 @@ async fn cmd_start(_foreground: bool, env: &Environment) -> Result<(), Box<dyn s
     let mut last_status: std::collections::HashMap<String, (bool, Option<f64>)> =
         std::collections::HashMap::new();
     let mut current_outage_id: Option<i64> = None;
+    // Delivered off the monitor loop; a slow webhook must not stall the state machine
+    let notifier = NotificationQueue::spawn(notifications::from_config(
+        &app.config.notifications,
+        app.config.display.timezone,
+    ));
+    let hooks = HookRunner::new(&app.config.hooks);
+    // Kept across outages so learned path lengths shorten later traces
+    let analyzer = std::sync::Arc::new(
+        HopAnalyzer::default().with_match_prefix(app.config.monitor.traceroute_match_prefix),
+    );
+    // Merges per-target lines during storms ("3 targets failed")
+    let mut coalescer = ChangeCoalescer::new(cli::start::COALESCE_WINDOW);
+    // One "all targets reporting healthy" line instead of a line per target at startup
+    let mut startup = StartupSummary::new(
+        targets.len(),
+        std::time::Duration::from_secs(app.config.display.startup_settle_secs),
+        std::time::Instant::now(),
+    );
+    // Live streaks for `vigil status`, rewritten at most once a second
+    let live_path = app.live_state_path().ok();
+    let mut live_written: Option<std::time::Instant> = None;
+    // Prometheus scrape endpoint, only when configured
+    let metrics = app.config.monitor.metrics_addr.as_deref().and_then(|addr| {
+        let registry = MetricsRegistry::default();
+        match vigil::monitor::metrics::spawn_server(addr, registry.clone()) {
+            Ok(()) => {
+                println!("Metrics: http://{}/metrics\n", addr);
+                Some(registry)
+            }
+            Err(e) => {
+                tracing::error!("Metrics server not started: {}", e);
+                None
+            }
+        }
+    });
+    // Every ping, written in batches, when monitor.log_all_pings is set
+    let log_all_pings = app.config.monitor.log_all_pings;
+    let mut ping_batch = Vec::with_capacity(cli::start::PING_BATCH_SIZE);
+    let mut ping_flush = tokio::time::interval(cli::start::PING_FLUSH_INTERVAL);
+    // Repeated traces while an outage lasts; restarted at each OFFLINE
+    let trace_every = app
+        .config
+        .monitor
+        .trace_interval_secs
+        .filter(|secs| *secs > 0 && app.config.monitor.traceroute_enabled)
+        .map(std::time::Duration::from_secs);
+    let mut trace_timer =
+        tokio::time::interval(trace_every.unwrap_or(std::time::Duration::from_secs(3600)));
+    trace_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // Periodic traces run in their own task and report back here, so pings
+    // keep being processed while traceroute works through the hops
+    let (trace_tx, mut trace_rx) = tokio::sync::mpsc::channel(8);
+    let mut periodic_trace: Option<tokio::task::JoinHandle<()>> = None;
+    // Lets `vigil status` tell a running monitor from one that crashed
+    let mut session_heartbeat = tokio::time::interval(std::time::Duration::from_secs(
+        SESSION_HEARTBEAT_SECS as u64,
+    ));
+    // "Still offline" lines during long outages, started at each OFFLINE
+    let mut heartbeat = OutageHeartbeat::new(std::time::Duration::from_secs(
+        app.config
+            .monitor
+            .ongoing_outage_heartbeat_secs
+            .unwrap_or(0),
+    ));
 
     loop {
+        let flush_at = coalescer.deadline();
+        let settle_at = startup.deadline();
+        let heartbeat_at = heartbeat.deadline();
+
         tokio::select! {
-            // Handle Ctrl+C
-            _ = signal::ctrl_c() => {
-                println!("\n\nShutting down...");
+            // Settling window over; summarize whichever targets answered
+            _ = tokio::time::sleep_until(settle_at.map(tokio::time::Instant::from_std).unwrap_or_else(tokio::time::Instant::now)), if settle_at.is_some() => {
+                if let Some(line) = startup.finish(chrono::Utc::now(), &app.config.display) {
+                    println!("{}", line);
+                }
+            }
 
-                // End any ongoing outage
-                if let Some(outage) = tracker.current_outage_mut() {
-                    outage.end();
-                    outage.notes = Some("Monitor shutdown during outage".to_string());
-                    if let Some(id) = current_outage_id {
-                        outage.id = Some(id);
-                        if let Err(e) = app.db.update_outage(outage) {
-                            tracing::error!("Failed to update outage on shutdown: {}", e);
+            // Print buffered status changes once their window has passed
+            _ = tokio::time::sleep_until(flush_at.map(tokio::time::Instant::from_std).unwrap_or_else(tokio::time::Instant::now)), if flush_at.is_some() => {
+                for line in coalescer.flush(&app.config.display) {
+                    println!("{}", line);
+                }
+            }
+
+            // Write buffered pings that haven't filled a batch yet
+            _ = ping_flush.tick(), if log_all_pings => {
+                flush_ping_batch(&app, &mut ping_batch);
+            }
+
+            // Record that this session is still alive
+            _ = session_heartbeat.tick(), if session_id.is_some() => {
+                if let Some(id) = session_id {
+                    if let Err(e) = app.db.touch_session(id, chrono::Utc::now()) {
+                        tracing::debug!("Failed to record session heartbeat: {}", e);
+                    }
+                }
+            }
+
+            // Trace again during long outages to see whether the failure point moves
+            _ = trace_timer.tick(), if trace_every.is_some() && current_outage_id.is_some() => {
+                let Some(outage_id) = current_outage_id else { continue };
+                // A slow trace may still be going; don't pile up another
+                if periodic_trace.as_ref().is_some_and(|task| !task.is_finished()) {
+                    continue;
+                }
+                let affected = tracker
+                    .current_outage()
+                    .map(|o| o.affected_targets.clone())
+                    .unwrap_or_default();
+                let trace_targets = outage_trace_targets(&app.config.monitor, &affected, &targets);
+
+                let analyzer = analyzer.clone();
+                let trace_tx = trace_tx.clone();
+                periodic_trace = Some(tokio::spawn(async move {
+                    for trace_target in trace_targets {
+                        println!("   Running {} traceroute to {}...", TraceTrigger::Periodic, trace_target);
+                        // Never canceled from here; aborting the task kills the child
+                        let Some(trace) = analyzer.trace_cancelable(&trace_target, std::future::pending::<()>()).await else {
+                            return;
+                        };
+                        if trace_tx.send((outage_id, trace)).await.is_err() {
+                            return;
                         }
                     }
+                }));
+            }
+
+            // A periodic trace finished
+            Some((outage_id, trace)) = trace_rx.recv() => {
+                if let Some((hop, ip)) = HopAnalyzer::identify_failing_hop(&trace) {
+                    println!("   Failing hop now: {} — {}", hop, ip);
                 }
+                if let Err(e) = app.db.insert_traceroute(Some(outage_id), &trace, TraceTrigger::Periodic) {
+                    tracing::error!("Failed to save traceroute: {}", e);
+                }
+            }
+
+            // Say the outage is still going on, so the log isn't silent until recovery
+            _ = tokio::time::sleep_until(heartbeat_at.map(tokio::time::Instant::from_std).unwrap_or_else(tokio::time::Instant::now)), if heartbeat_at.is_some() => {
+                let notify = heartbeat.beat(std::time::Instant::now());
+                let Some(outage) = tracker.current_outage().filter(|_| tracker.state() == ConnectivityState::Offline).cloned() else {
+                    heartbeat.stop();
+                    continue;
+                };
+
+                let now = chrono::Utc::now();
+                let owner = outage
+                    .failing_hop_ip
+                    .as_deref()
+                    .map(|ip| tokio::task::block_in_place(|| describe_ip(ip, app.config.display.reverse_dns)));
+                let message = cli::start::heartbeat_message(&outage, owner.as_deref(), now);
+                println!("[{}] ⏳ {}", app.config.display.format_time(now, "%H:%M:%S"), message);
+                tracing::warn!("{}", message);
+
+                // `vigil ack` runs in another process, so its flag is only in the database
+                let acknowledged = current_outage_id
+                    .and_then(|id| app.db.get_outage(id).ok().flatten())
+                    .is_some_and(|stored| stored.acknowledged);
+                if notify && outage.counts_against_availability() && !acknowledged {
+                    let event = NotificationEvent::Ongoing { outage, at: now };
+                    notifier.send(event);
+                }
+            }
+
+            // Handle Ctrl+C
+            _ = signal::ctrl_c() => {
+                println!("\n\nShutting down...");
+                end_outage_on_shutdown(&app, &mut tracker, current_outage_id);
                 break;
             }
 
@@ -321,6 +794,43 @@ async fn cmd_start(_foreground: bool, env: &Environment) -> Result<(), Box<dyn s
                         // Process through state machine
                         let event = tracker.process(&ping_result);
 
+                        if log_all_pings {
+                            ping_batch.push(ping_result.clone());
+                            if ping_batch.len() >= cli::start::PING_BATCH_SIZE {
+                                flush_ping_batch(&app, &mut ping_batch);
+                            }
+                        } else if let Err(e) = app.db.insert_latency_sample(&ping_result) {
+                            // Every reply, so rolling stats don't rest on change-only rows
+                            tracing::error!("Failed to log latency sample: {}", e);
+                        }
+
+                        if let Some(metrics) = &metrics {
+                            metrics.update(&tracker, &targets);
+                            if matches!(event, StateEvent::Offline { .. }) {
+                                metrics.record_outage();
+                            }
+                        }
+
+                        if let Some(path) = &live_path {
+                            if live_written.is_none_or(|t| t.elapsed() >= std::time::Duration::from_secs(1)) {
+                                let snapshot = LiveSnapshot::from_tracker(&tracker, &targets, chrono::Utc::now());
+                                if let Err(e) = snapshot.write(path) {
+                                    tracing::debug!("Failed to write live state: {}", e);
+                                }
+                                live_written = Some(std::time::Instant::now());
+                            }
+                        }
+
+                        // Keep buffered target lines ahead of the state banner they led to
+                        if !matches!(event, StateEvent::NoChange) {
+                            for line in coalescer.flush(&app.config.display) {
+                                println!("{}", line);
+                            }
+                        }
+
+                        // Hooks run in the background so a slow script never delays monitoring
+                        hooks.fire(&event);
+
                         // Handle state events
                         match event {
                             StateEvent::Degraded { ref failing_targets } => {
@@ -328,32 +838,114 @@ async fn cmd_start(_foreground: bool, env: &Environment) -> Result<(), Box<dyn s
                                     "\n⚠️  STATE: DEGRADED - Failing targets: {}\n",
                                     failing_targets.join(", ")
                                 );
+
+                                if let Some(degraded) = tracker.current_degraded_mut() {
+                                    match app.db.insert_degraded_event(degraded) {
+                                        Ok(id) => degraded.id = Some(id),
+                                        Err(e) => tracing::error!("Failed to record degraded period: {}", e),
+                                    }
+                                }
+                            }
+                            StateEvent::DegradedRecovered { ref degraded } => {
+                                println!(
+                                    "\n🟢 STATE: ONLINE - Recovered from DEGRADED after {:.1}s (no outage)\n",
+                                    degraded.duration_secs.unwrap_or(0.0)
+                                );
+                                if degraded.id.is_some() {
+                                    if let Err(e) = app.db.update_degraded_event(degraded) {
+                                        tracing::error!("Failed to update degraded period: {}", e);
+                                    }
+                                }
+                            }
+                            StateEvent::FamilyLost { family, ref failing_targets } => {
+                                println!(
+                                    "\n⚠️  {} connectivity lost - {} still working: {}\n",
+                                    family,
+                                    family.other(),
+                                    failing_targets.join(", ")
+                                );
+                            }
+                            StateEvent::TargetSpecific { ref failing_targets } => {
+                                println!(
+                                    "\n⚠️  Canaries reachable - issue is specific to: {}\n",
+                                    failing_targets.join(", ")
+                                );
+
+                                // Recorded on the DEGRADED period it happened in
+                                if let Some(degraded) = tracker.current_degraded_mut() {
+                                    degraded.target_specific = true;
+                                    degraded.failing_targets = failing_targets.clone();
+                                    let stored = match degraded.id {
+                                        Some(_) => app.db.update_degraded_event(degraded),
+                                        None => app.db.insert_degraded_event(degraded).map(|id| degraded.id = Some(id)),
+                                    };
+                                    if let Err(e) = stored {
+                                        tracing::error!("Failed to record target-specific issue: {}", e);
+                                    }
+                                }
+
+                                let event = NotificationEvent::TargetSpecific {
+                                    failing_targets: failing_targets.clone(),
+                                    at: ping_result.timestamp,
+                                };
+                                notifier.send(event);
                             }
                             StateEvent::Offline { ref outage } => {
                                 println!(
                                     "\n🔴 STATE: OFFLINE - Outage started at {}",
-                                    outage.start_time.format("%H:%M:%S")
+                                    app.config.display.format_time(outage.start_time, "%H:%M:%S")
                                 );
 
-                                // Run traceroute to identify failing hop
-                                let analyzer = HopAnalyzer::default();
-                                let trace_target = targets.first()
-                                    .map(|t| t.ip.as_str())
-                                    .unwrap_or("8.8.8.8");
+                                // Outages inside a maintenance window are expected; judged by when
+                                // the outage started, before the traces below take their time
+                                let started_local = outage.start_time.with_timezone(&chrono::Local).naive_local();
+                                let planned = vigil::config::is_in_maintenance_window(started_local, &app.config.maintenance.windows);
+                                if planned {
+                                    println!("   Inside maintenance window - recording as planned");
+                                }
 
-                                println!("   Running traceroute to {}...", trace_target);
-                                let trace_result = analyzer.trace(trace_target).await;
+                                // Trace the targets that actually failed; they may take different paths
+                                let trace_targets = outage_trace_targets(
+                                    &app.config.monitor,
+                                    &outage.affected_targets,
+                                    &targets,
+                                );
+                                if !app.config.monitor.traceroute_enabled {
+                                    println!("   Traceroute disabled - recording outage without hop info\n");
+                                }
+
+                                let mut traces = Vec::new();
+                                let mut canceled = false;
+                                for trace_target in &trace_targets {
+                                    println!("   Running traceroute to {}...", trace_target);
+
+                                    // Ctrl+C during the trace aborts it and shuts down
+                                    match analyzer.trace_cancelable(trace_target, signal::ctrl_c()).await {
+                                        Some(trace) => traces.push(trace),
+                                        None => {
+                                            canceled = true;
+                                            break;
+                                        }
+                                    }
+                                }
 
                                 let mut outage_to_save = outage.clone();
+                                if planned {
+                                    outage_to_save.planned = true;
+                                    outage_to_save.notes = Some("Planned maintenance window".to_string());
+                                }
 
-                                // Identify and record failing hop
-                                if let Some((hop, ip)) = HopAnalyzer::identify_failing_hop(&trace_result) {
-                                    println!("   Failing hop identified: {} ({})\n", hop, ip);
+                                // Identify and record failing hop from the first trace that shows one
+                                if canceled {
+                                    println!("   Traceroute canceled\n");
+                                } else if let Some((hop, ip)) = traces.iter().find_map(HopAnalyzer::identify_failing_hop) {
+                                    let owner = tokio::task::block_in_place(|| describe_ip(&ip, app.config.display.reverse_dns));
+                                    println!("   Failing hop identified: {} — {}\n", hop, owner);
                                     outage_to_save.failing_hop = Some(hop);
                                     outage_to_save.failing_hop_ip = Some(ip);
-                                } else if !trace_result.success {
+                                } else if traces.iter().any(|t| !t.success) {
                                     println!("   Could not identify failing hop (all timeouts)\n");
-                                } else {
+                                } else if !traces.is_empty() {
                                     println!("   Traceroute succeeded (intermittent issue)\n");
                                 }
 
@@ -362,10 +954,23 @@ async fn cmd_start(_foreground: bool, env: &Environment) -> Result<(), Box<dyn s
                                     Ok(id) => {
                                         current_outage_id = Some(id);
                                         tracing::info!("Outage recorded with ID {}", id);
+                                        trace_timer.reset();
+
+                                        // Link the degraded period that led here
+                                        if let Some(mut degraded) = tracker.take_degraded() {
+                                            degraded.escalate(id);
+                                            if degraded.id.is_some() {
+                                                if let Err(e) = app.db.update_degraded_event(&degraded) {
+                                                    tracing::error!("Failed to update degraded period: {}", e);
+                                                }
+                                            }
+                                        }
 
-                                        // Also save traceroute
-                                        if let Err(e) = app.db.insert_traceroute(Some(id), &trace_result) {
-                                            tracing::error!("Failed to save traceroute: {}", e);
+                                        // Also save traceroutes
+                                        for trace in &traces {
+                                            if let Err(e) = app.db.insert_traceroute(Some(id), trace, TraceTrigger::StateChange) {
+                                                tracing::error!("Failed to save traceroute: {}", e);
+                                            }
                                         }
 
                                         // Update tracker's outage with failing hop info
@@ -373,32 +978,60 @@ async fn cmd_start(_foreground: bool, env: &Environment) -> Result<(), Box<dyn s
                                             current.id = Some(id);
                                             current.failing_hop = outage_to_save.failing_hop;
                                             current.failing_hop_ip = outage_to_save.failing_hop_ip.clone();
+                                            current.planned = outage_to_save.planned;
+                                            current.notes = outage_to_save.notes.clone();
                                         }
                                     }
                                     Err(e) => {
                                         tracing::error!("Failed to record outage: {}", e);
                                     }
                                 }
+
+                                if !outage_to_save.planned {
+                                    let event = NotificationEvent::Offline { outage: outage_to_save };
+                                    notifier.send(event);
+                                }
+                                heartbeat.start(std::time::Instant::now());
+
+                                if canceled {
+                                    println!("\nShutting down...");
+                                    end_outage_on_shutdown(&app, &mut tracker, current_outage_id);
+                                    break;
+                                }
                             }
                             StateEvent::Recovered { ref outage } => {
                                 println!(
                                     "\n🟢 STATE: ONLINE - Outage ended, duration: {:.1}s\n",
                                     outage.duration_secs.unwrap_or(0.0)
                                 );
-                                // Update outage in database
-                                if let Some(id) = current_outage_id.take() {
-                                    let mut updated_outage = outage.clone();
-                                    updated_outage.id = Some(id);
-                                    if let Err(e) = app.db.update_outage(&updated_outage) {
-                                        tracing::error!("Failed to update outage: {}", e);
+
+                                // A laptop going to sleep looks like an outage; tag it so stats skip it
+                                let mut outage = outage.clone();
+                                if let Some(end) = outage.end_time {
+                                    let sleeps = tokio::task::block_in_place(|| detect_sleep_windows(outage.start_time, end));
+                                    if mostly_asleep(&sleeps, outage.start_time, end) {
+                                        println!("   Mostly during system sleep - excluded from stats\n");
+                                        outage.system_sleep = true;
                                     }
                                 }
+
+                                // Update outage in database (or insert it if that failed at OFFLINE)
+                                heartbeat.stop();
+                                if let Err(e) = app.db.record_recovered_outage(&outage, current_outage_id.take()) {
+                                    tracing::error!("Failed to record outage: {}", e);
+                                }
+
+                                if outage.counts_against_availability() {
+                                    let event = NotificationEvent::Recovered { outage };
+                                    notifier.send(event);
+                                }
                             }
                             StateEvent::NoChange => {}
                         }
 
                         // Display ping result
                         let status_char = match tracker.state() {
+                            ConnectivityState::Unknown => if ping_result.success { "·" } else { "!" },
                             ConnectivityState::Online => if ping_result.success { "✓" } else { "!" },
                             ConnectivityState::Degraded => if ping_result.success { "~" } else { "✗" },
                             ConnectivityState::Offline => if ping_result.success { "?" } else { "✗" },
@@ -406,28 +1039,47 @@ async fn cmd_start(_foreground: bool, env: &Environment) -> Result<(), Box<dyn s
 
                         let latency_str = ping_result
                             .latency_ms
-                            .map(|l| format!("{:.1}ms", l))
+                            .map(|l| cli::helpers::format_latency(l, app.config.display.latency_precision))
                             .unwrap_or_else(|| ping_result.error.clone().unwrap_or_else(|| "timeout".to_string()));
 
                         // Only print if status changed or first result
                         let key = ping_result.target.clone();
                         let current = (ping_result.success, ping_result.latency_ms.map(|l| l.round()));
-                        let should_print = last_status.get(&key) != Some(&current);
+                        let previous = last_status.get(&key).copied();
+                        let should_print = previous != Some(current);
 
                         if should_print {
-                            let timestamp = ping_result.timestamp.format("%H:%M:%S");
-                            println!(
-                                "[{}] {} {} ({}) - {}",
-                                timestamp,
-                                status_char,
-                                ping_result.target_name,
-                                ping_result.target,
-                                latency_str
-                            );
+                            let kind = match previous {
+                                _ if !ping_result.success => ChangeKind::Failed,
+                                Some((false, _)) => ChangeKind::Recovered,
+                                _ => ChangeKind::Latency,
+                            };
+                            let absorbed = previous.is_none()
+                                && startup.absorb(&ping_result.target_name, ping_result.success);
+                            if !absorbed {
+                                coalescer.push(
+                                    StatusChange {
+                                        time: ping_result.timestamp,
+                                        status_char,
+                                        target_name: ping_result.target_name.clone(),
+                                        target: ping_result.target.clone(),
+                                        detail: latency_str,
+                                        kind,
+                                    },
+                                    std::time::Instant::now(),
+                                );
+                            }
+                            if startup.is_complete() {
+                                if let Some(line) = startup.finish(ping_result.timestamp, &app.config.display) {
+                                    println!("{}", line);
+                                }
+                            }
 
                             // Log to database (sample - only on changes)
-                            if let Err(e) = app.db.insert_ping(&ping_result) {
-                                tracing::error!("Failed to log ping: {}", e);
+                            if !log_all_pings {
+                                if let Err(e) = app.db.insert_ping(&ping_result) {
+                                    tracing::error!("Failed to log ping: {}", e);
+                                }
                             }
 
                             last_status.insert(key, current);
@@ -442,30 +1094,202 @@ async fn cmd_start(_foreground: bool, env: &Environment) -> Result<(), Box<dyn s
         }
     }
 
+    if let Some(task) = periodic_trace {
+        task.abort();
+    }
+    notifier.close().await;
+    for line in coalescer.flush(&app.config.display) {
+        println!("{}", line);
+    }
+    flush_ping_batch(&app, &mut ping_batch);
+
+    // A stale snapshot would be ignored anyway, but don't leave it behind
+    if let Some(path) = &live_path {
+        let _ = std::fs::remove_file(path);
+    }
+
+    if let Some(id) = session_id {
+        if let Err(e) = app.db.end_session(id, chrono::Utc::now()) {
+            tracing::error!("Failed to close monitor session: {}", e);
+        }
+    }
+
     println!("Monitor stopped.");
     Ok(())
 }
 
-async fn cmd_status(env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
+/// Write buffered pings in one transaction; the buffer is cleared either way
+fn flush_ping_batch(app: &App, batch: &mut Vec<vigil::models::PingResult>) {
+    if batch.is_empty() {
+        return;
+    }
+    if let Err(e) = app.db.insert_pings_batch(batch) {
+        tracing::error!("Failed to log {} pings: {}", batch.len(), e);
+    }
+    batch.clear();
+}
+
+/// End any ongoing outage when the monitor stops
+fn end_outage_on_shutdown(app: &App, tracker: &mut ConnectivityTracker, outage_id: Option<i64>) {
+    if let Some(outage) = tracker.current_outage_mut() {
+        outage.end();
+        outage.notes = Some(SHUTDOWN_NOTE.to_string());
+        // Saved even if recording it at OFFLINE failed
+        if let Err(e) = app.db.record_recovered_outage(outage, outage_id) {
+            tracing::error!("Failed to update outage on shutdown: {}", e);
+        }
+    }
+
+    // Close a degraded period still in progress
+    if let Some(degraded) = tracker.current_degraded_mut() {
+        if degraded.end_time.is_none() && degraded.id.is_some() {
+            degraded.end_at(chrono::Utc::now());
+            if let Err(e) = app.db.update_degraded_event(degraded) {
+                tracing::error!("Failed to update degraded period on shutdown: {}", e);
+            }
+        }
+    }
+}
+
+async fn cmd_status(
+    brief: bool,
+    target: Option<&str>,
+    window: &str,
+    probe_count: u32,
+    refresh: Option<u64>,
+    count: Option<u32>,
+    env: &Environment,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let app = App::with_env(*env)?;
+    match refresh {
+        Some(secs) => {
+            let every = std::time::Duration::from_secs(secs);
+            cli::status::run_refreshing(&app, brief, target, window, probe_count, every, count)
+                .await
+        }
+        None => cli::status::run(&app, brief, target, window, probe_count).await,
+    }
+}
+
+fn cmd_outages(
+    last: &str,
+    columns: &str,
+    group_by: Option<&str>,
+    ongoing: bool,
+    remote: Option<&str>,
+    env: &Environment,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(url) = remote {
+        // Only the local display settings are needed; the data comes from the remote
+        let config = Config::load_for_env(env)?;
+        let source = cli::source::HttpRemote::new(url);
+        if ongoing {
+            if cli::outages::run_ongoing_from(&source, &config.display)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        return cli::outages::run_from(&source, &config.display, last, columns, group_by);
+    }
+
+    let app = App::with_env(*env)?;
+    if ongoing {
+        // Non-zero exit lets scripts check "is anything down right now"
+        if cli::outages::run_ongoing(&app)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    cli::outages::run(&app, last, columns, group_by)
+}
+
+fn cmd_stats(
+    period: &str,
+    target: Option<&str>,
+    chart_data: Option<&str>,
+    json: bool,
+    env: &Environment,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let app = App::with_env(*env)?;
+    cli::stats::run(&app, period, target, chart_data, json)
+}
+
+fn cmd_serve(bind: &str, port: u16, env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
+    let app = App::with_env(*env)?;
+    cli::serve::run(&app, bind, port)
+}
+
+fn cmd_ack(id: i64, env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
+    let app = App::with_env(*env)?;
+    if !app.db.acknowledge_outage(id)? {
+        return Err(format!("No outage with ID {}", id).into());
+    }
+    println!("Outage {} acknowledged", id);
+    Ok(())
+}
+
+fn cmd_outage(id: i64, env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
+    let app = App::with_env(*env)?;
+    cli::outage_detail::run(&app, id)
+}
+
+fn cmd_explain(id: i64, env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
     let app = App::with_env(*env)?;
-    cli::status::run(&app).await
+    cli::explain::run(&app, id)
+}
+
+fn cmd_mark(label: &str, env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
+    let app = App::with_env(*env)?;
+    let now = chrono::Utc::now();
+    app.db.insert_marker(now, label)?;
+    println!(
+        "Marked {}: {}",
+        app.config.display.format_time(now, "%Y-%m-%d %H:%M:%S"),
+        label
+    );
+    Ok(())
 }
 
-fn cmd_outages(last: &str, env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_trends(period: &str, by: &str, env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
     let app = App::with_env(*env)?;
-    cli::outages::run(&app, last)
+    cli::trends::run(&app, period, by)
 }
 
-fn cmd_stats(period: &str, env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_export(
+    what: &str,
+    period: &str,
+    output: Option<&std::path::Path>,
+    resume: bool,
+    env: &Environment,
+) -> Result<(), Box<dyn std::error::Error>> {
     let app = App::with_env(*env)?;
-    cli::stats::run(&app, period)
+    cli::export::run(&app, what, period, output, resume)
 }
 
-async fn cmd_trace(target: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let analyzer = HopAnalyzer::default();
-    let result = analyzer.trace(target).await;
+async fn cmd_trace(target: &str, env: &Environment) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load_for_env(env)?;
+    let analyzer = HopAnalyzer::default().with_match_prefix(config.monitor.traceroute_match_prefix);
 
-    print!("{}", format_traceroute(&result));
+    // A pattern matching configured targets traces each of them
+    let matches = cli::helpers::resolve_targets(target, &config.all_targets());
+    let trace_targets: Vec<String> = if !matches.is_empty() {
+        matches.into_iter().map(|t| t.ip).collect()
+    } else if target.contains(['*', '?']) {
+        return Err(format!("No configured targets match '{}'", target).into());
+    } else {
+        vec![target.to_string()]
+    };
+
+    for (i, trace_target) in trace_targets.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let result = analyzer.trace(trace_target).await;
+        print!(
+            "{}",
+            format_traceroute(&result, config.display.latency_precision)
+        );
+    }
 
     Ok(())
 }
@@ -494,6 +1318,26 @@ fn cmd_cleanup(days: Option<u32>, env: &Environment) -> Result<(), Box<dyn std::
     let deleted = app.db.cleanup(retention_days)?;
     println!("Database: Deleted {} old records", deleted);
 
+    if let Some(days) = app.config.database.compact_after_days {
+        let context = app.config.database.outage_context_mins;
+        let compacted = app.db.compact_keeping_outage_context(
+            chrono::Utc::now() - chrono::Duration::days(days as i64),
+            chrono::Duration::minutes(context as i64),
+        )?;
+        println!(
+            "Database: Deleted {} pings older than {} days outside ±{}m of an outage",
+            compacted, days, context
+        );
+    }
+
+    if let Some(max) = app.config.database.max_pings_per_target {
+        let trimmed = app.db.cleanup_pings_beyond(max)?;
+        println!(
+            "Database: Deleted {} ping samples beyond {} per target",
+            trimmed, max
+        );
+    }
+
     // Clean up old log files
     if let Ok(Some(log_path)) = app.config.log_path_for_env(env) {
         if let Some(log_dir) = log_path.parent() {
@@ -531,6 +1375,56 @@ fn cmd_version(verbose: bool, env: &Environment) -> Result<(), Box<dyn std::erro
     Ok(())
 }
 
+fn cmd_backup(
+    output: Option<std::path::PathBuf>,
+    env: &Environment,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let app = App::with_env(*env)?;
+    let path = match output {
+        Some(path) => path,
+        None => {
+            let name = format!(
+                "monitor.db.backup_{}",
+                chrono::Utc::now().format("%Y%m%d_%H%M%S")
+            );
+            app.db_path()?.with_file_name(name)
+        }
+    };
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()).into());
+    }
+
+    app.db.backup_to(&path)?;
+    println!("Backup written to {}", path.display());
+    Ok(())
+}
+
+fn cmd_restore(
+    backup: &std::path::Path,
+    env: &Environment,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load_for_env(env)?;
+    let db_path = config.database_path_for_env(env)?;
+
+    // The running monitor refreshes its live state every second
+    let live_path = db_path.with_extension("live.json");
+    let max_age = chrono::Duration::seconds(cli::status::LIVE_MAX_AGE_SECS);
+    if LiveSnapshot::read_fresh(&live_path, chrono::Utc::now(), max_age).is_some() {
+        return Err("The monitor is running - stop it before restoring".into());
+    }
+
+    // Keep what's being replaced, in case the wrong backup was picked
+    if db_path.exists() {
+        let previous = db_path.with_extension("db.before-restore");
+        std::fs::copy(&db_path, &previous)?;
+        println!("Previous database kept as {}", previous.display());
+    }
+
+    vigil::db::Database::restore(backup, &db_path)?;
+    println!("Restored {} from {}", db_path.display(), backup.display());
+    Ok(())
+}
+
 fn cmd_upgrade(
     dry_run: bool,
     no_backup: bool,
@@ -545,12 +1439,28 @@ fn cmd_upgrade(
         return Ok(());
     }
 
+    let from = vigil::db::Database::stored_version(&db_path)?;
+    let to = vigil::DB_SCHEMA_VERSION as i32;
     println!("Database: {}", db_path.display());
-    println!("Current schema version: {}", vigil::DB_SCHEMA_VERSION);
+    println!("Schema version: {} (this build: {})", from, to);
+
+    if from > to {
+        println!("\nDatabase was written by a newer vigil; nothing to do.");
+        return Ok(());
+    }
+    let pending = vigil::db::pending_migrations(from);
+    if pending.is_empty() {
+        println!("\nDatabase is at the latest schema version.");
+        return Ok(());
+    }
+
+    println!("\nPending migrations:");
+    for (version, description) in &pending {
+        println!("  v{}: {}", version, description);
+    }
 
     if dry_run {
         println!("\n[Dry run] No changes will be made.");
-        println!("Database is at the latest schema version.");
         return Ok(());
     }
 
@@ -562,9 +1472,13 @@ fn cmd_upgrade(
         println!("\nBackup created: {}", backup_path.display());
     }
 
-    // Open database (this will run any pending migrations)
-    let _app = App::with_env(*env)?;
+    // Opening the database applies the pending migrations
+    let app = App::with_env(*env)?;
 
-    println!("\nDatabase is up to date.");
+    println!(
+        "\nUpgraded schema v{} → v{}",
+        from,
+        app.db.current_version()?
+    );
     Ok(())
 }
@@ -1,9 +1,11 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Represents the current connectivity state
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConnectivityState {
+    /// No results yet for every target
+    Unknown,
     Online,
     Degraded,
     Offline,
@@ -12,6 +14,7 @@ pub enum ConnectivityState {
 impl std::fmt::Display for ConnectivityState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            ConnectivityState::Unknown => write!(f, "UNKNOWN"),
             ConnectivityState::Online => write!(f, "ONLINE"),
             ConnectivityState::Degraded => write!(f, "DEGRADED"),
             ConnectivityState::Offline => write!(f, "OFFLINE"),
@@ -27,7 +30,57 @@ pub struct PingResult {
     pub timestamp: DateTime<Utc>,
     pub success: bool,
     pub latency_ms: Option<f64>,
+    /// Standard deviation of round-trip times within the sample (ping's stddev)
+    #[serde(default)]
+    pub jitter_ms: Option<f64>,
+    /// Fraction of echo requests lost within a multi-packet sample (0.0-1.0)
+    #[serde(default)]
+    pub loss: Option<f64>,
     pub error: Option<String>,
+    /// What kind of failure `error` is; not stored in the database
+    #[serde(default)]
+    pub error_kind: Option<PingErrorKind>,
+    /// Address a hostname target resolved to for this check
+    #[serde(default)]
+    pub resolved_ip: Option<String>,
+}
+
+/// Why a ping failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PingErrorKind {
+    Timeout,
+    NoRoute,
+    NetworkUnreachable,
+    /// A hostname target could not be resolved; says nothing about reachability
+    DnsFailure,
+    Other,
+}
+
+impl PingErrorKind {
+    /// Classify one of the normalized error messages every check produces
+    /// ("Request timeout", "No route to host", ...)
+    pub fn of(error: &str) -> Self {
+        match error {
+            "Request timeout" => PingErrorKind::Timeout,
+            "No route to host" => PingErrorKind::NoRoute,
+            "Network unreachable" => PingErrorKind::NetworkUnreachable,
+            "DNS resolution failed" => PingErrorKind::DnsFailure,
+            _ => PingErrorKind::Other,
+        }
+    }
+}
+
+impl std::fmt::Display for PingErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PingErrorKind::Timeout => write!(f, "timeout"),
+            PingErrorKind::NoRoute => write!(f, "no route to host"),
+            PingErrorKind::NetworkUnreachable => write!(f, "network unreachable"),
+            PingErrorKind::DnsFailure => write!(f, "DNS failure"),
+            PingErrorKind::Other => write!(f, "error"),
+        }
+    }
 }
 
 /// A network hop from traceroute
@@ -49,6 +102,56 @@ pub struct TracerouteResult {
     pub success: bool,
 }
 
+/// Why a traceroute was run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceTrigger {
+    /// At the transition to OFFLINE
+    StateChange,
+    /// Repeated every `monitor.trace_interval_secs` while the outage lasts
+    Periodic,
+}
+
+impl TraceTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TraceTrigger::StateChange => "state_change",
+            TraceTrigger::Periodic => "periodic",
+        }
+    }
+}
+
+impl std::fmt::Display for TraceTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for TraceTrigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "state_change" => Ok(TraceTrigger::StateChange),
+            "periodic" => Ok(TraceTrigger::Periodic),
+            _ => Err(format!(
+                "Unknown trace trigger '{}'. Use: state_change, periodic",
+                s
+            )),
+        }
+    }
+}
+
+/// How often an IP was the last responding hop of a failed traceroute
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HopFrequency {
+    pub ip: String,
+    pub occurrences: u32,
+}
+
+/// Notes of an outage that was still going on when the monitor stopped
+pub const SHUTDOWN_NOTE: &str = "Monitor shutdown during outage";
+
 /// An outage event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Outage {
@@ -60,6 +163,15 @@ pub struct Outage {
     pub failing_hop: Option<u8>,
     pub failing_hop_ip: Option<String>,
     pub notes: Option<String>,
+    /// Occurred during a planned maintenance window (excluded from stats)
+    #[serde(default)]
+    pub planned: bool,
+    /// Acknowledged by the user; reminders are suppressed
+    #[serde(default)]
+    pub acknowledged: bool,
+    /// Overlapped a system sleep period (excluded from stats)
+    #[serde(default)]
+    pub system_sleep: bool,
 }
 
 impl Outage {
@@ -73,21 +185,207 @@ impl Outage {
             failing_hop: None,
             failing_hop_ip: None,
             notes: None,
+            planned: false,
+            acknowledged: false,
+            system_sleep: false,
         }
     }
 
     pub fn end(&mut self) {
-        let now = Utc::now();
-        self.end_time = Some(now);
-        self.duration_secs = Some((now - self.start_time).num_milliseconds() as f64 / 1000.0);
+        self.end_at(Utc::now());
+    }
+
+    /// End the outage at a specific time
+    pub fn end_at(&mut self, at: DateTime<Utc>) {
+        self.end_time = Some(at);
+        self.duration_secs = Some((at - self.start_time).num_milliseconds() as f64 / 1000.0);
+    }
+
+    /// Closed because the monitor stopped, not because connectivity came back
+    pub fn ended_by_shutdown(&self) -> bool {
+        self.notes.as_deref() == Some(SHUTDOWN_NOTE)
+    }
+
+    /// Whether the outage counts against availability (not planned, not asleep)
+    pub fn counts_against_availability(&self) -> bool {
+        !self.planned && !self.system_sleep
+    }
+
+    /// Duration so far: the recorded duration, or time since start if still ongoing
+    pub fn elapsed_secs(&self, now: DateTime<Utc>) -> f64 {
+        self.duration_secs.unwrap_or_else(|| {
+            ((now - self.start_time).num_milliseconds() as f64 / 1000.0).max(0.0)
+        })
+    }
+
+    /// Classify the outage by how long it lasted
+    pub fn severity(&self) -> OutageSeverity {
+        match self.duration_secs {
+            None => OutageSeverity::Ongoing,
+            Some(d) if d < 10.0 => OutageSeverity::Minor,
+            Some(d) if d < 60.0 => OutageSeverity::Moderate,
+            Some(_) => OutageSeverity::Major,
+        }
+    }
+}
+
+/// A DEGRADED period: some targets failing, not (yet) an outage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DegradedEvent {
+    pub id: Option<i64>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub duration_secs: Option<f64>,
+    pub failing_targets: Vec<String>,
+    /// The outage this period turned into, if it escalated to OFFLINE
+    pub outage_id: Option<i64>,
+    /// The targets failed while the canaries stayed reachable
+    pub target_specific: bool,
+}
+
+impl DegradedEvent {
+    pub fn new(failing_targets: Vec<String>) -> Self {
+        Self {
+            id: None,
+            start_time: Utc::now(),
+            end_time: None,
+            duration_secs: None,
+            failing_targets,
+            outage_id: None,
+            target_specific: false,
+        }
+    }
+
+    /// End the degraded period at a specific time
+    pub fn end_at(&mut self, at: DateTime<Utc>) {
+        self.end_time = Some(at);
+        self.duration_secs = Some((at - self.start_time).num_milliseconds() as f64 / 1000.0);
+    }
+
+    /// Link the period to the outage it escalated into
+    pub fn escalate(&mut self, outage_id: i64) {
+        self.outage_id = Some(outage_id);
+    }
+
+    /// Recovered without becoming an outage
+    pub fn is_near_miss(&self) -> bool {
+        self.end_time.is_some() && self.outage_id.is_none()
     }
 }
 
+/// Outage severity based on duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutageSeverity {
+    /// Under 10 seconds
+    Minor,
+    /// Under a minute
+    Moderate,
+    /// A minute or longer
+    Major,
+    /// Still in progress
+    Ongoing,
+}
+
+impl std::fmt::Display for OutageSeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutageSeverity::Minor => write!(f, "minor"),
+            OutageSeverity::Moderate => write!(f, "moderate"),
+            OutageSeverity::Major => write!(f, "major"),
+            OutageSeverity::Ongoing => write!(f, "ongoing"),
+        }
+    }
+}
+
+/// IP address family of a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AddressFamily {
+    V4,
+    V6,
+}
+
+impl AddressFamily {
+    /// Family of an IP address string (None for hostnames)
+    pub fn of(ip: &str) -> Option<Self> {
+        match ip.parse::<std::net::IpAddr>().ok()? {
+            std::net::IpAddr::V4(_) => Some(AddressFamily::V4),
+            std::net::IpAddr::V6(_) => Some(AddressFamily::V6),
+        }
+    }
+
+    /// The other family
+    pub fn other(&self) -> Self {
+        match self {
+            AddressFamily::V4 => AddressFamily::V6,
+            AddressFamily::V6 => AddressFamily::V4,
+        }
+    }
+}
+
+impl std::fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressFamily::V4 => write!(f, "IPv4"),
+            AddressFamily::V6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+/// How a target's reachability is checked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CheckKind {
+    /// ICMP echo (the default)
+    Icmp,
+    /// Time to complete a TCP handshake with `port`, for networks that drop ICMP
+    TcpConnect { port: u16 },
+    /// A GET request to `url`, timed to the first byte of the response
+    HttpGet {
+        url: String,
+        /// Status codes that count as healthy, e.g. "2xx", "204" or "2xx,3xx"
+        #[serde(default = "default_expected_status")]
+        expected_status: String,
+        /// Text the response body must contain (e.g. a captive-portal check page)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        body_contains: Option<String>,
+    },
+}
+
+fn default_expected_status() -> String {
+    "2xx".to_string()
+}
+
+/// Whether `code` matches an expected-status spec: comma-separated codes where
+/// `x` matches any digit ("2xx", "30x", "200")
+pub fn status_matches(expected: &str, code: u16) -> bool {
+    let code = code.to_string();
+    expected.split(',').map(str::trim).any(|pattern| {
+        pattern.len() == code.len()
+            && pattern
+                .chars()
+                .zip(code.chars())
+                .all(|(p, c)| p.eq_ignore_ascii_case(&'x') || p == c)
+    })
+}
+
 /// A monitoring target
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Target {
     pub name: String,
     pub ip: String,
+    /// How the target is checked (ICMP when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check: Option<CheckKind>,
+    /// Consecutive failures before this target counts as failing (default 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub degraded_threshold: Option<u32>,
+    /// Consecutive successes before a failing target counts as healthy again (default 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_threshold: Option<u32>,
+    /// Local address or interface name pings leave from, to watch one uplink
+    /// of a multi-homed machine (system ping only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
 impl Target {
@@ -95,10 +393,195 @@ impl Target {
         Self {
             name: name.into(),
             ip: ip.into(),
+            check: None,
+            degraded_threshold: None,
+            recovery_threshold: None,
+            source: None,
+        }
+    }
+
+    /// Check this target with `check` instead of ICMP
+    pub fn with_check(mut self, check: CheckKind) -> Self {
+        self.check = Some(check);
+        self
+    }
+
+    /// What the target's results, state and logged pings are keyed on: the
+    /// address, with the check and source appended when set
+    /// ("8.8.8.8/tcp:53", "8.8.8.8@en1"), so the same host checked another
+    /// way or over another uplink is tracked on its own
+    pub fn key(&self) -> String {
+        let mut key = self.ip.clone();
+        match &self.check {
+            None | Some(CheckKind::Icmp) => {}
+            Some(CheckKind::TcpConnect { port }) => key.push_str(&format!("/tcp:{}", port)),
+            Some(CheckKind::HttpGet { .. }) => key.push_str("/http"),
+        }
+        if let Some(source) = self
+            .source
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            key.push('@');
+            key.push_str(source);
+        }
+        key
+    }
+}
+
+/// How often a running monitor records that its session is still alive
+pub const SESSION_HEARTBEAT_SECS: i64 = 30;
+
+/// An open session not heard from for this long is taken to have crashed
+const SESSION_STALE_SECS: i64 = 3 * SESSION_HEARTBEAT_SECS;
+
+/// A single run of the monitor daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonSession {
+    pub id: i64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// Session was not shut down cleanly (closed on the next start)
+    pub crashed: bool,
+    /// Last time the running monitor said it was alive
+    #[serde(default)]
+    pub heartbeat_at: Option<DateTime<Utc>>,
+}
+
+impl DaemonSession {
+    /// Still open and heard from recently; an open session that went quiet crashed
+    pub fn is_running(&self, now: DateTime<Utc>) -> bool {
+        self.end_time.is_none() && now - self.last_seen() <= Duration::seconds(SESSION_STALE_SECS)
+    }
+
+    /// When the session was last known to be monitoring
+    pub fn last_seen(&self) -> DateTime<Utc> {
+        self.end_time
+            .or(self.heartbeat_at)
+            .unwrap_or(self.start_time)
+    }
+}
+
+/// A user-entered note on the timeline (`vigil mark`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Marker {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub label: String,
+}
+
+/// Monitor uptime across sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    /// Start of the currently running session, if any
+    pub current_start: Option<DateTime<Utc>>,
+    /// Total seconds observed across all sessions
+    pub total_observed_secs: f64,
+}
+
+impl SessionSummary {
+    /// Summarize sessions as of `now`. Running sessions count up to `now`,
+    /// ones that stopped sending heartbeats only up to their last one.
+    pub fn from_sessions(sessions: &[DaemonSession], now: DateTime<Utc>) -> Self {
+        let current_start = sessions
+            .iter()
+            .filter(|s| s.is_running(now))
+            .map(|s| s.start_time)
+            .max();
+
+        let total_observed_secs = sessions
+            .iter()
+            .map(|s| {
+                let end = if s.is_running(now) {
+                    now
+                } else {
+                    s.last_seen()
+                };
+                ((end - s.start_time).num_milliseconds() as f64 / 1000.0).max(0.0)
+            })
+            .sum();
+
+        Self {
+            current_start,
+            total_observed_secs,
         }
     }
 }
 
+/// Rolling latency and loss for one target over a window of pings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PingStats {
+    /// Pings in the window; only the successful ones when loss isn't known
+    pub samples: u32,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    /// Mean absolute difference between consecutive round trips; not the
+    /// per-ping standard deviation (`PingResult::jitter_ms`) that `stats` averages
+    #[serde(default)]
+    pub variation_ms: Option<f64>,
+    /// None when only successful pings were stored
+    pub loss_percent: Option<f64>,
+}
+
+impl PingStats {
+    /// Summarize successful latencies, oldest first, out of `samples` logged pings
+    pub fn from_latencies(latencies: Vec<f64>, samples: u32) -> Self {
+        let received = latencies.len();
+        let loss_percent = if samples > 0 {
+            (samples as f64 - received as f64) / samples as f64 * 100.0
+        } else {
+            0.0
+        };
+        Self {
+            samples,
+            loss_percent: Some(loss_percent),
+            ..Self::from_replies(latencies)
+        }
+    }
+
+    /// Summarize successful latencies, oldest first, when lost pings weren't stored
+    pub fn from_replies(mut latencies: Vec<f64>) -> Self {
+        let variation_ms = mean_abs_diff(&latencies);
+        latencies.sort_by(|a, b| a.total_cmp(b));
+        let received = latencies.len();
+
+        Self {
+            samples: received as u32,
+            min_ms: latencies.first().copied(),
+            avg_ms: (received > 0).then(|| latencies.iter().sum::<f64>() / received as f64),
+            max_ms: latencies.last().copied(),
+            p95_ms: percentile(&latencies, 95.0),
+            variation_ms,
+            loss_percent: None,
+        }
+    }
+}
+
+/// Jitter of a series of round trips: the mean absolute difference between
+/// consecutive values (None for fewer than two)
+pub fn mean_abs_diff(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let total: f64 = values
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).abs())
+        .sum();
+    Some(total / (values.len() - 1) as f64)
+}
+
+/// Nearest-rank percentile of sorted values
+pub fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
 /// Statistics summary
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Stats {
@@ -110,3 +593,124 @@ pub struct Stats {
     pub avg_outage_duration_secs: Option<f64>,
     pub most_common_failing_hop: Option<u8>,
 }
+
+impl Stats {
+    /// Summarize the given outages over a period
+    pub fn from_outages(outages: &[Outage], since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        let total_outages = outages.len() as u32;
+        let total_downtime_secs: f64 = outages.iter().filter_map(|o| o.duration_secs).sum();
+
+        let mut hop_counts: std::collections::HashMap<u8, u32> = std::collections::HashMap::new();
+        for outage in outages {
+            if let Some(hop) = outage.failing_hop {
+                *hop_counts.entry(hop).or_insert(0) += 1;
+            }
+        }
+
+        Self::from_totals(
+            total_outages,
+            total_downtime_secs,
+            &hop_counts,
+            since,
+            until,
+        )
+    }
+
+    /// Summarize precomputed totals (e.g. from the daily rollup) over a period
+    pub fn from_totals(
+        total_outages: u32,
+        total_downtime_secs: f64,
+        hop_counts: &std::collections::HashMap<u8, u32>,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Self {
+        let period_secs = (until - since).num_seconds() as f64;
+        let availability_percent = if period_secs > 0.0 {
+            ((period_secs - total_downtime_secs) / period_secs) * 100.0
+        } else {
+            100.0
+        };
+
+        let avg_outage_duration_secs = if total_outages > 0 {
+            Some(total_downtime_secs / total_outages as f64)
+        } else {
+            None
+        };
+
+        // Most common failing hop
+        let most_common_failing_hop = hop_counts
+            .iter()
+            .max_by_key(|(_, count)| **count)
+            .map(|(hop, _)| *hop);
+
+        Stats {
+            period_start: since,
+            period_end: until,
+            total_outages,
+            total_downtime_secs,
+            availability_percent,
+            avg_outage_duration_secs,
+            most_common_failing_hop,
+        }
+    }
+}
+
+/// Bucket size for outage trend rollups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrendGranularity {
+    Day,
+    Week,
+}
+
+impl TrendGranularity {
+    /// Start of the bucket containing a timestamp: midnight in `tz`, and
+    /// weeks start Monday
+    pub fn bucket_start<Tz: TimeZone>(&self, time: DateTime<Utc>, tz: &Tz) -> DateTime<Utc> {
+        let local = time.with_timezone(tz);
+        let date = match self {
+            TrendGranularity::Day => local.date_naive(),
+            TrendGranularity::Week => {
+                local.date_naive() - Duration::days(local.weekday().num_days_from_monday() as i64)
+            }
+        };
+        // Where a DST change skips midnight, the day starts an hour later
+        let midnight = date.and_time(NaiveTime::MIN);
+        tz.from_local_datetime(&midnight)
+            .earliest()
+            .or_else(|| {
+                tz.from_local_datetime(&(midnight + Duration::hours(1)))
+                    .earliest()
+            })
+            .map_or_else(|| midnight.and_utc(), |start| start.with_timezone(&Utc))
+    }
+
+    /// Start of the bucket after the one starting at `start`. Days around a
+    /// DST change are 23 or 25 hours, so this isn't a fixed step.
+    pub fn next_bucket<Tz: TimeZone>(&self, start: DateTime<Utc>, tz: &Tz) -> DateTime<Utc> {
+        let step = match self {
+            TrendGranularity::Day => Duration::days(1),
+            TrendGranularity::Week => Duration::weeks(1),
+        };
+        self.bucket_start(start + step + Duration::hours(2), tz)
+    }
+}
+
+impl std::str::FromStr for TrendGranularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "day" | "daily" => Ok(TrendGranularity::Day),
+            "week" | "weekly" => Ok(TrendGranularity::Week),
+            _ => Err(format!("Unknown granularity '{}'. Use: day, week", s)),
+        }
+    }
+}
+
+/// Outage count and downtime for one trend bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rollup {
+    pub bucket_start: DateTime<Utc>,
+    pub outages: u32,
+    pub downtime_secs: f64,
+}
@@ -0,0 +1,123 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::fmt;
+
+/// What this process is allowed to do with ICMP sockets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PingCapability {
+    /// Raw ICMP sockets (root or CAP_NET_RAW)
+    RawSocket,
+    /// Unprivileged ICMP datagram sockets (macOS, or Linux with ping_group_range)
+    DatagramSocket,
+    /// No ICMP sockets; only the system `ping` binary can be used
+    SystemOnly,
+}
+
+/// How pings are actually sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PingMethod {
+    /// ICMP echo sent from this process
+    Raw,
+    /// The system `ping` binary
+    System,
+}
+
+impl fmt::Display for PingMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PingMethod::Raw => write!(f, "raw"),
+            PingMethod::System => write!(f, "system"),
+        }
+    }
+}
+
+impl fmt::Display for PingCapability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PingCapability::RawSocket => write!(f, "raw ICMP sockets available"),
+            PingCapability::DatagramSocket => write!(f, "unprivileged ICMP sockets available"),
+            PingCapability::SystemOnly => write!(f, "no ICMP socket access"),
+        }
+    }
+}
+
+impl PingCapability {
+    /// Probe by trying to open ICMP sockets
+    pub fn probe() -> Self {
+        let capability = Self::probe_with(|socket_type| {
+            Socket::new(Domain::IPV4, socket_type, Some(Protocol::ICMPV4)).map(|_| ())
+        });
+        tracing::info!("Ping capability: {}", capability);
+        capability
+    }
+
+    /// Probe using a custom socket opener (raw first, then datagram)
+    pub fn probe_with<F>(open: F) -> Self
+    where
+        F: Fn(Type) -> std::io::Result<()>,
+    {
+        if open(Type::RAW).is_ok() {
+            PingCapability::RawSocket
+        } else if open(Type::DGRAM).is_ok() {
+            PingCapability::DatagramSocket
+        } else {
+            PingCapability::SystemOnly
+        }
+    }
+
+    /// The method used to ping, given whether in-process ICMP is wanted
+    pub fn ping_method(&self, prefer_native: bool) -> PingMethod {
+        match self {
+            PingCapability::RawSocket | PingCapability::DatagramSocket if prefer_native => {
+                PingMethod::Raw
+            }
+            _ => PingMethod::System,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Error, ErrorKind};
+
+    fn denied() -> std::io::Result<()> {
+        Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "Operation not permitted",
+        ))
+    }
+
+    #[test]
+    fn test_probe_with_mocked_sockets() {
+        assert_eq!(
+            PingCapability::probe_with(|_| Ok(())),
+            PingCapability::RawSocket
+        );
+        assert_eq!(
+            PingCapability::probe_with(|t| if t == Type::RAW { denied() } else { Ok(()) }),
+            PingCapability::DatagramSocket
+        );
+        assert_eq!(
+            PingCapability::probe_with(|_| denied()),
+            PingCapability::SystemOnly
+        );
+    }
+
+    #[test]
+    fn test_ping_method_decision() {
+        assert_eq!(PingCapability::RawSocket.ping_method(true), PingMethod::Raw);
+        assert_eq!(
+            PingCapability::DatagramSocket.ping_method(true),
+            PingMethod::Raw
+        );
+        assert_eq!(
+            PingCapability::SystemOnly.ping_method(true),
+            PingMethod::System
+        );
+        assert_eq!(
+            PingCapability::RawSocket.ping_method(false),
+            PingMethod::System
+        );
+        assert_eq!(PingMethod::System.to_string(), "system");
+    }
+}
@@ -0,0 +1,129 @@
+use crate::models::ConnectivityState;
+use crate::monitor::ConnectivityTracker;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A target's current run of identical results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Streak {
+    Successes(u32),
+    Failures(u32),
+    /// No result yet
+    None,
+}
+
+/// Live per-target state published by the running monitor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetSnapshot {
+    pub name: String,
+    pub ip: String,
+    /// `Target::key`, which tells apart one address watched over two uplinks
+    #[serde(default)]
+    pub key: String,
+    pub streak: Streak,
+    pub latency_ms: Option<f64>,
+    /// Left out of the aggregate state (`monitor.quarantine_after`)
+    #[serde(default)]
+    pub quarantined: bool,
+}
+
+/// What the running monitor currently knows, written next to the database
+/// so `vigil status` can show it without pinging again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveSnapshot {
+    pub updated_at: DateTime<Utc>,
+    pub state: ConnectivityState,
+    pub targets: Vec<TargetSnapshot>,
+}
+
+impl LiveSnapshot {
+    /// Capture the tracker's state, with targets in monitoring order
+    pub fn from_tracker(
+        tracker: &ConnectivityTracker,
+        order: &[crate::models::Target],
+        now: DateTime<Utc>,
+    ) -> Self {
+        let states = tracker.target_states();
+        let targets = order
+            .iter()
+            .filter_map(|t| states.get(&t.key()))
+            .map(|s| TargetSnapshot {
+                name: s.target.name.clone(),
+                ip: s.target.ip.clone(),
+                key: s.target.key(),
+                streak: s.streak(),
+                latency_ms: s.last_result.as_ref().and_then(|r| r.latency_ms),
+                quarantined: s.is_quarantined(),
+            })
+            .collect();
+
+        Self {
+            updated_at: now,
+            state: tracker.state(),
+            targets,
+        }
+    }
+
+    /// Write atomically (temp file + rename) so readers never see a partial file
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// Read a snapshot no older than `max_age`; stale or unreadable files are ignored
+    pub fn read_fresh(path: &Path, now: DateTime<Utc>, max_age: Duration) -> Option<Self> {
+        let bytes = std::fs::read(path).ok()?;
+        let snapshot: Self = serde_json::from_slice(&bytes)
+            .map_err(|e| tracing::debug!("Ignoring unreadable live state {:?}: {}", path, e))
+            .ok()?;
+        (now - snapshot.updated_at <= max_age).then_some(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MonitorConfig;
+    use crate::models::{PingResult, Target};
+
+    #[test]
+    fn test_snapshot_round_trip_and_staleness() {
+        let targets = vec![
+            Target::new("Google DNS", "8.8.8.8"),
+            Target::new("Cloudflare", "1.1.1.1"),
+        ];
+        let mut tracker = ConnectivityTracker::new(&MonitorConfig::default(), &targets);
+        for success in [true, true, false] {
+            tracker.process(&PingResult {
+                target: "1.1.1.1".to_string(),
+                target_name: "Cloudflare".to_string(),
+                timestamp: Utc::now(),
+                success,
+                latency_ms: success.then_some(12.0),
+                jitter_ms: None,
+                loss: None,
+                error: None,
+                error_kind: None,
+                resolved_ip: None,
+            });
+        }
+
+        let now = Utc::now();
+        let snapshot = LiveSnapshot::from_tracker(&tracker, &targets, now);
+        assert_eq!(snapshot.targets[0].streak, Streak::None);
+        assert_eq!(snapshot.targets[1].streak, Streak::Failures(1));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("monitor.live.json");
+        snapshot.write(&path).unwrap();
+
+        let read = LiveSnapshot::read_fresh(&path, now, Duration::seconds(30)).unwrap();
+        assert_eq!(read.targets[1].ip, "1.1.1.1");
+        assert!(
+            LiveSnapshot::read_fresh(&path, now + Duration::minutes(5), Duration::seconds(30))
+                .is_none()
+        );
+    }
+}
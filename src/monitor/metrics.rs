@@ -0,0 +1,216 @@
+use crate::models::{ConnectivityState, Target};
+use crate::monitor::{ConnectivityTracker, LiveSnapshot, Streak};
+use chrono::Utc;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use tiny_http::{Header, Method, Response, Server};
+
+/// What the running monitor exposes on `/metrics`, shared between the ping
+/// loop (writer) and the metrics server thread (reader)
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRegistry {
+    inner: Arc<Mutex<Metrics>>,
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    snapshot: Option<LiveSnapshot>,
+    outages_total: u64,
+}
+
+impl MetricsRegistry {
+    /// Refresh the gauges from the tracker's current state
+    pub fn update(&self, tracker: &ConnectivityTracker, order: &[Target]) {
+        let snapshot = LiveSnapshot::from_tracker(tracker, order, Utc::now());
+        self.lock().snapshot = Some(snapshot);
+    }
+
+    pub fn record_outage(&self) {
+        self.lock().outages_total += 1;
+    }
+
+    /// Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let metrics = self.lock();
+        let mut out = String::new();
+
+        let state = metrics
+            .snapshot
+            .as_ref()
+            .map_or(ConnectivityState::Unknown, |s| s.state);
+        let _ = writeln!(
+            out,
+            "# HELP vigil_connectivity_state Overall state: 0 unknown, 1 online, 2 degraded, 3 offline"
+        );
+        let _ = writeln!(out, "# TYPE vigil_connectivity_state gauge");
+        let _ = writeln!(out, "vigil_connectivity_state {}", state_value(state));
+
+        let targets = metrics
+            .snapshot
+            .as_ref()
+            .map_or(&[][..], |s| &s.targets[..]);
+
+        let _ = writeln!(
+            out,
+            "# HELP vigil_target_up Whether the target's last ping succeeded"
+        );
+        let _ = writeln!(out, "# TYPE vigil_target_up gauge");
+        for target in targets {
+            let up = matches!(target.streak, Streak::Successes(_)) as u8;
+            let _ = writeln!(
+                out,
+                "vigil_target_up{} {}",
+                labels(&target.name, &target.ip),
+                up
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP vigil_target_latency_ms Round-trip time of the target's last successful ping"
+        );
+        let _ = writeln!(out, "# TYPE vigil_target_latency_ms gauge");
+        for target in targets {
+            if let Some(latency) = target.latency_ms {
+                let _ = writeln!(
+                    out,
+                    "vigil_target_latency_ms{} {}",
+                    labels(&target.name, &target.ip),
+                    latency
+                );
+            }
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP vigil_outages_total Outages started since the monitor started"
+        );
+        let _ = writeln!(out, "# TYPE vigil_outages_total counter");
+        let _ = writeln!(out, "vigil_outages_total {}", metrics.outages_total);
+
+        out
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Metrics> {
+        // A panic mid-update leaves plain numbers behind; keep serving them
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn state_value(state: ConnectivityState) -> u8 {
+    match state {
+        ConnectivityState::Unknown => 0,
+        ConnectivityState::Online => 1,
+        ConnectivityState::Degraded => 2,
+        ConnectivityState::Offline => 3,
+    }
+}
+
+fn labels(name: &str, ip: &str) -> String {
+    format!(
+        "{{target=\"{}\",ip=\"{}\"}}",
+        escape_label(name),
+        escape_label(ip)
+    )
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Bind `addr` and serve `/metrics` from a background thread
+pub fn spawn_server(addr: &str, registry: MetricsRegistry) -> Result<(), String> {
+    let server = Server::http(addr).map_err(|e| format!("Failed to bind {}: {}", addr, e))?;
+    std::thread::spawn(move || serve(&server, &registry));
+    Ok(())
+}
+
+fn serve(server: &Server, registry: &MetricsRegistry) {
+    for request in server.incoming_requests() {
+        let path = request.url().split('?').next().unwrap_or("");
+        let response = match (request.method(), path) {
+            (Method::Get, "/metrics") => {
+                let header = Header::from_bytes("Content-Type", "text/plain; version=0.0.4")
+                    .expect("static header is valid");
+                Response::from_string(registry.render()).with_header(header)
+            }
+            (Method::Get, _) => Response::from_string("Not found").with_status_code(404),
+            _ => Response::from_string("Only GET is supported").with_status_code(405),
+        };
+        if let Err(e) = request.respond(response) {
+            tracing::debug!("Failed to send metrics response: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MonitorConfig;
+    use crate::models::PingResult;
+
+    fn ping(target: &Target, latency_ms: Option<f64>) -> PingResult {
+        PingResult {
+            target: target.ip.clone(),
+            target_name: target.name.clone(),
+            timestamp: Utc::now(),
+            success: latency_ms.is_some(),
+            latency_ms,
+            jitter_ms: None,
+            loss: None,
+            error: latency_ms.is_none().then(|| "Request timeout".to_string()),
+            error_kind: None,
+            resolved_ip: None,
+        }
+    }
+
+    #[test]
+    fn test_render_metrics() {
+        let targets = vec![
+            Target::new("Google DNS", "8.8.8.8"),
+            Target::new("Office \"VPN\"", "10.0.0.1"),
+        ];
+        let mut tracker = ConnectivityTracker::new(&MonitorConfig::default(), &targets);
+        tracker.process(&ping(&targets[0], Some(14.5)));
+        tracker.process(&ping(&targets[1], None));
+
+        let registry = MetricsRegistry::default();
+        registry.update(&tracker, &targets);
+        registry.record_outage();
+
+        let text = registry.render();
+        assert!(text.contains("vigil_connectivity_state 1\n"));
+        assert!(text.contains("vigil_target_up{target=\"Google DNS\",ip=\"8.8.8.8\"} 1\n"));
+        assert!(text.contains("vigil_target_up{target=\"Office \\\"VPN\\\"\",ip=\"10.0.0.1\"} 0\n"));
+        assert!(
+            text.contains("vigil_target_latency_ms{target=\"Google DNS\",ip=\"8.8.8.8\"} 14.5\n")
+        );
+        assert!(!text.contains("vigil_target_latency_ms{target=\"Office"));
+        assert!(text.contains("# TYPE vigil_outages_total counter\nvigil_outages_total 1\n"));
+    }
+
+    #[test]
+    fn test_metrics_endpoint() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr());
+        let registry = MetricsRegistry::default();
+        registry.record_outage();
+        std::thread::spawn(move || serve(&server, &registry));
+
+        let body = ureq::get(&format!("{}/metrics", url))
+            .call()
+            .unwrap()
+            .into_string()
+            .unwrap();
+        assert!(body.contains("vigil_connectivity_state 0"));
+        assert!(body.contains("vigil_outages_total 1"));
+
+        match ureq::get(&format!("{}/other", url)).call() {
+            Err(ureq::Error::Status(404, _)) => {}
+            other => panic!("expected 404, got {:?}", other.map(|r| r.status())),
+        }
+    }
+}
@@ -1,7 +1,20 @@
+pub mod capability;
+pub mod live;
+pub mod metrics;
+pub mod native;
+pub mod owner;
 pub mod ping;
+pub mod resolve;
+pub mod sleep;
 pub mod state;
 pub mod traceroute;
 
-pub use ping::PingMonitor;
+pub use capability::{PingCapability, PingMethod};
+pub use live::{LiveSnapshot, Streak, TargetSnapshot};
+pub use metrics::MetricsRegistry;
+pub use owner::{annotate_ip, describe_ip};
+pub use ping::{check_source, select_backend, PingBackend, PingMonitor, SystemPing};
+pub use resolve::DnsCache;
+pub use sleep::{detect_sleep_windows, mostly_asleep, SleepWindow};
 pub use state::{ConnectivityTracker, StateEvent, TargetState};
-pub use traceroute::{format_traceroute, HopAnalyzer};
+pub use traceroute::{format_traceroute, outage_trace_targets, select_trace_targets, HopAnalyzer};
@@ -0,0 +1,251 @@
+use crate::models::{PingErrorKind, PingResult};
+use crate::monitor::ping::{PingBackend, SystemPing};
+use chrono::Utc;
+use futures::future::BoxFuture;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::{Duration, Instant};
+
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_HEADER_LEN: usize = 8;
+
+/// Sends ICMP echo requests from this process instead of running `ping`
+pub struct NativePing {
+    socket_type: Type,
+    identifier: u16,
+    sequence: AtomicU16,
+}
+
+impl NativePing {
+    /// `socket_type` is `Type::RAW` (root / CAP_NET_RAW) or `Type::DGRAM` (unprivileged)
+    pub fn new(socket_type: Type) -> Self {
+        Self {
+            socket_type,
+            identifier: std::process::id() as u16,
+            sequence: AtomicU16::new(0),
+        }
+    }
+
+    fn next_sequence(&self) -> u16 {
+        self.sequence.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl PingBackend for NativePing {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn ping<'a>(
+        &'a self,
+        ip: &'a str,
+        name: &'a str,
+        timeout_ms: u64,
+    ) -> BoxFuture<'a, PingResult> {
+        // Hostnames and IPv6 targets still go through the system binary
+        if ip.parse::<Ipv4Addr>().is_err() {
+            return SystemPing.ping(ip, name, timeout_ms);
+        }
+
+        let timestamp = Utc::now();
+        let sequence = self.next_sequence();
+        let identifier = self.identifier;
+        let socket_type = self.socket_type;
+        let (ip, name) = (ip.to_string(), name.to_string());
+
+        Box::pin(async move {
+            let target = ip.clone();
+            let timeout = Duration::from_millis(timeout_ms);
+            let outcome = tokio::task::spawn_blocking(move || {
+                echo(&target, socket_type, identifier, sequence, timeout)
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("Ping task failed: {}", e)));
+
+            PingResult {
+                target: ip,
+                target_name: name,
+                timestamp,
+                success: outcome.is_ok(),
+                latency_ms: outcome.as_ref().ok().copied(),
+                // A single echo has no spread
+                jitter_ms: None,
+                loss: None,
+                error_kind: outcome.as_ref().err().map(|e| PingErrorKind::of(e)),
+                resolved_ip: None,
+                error: outcome.err(),
+            }
+        })
+    }
+}
+
+/// Send one echo request and wait for its reply; returns the RTT in ms
+fn echo(
+    target: &str,
+    socket_type: Type,
+    identifier: u16,
+    sequence: u16,
+    timeout: Duration,
+) -> Result<f64, String> {
+    let ip: Ipv4Addr = target
+        .parse()
+        .map_err(|_| "Native ping needs an IPv4 address".to_string())?;
+    let socket = Socket::new(Domain::IPV4, socket_type, Some(Protocol::ICMPV4))
+        .map_err(|e| format!("Failed to open ICMP socket: {}", e))?;
+    socket
+        .connect(&SockAddr::from(SocketAddr::new(IpAddr::V4(ip), 0)))
+        .map_err(|e| describe_send_error(&e))?;
+
+    let payload = token(sequence);
+    let request = build_echo_request(identifier, sequence, &payload);
+    let started = Instant::now();
+    socket.send(&request).map_err(|e| describe_send_error(&e))?;
+
+    let deadline = started + timeout;
+    let mut buf = [0u8; 1500];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err("Request timeout".to_string());
+        }
+        socket
+            .set_read_timeout(Some(remaining))
+            .map_err(|e| e.to_string())?;
+
+        match (&socket).read(&mut buf) {
+            Ok(len) => {
+                if is_matching_reply(&buf[..len], sequence, &payload) {
+                    return Ok(started.elapsed().as_secs_f64() * 1000.0);
+                }
+                // Someone else's reply (raw sockets see every ICMP packet)
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Err("Request timeout".to_string());
+            }
+            Err(e) => return Err(describe_send_error(&e)),
+        }
+    }
+}
+
+/// Map socket errors to the same messages the system ping path produces
+fn describe_send_error(e: &std::io::Error) -> String {
+    let message = e.to_string();
+    if message.contains("Network is unreachable") {
+        "Network unreachable".to_string()
+    } else if message.contains("No route to host") {
+        "No route to host".to_string()
+    } else {
+        format!("Ping failed: {}", message)
+    }
+}
+
+/// Payload identifying one request; Linux datagram sockets rewrite the
+/// identifier field, so replies are matched on sequence and payload instead
+fn token(sequence: u16) -> [u8; 8] {
+    let mut token = *b"vigil\0\0\0";
+    token[5..7].copy_from_slice(&sequence.to_be_bytes());
+    token
+}
+
+/// An ICMP echo request with a valid checksum
+fn build_echo_request(identifier: u16, sequence: u16, payload: &[u8]) -> Vec<u8> {
+    let mut packet = vec![ICMP_ECHO_REQUEST, 0, 0, 0];
+    packet.extend_from_slice(&identifier.to_be_bytes());
+    packet.extend_from_slice(&sequence.to_be_bytes());
+    packet.extend_from_slice(payload);
+
+    let checksum = internet_checksum(&packet);
+    packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    packet
+}
+
+/// RFC 1071 ones' complement checksum
+fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]) as u32)
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Whether `packet` is the echo reply to our request. Raw sockets (and macOS
+/// datagram sockets) deliver the IPv4 header too; it is skipped when present.
+fn is_matching_reply(packet: &[u8], sequence: u16, payload: &[u8]) -> bool {
+    let icmp = match packet.first() {
+        Some(first) if first >> 4 == 4 => {
+            let header_len = ((first & 0x0f) as usize) * 4;
+            packet.get(header_len..).unwrap_or_default()
+        }
+        _ => packet,
+    };
+
+    icmp.len() >= ICMP_HEADER_LEN + payload.len()
+        && icmp[0] == ICMP_ECHO_REPLY
+        && icmp[6..8] == sequence.to_be_bytes()
+        && &icmp[ICMP_HEADER_LEN..ICMP_HEADER_LEN + payload.len()] == payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply_to(request: &[u8]) -> Vec<u8> {
+        let mut reply = request.to_vec();
+        reply[0] = ICMP_ECHO_REPLY;
+        reply[2..4].copy_from_slice(&[0, 0]);
+        let checksum = internet_checksum(&reply);
+        reply[2..4].copy_from_slice(&checksum.to_be_bytes());
+        reply
+    }
+
+    #[test]
+    fn test_echo_request_checksum() {
+        let request = build_echo_request(0x1234, 7, &token(7));
+        assert_eq!(request[0], ICMP_ECHO_REQUEST);
+        assert_eq!(&request[4..8], &[0x12, 0x34, 0, 7]);
+        // A packet including its own checksum sums to zero
+        assert_eq!(internet_checksum(&request), 0);
+        // Odd-length data is padded with a zero byte
+        assert_eq!(internet_checksum(&[0xff]), !0xff00);
+    }
+
+    #[test]
+    fn test_matching_reply_with_and_without_ip_header() {
+        let payload = token(3);
+        let reply = reply_to(&build_echo_request(1, 3, &payload));
+        assert!(is_matching_reply(&reply, 3, &payload));
+
+        // 20-byte IPv4 header in front (raw sockets)
+        let mut with_header = vec![0x45];
+        with_header.extend_from_slice(&[0; 19]);
+        with_header.extend_from_slice(&reply);
+        assert!(is_matching_reply(&with_header, 3, &payload));
+
+        // Wrong sequence, another process's payload, or our own request echoed back
+        assert!(!is_matching_reply(&reply, 4, &payload));
+        assert!(!is_matching_reply(&reply, 3, &token(9)));
+        assert!(!is_matching_reply(
+            &build_echo_request(1, 3, &payload),
+            3,
+            &payload
+        ));
+        assert!(!is_matching_reply(&reply[..6], 3, &payload));
+    }
+
+    #[test]
+    fn test_non_ipv4_target_rejected() {
+        let err = echo("::1", Type::DGRAM, 1, 0, Duration::from_millis(10)).unwrap_err();
+        assert!(err.contains("IPv4"));
+    }
+}
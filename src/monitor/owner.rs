@@ -0,0 +1,179 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long `reverse_dns` waits for `host` before giving up on a name
+const REVERSE_DNS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A network prefix and who operates it
+struct Prefix {
+    network: IpAddr,
+    len: u8,
+    owner: &'static str,
+}
+
+const fn v4(a: u8, b: u8, c: u8, d: u8, len: u8, owner: &'static str) -> Prefix {
+    Prefix {
+        network: IpAddr::V4(Ipv4Addr::new(a, b, c, d)),
+        len,
+        owner,
+    }
+}
+
+const fn v6(segments: [u16; 3], len: u8, owner: &'static str) -> Prefix {
+    let [a, b, c] = segments;
+    Prefix {
+        network: IpAddr::V6(Ipv6Addr::new(a, b, c, 0, 0, 0, 0, 0)),
+        len,
+        owner,
+    }
+}
+
+/// Small built-in table of ISP, transit and DNS-provider ranges commonly seen as
+/// failing hops. Not exhaustive - it only has to name the usual suspects offline.
+const PREFIXES: &[Prefix] = &[
+    // Shared address space used for carrier-grade NAT inside ISP networks
+    v4(100, 64, 0, 0, 10, "ISP carrier-grade NAT"),
+    // Access ISPs
+    v4(96, 64, 0, 0, 10, "Comcast"),
+    v4(68, 80, 0, 0, 13, "Comcast"),
+    v4(12, 0, 0, 0, 8, "AT&T"),
+    v4(62, 154, 0, 0, 15, "Deutsche Telekom"),
+    // Transit providers
+    v4(4, 0, 0, 0, 9, "Lumen (Level 3)"),
+    v4(154, 54, 0, 0, 16, "Cogent"),
+    v4(129, 250, 0, 0, 16, "NTT"),
+    v4(62, 115, 0, 0, 16, "Arelion (Telia)"),
+    // DNS and cloud providers
+    v4(8, 8, 8, 0, 24, "Google"),
+    v4(8, 8, 4, 0, 24, "Google"),
+    v4(142, 250, 0, 0, 15, "Google"),
+    v4(172, 217, 0, 0, 16, "Google"),
+    v4(1, 1, 1, 0, 24, "Cloudflare"),
+    v4(1, 0, 0, 0, 24, "Cloudflare"),
+    v4(104, 16, 0, 0, 13, "Cloudflare"),
+    v4(162, 158, 0, 0, 15, "Cloudflare"),
+    v4(9, 9, 9, 0, 24, "Quad9"),
+    v6([0x2001, 0x4860, 0], 32, "Google"),
+    v6([0x2606, 0x4700, 0], 32, "Cloudflare"),
+    v6([0x2620, 0x00fe, 0], 48, "Quad9"),
+];
+
+impl Prefix {
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.len as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.len as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Who operates `ip`, from the built-in prefix table (works offline).
+/// The most specific matching prefix wins.
+pub fn annotate_ip(ip: &str) -> Option<String> {
+    let ip: IpAddr = ip.trim().parse().ok()?;
+    PREFIXES
+        .iter()
+        .filter(|p| p.contains(ip))
+        .max_by_key(|p| p.len)
+        .map(|p| p.owner.to_string())
+}
+
+/// Reverse DNS name for `ip` via `host`, if it has one and answers within
+/// `REVERSE_DNS_TIMEOUT`
+pub fn reverse_dns(ip: &str) -> Option<String> {
+    let mut child = Command::new("host")
+        .arg(ip)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let started = Instant::now();
+    while child.try_wait().ok()?.is_none() {
+        if started.elapsed() >= REVERSE_DNS_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_host_pointer(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Extract the name from "1.1.1.1.in-addr.arpa domain name pointer one.one.one.one."
+fn parse_host_pointer(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.split_once("domain name pointer "))
+        .map(|(_, name)| name.trim().trim_end_matches('.').to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// "96.120.1.1 (Comcast)"; with `lookup_dns`, unknown owners fall back to the rDNS name
+pub fn describe_ip(ip: &str, lookup_dns: bool) -> String {
+    let owner = annotate_ip(ip).or_else(|| lookup_dns.then(|| reverse_dns(ip)).flatten());
+    match owner {
+        Some(owner) => format!("{} ({})", ip, owner),
+        None => ip.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_ip_from_builtin_table() {
+        assert_eq!(annotate_ip("96.120.18.1").as_deref(), Some("Comcast"));
+        assert_eq!(annotate_ip("68.85.102.5").as_deref(), Some("Comcast"));
+        assert_eq!(
+            annotate_ip("4.69.219.66").as_deref(),
+            Some("Lumen (Level 3)")
+        );
+        assert_eq!(annotate_ip("154.54.42.97").as_deref(), Some("Cogent"));
+        assert_eq!(
+            annotate_ip("100.72.0.1").as_deref(),
+            Some("ISP carrier-grade NAT")
+        );
+        assert_eq!(annotate_ip("1.1.1.1").as_deref(), Some("Cloudflare"));
+        assert_eq!(
+            annotate_ip("2001:4860:4860::8888").as_deref(),
+            Some("Google")
+        );
+        assert_eq!(annotate_ip("2620:fe::fe").as_deref(), Some("Quad9"));
+    }
+
+    #[test]
+    fn test_annotate_ip_unknown_or_private() {
+        assert!(annotate_ip("192.168.1.1").is_none());
+        assert!(annotate_ip("10.0.0.1").is_none());
+        assert!(annotate_ip("96.128.0.1").is_none());
+        assert!(annotate_ip("not-an-ip").is_none());
+    }
+
+    #[test]
+    fn test_describe_and_parse_pointer() {
+        assert_eq!(describe_ip("96.120.18.1", false), "96.120.18.1 (Comcast)");
+        assert_eq!(describe_ip("192.168.1.1", false), "192.168.1.1");
+        assert_eq!(
+            parse_host_pointer("1.1.1.1.in-addr.arpa domain name pointer one.one.one.one.\n"),
+            Some("one.one.one.one".to_string())
+        );
+        assert!(
+            parse_host_pointer("Host 1.168.192.in-addr.arpa. not found: 3(NXDOMAIN)").is_none()
+        );
+    }
+}
@@ -1,25 +1,114 @@
-use crate::config::Config;
-use crate::models::{PingResult, Target};
+use crate::config::{Config, MonitorConfig, PingBackendKind, PingMode};
+use crate::models::{status_matches, AddressFamily, CheckKind, PingErrorKind, PingResult, Target};
+use crate::monitor::capability::{PingCapability, PingMethod};
+use crate::monitor::native::NativePing;
+use crate::monitor::resolve::DnsCache;
 use chrono::Utc;
-use std::time::Duration;
+use futures::future::BoxFuture;
+use socket2::Type;
+use std::borrow::Cow;
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
 use tokio::process::Command;
 use tokio::sync::mpsc;
-use tokio::time::interval;
+use tokio::time::{interval, MissedTickBehavior};
+
+/// Sends a single echo request and reports the result
+pub trait PingBackend: Send + Sync {
+    /// Short name shown at startup and in logs
+    fn name(&self) -> &'static str;
+
+    fn ping<'a>(&'a self, ip: &'a str, name: &'a str, timeout_ms: u64)
+        -> BoxFuture<'a, PingResult>;
+}
+
+/// Runs the system `ping` binary and parses its output
+pub struct SystemPing;
+
+impl PingBackend for SystemPing {
+    fn name(&self) -> &'static str {
+        "system"
+    }
+
+    fn ping<'a>(
+        &'a self,
+        ip: &'a str,
+        name: &'a str,
+        timeout_ms: u64,
+    ) -> BoxFuture<'a, PingResult> {
+        Box::pin(ping_target(ip, name, timeout_ms, None))
+    }
+}
+
+/// Pick the configured backend, falling back to system ping when this
+/// process cannot open ICMP sockets
+pub fn select_backend(kind: PingBackendKind, capability: PingCapability) -> Arc<dyn PingBackend> {
+    let wants_native = kind == PingBackendKind::Native;
+    match capability.ping_method(wants_native) {
+        PingMethod::Raw => {
+            let socket_type = if capability == PingCapability::RawSocket {
+                Type::RAW
+            } else {
+                Type::DGRAM
+            };
+            Arc::new(NativePing::new(socket_type))
+        }
+        PingMethod::System => {
+            if wants_native {
+                tracing::warn!(
+                    "Native ping unavailable ({}), falling back to system ping",
+                    capability
+                );
+            }
+            Arc::new(SystemPing)
+        }
+    }
+}
 
 /// Ping monitor that continuously pings multiple targets
 pub struct PingMonitor {
     targets: Vec<Target>,
     interval: Duration,
+    /// Largest random offset from `interval` for each sweep
+    jitter: Duration,
     timeout_ms: u64,
+    mode: PingMode,
+    backend: Arc<dyn PingBackend>,
+    /// Checks per sample
+    count: u32,
+    /// Immediate retries of a failed check
+    retries: u32,
+    max_loss: Option<f64>,
+    /// Addresses of hostname targets
+    dns: Arc<DnsCache>,
 }
 
 impl PingMonitor {
-    /// Create a new ping monitor from configuration
+    /// Create a new ping monitor from configuration (canary targets are pinged too)
     pub fn new(config: &Config) -> Self {
+        let mut targets = config.all_targets();
+        targets.extend(config.monitor.canary_targets.iter().cloned());
+
         Self {
-            targets: config.all_targets(),
+            targets,
             interval: Duration::from_millis(config.monitor.ping_interval_ms),
+            jitter: Duration::from_millis(config.monitor.interval_jitter_ms),
             timeout_ms: config.monitor.ping_timeout_ms,
+            mode: config.monitor.ping_mode,
+            backend: match config.monitor.ping_backend {
+                PingBackendKind::System => Arc::new(SystemPing),
+                kind => select_backend(kind, PingCapability::probe()),
+            },
+            count: config.monitor.ping_count.max(1),
+            retries: config.monitor.ping_retries,
+            max_loss: config.monitor.max_acceptable_loss,
+            dns: Arc::new(DnsCache::new(
+                Duration::from_secs(config.monitor.dns_cache_ttl_secs),
+                Duration::from_millis(config.monitor.ping_timeout_ms),
+            )),
         }
     }
 
@@ -28,13 +117,68 @@ impl PingMonitor {
         Self {
             targets,
             interval,
+            jitter: Duration::ZERO,
             timeout_ms,
+            mode: PingMode::default(),
+            backend: Arc::new(SystemPing),
+            count: 1,
+            retries: 0,
+            max_loss: None,
+            dns: Arc::new(DnsCache::new(
+                Duration::from_secs(MonitorConfig::default().dns_cache_ttl_secs),
+                Duration::from_millis(timeout_ms),
+            )),
         }
     }
 
-    /// Run a single ping to a target
+    /// Send `count` checks per sample; more than `max_loss` lost fails the sample
+    pub fn with_sampling(mut self, count: u32, max_loss: Option<f64>) -> Self {
+        self.count = count.max(1);
+        self.max_loss = max_loss;
+        self
+    }
+
+    /// Retry a failed check up to `retries` times before it counts as lost
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Start each sweep up to `jitter` early or late, at random
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Send pings through a specific backend
+    pub fn with_backend(mut self, backend: Arc<dyn PingBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Name of the backend pings are sent through
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// Set how each sweep over the targets is issued
+    pub fn with_mode(mut self, mode: PingMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Run a single check (ping, TCP connect or HTTP GET) against a target
     pub async fn ping(&self, target: &Target) -> PingResult {
-        ping_target(&target.ip, &target.name, self.timeout_ms).await
+        let result = sample(
+            self.backend.as_ref(),
+            &self.dns,
+            target,
+            self.timeout_ms,
+            self.count,
+            self.retries,
+        )
+        .await;
+        apply_loss_threshold(result, self.max_loss)
     }
 
     /// Start continuous monitoring, sending results to the returned receiver
@@ -42,21 +186,39 @@ impl PingMonitor {
         let (tx, rx) = mpsc::channel(100);
         let targets = self.targets.clone();
         let interval_duration = self.interval;
+        let jitter = self.jitter;
         let timeout_ms = self.timeout_ms;
+        let mode = self.mode;
+        let backend = self.backend.clone();
+        let dns = self.dns.clone();
+        let (count, retries, max_loss) = (self.count, self.retries, self.max_loss);
 
         tokio::spawn(async move {
+            // The ticker paces whole sweeps; a slow sequential sweep delays the next tick
             let mut ticker = interval(interval_duration);
+            if mode == PingMode::Sequential {
+                // Don't fire catch-up ticks back to back after a sweep that overran
+                ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            }
 
-            loop {
-                ticker.tick().await;
+            let mut rng = fastrand::Rng::new();
+            let mut next_sweep = tokio::time::Instant::now();
 
-                // Ping all targets concurrently
-                let futures: Vec<_> = targets
-                    .iter()
-                    .map(|t| ping_target(&t.ip, &t.name, timeout_ms))
-                    .collect();
+            loop {
+                if jitter.is_zero() {
+                    ticker.tick().await;
+                } else {
+                    tokio::time::sleep_until(next_sweep).await;
+                    // Offsets don't accumulate: each is drawn around the nominal interval
+                    next_sweep += jittered_interval(interval_duration, jitter, &mut rng);
+                }
 
-                let results = futures::future::join_all(futures).await;
+                let results = sweep(&targets, mode, |t| async {
+                    let result =
+                        sample(backend.as_ref(), &dns, t, timeout_ms, count, retries).await;
+                    apply_loss_threshold(result, max_loss)
+                })
+                .await;
 
                 for result in results {
                     if tx.send(result).await.is_err() {
@@ -64,6 +226,9 @@ impl PingMonitor {
                         return;
                     }
                 }
+
+                // Like MissedTickBehavior::Delay: no catch-up burst after a slow sweep
+                next_sweep = next_sweep.max(tokio::time::Instant::now());
             }
         });
 
@@ -76,40 +241,359 @@ impl PingMonitor {
     }
 }
 
-/// Execute a single ping to a target IP
-async fn ping_target(ip: &str, name: &str, timeout_ms: u64) -> PingResult {
+/// Time until the next sweep: `interval` moved by a uniform random offset of
+/// at most `jitter` either way, so the average stays `interval`
+fn jittered_interval(interval: Duration, jitter: Duration, rng: &mut fastrand::Rng) -> Duration {
+    let jitter_ms = jitter.min(interval).as_millis() as u64;
+    let interval_ms = interval.as_millis() as u64;
+    Duration::from_millis(interval_ms - jitter_ms + rng.u64(0..=2 * jitter_ms))
+}
+
+/// Ping every target once, either all at once or one after another
+async fn sweep<'a, F, Fut>(targets: &'a [Target], mode: PingMode, ping: F) -> Vec<PingResult>
+where
+    F: Fn(&'a Target) -> Fut,
+    Fut: Future<Output = PingResult>,
+{
+    match mode {
+        PingMode::Concurrent => futures::future::join_all(targets.iter().map(ping)).await,
+        PingMode::Sequential => {
+            let mut results = Vec::with_capacity(targets.len());
+            for target in targets {
+                results.push(ping(target).await);
+            }
+            results
+        }
+    }
+}
+
+/// Resolve a hostname target, then sample its address. The result's `target`
+/// is the target's key (see `Target::key`), and it records the address it was
+/// checked at.
+async fn sample(
+    backend: &dyn PingBackend,
+    dns: &DnsCache,
+    target: &Target,
+    timeout_ms: u64,
+    count: u32,
+    retries: u32,
+) -> PingResult {
+    // HTTP checks resolve their URL's host themselves
+    let literal = target.ip.parse::<IpAddr>().is_ok();
+    if literal || matches!(target.check, Some(CheckKind::HttpGet { .. })) {
+        let mut result = sample_address(backend, target, timeout_ms, count, retries).await;
+        result.target = target.key();
+        return result;
+    }
+
+    let ip = match dns.resolve(&target.ip).await {
+        Ok(ip) => ip.to_string(),
+        Err(error) => return dns_failure(target, error),
+    };
+    let resolved = Target {
+        ip: ip.clone(),
+        ..target.clone()
+    };
+    let mut result = sample_address(backend, &resolved, timeout_ms, count, retries).await;
+    result.target = target.key();
+    result.resolved_ip = Some(ip);
+    result
+}
+
+/// A hostname that didn't resolve was never checked; say so instead of timing out
+fn dns_failure(target: &Target, error: String) -> PingResult {
+    PingResult {
+        target: target.key(),
+        target_name: target.name.clone(),
+        timestamp: Utc::now(),
+        success: false,
+        latency_ms: None,
+        jitter_ms: None,
+        loss: None,
+        error: Some(error),
+        error_kind: Some(PingErrorKind::DnsFailure),
+        resolved_ip: None,
+    }
+}
+
+/// Check `target` `count` times in a row and fold the results into one sample
+async fn sample_address(
+    backend: &dyn PingBackend,
+    target: &Target,
+    timeout_ms: u64,
+    count: u32,
+    retries: u32,
+) -> PingResult {
+    if count <= 1 {
+        return check_with_retries(backend, target, timeout_ms, retries).await;
+    }
+
+    let mut results = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        results.push(check_with_retries(backend, target, timeout_ms, retries).await);
+    }
+    aggregate(results)
+}
+
+/// Check `target`, trying again up to `retries` times while it fails: the
+/// first success (with its latency) or the last failure
+async fn check_with_retries(
+    backend: &dyn PingBackend,
+    target: &Target,
+    timeout_ms: u64,
+    retries: u32,
+) -> PingResult {
+    let mut result = check_target(backend, target, timeout_ms).await;
+    for _ in 0..retries {
+        if result.success {
+            break;
+        }
+        tracing::debug!("Retrying {} after: {:?}", target.ip, result.error);
+        result = check_target(backend, target, timeout_ms).await;
+    }
+    result
+}
+
+/// One result for a multi-packet sample: succeeds if any reply arrived, with
+/// the mean latency of the replies and their standard deviation as jitter
+fn aggregate(results: Vec<PingResult>) -> PingResult {
+    let total = results.len();
+    let latencies: Vec<f64> = results
+        .iter()
+        .filter(|r| r.success)
+        .filter_map(|r| r.latency_ms)
+        .collect();
+    let received = results.iter().filter(|r| r.success).count();
+
+    let mean =
+        (!latencies.is_empty()).then(|| latencies.iter().sum::<f64>() / latencies.len() as f64);
+    let jitter = mean.filter(|_| latencies.len() > 1).map(|mean| {
+        let variance =
+            latencies.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / latencies.len() as f64;
+        variance.sqrt()
+    });
+
+    let (error, error_kind) = match results.last() {
+        Some(last) if received == 0 => (last.error.clone(), last.error_kind),
+        _ => (None, None),
+    };
+    let first = results
+        .into_iter()
+        .next()
+        .expect("a sample has at least one result");
+
+    PingResult {
+        success: received > 0,
+        latency_ms: mean,
+        jitter_ms: jitter,
+        loss: Some((total - received) as f64 / total as f64),
+        error,
+        error_kind,
+        ..first
+    }
+}
+
+/// Fail a sample that lost more than `max_loss` of its packets
+fn apply_loss_threshold(mut result: PingResult, max_loss: Option<f64>) -> PingResult {
+    if let (Some(max_loss), Some(loss)) = (max_loss, result.loss) {
+        if result.success && loss > max_loss {
+            result.success = false;
+            result.error = Some(format!("{:.0}% packet loss", loss * 100.0));
+            result.error_kind = Some(PingErrorKind::Other);
+        }
+    }
+    result
+}
+
+/// Check `target` the way it is configured; ICMP goes through `backend`
+fn check_target<'a>(
+    backend: &'a dyn PingBackend,
+    target: &'a Target,
+    timeout_ms: u64,
+) -> BoxFuture<'a, PingResult> {
+    match &target.check {
+        // Only the system ping can pick the interface, whatever the backend
+        None | Some(CheckKind::Icmp) => match &target.source {
+            Some(source) => Box::pin(ping_target(
+                &target.ip,
+                &target.name,
+                timeout_ms,
+                Some(source),
+            )),
+            None => backend.ping(&target.ip, &target.name, timeout_ms),
+        },
+        Some(CheckKind::TcpConnect { port }) => {
+            Box::pin(tcp_connect(&target.ip, *port, &target.name, timeout_ms))
+        }
+        Some(CheckKind::HttpGet {
+            url,
+            expected_status,
+            body_contains,
+        }) => {
+            let expected = HttpExpectation {
+                status: expected_status.clone(),
+                body_contains: body_contains.clone(),
+            };
+            Box::pin(http_get(
+                &target.ip,
+                url,
+                expected,
+                &target.name,
+                timeout_ms,
+            ))
+        }
+    }
+}
+
+/// Time a TCP handshake with `ip:port`; the connection is closed straight away
+async fn tcp_connect(ip: &str, port: u16, name: &str, timeout_ms: u64) -> PingResult {
     let timestamp = Utc::now();
+    let started = Instant::now();
+    let timeout = Duration::from_millis(timeout_ms);
 
-    // macOS ping command: -c 1 (one packet), -W timeout in ms
-    let output = Command::new("ping")
-        .args(["-c", "1", "-W", &timeout_ms.to_string(), ip])
-        .output()
-        .await;
+    let outcome = match tokio::time::timeout(timeout, TcpStream::connect((ip, port))).await {
+        Ok(Ok(_stream)) => Ok(started.elapsed().as_secs_f64() * 1000.0),
+        Ok(Err(e)) => Err(describe_connect_error(&e)),
+        Err(_) => Err("Request timeout".to_string()),
+    };
+    check_result(ip, name, timestamp, outcome)
+}
+
+/// What an HTTP check asks of the response
+#[derive(Debug, Clone)]
+struct HttpExpectation {
+    status: String,
+    body_contains: Option<String>,
+}
+
+/// GET `url` and time it to the response headers (TTFB). Fails on a status
+/// outside `expected.status`, a body missing `expected.body_contains`, or no response.
+async fn http_get(
+    ip: &str,
+    url: &str,
+    expected: HttpExpectation,
+    name: &str,
+    timeout_ms: u64,
+) -> PingResult {
+    let timestamp = Utc::now();
+    let request_url = url.to_string();
+    let timeout = Duration::from_millis(timeout_ms);
+
+    let outcome = tokio::task::spawn_blocking(move || {
+        let started = Instant::now();
+        // A redirect is checked as the target's own answer, not followed
+        let agent = ureq::AgentBuilder::new()
+            .timeout(timeout)
+            .redirects(0)
+            .build();
+        let response = match agent.get(&request_url).call() {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(format!("HTTP request failed: {}", e)),
+        };
+        let ttfb_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let status = response.status();
+        if !status_matches(&expected.status, status) {
+            return Err(format!("HTTP {} (expected {})", status, expected.status));
+        }
+        if let Some(needle) = &expected.body_contains {
+            let body = response
+                .into_string()
+                .map_err(|e| format!("Failed to read response body: {}", e))?;
+            if !body.contains(needle.as_str()) {
+                return Err(format!("Response body does not contain \"{}\"", needle));
+            }
+        }
+        Ok(ttfb_ms)
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("Check task failed: {}", e)));
+    check_result(ip, name, timestamp, outcome)
+}
+
+/// Same messages as the ICMP path where the causes overlap
+fn describe_connect_error(e: &std::io::Error) -> String {
+    match e.kind() {
+        std::io::ErrorKind::ConnectionRefused => "Connection refused".to_string(),
+        std::io::ErrorKind::TimedOut => "Request timeout".to_string(),
+        _ => {
+            let message = e.to_string();
+            if message.contains("Network is unreachable") {
+                "Network unreachable".to_string()
+            } else if message.contains("No route to host") {
+                "No route to host".to_string()
+            } else {
+                format!("Connect failed: {}", message)
+            }
+        }
+    }
+}
+
+fn check_result(
+    ip: &str,
+    name: &str,
+    timestamp: chrono::DateTime<Utc>,
+    outcome: Result<f64, String>,
+) -> PingResult {
+    PingResult {
+        target: ip.to_string(),
+        target_name: name.to_string(),
+        timestamp,
+        success: outcome.is_ok(),
+        latency_ms: outcome.as_ref().ok().copied(),
+        jitter_ms: None,
+        loss: None,
+        error_kind: outcome.as_ref().err().map(|e| PingErrorKind::of(e)),
+        resolved_ip: None,
+        error: outcome.err(),
+    }
+}
+
+/// Execute a single ping to a target IP, optionally from a source address or interface
+async fn ping_target(ip: &str, name: &str, timeout_ms: u64, source: Option<&str>) -> PingResult {
+    let timestamp = Utc::now();
+
+    let (program, args) = build_ping_args(HostOs::current(), ip, timeout_ms, source);
+    let mut command = Command::new(program);
+    command.args(&args).kill_on_drop(true);
+
+    // ping6 has no per-packet timeout flag, so bound every ping here
+    let limit = Duration::from_millis(timeout_ms) + Duration::from_secs(1);
+    let output = match tokio::time::timeout(limit, command.output()).await {
+        Ok(output) => output,
+        Err(_) => {
+            return PingResult {
+                target: ip.to_string(),
+                target_name: name.to_string(),
+                timestamp,
+                success: false,
+                latency_ms: None,
+                jitter_ms: None,
+                loss: None,
+                error: Some("Request timeout".to_string()),
+                error_kind: Some(PingErrorKind::Timeout),
+                resolved_ip: None,
+            }
+        }
+    };
 
     match output {
         Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let success = output.status.success();
-            let latency_ms = if success {
-                parse_latency(&stdout)
-            } else {
-                None
-            };
+            let stdout = decode_output(&output.stdout, "ping");
+            let stderr = decode_output(&output.stderr, "ping");
+            let outcome = classify_ping_output(output.status.success(), &stdout, &stderr);
 
             PingResult {
                 target: ip.to_string(),
                 target_name: name.to_string(),
                 timestamp,
-                success,
-                latency_ms,
-                error: if success {
-                    None
-                } else {
-                    Some(parse_error(
-                        &stdout,
-                        &String::from_utf8_lossy(&output.stderr),
-                    ))
-                },
+                success: outcome.is_ok(),
+                latency_ms: outcome.as_ref().ok().copied(),
+                jitter_ms: outcome.is_ok().then(|| parse_jitter(&stdout)).flatten(),
+                loss: None,
+                error_kind: outcome.as_ref().err().map(|e| PingErrorKind::of(e)),
+                resolved_ip: None,
+                error: outcome.err(),
             }
         }
         Err(e) => PingResult {
@@ -118,42 +602,236 @@ async fn ping_target(ip: &str, name: &str, timeout_ms: u64) -> PingResult {
             timestamp,
             success: false,
             latency_ms: None,
+            jitter_ms: None,
+            loss: None,
             error: Some(format!("Failed to execute ping: {}", e)),
+            error_kind: Some(PingErrorKind::Other),
+            resolved_ip: None,
         },
     }
 }
 
-/// Parse latency from ping output
-/// Looks for pattern: time=X.XXX ms
-fn parse_latency(output: &str) -> Option<f64> {
-    // Look for "time=14.123 ms" pattern
-    for line in output.lines() {
-        if let Some(time_idx) = line.find("time=") {
-            let after_time = &line[time_idx + 5..];
-            // Find the end of the number (space or "ms")
-            let end_idx = after_time.find([' ', 'm']).unwrap_or(after_time.len());
-            let num_str = &after_time[..end_idx];
-            if let Ok(latency) = num_str.parse::<f64>() {
-                return Some(latency);
+/// Round-trip time of a ping run, or why it failed. Some pings exit 0 even
+/// though the packet was lost; a reply without a measurable RTT is a failure.
+fn classify_ping_output(exit_success: bool, stdout: &str, stderr: &str) -> Result<f64, String> {
+    if !exit_success {
+        return Err(parse_error(stdout, stderr));
+    }
+    parse_latency(stdout).ok_or_else(|| {
+        tracing::debug!("ping exited 0 without a reply time: {}", stdout.trim());
+        match parse_error(stdout, stderr) {
+            error if error == "Ping failed" => "No reply time in ping output".to_string(),
+            error => error,
+        }
+    })
+}
+
+/// Platform whose ping/traceroute flags to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HostOs {
+    MacOs,
+    /// Linux (iputils) flags; also used on other non-macOS systems
+    Linux,
+}
+
+impl HostOs {
+    pub(crate) fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            HostOs::MacOs
+        } else {
+            HostOs::Linux
+        }
+    }
+}
+
+/// Program and arguments for one ping.
+///
+/// macOS: `ping -c 1 -W <ms>`, and `ping6 -c 1` for IPv6 (no timeout flag).
+/// A source address is `-S <addr>`, an interface `-b <iface>` (`-B` for ping6).
+/// Linux: `-W` is in whole seconds, so the timeout is rounded up and also set
+/// as the `-w` deadline; IPv6 is `ping -6`. `-I` takes an address or interface.
+pub(crate) fn build_ping_args(
+    os: HostOs,
+    ip: &str,
+    timeout_ms: u64,
+    source: Option<&str>,
+) -> (&'static str, Vec<String>) {
+    let ipv6 = AddressFamily::of(ip) == Some(AddressFamily::V6);
+    let mut args: Vec<String> = Vec::new();
+
+    if let Some(source) = source {
+        let address = source.parse::<IpAddr>().is_ok();
+        let flag = match os {
+            HostOs::MacOs if address => "-S",
+            HostOs::MacOs if ipv6 => "-B",
+            HostOs::MacOs => "-b",
+            HostOs::Linux => "-I",
+        };
+        args.extend([flag.to_string(), source.to_string()]);
+    }
+
+    let program = match os {
+        HostOs::MacOs if ipv6 => {
+            args.extend(["-c".to_string(), "1".to_string()]);
+            "ping6"
+        }
+        HostOs::MacOs => {
+            args.extend(["-c".to_string(), "1".to_string()]);
+            args.extend(["-W".to_string(), timeout_ms.to_string()]);
+            "ping"
+        }
+        HostOs::Linux => {
+            let secs = timeout_ms.div_ceil(1000).max(1).to_string();
+            if ipv6 {
+                args.push("-6".to_string());
             }
+            args.extend(["-c".to_string(), "1".to_string()]);
+            args.extend(["-W".to_string(), secs.clone(), "-w".to_string(), secs]);
+            "ping"
         }
+    };
+
+    args.push(ip.to_string());
+    (program, args)
+}
+
+/// Check that a target's `source` exists on this machine: an address must be
+/// assigned to a local interface, an interface name must exist
+pub fn check_source(source: &str) -> Result<(), String> {
+    if let Ok(ip) = source.parse::<IpAddr>() {
+        return std::net::UdpSocket::bind((ip, 0))
+            .map(drop)
+            .map_err(|_| format!("source address {} is not assigned to this machine", source));
+    }
+
+    let exists = match HostOs::current() {
+        HostOs::MacOs => std::process::Command::new("ifconfig")
+            .arg(source)
+            .output()
+            .is_ok_and(|output| output.status.success()),
+        HostOs::Linux => std::path::Path::new("/sys/class/net").join(source).exists(),
+    };
+    if exists {
+        Ok(())
+    } else {
+        Err(format!("source interface {} does not exist", source))
     }
-    None
+}
+
+/// Decode command output, logging when it is not valid UTF-8
+///
+/// Invalid bytes become U+FFFD; parsers skip lines containing it rather than
+/// guess at what the bytes were.
+pub(crate) fn decode_output<'a>(bytes: &'a [u8], command: &str) -> Cow<'a, str> {
+    let text = String::from_utf8_lossy(bytes);
+    if let Cow::Owned(_) = text {
+        let mangled = text
+            .lines()
+            .filter(|line| line.contains(char::REPLACEMENT_CHARACTER))
+            .count();
+        tracing::debug!(
+            "{} output is not valid UTF-8 ({} line{} affected)",
+            command,
+            mangled,
+            if mangled == 1 { "" } else { "s" }
+        );
+    }
+    text
+}
+
+/// A line ping printed about one packet: a reply ("64 bytes from ...: icmp_seq=0
+/// ... time=14.1 ms"), a per-packet error ("From 10.0.0.1 icmp_seq=1 Destination
+/// Host Unreachable", "Request timeout for icmp_seq 0") or its own diagnostic
+/// ("ping: sendto: No route to host"). Warnings from elsewhere are none of these.
+fn is_packet_line(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("ping:")
+        || line.starts_with("From ")
+        || line.contains("bytes from")
+        || line.contains("icmp_seq")
+}
+
+/// Lines after the "--- <host> ping statistics ---" header
+fn statistics_block(output: &str) -> impl Iterator<Item = &str> {
+    output
+        .lines()
+        .skip_while(|line| !(line.starts_with("---") && line.contains("ping statistics")))
+        .skip(1)
+}
+
+/// Parse latency from ping output
+/// Looks for the `time=X.XXX ms` field of a reply line
+fn parse_latency(output: &str) -> Option<f64> {
+    output
+        .lines()
+        // A mangled line could yield a wrong number; only trust clean lines
+        .filter(|line| !line.contains(char::REPLACEMENT_CHARACTER))
+        .filter(|line| line.contains("bytes from") && line.contains("icmp_seq"))
+        .find_map(|line| {
+            line.split_whitespace()
+                .find_map(|field| field.strip_prefix("time="))
+                .and_then(|time| time.trim_end_matches("ms").parse::<f64>().ok())
+        })
+}
+
+/// Packet loss percentage from the statistics block, e.g.
+/// `1 packets transmitted, 0 packets received, 100.0% packet loss`
+fn parse_loss_percent(output: &str) -> Option<f64> {
+    statistics_block(output).find_map(|line| {
+        line.split(',')
+            .map(str::trim)
+            .find_map(|part| part.strip_suffix("% packet loss"))
+            .and_then(|percent| percent.parse().ok())
+    })
+}
+
+/// Parse the stddev from the summary line, e.g.
+/// `round-trip min/avg/max/stddev = 14.1/14.5/15.0/0.4 ms` (macOS)
+/// or `rtt min/avg/max/mdev = ...` (Linux)
+fn parse_jitter(output: &str) -> Option<f64> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("round-trip") || line.starts_with("rtt"))
+        .filter(|line| !line.contains(char::REPLACEMENT_CHARACTER))
+        .find_map(|line| {
+            let (_, values) = line.split_once('=')?;
+            let values = values.split_whitespace().next()?;
+            let fields: Vec<&str> = values.split('/').collect();
+            match fields.as_slice() {
+                [_, _, _, stddev] => stddev.parse().ok(),
+                _ => None,
+            }
+        })
 }
 
 /// Parse error message from ping output
 fn parse_error(stdout: &str, stderr: &str) -> String {
-    // Check for common error patterns
-    if stdout.contains("100.0% packet loss") || stdout.contains("100% packet loss") {
+    if parse_loss_percent(stdout) == Some(100.0) {
         return "Request timeout".to_string();
     }
-    if stdout.contains("No route to host") {
+
+    // Only trust ping's own lines; unrelated warnings may mention these words
+    let packet_lines: Vec<&str> = stdout
+        .lines()
+        .chain(stderr.lines())
+        .filter(|line| is_packet_line(line))
+        .collect();
+    let reported = |needle: &str| packet_lines.iter().any(|line| line.contains(needle));
+
+    if reported("No route to host") {
         return "No route to host".to_string();
     }
-    if stdout.contains("Network is unreachable") {
+    if reported("Network is unreachable") {
         return "Network unreachable".to_string();
     }
-    if stderr.contains("Unknown host") || stderr.contains("cannot resolve") {
+    const DNS_ERRORS: [&str; 4] = [
+        "Unknown host",
+        "cannot resolve",
+        "Name or service not known",
+        "Temporary failure in name resolution",
+    ];
+    if DNS_ERRORS.iter().any(|needle| stderr.contains(needle)) {
         return "DNS resolution failed".to_string();
     }
 
@@ -168,6 +846,268 @@ fn parse_error(stdout: &str, stderr: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Run a sweep with a mock ping that logs when each ping starts and finishes
+    async fn instrumented_sweep(mode: PingMode) -> (Vec<PingResult>, Vec<String>) {
+        let targets = vec![
+            Target::new("Gateway", "192.168.1.1"),
+            Target::new("Google DNS", "8.8.8.8"),
+            Target::new("Cloudflare", "1.1.1.1"),
+        ];
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let results = sweep(&targets, mode, |t| {
+            let log = log.clone();
+            async move {
+                log.lock().unwrap().push(format!("start {}", t.ip));
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                log.lock().unwrap().push(format!("end {}", t.ip));
+                PingResult {
+                    target: t.ip.clone(),
+                    target_name: t.name.clone(),
+                    timestamp: Utc::now(),
+                    success: true,
+                    latency_ms: Some(1.0),
+                    jitter_ms: None,
+                    loss: None,
+                    error: None,
+                    error_kind: None,
+                    resolved_ip: None,
+                }
+            }
+        })
+        .await;
+
+        let log = log.lock().unwrap().clone();
+        (results, log)
+    }
+
+    #[test]
+    fn test_jittered_interval_stays_in_bounds() {
+        let interval = Duration::from_millis(1000);
+        let jitter = Duration::from_millis(100);
+        let mut rng = fastrand::Rng::with_seed(7);
+
+        let waits: Vec<u128> = (0..2000)
+            .map(|_| jittered_interval(interval, jitter, &mut rng).as_millis())
+            .collect();
+        assert!(waits.iter().all(|ms| (900..=1100).contains(ms)));
+        assert!(waits.windows(2).any(|pair| pair[0] != pair[1]));
+
+        let mean = waits.iter().sum::<u128>() as f64 / waits.len() as f64;
+        assert!((mean - 1000.0).abs() < 5.0, "mean wait {}ms", mean);
+
+        // Jitter wider than the interval can't produce negative waits
+        let wide = jittered_interval(interval, Duration::from_secs(5), &mut rng);
+        assert!(wide <= Duration::from_millis(2000));
+    }
+
+    #[tokio::test]
+    async fn test_sequential_sweep_pings_in_order() {
+        let (results, log) = instrumented_sweep(PingMode::Sequential).await;
+
+        assert_eq!(
+            log,
+            vec![
+                "start 192.168.1.1",
+                "end 192.168.1.1",
+                "start 8.8.8.8",
+                "end 8.8.8.8",
+                "start 1.1.1.1",
+                "end 1.1.1.1",
+            ]
+        );
+        let order: Vec<&str> = results.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(order, vec!["192.168.1.1", "8.8.8.8", "1.1.1.1"]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_sweep_overlaps() {
+        let (results, log) = instrumented_sweep(PingMode::Concurrent).await;
+
+        // Every ping starts before any finishes
+        assert!(log[..3].iter().all(|e| e.starts_with("start")));
+        assert_eq!(results.len(), 3);
+    }
+
+    #[test]
+    fn test_native_backend_falls_back_without_sockets() {
+        let backend = select_backend(PingBackendKind::Native, PingCapability::SystemOnly);
+        assert_eq!(backend.name(), "system");
+
+        let backend = select_backend(PingBackendKind::Native, PingCapability::DatagramSocket);
+        assert_eq!(backend.name(), "native");
+
+        let backend = select_backend(PingBackendKind::System, PingCapability::RawSocket);
+        assert_eq!(backend.name(), "system");
+    }
+
+    fn echo(success: bool, latency_ms: f64) -> PingResult {
+        PingResult {
+            target: "8.8.8.8".to_string(),
+            target_name: "Google DNS".to_string(),
+            timestamp: Utc::now(),
+            success,
+            latency_ms: success.then_some(latency_ms),
+            jitter_ms: None,
+            loss: None,
+            error: (!success).then(|| "Request timeout".to_string()),
+            error_kind: None,
+            resolved_ip: None,
+        }
+    }
+
+    #[test]
+    fn test_loss_threshold_at_below_and_above() {
+        let sample = aggregate(vec![
+            echo(true, 10.0),
+            echo(false, 0.0),
+            echo(true, 20.0),
+            echo(true, 30.0),
+        ]);
+        assert!(sample.success);
+        assert_eq!(sample.loss, Some(0.25));
+        assert_eq!(sample.latency_ms, Some(20.0));
+        assert!(sample.jitter_ms.unwrap() > 8.0);
+
+        // Loss exactly at the limit, or below it, is still a success
+        assert!(apply_loss_threshold(sample.clone(), Some(0.25)).success);
+        assert!(apply_loss_threshold(sample.clone(), Some(0.5)).success);
+        assert!(apply_loss_threshold(sample.clone(), None).success);
+
+        let failed = apply_loss_threshold(sample, Some(0.1));
+        assert!(!failed.success);
+        assert_eq!(failed.error.as_deref(), Some("25% packet loss"));
+
+        // Total loss fails without any threshold
+        let lost = aggregate(vec![echo(false, 0.0), echo(false, 0.0)]);
+        assert!(!lost.success);
+        assert_eq!(lost.loss, Some(1.0));
+        assert_eq!(lost.error.as_deref(), Some("Request timeout"));
+    }
+
+    /// Fails every `fail_every`-th echo
+    struct FlakyBackend {
+        calls: std::sync::atomic::AtomicU32,
+        fail_every: u32,
+    }
+
+    impl PingBackend for FlakyBackend {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn ping<'a>(&'a self, _: &'a str, _: &'a str, _: u64) -> BoxFuture<'a, PingResult> {
+            let call = self
+                .calls
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+                + 1;
+            Box::pin(async move { echo(!call.is_multiple_of(self.fail_every), 5.0) })
+        }
+    }
+
+    /// Loses the first echo, then answers in 7ms
+    struct FailsOnce {
+        calls: std::sync::atomic::AtomicU32,
+    }
+
+    impl PingBackend for FailsOnce {
+        fn name(&self) -> &'static str {
+            "fails-once"
+        }
+
+        fn ping<'a>(&'a self, _: &'a str, _: &'a str, _: u64) -> BoxFuture<'a, PingResult> {
+            let call = self
+                .calls
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Box::pin(async move { echo(call > 0, 7.0) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_recovers_single_lost_ping() {
+        let target = Target::new("Google DNS", "8.8.8.8");
+        let monitor = |retries| {
+            PingMonitor::with_settings(vec![], Duration::from_secs(1), 1000)
+                .with_backend(Arc::new(FailsOnce {
+                    calls: Default::default(),
+                }))
+                .with_retries(retries)
+        };
+
+        let result = monitor(1).ping(&target).await;
+        assert!(result.success);
+        assert_eq!(result.latency_ms, Some(7.0));
+        assert!(result.error.is_none());
+
+        let result = monitor(0).ping(&target).await;
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("Request timeout"));
+    }
+
+    /// Succeeds, reporting the address it was asked to ping as the error text
+    struct AddressEcho;
+
+    impl PingBackend for AddressEcho {
+        fn name(&self) -> &'static str {
+            "address-echo"
+        }
+
+        fn ping<'a>(&'a self, ip: &'a str, _: &'a str, _: u64) -> BoxFuture<'a, PingResult> {
+            Box::pin(async move {
+                PingResult {
+                    target: ip.to_string(),
+                    error: Some(ip.to_string()),
+                    ..echo(true, 1.0)
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hostname_target_is_pinged_by_resolved_address() {
+        let monitor = PingMonitor::with_settings(vec![], Duration::from_secs(1), 1000)
+            .with_backend(Arc::new(AddressEcho));
+
+        let result = monitor.ping(&Target::new("Loopback", "localhost")).await;
+        let resolved = result.resolved_ip.clone().unwrap();
+        assert!(resolved.parse::<IpAddr>().unwrap().is_loopback());
+        assert_eq!(result.error.as_deref(), Some(resolved.as_str()));
+        // Still keyed by the configured name for the state machine
+        assert_eq!(result.target, "localhost");
+
+        let literal = monitor.ping(&Target::new("Gateway", "192.168.1.1")).await;
+        assert_eq!(literal.target, "192.168.1.1");
+        assert!(literal.resolved_ip.is_none());
+
+        let failed = dns_failure(
+            &Target::new("Nowhere", "nosuch.invalid"),
+            "DNS resolution failed".to_string(),
+        );
+        assert!(!failed.success);
+        assert_eq!(failed.error_kind, Some(PingErrorKind::DnsFailure));
+    }
+
+    #[tokio::test]
+    async fn test_monitor_fails_samples_over_loss_limit() {
+        let target = Target::new("Google DNS", "8.8.8.8");
+        let monitor = |max_loss| {
+            PingMonitor::with_settings(vec![], Duration::from_secs(1), 1000)
+                .with_backend(Arc::new(FlakyBackend {
+                    calls: Default::default(),
+                    fail_every: 3,
+                }))
+                .with_sampling(3, max_loss)
+        };
+
+        // 1 of 3 lost
+        let strict = monitor(Some(0.2)).ping(&target).await;
+        assert!(!strict.success);
+        let lenient = monitor(Some(0.5)).ping(&target).await;
+        assert!(lenient.success);
+        assert!((lenient.loss.unwrap() - 1.0 / 3.0).abs() < 1e-9);
+    }
 
     #[test]
     fn test_parse_latency_success() {
@@ -196,6 +1136,73 @@ round-trip min/avg/max/stddev = 14.123/14.123/14.123/0.000 ms"#;
         assert!(latency.is_none());
     }
 
+    #[test]
+    fn test_exit_zero_without_reply_is_failure() {
+        let output = r#"PING 8.8.8.8 (8.8.8.8): 56 data bytes
+
+--- 8.8.8.8 ping statistics ---
+1 packets transmitted, 0 packets received, 100.0% packet loss"#;
+        assert_eq!(
+            classify_ping_output(true, output, ""),
+            Err("Request timeout".to_string())
+        );
+        assert_eq!(
+            classify_ping_output(true, "PING 8.8.8.8 (8.8.8.8): 56 data bytes", ""),
+            Err("No reply time in ping output".to_string())
+        );
+
+        let reply = "64 bytes from 8.8.8.8: icmp_seq=0 ttl=117 time=14.123 ms";
+        assert_eq!(classify_ping_output(true, reply, ""), Ok(14.123));
+    }
+
+    #[test]
+    fn test_parse_jitter_from_summary_line() {
+        let output = r#"PING 8.8.8.8 (8.8.8.8): 56 data bytes
+64 bytes from 8.8.8.8: icmp_seq=0 ttl=117 time=14.123 ms
+64 bytes from 8.8.8.8: icmp_seq=1 ttl=117 time=16.877 ms
+
+--- 8.8.8.8 ping statistics ---
+2 packets transmitted, 2 packets received, 0.0% packet loss
+round-trip min/avg/max/stddev = 14.123/15.500/16.877/1.377 ms"#;
+        assert_eq!(parse_jitter(output), Some(1.377));
+
+        let linux = "rtt min/avg/max/mdev = 0.031/0.045/0.060/0.012 ms";
+        assert_eq!(parse_jitter(linux), Some(0.012));
+    }
+
+    #[test]
+    fn test_parse_jitter_single_packet_is_zero() {
+        let output = r#"--- 8.8.8.8 ping statistics ---
+1 packets transmitted, 1 packets received, 0.0% packet loss
+round-trip min/avg/max/stddev = 14.123/14.123/14.123/0.000 ms"#;
+        assert_eq!(parse_jitter(output), Some(0.0));
+    }
+
+    #[test]
+    fn test_parse_jitter_missing_summary() {
+        assert!(parse_jitter("Request timeout for icmp_seq 0").is_none());
+        assert!(parse_jitter("round-trip min/avg/max = 1/2/3 ms").is_none());
+    }
+
+    #[test]
+    fn test_parse_latency_skips_invalid_utf8() {
+        let bytes = b"64 bytes from 8.8.8.8: icmp_seq=0 ttl=117 time=1\xff4.1 ms\n\
+64 bytes from 8.8.8.8: icmp_seq=1 ttl=117 \xfetime=9.5 ms\n";
+        let output = decode_output(bytes, "ping");
+        assert!(matches!(output, Cow::Owned(_)));
+        assert!(parse_latency(&output).is_none());
+
+        // A clean line after a mangled one still parses
+        let bytes = b"garbage \xc3\x28 time=99.9 ms\n\
+64 bytes from 8.8.8.8: icmp_seq=1 ttl=117 time=14.2 ms\n";
+        assert_eq!(parse_latency(&decode_output(bytes, "ping")), Some(14.2));
+
+        assert!(matches!(
+            decode_output(b"time=1.0 ms", "ping"),
+            Cow::Borrowed(_)
+        ));
+    }
+
     #[test]
     fn test_parse_error_timeout() {
         let stdout = r#"PING 8.8.8.8 (8.8.8.8): 56 data bytes
@@ -221,9 +1228,96 @@ round-trip min/avg/max/stddev = 14.123/14.123/14.123/0.000 ms"#;
         assert_eq!(error, "Network unreachable");
     }
 
+    #[test]
+    fn test_parsers_ignore_distractor_lines() {
+        let stdout = r#"ping: Warning: time=99.9 ms offset configured for this interface
+PING 8.8.8.8 (8.8.8.8): 56 data bytes
+warning: route to 10.0.0.0/8 unreachable, using default (No route to host)
+64 bytes from 8.8.8.8: icmp_seq=0 ttl=117 time=14.123 ms
+note: 100% packet loss is reported when every probe is lost
+
+--- 8.8.8.8 ping statistics ---
+1 packets transmitted, 1 packets received, 0.0% packet loss
+round-trip min/avg/max/stddev = 14.123/14.123/14.123/0.000 ms"#;
+
+        assert_eq!(parse_latency(stdout), Some(14.123));
+        assert_eq!(parse_loss_percent(stdout), Some(0.0));
+        assert_eq!(parse_error(stdout, ""), "Ping failed");
+
+        let lost = r#"note: 0% packet loss expected on this link
+PING 8.8.8.8 (8.8.8.8): 56 data bytes
+Request timeout for icmp_seq 0
+
+--- 8.8.8.8 ping statistics ---
+1 packets transmitted, 0 packets received, 100.0% packet loss"#;
+        assert_eq!(parse_latency(lost), None);
+        assert_eq!(parse_loss_percent(lost), Some(100.0));
+        assert_eq!(parse_error(lost, ""), "Request timeout");
+    }
+
+    #[test]
+    fn test_error_kind_for_each_pattern() {
+        let kind = |stdout: &str, stderr: &str| PingErrorKind::of(&parse_error(stdout, stderr));
+
+        let lost = r#"PING 8.8.8.8 (8.8.8.8): 56 data bytes
+
+--- 8.8.8.8 ping statistics ---
+1 packets transmitted, 0 packets received, 100.0% packet loss"#;
+        assert_eq!(kind(lost, ""), PingErrorKind::Timeout);
+        assert_eq!(
+            kind("ping: sendto: No route to host", ""),
+            PingErrorKind::NoRoute
+        );
+        assert_eq!(
+            kind("", "ping: sendto: Network is unreachable"),
+            PingErrorKind::NetworkUnreachable
+        );
+        // macOS and Linux wording for an unresolvable hostname
+        assert_eq!(
+            kind("", "ping: cannot resolve nosuch.example: Unknown host"),
+            PingErrorKind::DnsFailure
+        );
+        assert_eq!(
+            kind("", "ping: nosuch.example: Name or service not known"),
+            PingErrorKind::DnsFailure
+        );
+        assert_eq!(
+            kind(
+                "",
+                "ping: example.com: Temporary failure in name resolution"
+            ),
+            PingErrorKind::DnsFailure
+        );
+        assert_eq!(
+            kind("", "ping: invalid option -- 'W'"),
+            PingErrorKind::Other
+        );
+        assert_eq!(kind("", ""), PingErrorKind::Other);
+
+        // Exit 0 without a reply time is lost, not a timeout
+        let error =
+            classify_ping_output(true, "PING 8.8.8.8 (8.8.8.8): 56 data bytes", "").unwrap_err();
+        assert_eq!(PingErrorKind::of(&error), PingErrorKind::Other);
+    }
+
+    #[test]
+    fn test_parse_error_from_packet_lines() {
+        let linux = r#"PING 10.9.9.9 (10.9.9.9) 56(84) bytes of data.
+From 192.168.1.1 icmp_seq=1 Destination Host Unreachable (No route to host)
+
+--- 10.9.9.9 ping statistics ---
+1 packets transmitted, 0 received, +1 errors"#;
+        assert_eq!(parse_error(linux, ""), "No route to host");
+
+        assert_eq!(
+            parse_error("", "ping: sendto: Network is unreachable"),
+            "Network unreachable"
+        );
+    }
+
     #[tokio::test]
     async fn test_ping_localhost() {
-        let result = ping_target("127.0.0.1", "localhost", 2000).await;
+        let result = ping_target("127.0.0.1", "localhost", 2000, None).await;
         assert!(result.success, "Ping to localhost should succeed");
         assert!(result.latency_ms.is_some(), "Should have latency");
         assert!(
@@ -232,14 +1326,168 @@ round-trip min/avg/max/stddev = 14.123/14.123/14.123/0.000 ms"#;
         );
     }
 
+    #[tokio::test]
+    async fn test_ping_ipv6_localhost() {
+        let result = ping_target("::1", "localhost", 2000, None).await;
+        assert!(result.success, "Ping to IPv6 loopback should succeed");
+        assert!(result.latency_ms.is_some(), "Should have latency");
+    }
+
+    #[test]
+    fn test_build_ping_args_macos() {
+        let (program, args) = build_ping_args(HostOs::MacOs, "192.0.2.1", 1500, None);
+        assert_eq!(program, "ping");
+        assert_eq!(args, vec!["-c", "1", "-W", "1500", "192.0.2.1"]);
+
+        let (program, args) = build_ping_args(HostOs::MacOs, "2001:db8::1", 1500, None);
+        assert_eq!(program, "ping6");
+        assert_eq!(args, vec!["-c", "1", "2001:db8::1"]);
+    }
+
+    #[test]
+    fn test_build_ping_args_linux() {
+        // -W is whole seconds on Linux: 1500ms rounds up to 2
+        let (program, args) = build_ping_args(HostOs::Linux, "192.0.2.1", 1500, None);
+        assert_eq!(program, "ping");
+        assert_eq!(args, vec!["-c", "1", "-W", "2", "-w", "2", "192.0.2.1"]);
+
+        let (_, args) = build_ping_args(HostOs::Linux, "192.0.2.1", 200, None);
+        assert_eq!(args, vec!["-c", "1", "-W", "1", "-w", "1", "192.0.2.1"]);
+
+        let (program, args) = build_ping_args(HostOs::Linux, "2001:db8::1", 2000, None);
+        assert_eq!(program, "ping");
+        assert_eq!(
+            args,
+            vec!["-6", "-c", "1", "-W", "2", "-w", "2", "2001:db8::1"]
+        );
+    }
+
+    #[test]
+    fn test_build_ping_args_with_source() {
+        let (_, args) = build_ping_args(HostOs::MacOs, "192.0.2.1", 1500, Some("192.168.1.20"));
+        assert_eq!(
+            args,
+            vec!["-S", "192.168.1.20", "-c", "1", "-W", "1500", "192.0.2.1"]
+        );
+
+        let (_, args) = build_ping_args(HostOs::MacOs, "192.0.2.1", 1500, Some("en1"));
+        assert_eq!(
+            args,
+            vec!["-b", "en1", "-c", "1", "-W", "1500", "192.0.2.1"]
+        );
+
+        let (program, args) = build_ping_args(HostOs::MacOs, "2001:db8::1", 1500, Some("en1"));
+        assert_eq!(program, "ping6");
+        assert_eq!(args, vec!["-B", "en1", "-c", "1", "2001:db8::1"]);
+
+        let (_, args) = build_ping_args(HostOs::Linux, "192.0.2.1", 1500, Some("wlan0"));
+        assert_eq!(
+            args,
+            vec!["-I", "wlan0", "-c", "1", "-W", "2", "-w", "2", "192.0.2.1"]
+        );
+    }
+
+    #[test]
+    fn test_check_source() {
+        assert!(check_source("127.0.0.1").is_ok());
+        // TEST-NET-3 is never assigned locally
+        assert!(check_source("203.0.113.77").is_err());
+        assert!(check_source("no-such-interface0").is_err());
+    }
+
     #[tokio::test]
     async fn test_ping_invalid_ip() {
         // Using a non-routable IP that should timeout quickly
-        let result = ping_target("192.0.2.1", "test", 1000).await;
+        let result = ping_target("192.0.2.1", "test", 1000, None).await;
         assert!(!result.success, "Ping to non-routable IP should fail");
         assert!(result.error.is_some(), "Should have error message");
     }
 
+    #[tokio::test]
+    async fn test_tcp_connect_check() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let target = Target::new("web", "127.0.0.1").with_check(CheckKind::TcpConnect { port });
+        let monitor = PingMonitor::with_settings(vec![], Duration::from_secs(1), 1000);
+
+        let result = monitor.ping(&target).await;
+        assert!(result.success, "error: {:?}", result.error);
+        assert_eq!(result.target, format!("127.0.0.1/tcp:{}", port));
+        assert!(result.latency_ms.is_some());
+
+        // Nothing listens once the listener is gone
+        drop(listener);
+        let result = monitor.ping(&target).await;
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("Connection refused"));
+    }
+
+    #[test]
+    fn test_status_matches() {
+        assert!(status_matches("2xx", 200));
+        assert!(status_matches("2xx", 204));
+        assert!(!status_matches("2xx", 302));
+        assert!(status_matches("204", 204));
+        assert!(!status_matches("204", 200));
+        assert!(status_matches("2xx, 3XX", 301));
+        assert!(!status_matches("2xx", 2000));
+    }
+
+    #[tokio::test]
+    async fn test_http_get_check() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                if request.url() == "/moved" {
+                    let response = tiny_http::Response::from_string("")
+                        .with_status_code(302)
+                        .with_header("Location: /portal".parse::<tiny_http::Header>().unwrap());
+                    let _ = request.respond(response);
+                    continue;
+                }
+                let (code, body) = match request.url() {
+                    "/portal" => (200, "<html>Success</html>"),
+                    "/login" => (200, "<html>Sign in to Wi-Fi</html>"),
+                    "/empty" => (204, ""),
+                    _ => (503, "unavailable"),
+                };
+                let response = tiny_http::Response::from_string(body).with_status_code(code);
+                let _ = request.respond(response);
+            }
+        });
+
+        let monitor = PingMonitor::with_settings(vec![], Duration::from_secs(1), 2000);
+        let check = |path: &str, expected_status: &str, body_contains: Option<&str>| {
+            Target::new("Web", "127.0.0.1").with_check(CheckKind::HttpGet {
+                url: format!("http://{}{}", addr, path),
+                expected_status: expected_status.to_string(),
+                body_contains: body_contains.map(str::to_string),
+            })
+        };
+
+        let ok = monitor
+            .ping(&check("/portal", "2xx", Some("Success")))
+            .await;
+        assert!(ok.success, "error: {:?}", ok.error);
+        assert!(ok.latency_ms.is_some());
+
+        // A captive portal answers 200 with the wrong page
+        let portal = monitor.ping(&check("/login", "2xx", Some("Success"))).await;
+        assert!(!portal.success);
+        assert!(portal.error.unwrap().contains("Success"));
+
+        let down = monitor.ping(&check("/status", "2xx", None)).await;
+        assert_eq!(down.error.as_deref(), Some("HTTP 503 (expected 2xx)"));
+        assert!(!monitor.ping(&check("/empty", "200", None)).await.success);
+        assert!(monitor.ping(&check("/empty", "204", None)).await.success);
+
+        // Portals redirect to their login page; the redirect itself is the answer
+        let moved = monitor.ping(&check("/moved", "2xx", None)).await;
+        assert_eq!(moved.error.as_deref(), Some("HTTP 302 (expected 2xx)"));
+        assert!(monitor.ping(&check("/moved", "3xx", None)).await.success);
+    }
+
     #[test]
     fn test_ping_monitor_creation() {
         let config = Config::default();
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Resolves hostname targets, remembering each answer for `ttl` so a sweep
+/// every second doesn't query DNS every second
+pub struct DnsCache {
+    ttl: Duration,
+    /// Longest a lookup may take; a hung resolver must not stall the sweep
+    timeout: Duration,
+    entries: Mutex<HashMap<String, (IpAddr, Instant)>>,
+}
+
+impl DnsCache {
+    pub fn new(ttl: Duration, timeout: Duration) -> Self {
+        Self {
+            ttl,
+            timeout,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The address to check for `host`. IP literals are returned as they are.
+    /// When a lookup fails or times out, the last address that resolved is
+    /// reused however old it is, so an outage that takes DNS down with it
+    /// still shows up as failed pings; with no address ever resolved it is
+    /// "DNS resolution failed", like the system ping's.
+    pub async fn resolve(&self, host: &str) -> Result<IpAddr, String> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(ip);
+        }
+        if let Some(ip) = self.cached(host, Instant::now()) {
+            return Ok(ip);
+        }
+
+        let lookup = tokio::time::timeout(self.timeout, tokio::net::lookup_host((host, 0))).await;
+        let resolved = match lookup {
+            Ok(Ok(mut addrs)) => addrs.next().map(|addr| addr.ip()),
+            Ok(Err(e)) => {
+                tracing::debug!("Could not resolve {}: {}", host, e);
+                None
+            }
+            Err(_) => {
+                tracing::debug!("Resolving {} timed out after {:?}", host, self.timeout);
+                None
+            }
+        };
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match resolved {
+            Some(ip) => {
+                tracing::debug!("Resolved {} to {}", host, ip);
+                entries.insert(host.to_string(), (ip, Instant::now()));
+                Ok(ip)
+            }
+            None => entries
+                .get(host)
+                .map(|(ip, _)| *ip)
+                .inspect(|ip| tracing::debug!("Using last known address {} for {}", ip, host))
+                .ok_or_else(|| "DNS resolution failed".to_string()),
+        }
+    }
+
+    fn cached(&self, host: &str, now: Instant) -> Option<IpAddr> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(host)
+            .filter(|(_, at)| now.saturating_duration_since(*at) < self.ttl)
+            .map(|(ip, _)| *ip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache() -> DnsCache {
+        DnsCache::new(Duration::from_secs(300), Duration::from_secs(2))
+    }
+
+    #[tokio::test]
+    async fn test_resolve_localhost() {
+        let cache = cache();
+
+        let ip = cache.resolve("localhost").await.unwrap();
+        assert!(ip.is_loopback());
+        // Answered from the cache until the entry expires
+        assert_eq!(cache.cached("localhost", Instant::now()), Some(ip));
+        assert_eq!(
+            cache.cached("localhost", Instant::now() + Duration::from_secs(301)),
+            None
+        );
+
+        // Literals never touch DNS or the cache
+        assert_eq!(
+            cache.resolve("192.168.1.1").await.unwrap(),
+            "192.168.1.1".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(cache.cached("192.168.1.1", Instant::now()), None);
+    }
+
+    #[tokio::test]
+    async fn test_failed_lookup_falls_back_to_stale_address() {
+        let cache = DnsCache::new(Duration::ZERO, Duration::from_secs(2));
+        let last_known: IpAddr = "192.0.2.10".parse().unwrap();
+        // Resolved long ago; the TTL of zero means it is always expired
+        cache
+            .entries
+            .lock()
+            .unwrap()
+            .insert("nosuch.invalid".to_string(), (last_known, Instant::now()));
+
+        assert_eq!(cache.resolve("nosuch.invalid").await, Ok(last_known));
+        assert_eq!(
+            cache.resolve("other.invalid").await,
+            Err("DNS resolution failed".to_string())
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// Pings keep failing for a while after wake until Wi-Fi reassociates
+const WAKE_GRACE_SECS: i64 = 30;
+
+/// Share of an outage that must fall in sleep for the sleep to explain it
+const SLEEP_SHARE: f64 = 0.5;
+
+/// A period during which the machine was asleep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SleepWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl SleepWindow {
+    /// How much of an outage in `[start, end]` falls in this sleep (or the reconnect right after it)
+    pub fn overlap(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Duration {
+        let until = end.min(self.end + Duration::seconds(WAKE_GRACE_SECS));
+        (until - start.max(self.start)).max(Duration::zero())
+    }
+
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.start <= at && at <= self.end + Duration::seconds(WAKE_GRACE_SECS)
+    }
+}
+
+/// Whether an outage in `[start, end]` happened mostly while asleep. A long
+/// outage that only touches a lid-close is still a real outage.
+pub fn mostly_asleep(windows: &[SleepWindow], start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+    let total = end - start;
+    if total <= Duration::zero() {
+        return windows.iter().any(|w| w.contains(start));
+    }
+    let slept: Duration = windows
+        .iter()
+        .map(|w| w.overlap(start, end))
+        .fold(Duration::zero(), |sum, d| sum + d);
+    slept.num_milliseconds() as f64 >= total.num_milliseconds() as f64 * SLEEP_SHARE
+}
+
+/// Sleep periods between `since` and `until`, from `pmset -g log`.
+/// Returns nothing if pmset fails.
+#[cfg(target_os = "macos")]
+pub fn detect_sleep_windows(since: DateTime<Utc>, until: DateTime<Utc>) -> Vec<SleepWindow> {
+    let output = match std::process::Command::new("pmset")
+        .args(["-g", "log"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            tracing::debug!("pmset exited with {}", output.status);
+            return Vec::new();
+        }
+        Err(e) => {
+            tracing::debug!("pmset unavailable: {}", e);
+            return Vec::new();
+        }
+    };
+
+    parse_pmset_log(&String::from_utf8_lossy(&output.stdout))
+        .into_iter()
+        .filter(|w| w.end >= since && w.start <= until)
+        .collect()
+}
+
+/// There is no pmset to ask outside macOS
+#[cfg(not(target_os = "macos"))]
+pub fn detect_sleep_windows(_since: DateTime<Utc>, _until: DateTime<Utc>) -> Vec<SleepWindow> {
+    Vec::new()
+}
+
+/// Pair `Sleep` entries with the `Wake`/`DarkWake` that follows them
+pub fn parse_pmset_log(output: &str) -> Vec<SleepWindow> {
+    let mut windows = Vec::new();
+    let mut asleep_since: Option<DateTime<Utc>> = None;
+
+    for line in output.lines() {
+        let Some((timestamp, event)) = parse_pmset_line(line) else {
+            continue;
+        };
+
+        match event {
+            "Sleep" => {
+                // Keep the earliest start if several sleeps are logged before a wake
+                asleep_since.get_or_insert(timestamp);
+            }
+            "Wake" | "DarkWake" => {
+                if let Some(start) = asleep_since.take() {
+                    windows.push(SleepWindow {
+                        start,
+                        end: timestamp,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    windows
+}
+
+/// Split "2024-01-15 14:23:05 +0100 Sleep  ..." into its timestamp and event type
+fn parse_pmset_line(line: &str) -> Option<(DateTime<Utc>, &str)> {
+    // The timestamp is the first three whitespace-separated fields
+    let mut fields = line.split_whitespace();
+    let stamp = format!("{} {} {}", fields.next()?, fields.next()?, fields.next()?);
+    let timestamp = DateTime::parse_from_str(&stamp, "%Y-%m-%d %H:%M:%S %z").ok()?;
+    let event = fields.next()?;
+    Some((timestamp.with_timezone(&Utc), event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PMSET_LOG: &str = "\
+Time stamp                Domain              Message                                                                 Duration  Delay
+==========                ======              =======                                                                 ========  =====
+UUID: 4F1C2E6A-0B3D-4C55-9A2E-7D1F0E8B9C21
+2024-01-15 08:12:44 +0100 Assertions          \tPID 123(coreaudiod) Created PreventUserIdleSystemSleep \"com.apple.audio\"
+2024-01-15 12:30:01 +0100 Sleep               \tEntering Sleep state due to 'Clamshell Sleep':TCPKeepAlive=active Using Batt (Charge:80%) 2 secs
+2024-01-15 12:45:10 +0100 DarkWake            \tDarkWake from Deep Idle [CDNP] : due to EC.RTC/Maintenance Using BATT (Charge:79%) 15 secs
+2024-01-15 12:45:25 +0100 Sleep               \tEntering Sleep state due to 'Maintenance Sleep':TCPKeepAlive=active Using Batt (Charge:79%) 1 secs
+2024-01-15 13:05:42 +0100 Wake                \tWake from Deep Idle [CDNVA] : due to EC.LidOpen/Lid Open Using BATT (Charge:78%)
+2024-01-15 13:05:43 +0100 Kernel Client Acks  \tDelays to Sleep notifications: [IOUSBHostDevice is slow(300 ms)]
+";
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_pmset_log() {
+        let windows = parse_pmset_log(PMSET_LOG);
+        assert_eq!(
+            windows,
+            vec![
+                SleepWindow {
+                    start: at("2024-01-15T11:30:01Z"),
+                    end: at("2024-01-15T11:45:10Z"),
+                },
+                SleepWindow {
+                    start: at("2024-01-15T11:45:25Z"),
+                    end: at("2024-01-15T12:05:42Z"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pmset_log_ignores_unmatched_sleep() {
+        let log = "2024-01-15 12:30:01 +0100 Sleep               \tEntering Sleep state";
+        assert!(parse_pmset_log(log).is_empty());
+        assert!(parse_pmset_log("garbage\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_mostly_asleep_includes_wake_grace() {
+        let windows = [SleepWindow {
+            start: at("2024-01-15T11:30:00Z"),
+            end: at("2024-01-15T11:45:00Z"),
+        }];
+        let asleep = |start: &str, end: &str| mostly_asleep(&windows, at(start), at(end));
+
+        // Outage spanning the sleep
+        assert!(asleep("2024-01-15T11:29:50Z", "2024-01-15T11:45:20Z"));
+        // Outage starting while reconnecting after wake
+        assert!(asleep("2024-01-15T11:45:20Z", "2024-01-15T11:45:40Z"));
+        // Outage well after wake
+        assert!(!asleep("2024-01-15T11:50:00Z", "2024-01-15T11:51:00Z"));
+        // Outage that ended before sleep
+        assert!(!asleep("2024-01-15T11:00:00Z", "2024-01-15T11:10:00Z"));
+        // A two-hour outage that only touched the sleep
+        assert!(!asleep("2024-01-15T09:40:00Z", "2024-01-15T11:40:00Z"));
+        assert_eq!(
+            windows[0].overlap(at("2024-01-15T09:40:00Z"), at("2024-01-15T11:40:00Z")),
+            Duration::minutes(10)
+        );
+    }
+}
@@ -1,12 +1,31 @@
 use crate::config::MonitorConfig;
-use crate::models::{ConnectivityState, Outage, PingResult, Target};
-use std::collections::HashMap;
+use crate::models::{
+    mean_abs_diff, AddressFamily, ConnectivityState, DegradedEvent, Outage, PingErrorKind,
+    PingResult, Target,
+};
+use crate::monitor::live::Streak;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+
+/// Replies in a row that `monitor.jitter_threshold_ms` is measured over
+const JITTER_WINDOW: usize = 10;
 
 /// Event emitted when state changes
 #[derive(Debug, Clone)]
 pub enum StateEvent {
     /// Entered DEGRADED state - some targets failing
     Degraded { failing_targets: Vec<String> },
+    /// Offline threshold reached but canaries are still reachable,
+    /// so the problem is specific to the configured targets
+    TargetSpecific { failing_targets: Vec<String> },
+    /// Offline threshold reached but only one address family is down
+    /// (e.g. IPv6 lost while IPv4 works), so no outage is declared
+    FamilyLost {
+        family: AddressFamily,
+        failing_targets: Vec<String>,
+    },
+    /// Left DEGRADED for ONLINE without an outage (a near miss)
+    DegradedRecovered { degraded: DegradedEvent },
     /// Entered OFFLINE state - outage started
     Offline { outage: Outage },
     /// Recovered to ONLINE state - outage ended
@@ -22,6 +41,13 @@ pub struct TargetState {
     pub last_result: Option<PingResult>,
     pub consecutive_failures: u32,
     pub consecutive_successes: u32,
+    /// Escalated by the target's own thresholds (one failure / one success by default)
+    failing: bool,
+    /// Round trips of the current run of replies, up to `JITTER_WINDOW`
+    latencies: VecDeque<f64>,
+    /// Failed long enough while others worked to be left out of the
+    /// aggregate (`monitor.quarantine_after`) until it replies again
+    quarantined: bool,
 }
 
 impl TargetState {
@@ -31,6 +57,9 @@ impl TargetState {
             last_result: None,
             consecutive_failures: 0,
             consecutive_successes: 0,
+            failing: false,
+            latencies: VecDeque::with_capacity(JITTER_WINDOW),
+            quarantined: false,
         }
     }
 
@@ -39,16 +68,100 @@ impl TargetState {
         if result.success {
             self.consecutive_failures = 0;
             self.consecutive_successes += 1;
+            if self.consecutive_successes >= self.target.recovery_threshold.unwrap_or(1) {
+                self.failing = false;
+            }
+            if let Some(latency) = result.latency_ms {
+                if self.latencies.len() == JITTER_WINDOW {
+                    self.latencies.pop_front();
+                }
+                self.latencies.push_back(latency);
+            }
         } else {
+            self.latencies.clear();
             self.consecutive_successes = 0;
             self.consecutive_failures += 1;
+            if self.consecutive_failures >= self.target.degraded_threshold.unwrap_or(1) {
+                self.failing = true;
+            }
         }
         self.last_result = Some(result.clone());
     }
 
-    /// Check if this target is currently failing
+    /// Forget every result, as if the target had just been added
+    pub fn reset(&mut self) {
+        self.last_result = None;
+        self.consecutive_failures = 0;
+        self.consecutive_successes = 0;
+        self.failing = false;
+        self.latencies.clear();
+        self.quarantined = false;
+    }
+
+    /// Left out of the aggregate state until it replies again
+    pub fn is_quarantined(&self) -> bool {
+        self.quarantined
+    }
+
+    /// Jitter over the last `JITTER_WINDOW` replies, once that many have
+    /// arrived in a row
+    pub fn sustained_jitter_ms(&self) -> Option<f64> {
+        if self.latencies.len() < JITTER_WINDOW {
+            return None;
+        }
+        let latencies: Vec<f64> = self.latencies.iter().copied().collect();
+        mean_abs_diff(&latencies)
+    }
+
+    /// Check if this target is currently failing, per its own thresholds
     pub fn is_failing(&self) -> bool {
-        self.consecutive_failures > 0
+        self.failing
+    }
+
+    /// The current run of successes or failures
+    pub fn streak(&self) -> Streak {
+        if self.consecutive_failures > 0 {
+            Streak::Failures(self.consecutive_failures)
+        } else if self.consecutive_successes > 0 {
+            Streak::Successes(self.consecutive_successes)
+        } else {
+            Streak::None
+        }
+    }
+}
+
+/// Pass/fail of the last `size` samples (`monitor.window_samples`)
+#[derive(Debug, Clone)]
+struct FailureWindow {
+    samples: VecDeque<bool>,
+    size: usize,
+    failures: u32,
+}
+
+impl FailureWindow {
+    fn new(size: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(size),
+            size,
+            failures: 0,
+        }
+    }
+
+    fn push(&mut self, failed: bool) {
+        if self.samples.len() == self.size && self.samples.pop_front() == Some(true) {
+            self.failures -= 1;
+        }
+        self.samples.push_back(failed);
+        self.failures += failed as u32;
+    }
+
+    fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    fn clear(&mut self) {
+        self.samples.clear();
+        self.failures = 0;
     }
 }
 
@@ -57,11 +170,24 @@ pub struct ConnectivityTracker {
     state: ConnectivityState,
     config: MonitorConfig,
     target_states: HashMap<String, TargetState>,
+    canary_states: HashMap<String, TargetState>,
     current_outage: Option<Outage>,
+    /// The DEGRADED period in progress, or the one that just escalated to OFFLINE
+    current_degraded: Option<DegradedEvent>,
+    /// `TargetSpecific` was already sent during this DEGRADED period
+    target_specific_reported: bool,
+    /// `FamilyLost` was already sent during this DEGRADED period
+    family_lost_reported: bool,
+    /// When the recovery threshold was met while OFFLINE, during the grace period
+    recovery_pending_since: Option<DateTime<Utc>>,
+    /// When DEGRADED or OFFLINE was last entered, for `min_state_duration_secs`
+    state_entered_at: Option<DateTime<Utc>>,
 
     // Aggregate counters for state transitions
     aggregate_failures: u32,
     aggregate_successes: u32,
+    /// Replaces `aggregate_failures` for the thresholds when configured
+    window: Option<FailureWindow>,
 }
 
 impl ConnectivityTracker {
@@ -69,36 +195,130 @@ impl ConnectivityTracker {
     pub fn new(config: &MonitorConfig, targets: &[Target]) -> Self {
         let target_states = targets
             .iter()
-            .map(|t| (t.ip.clone(), TargetState::new(t.clone())))
+            .map(|t| (t.key(), TargetState::new(t.clone())))
+            .collect();
+
+        let canary_states = config
+            .canary_targets
+            .iter()
+            .filter(|t| !targets.iter().any(|m| m.key() == t.key()))
+            .map(|t| (t.key(), TargetState::new(t.clone())))
             .collect();
 
         Self {
-            state: ConnectivityState::Online,
+            state: ConnectivityState::Unknown,
             config: config.clone(),
             target_states,
+            canary_states,
             current_outage: None,
+            current_degraded: None,
+            target_specific_reported: false,
+            family_lost_reported: false,
+            recovery_pending_since: None,
+            state_entered_at: None,
             aggregate_failures: 0,
             aggregate_successes: 0,
+            window: config
+                .window_samples
+                .map(|n| FailureWindow::new(n.max(config.offline_threshold) as usize)),
+        }
+    }
+
+    /// Start tracking `target` from scratch. A target already tracked only has
+    /// its settings replaced and keeps its streaks. Returns true if it was new.
+    pub fn add_target(&mut self, target: Target) -> bool {
+        let added = self.insert_target(target);
+        self.recompute_aggregates();
+        added
+    }
+
+    /// Stop tracking the target with `key` (see `Target::key`). An outage in
+    /// progress still lists it as affected.
+    pub fn remove_target(&mut self, key: &str) -> Option<TargetState> {
+        let removed = self.target_states.remove(key);
+        if removed.is_some() {
+            self.recompute_aggregates();
+        }
+        removed
+    }
+
+    /// Switch to a new target list (e.g. a reloaded config): targets in both
+    /// keep their state, new ones start fresh, missing ones are dropped
+    pub fn set_targets(&mut self, targets: &[Target]) {
+        self.target_states
+            .retain(|key, _| targets.iter().any(|t| &t.key() == key));
+        for target in targets {
+            self.insert_target(target.clone());
+        }
+        self.recompute_aggregates();
+    }
+
+    /// Forget every target's results and the aggregate counters. The state and
+    /// any outage in progress are kept, to be re-evaluated from new results.
+    pub fn reset(&mut self) {
+        for state in self.target_states.values_mut() {
+            state.reset();
+        }
+        self.aggregate_successes = 0;
+        self.recovery_pending_since = None;
+        self.reset_failures();
+    }
+
+    fn insert_target(&mut self, target: Target) -> bool {
+        let key = target.key();
+        match self.target_states.get_mut(&key) {
+            Some(state) => {
+                state.target = target;
+                false
+            }
+            None => {
+                // A canary promoted to a monitored target drives state from now on
+                self.canary_states.remove(&key);
+                self.target_states.insert(key, TargetState::new(target));
+                true
+            }
+        }
+    }
+
+    /// Keep the aggregate counters true to the current targets: a run of
+    /// failures ends once too few remaining targets are failing to sustain it
+    fn recompute_aggregates(&mut self) {
+        let failing = self.active_targets().filter(|t| t.is_failing()).count();
+        if failing < self.config.quorum.required(self.active_targets().count()) {
+            self.reset_failures();
+        } else {
+            self.aggregate_successes = 0;
         }
     }
 
     /// Process a ping result, returns any state change event
     pub fn process(&mut self, result: &PingResult) -> StateEvent {
+        if self.config.ignore_dns_failures && result.error_kind == Some(PingErrorKind::DnsFailure) {
+            tracing::debug!("Ignoring DNS failure for {}", result.target);
+            return StateEvent::NoChange;
+        }
+
         // Update target-specific state
         if let Some(target_state) = self.target_states.get_mut(&result.target) {
             target_state.update(result);
+        } else if let Some(canary_state) = self.canary_states.get_mut(&result.target) {
+            // Canaries only gate the offline transition, they never drive state
+            canary_state.update(result);
+            return StateEvent::NoChange;
         }
+        self.update_quarantine(&result.target);
 
         // Count currently failing targets
         let failing_targets: Vec<String> = self
-            .target_states
-            .values()
+            .active_targets()
             .filter(|t| t.is_failing())
-            .map(|t| t.target.ip.clone())
+            .map(|t| t.target.key())
             .collect();
+        let active = self.active_targets().count();
 
-        let any_failing = !failing_targets.is_empty();
-        let all_healthy = failing_targets.is_empty();
+        // Below the quorum, the failing targets are treated as noise
+        let any_failing = failing_targets.len() >= self.config.quorum.required(active);
+        let all_healthy = !any_failing;
 
         // Update aggregate counters
         if any_failing {
@@ -108,46 +328,176 @@ impl ConnectivityTracker {
             self.aggregate_failures = 0;
             self.aggregate_successes += 1;
         }
+        if let Some(window) = self.window.as_mut() {
+            window.push(any_failing);
+        }
+        // Thresholds are only checked on a failing sample, in either mode
+        let failures = match &self.window {
+            Some(window) if any_failing => window.failures(),
+            _ => self.aggregate_failures,
+        };
+
+        // Leave the startup state once every target has reported
+        if self.state == ConnectivityState::Unknown && self.has_data_for_all_targets() {
+            self.state = ConnectivityState::Online;
+            tracing::info!("State: UNKNOWN -> ONLINE (all targets reporting)");
+        }
+
+        // A single flaky target can be configured not to degrade on its own
+        let min_failing = self.config.degraded_min_targets.clamp(1, active.max(1));
+        // ...or not to take the whole connection OFFLINE
+        let min_offline = self.config.offline_min_targets.clamp(1, active.max(1));
 
         // State machine transitions
         match self.state {
-            ConnectivityState::Online => {
-                if self.aggregate_failures >= self.config.degraded_threshold {
+            ConnectivityState::Unknown | ConnectivityState::Online => {
+                if failures >= self.config.degraded_threshold
+                    && failing_targets.len() >= min_failing
+                {
+                    let previous = self.state;
                     self.state = ConnectivityState::Degraded;
+                    self.state_entered_at = Some(result.timestamp);
+                    self.current_degraded = Some(DegradedEvent::new(failing_targets.clone()));
                     tracing::warn!(
-                        "State: ONLINE -> DEGRADED ({} consecutive failures)",
-                        self.aggregate_failures
+                        "State: {} -> DEGRADED ({})",
+                        previous,
+                        self.describe_failures(failures)
                     );
                     return StateEvent::Degraded {
                         failing_targets: failing_targets.clone(),
                     };
                 }
+
+                // Replies still arrive, but the link is getting unstable
+                let jittery = self.jittery_targets();
+                if self.state == ConnectivityState::Online && !jittery.is_empty() {
+                    self.state = ConnectivityState::Degraded;
+                    self.state_entered_at = Some(result.timestamp);
+                    self.current_degraded = Some(DegradedEvent::new(jittery.clone()));
+                    tracing::warn!(
+                        "State: ONLINE -> DEGRADED (link degrading, jitter above {}ms: {})",
+                        self.config.jitter_threshold_ms.unwrap_or_default(),
+                        jittery.join(", ")
+                    );
+                    return StateEvent::Degraded {
+                        failing_targets: jittery,
+                    };
+                }
             }
             ConnectivityState::Degraded => {
-                if all_healthy && self.aggregate_successes >= self.config.recovery_threshold {
+                if all_healthy
+                    && self.aggregate_successes >= self.config.recovery_threshold
+                    && !self.held_down(result.timestamp)
+                    && self.jittery_targets().is_empty()
+                {
                     self.state = ConnectivityState::Online;
-                    self.aggregate_failures = 0;
+                    self.reset_failures();
+                    self.target_specific_reported = false;
+                    self.family_lost_reported = false;
                     tracing::info!(
                         "State: DEGRADED -> ONLINE ({} consecutive successes)",
                         self.aggregate_successes
                     );
-                    return StateEvent::NoChange; // No outage to report
+                    return match self.current_degraded.take() {
+                        Some(mut degraded) => {
+                            degraded.end_at(Utc::now());
+                            StateEvent::DegradedRecovered { degraded }
+                        }
+                        None => StateEvent::NoChange,
+                    };
                 }
-                if self.aggregate_failures >= self.config.offline_threshold {
+                if failures >= self.config.offline_threshold {
+                    if let Some(family) = self.family_specific_loss() {
+                        if !self.family_lost_reported {
+                            self.family_lost_reported = true;
+                            tracing::warn!(
+                                "{} connectivity lost, {} still working: {}",
+                                family,
+                                family.other(),
+                                failing_targets.join(", ")
+                            );
+                            return StateEvent::FamilyLost {
+                                family,
+                                failing_targets,
+                            };
+                        }
+                        return StateEvent::NoChange;
+                    }
+                }
+
+                let too_few_failing = failing_targets.len() < min_offline;
+                if failures >= self.config.offline_threshold
+                    && (too_few_failing || self.canaries_reachable())
+                {
+                    if !self.target_specific_reported {
+                        self.target_specific_reported = true;
+                        if too_few_failing {
+                            tracing::warn!(
+                                "Offline threshold reached but only {} of {} required targets failing - target-specific issue: {}",
+                                failing_targets.len(),
+                                min_offline,
+                                failing_targets.join(", ")
+                            );
+                        } else {
+                            tracing::warn!(
+                                "Offline threshold reached but canaries are reachable - target-specific issue: {}",
+                                failing_targets.join(", ")
+                            );
+                        }
+                        return StateEvent::TargetSpecific { failing_targets };
+                    }
+                } else if failures >= self.config.offline_threshold {
+                    self.target_specific_reported = false;
+                    self.family_lost_reported = false;
                     self.state = ConnectivityState::Offline;
+                    self.state_entered_at = Some(result.timestamp);
                     let outage = self.start_outage(failing_targets.clone());
+                    // Kept until the outage has an ID to link it to (see `take_degraded`)
+                    if let Some(degraded) = self.current_degraded.as_mut() {
+                        degraded.end_at(outage.start_time);
+                    }
                     tracing::error!(
-                        "State: DEGRADED -> OFFLINE ({} consecutive failures) - Outage started",
-                        self.aggregate_failures
+                        "State: DEGRADED -> OFFLINE ({}) - Outage started",
+                        self.describe_failures(failures)
                     );
                     return StateEvent::Offline { outage };
                 }
             }
             ConnectivityState::Offline => {
-                if all_healthy && self.aggregate_successes >= self.config.recovery_threshold {
-                    if let Some(outage) = self.end_outage() {
+                if !all_healthy {
+                    // Record the full blast radius, not just what failed first
+                    if let Some(outage) = self.current_outage.as_mut() {
+                        for ip in &failing_targets {
+                            if !outage.affected_targets.contains(ip) {
+                                tracing::info!("{} started failing during the outage", ip);
+                                outage.affected_targets.push(ip.clone());
+                            }
+                        }
+                    }
+                    // A failure during the grace period keeps the outage open
+                    if self.recovery_pending_since.take().is_some() {
+                        tracing::info!("Recovery interrupted by failure - outage continues");
+                    }
+                } else if self.aggregate_successes >= self.config.recovery_threshold {
+                    let grace = Duration::seconds(self.config.recovery_grace_secs as i64);
+                    let recovered_at = *self.recovery_pending_since.get_or_insert(result.timestamp);
+                    if result.timestamp - recovered_at < grace || self.held_down(result.timestamp) {
+                        return StateEvent::NoChange;
+                    }
+
+                    self.recovery_pending_since = None;
+                    let ended = if grace.is_zero() {
+                        self.end_outage()
+                    } else {
+                        // The outage ended when connectivity returned, not when the grace ran out
+                        self.current_outage.take().map(|mut outage| {
+                            outage.end_at(recovered_at);
+                            outage
+                        })
+                    };
+                    if let Some(outage) = ended {
                         self.state = ConnectivityState::Online;
-                        self.aggregate_failures = 0;
+                        self.reset_failures();
                         tracing::info!(
                             "State: OFFLINE -> ONLINE ({} consecutive successes) - Outage ended, duration: {:.1}s",
                             self.aggregate_successes,
@@ -162,6 +512,118 @@ impl ConnectivityTracker {
         StateEvent::NoChange
     }
 
+    /// Still inside the minimum time in the current bad state
+    fn held_down(&self, now: DateTime<Utc>) -> bool {
+        let hold = Duration::seconds(self.config.min_state_duration_secs as i64);
+        self.state_entered_at
+            .is_some_and(|entered| now - entered < hold)
+    }
+
+    /// Targets whose sustained jitter is above `jitter_threshold_ms`, sorted
+    fn jittery_targets(&self) -> Vec<String> {
+        let Some(threshold) = self.config.jitter_threshold_ms else {
+            return Vec::new();
+        };
+        let mut jittery: Vec<String> = self
+            .active_targets()
+            .filter(|t| t.sustained_jitter_ms().is_some_and(|ms| ms > threshold))
+            .map(|t| t.target.key())
+            .collect();
+        jittery.sort();
+        jittery
+    }
+
+    /// Start counting failures afresh after a recovery
+    fn reset_failures(&mut self) {
+        self.aggregate_failures = 0;
+        if let Some(window) = self.window.as_mut() {
+            window.clear();
+        }
+    }
+
+    /// "5 consecutive failures" or "5 failures in the last 10 samples"
+    fn describe_failures(&self, failures: u32) -> String {
+        match &self.window {
+            Some(window) => format!("{} failures in the last {} samples", failures, window.size),
+            None => format!("{} consecutive failures", failures),
+        }
+    }
+
+    /// Targets that count towards the aggregate state (not quarantined)
+    fn active_targets(&self) -> impl Iterator<Item = &TargetState> {
+        self.target_states.values().filter(|t| !t.quarantined)
+    }
+
+    /// Quarantine a target after `quarantine_after` failures in a row while
+    /// another target still works (so a real outage never quarantines
+    /// anything); it rejoins on its next reply
+    fn update_quarantine(&mut self, key: &str) {
+        let Some(after) = self.config.quarantine_after else {
+            return;
+        };
+        let others_working = self
+            .active_targets()
+            .any(|t| t.target.key() != key && t.last_result.as_ref().is_some_and(|r| r.success));
+        let Some(state) = self.target_states.get_mut(key) else {
+            return;
+        };
+
+        if state.quarantined && state.consecutive_successes > 0 {
+            state.quarantined = false;
+            tracing::info!(
+                "{} ({}) replied again, no longer quarantined",
+                state.target.name,
+                key
+            );
+        } else if !state.quarantined && state.consecutive_failures >= after && others_working {
+            state.quarantined = true;
+            tracing::warn!(
+                "{} ({}) quarantined after {} failures in a row; it no longer affects the state until it replies",
+                state.target.name,
+                key,
+                state.consecutive_failures
+            );
+        }
+    }
+
+    /// Check whether every monitored target has at least one result
+    fn has_data_for_all_targets(&self) -> bool {
+        self.target_states.values().all(|t| t.last_result.is_some())
+    }
+
+    /// The address family that is entirely down while the other family works,
+    /// if targets of both families are monitored
+    fn family_specific_loss(&self) -> Option<AddressFamily> {
+        let family_state = |family: AddressFamily| {
+            let members: Vec<&TargetState> = self
+                .target_states
+                .values()
+                .filter(|t| !t.quarantined && AddressFamily::of(&t.target.ip) == Some(family))
+                .collect();
+            let all_failing = !members.is_empty() && members.iter().all(|t| t.is_failing());
+            let all_up = !members.is_empty()
+                && members
+                    .iter()
+                    .all(|t| t.last_result.as_ref().is_some_and(|r| r.success));
+            (all_failing, all_up)
+        };
+
+        let (v4_down, v4_up) = family_state(AddressFamily::V4);
+        let (v6_down, v6_up) = family_state(AddressFamily::V6);
+        match (v4_down && v6_up, v6_down && v4_up) {
+            (true, _) => Some(AddressFamily::V4),
+            (_, true) => Some(AddressFamily::V6),
+            _ => None,
+        }
+    }
+
+    /// Check whether any canary target answered its most recent ping
+    fn canaries_reachable(&self) -> bool {
+        self.canary_states
+            .values()
+            .any(|c| c.last_result.as_ref().is_some_and(|r| r.success))
+    }
+
     /// Start a new outage
     fn start_outage(&mut self, affected_targets: Vec<String>) -> Outage {
         let outage = Outage::new(affected_targets);
@@ -194,32 +656,64 @@ impl ConnectivityTracker {
         self.current_outage.as_mut()
     }
 
+    /// The DEGRADED period in progress (for recording its ID once stored)
+    pub fn current_degraded_mut(&mut self) -> Option<&mut DegradedEvent> {
+        self.current_degraded.as_mut()
+    }
+
+    /// Hand over the degraded period, e.g. once it escalated into an outage
+    pub fn take_degraded(&mut self) -> Option<DegradedEvent> {
+        self.current_degraded.take()
+    }
+
     /// Get all target states
     pub fn target_states(&self) -> &HashMap<String, TargetState> {
         &self.target_states
     }
 
-    /// Get failing targets
+    /// Get failing targets (quarantined ones aren't counted)
     pub fn failing_targets(&self) -> Vec<&TargetState> {
-        self.target_states
-            .values()
-            .filter(|t| t.is_failing())
-            .collect()
+        self.active_targets().filter(|t| t.is_failing()).collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
+    use crate::config::Quorum;
 
     fn make_config() -> MonitorConfig {
         MonitorConfig {
             ping_interval_ms: 1000,
+            interval_jitter_ms: 0,
             ping_timeout_ms: 2000,
             degraded_threshold: 3,
             offline_threshold: 5,
+            window_samples: None,
             recovery_threshold: 2,
+            recovery_grace_secs: 0,
+            min_state_duration_secs: 0,
+            quorum: Quorum::Any,
+            ignore_dns_failures: false,
+            jitter_threshold_ms: None,
+            quarantine_after: None,
+            dns_cache_ttl_secs: 300,
+            degraded_min_targets: 1,
+            offline_min_targets: 1,
+            canary_targets: vec![],
+            traceroute_enabled: true,
+            max_outage_traces: 2,
+            trace_target: None,
+            trace_interval_secs: None,
+            ongoing_outage_heartbeat_secs: None,
+            traceroute_match_prefix: 24,
+            ping_mode: Default::default(),
+            ping_backend: Default::default(),
+            ping_count: 1,
+            ping_retries: 0,
+            max_acceptable_loss: None,
+            metrics_addr: None,
+            log_all_pings: false,
         }
     }
 
@@ -237,7 +731,18 @@ mod tests {
             timestamp: Utc::now(),
             success: true,
             latency_ms: Some(10.0),
+            jitter_ms: None,
+            loss: None,
             error: None,
+            error_kind: None,
+            resolved_ip: None,
+        }
+    }
+
+    fn latency_ping(target: &str, latency_ms: f64) -> PingResult {
+        PingResult {
+            latency_ms: Some(latency_ms),
+            ..success_ping(target)
         }
     }
 
@@ -248,25 +753,119 @@ mod tests {
             timestamp: Utc::now(),
             success: false,
             latency_ms: None,
+            jitter_ms: None,
+            loss: None,
             error: Some("timeout".to_string()),
+            error_kind: None,
+            resolved_ip: None,
+        }
+    }
+
+    /// Give every target a first (successful) result so the tracker leaves UNKNOWN
+    fn prime(tracker: &mut ConnectivityTracker) {
+        for target in make_targets() {
+            tracker.process(&success_ping(&target.ip));
         }
     }
 
+    /// One target at 50% loss: fail, succeed, fail, succeed...
+    fn run_half_loss(config: &MonitorConfig, pings: usize) -> (ConnectivityTracker, bool) {
+        let targets = vec![Target::new("Google DNS", "8.8.8.8")];
+        let mut tracker = ConnectivityTracker::new(config, &targets);
+        tracker.process(&success_ping("8.8.8.8"));
+
+        let mut went_offline = false;
+        for i in 0..pings {
+            let ping = if i % 2 == 0 {
+                failure_ping("8.8.8.8")
+            } else {
+                success_ping("8.8.8.8")
+            };
+            went_offline |= matches!(tracker.process(&ping), StateEvent::Offline { .. });
+        }
+        (tracker, went_offline)
+    }
+
     #[test]
-    fn test_initial_state_online() {
+    fn test_half_loss_never_trips_consecutive_thresholds() {
+        let (tracker, went_offline) = run_half_loss(&make_config(), 40);
+        assert!(!went_offline);
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+    }
+
+    #[test]
+    fn test_half_loss_trips_window_detector() {
+        let config = MonitorConfig {
+            window_samples: Some(10),
+            ..make_config()
+        };
+        let (tracker, went_offline) = run_half_loss(&config, 40);
+        assert!(went_offline);
+        assert_eq!(tracker.state(), ConnectivityState::Offline);
+        // Alternating results never give the 2 successes in a row recovery needs
+        assert!(tracker.current_outage().is_some());
+    }
+
+    #[test]
+    fn test_failure_window_slides() {
+        let mut window = FailureWindow::new(4);
+        for failed in [true, true, false, true] {
+            window.push(failed);
+        }
+        assert_eq!(window.failures(), 3);
+        window.push(false);
+        window.push(false);
+        assert_eq!(window.failures(), 1);
+        window.clear();
+        assert_eq!(window.failures(), 0);
+    }
+
+    #[test]
+    fn test_initial_state_unknown() {
         let config = make_config();
         let targets = make_targets();
         let tracker = ConnectivityTracker::new(&config, &targets);
 
-        assert_eq!(tracker.state(), ConnectivityState::Online);
+        assert_eq!(tracker.state(), ConnectivityState::Unknown);
         assert!(tracker.current_outage().is_none());
     }
 
+    #[test]
+    fn test_unknown_until_all_targets_report() {
+        let config = make_config();
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+
+        tracker.process(&success_ping("8.8.8.8"));
+        assert_eq!(tracker.state(), ConnectivityState::Unknown);
+
+        tracker.process(&success_ping("1.1.1.1"));
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+    }
+
+    #[test]
+    fn test_unknown_escalates_like_online() {
+        let config = make_config();
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+
+        // Failures before every target has reported still degrade on schedule
+        for _ in 0..2 {
+            tracker.process(&failure_ping("8.8.8.8"));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Unknown);
+
+        let event = tracker.process(&failure_ping("8.8.8.8"));
+        assert!(matches!(event, StateEvent::Degraded { .. }));
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+    }
+
     #[test]
     fn test_online_to_degraded() {
         let config = make_config();
         let targets = make_targets();
         let mut tracker = ConnectivityTracker::new(&config, &targets);
+        prime(&mut tracker);
 
         // Send failures until degraded threshold (3)
         for i in 0..3 {
@@ -338,6 +937,131 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_affected_targets_accumulate_during_outage() {
+        let config = make_config();
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+
+        for _ in 0..5 {
+            tracker.process(&failure_ping("8.8.8.8"));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Offline);
+        assert_eq!(
+            tracker.current_outage().unwrap().affected_targets,
+            vec!["8.8.8.8"]
+        );
+
+        // A second target goes down mid-outage and joins the affected set
+        tracker.process(&failure_ping("1.1.1.1"));
+        tracker.process(&failure_ping("1.1.1.1"));
+        tracker.process(&failure_ping("8.8.8.8"));
+
+        tracker.process(&success_ping("8.8.8.8"));
+        tracker.process(&success_ping("1.1.1.1"));
+        match tracker.process(&success_ping("8.8.8.8")) {
+            StateEvent::Recovered { outage } => {
+                assert_eq!(outage.affected_targets, vec!["8.8.8.8", "1.1.1.1"]);
+            }
+            other => panic!("expected Recovered, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_failure_during_recovery_grace_keeps_outage_open() {
+        let config = MonitorConfig {
+            recovery_grace_secs: 10,
+            ..make_config()
+        };
+        let targets = vec![Target::new("Google DNS", "8.8.8.8")];
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        let t0 = Utc::now();
+        let at = |mut ping: PingResult, secs: i64| {
+            ping.timestamp = t0 + Duration::seconds(secs);
+            ping
+        };
+
+        for _ in 0..5 {
+            tracker.process(&at(failure_ping("8.8.8.8"), 0));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Offline);
+
+        // Threshold met at t0+2, but the grace period has not passed
+        tracker.process(&at(success_ping("8.8.8.8"), 1));
+        tracker.process(&at(success_ping("8.8.8.8"), 2));
+        let event = tracker.process(&at(success_ping("8.8.8.8"), 5));
+        assert!(matches!(event, StateEvent::NoChange));
+        assert_eq!(tracker.state(), ConnectivityState::Offline);
+
+        // The link drops again: same outage, no recovery
+        let event = tracker.process(&at(failure_ping("8.8.8.8"), 6));
+        assert!(matches!(event, StateEvent::NoChange));
+        assert_eq!(tracker.state(), ConnectivityState::Offline);
+        assert!(tracker.current_outage().is_some());
+
+        // A full grace period of success closes it, ending when connectivity returned
+        tracker.process(&at(success_ping("8.8.8.8"), 7));
+        tracker.process(&at(success_ping("8.8.8.8"), 8));
+        assert!(matches!(
+            tracker.process(&at(success_ping("8.8.8.8"), 17)),
+            StateEvent::NoChange
+        ));
+        match tracker.process(&at(success_ping("8.8.8.8"), 18)) {
+            StateEvent::Recovered { outage } => {
+                assert_eq!(outage.end_time, Some(t0 + Duration::seconds(8)));
+            }
+            other => panic!("expected Recovered, got {:?}", other),
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+    }
+
+    #[test]
+    fn test_hold_down_delays_recovery() {
+        let config = MonitorConfig {
+            min_state_duration_secs: 30,
+            ..make_config()
+        };
+        let targets = vec![Target::new("Google DNS", "8.8.8.8")];
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        let t0 = Utc::now();
+        let at = |mut ping: PingResult, secs: i64| {
+            ping.timestamp = t0 + Duration::seconds(secs);
+            ping
+        };
+
+        // DEGRADED at t0+3: recovery pings right after are held
+        for secs in 1..=3 {
+            tracker.process(&at(failure_ping("8.8.8.8"), secs));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+        for secs in 4..=10 {
+            let event = tracker.process(&at(success_ping("8.8.8.8"), secs));
+            assert!(matches!(event, StateEvent::NoChange));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+        assert!(matches!(
+            tracker.process(&at(success_ping("8.8.8.8"), 33)),
+            StateEvent::DegradedRecovered { .. }
+        ));
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+
+        // OFFLINE at t0+44: the hold-down restarts and keeps the outage open
+        for secs in 40..=44 {
+            tracker.process(&at(failure_ping("8.8.8.8"), secs));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Offline);
+        for secs in 45..=60 {
+            let event = tracker.process(&at(success_ping("8.8.8.8"), secs));
+            assert!(matches!(event, StateEvent::NoChange));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Offline);
+        assert!(matches!(
+            tracker.process(&at(success_ping("8.8.8.8"), 74)),
+            StateEvent::Recovered { .. }
+        ));
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+    }
+
     #[test]
     fn test_degraded_recovery_without_outage() {
         let config = make_config();
@@ -360,11 +1084,54 @@ mod tests {
         assert!(tracker.current_outage().is_none());
     }
 
+    #[test]
+    fn test_degraded_event_recorded_and_finalized() {
+        let config = make_config();
+        let mut tracker = ConnectivityTracker::new(&config, &make_targets());
+        prime(&mut tracker);
+
+        for _ in 0..3 {
+            tracker.process(&failure_ping("8.8.8.8"));
+        }
+        let open = tracker.current_degraded_mut().unwrap();
+        assert_eq!(open.failing_targets, vec!["8.8.8.8".to_string()]);
+        assert!(open.end_time.is_none());
+
+        tracker.process(&success_ping("8.8.8.8"));
+        match tracker.process(&success_ping("8.8.8.8")) {
+            StateEvent::DegradedRecovered { degraded } => {
+                assert!(degraded.is_near_miss());
+                assert!(degraded.duration_secs.is_some());
+            }
+            other => panic!("expected DegradedRecovered, got {:?}", other),
+        }
+        assert!(tracker.take_degraded().is_none());
+    }
+
+    #[test]
+    fn test_degraded_event_escalates_with_outage() {
+        let config = make_config();
+        let mut tracker = ConnectivityTracker::new(&config, &make_targets());
+        prime(&mut tracker);
+
+        for _ in 0..5 {
+            tracker.process(&failure_ping("8.8.8.8"));
+        }
+        let outage = tracker.current_outage().unwrap().clone();
+        let mut degraded = tracker.take_degraded().unwrap();
+        assert_eq!(degraded.end_time, Some(outage.start_time));
+
+        degraded.escalate(42);
+        assert_eq!(degraded.outage_id, Some(42));
+        assert!(!degraded.is_near_miss());
+    }
+
     #[test]
     fn test_single_failure_no_state_change() {
         let config = make_config();
         let targets = make_targets();
         let mut tracker = ConnectivityTracker::new(&config, &targets);
+        prime(&mut tracker);
 
         // Single failure should not change state
         let event = tracker.process(&failure_ping("8.8.8.8"));
@@ -399,6 +1166,488 @@ mod tests {
         assert_eq!(tracker.state(), ConnectivityState::Degraded);
     }
 
+    #[test]
+    fn test_degraded_min_targets_ignores_single_flaky_target() {
+        let config = MonitorConfig {
+            degraded_min_targets: 2,
+            ..make_config()
+        };
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        prime(&mut tracker);
+
+        // One target failing well past every threshold never degrades
+        for _ in 0..20 {
+            let event = tracker.process(&failure_ping("8.8.8.8"));
+            assert!(matches!(event, StateEvent::NoChange));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+    }
+
+    #[test]
+    fn test_degraded_min_targets_two_targets_degrade() {
+        let config = MonitorConfig {
+            degraded_min_targets: 2,
+            ..make_config()
+        };
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        prime(&mut tracker);
+
+        tracker.process(&failure_ping("8.8.8.8"));
+        tracker.process(&failure_ping("8.8.8.8"));
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+
+        // The second target failing alongside the first crosses both thresholds
+        let event = tracker.process(&failure_ping("1.1.1.1"));
+        match event {
+            StateEvent::Degraded { failing_targets } => assert_eq!(failing_targets.len(), 2),
+            other => panic!("expected Degraded, got {:?}", other),
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+    }
+
+    #[test]
+    fn test_quorum_two_ignores_one_failing_target() {
+        let config = MonitorConfig {
+            quorum: Quorum::Count(2),
+            ..make_config()
+        };
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        prime(&mut tracker);
+
+        // One target down: below quorum, the counters never advance
+        for _ in 0..20 {
+            let event = tracker.process(&failure_ping("8.8.8.8"));
+            assert!(matches!(event, StateEvent::NoChange));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+
+        // A second target joins: each sample now counts toward DEGRADED
+        tracker.process(&failure_ping("1.1.1.1"));
+        tracker.process(&failure_ping("8.8.8.8"));
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+        match tracker.process(&failure_ping("1.1.1.1")) {
+            StateEvent::Degraded { failing_targets } => assert_eq!(failing_targets.len(), 2),
+            other => panic!("expected Degraded, got {:?}", other),
+        }
+
+        // Back below quorum counts as healthy, so it recovers with one still failing
+        tracker.process(&success_ping("1.1.1.1"));
+        tracker.process(&failure_ping("8.8.8.8"));
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+    }
+
+    #[test]
+    fn test_added_target_starts_fresh_while_others_keep_streaks() {
+        let mut tracker = ConnectivityTracker::new(&make_config(), &make_targets());
+        for _ in 0..4 {
+            prime(&mut tracker);
+        }
+
+        assert!(tracker.add_target(Target::new("Quad9", "9.9.9.9")));
+        let states = tracker.target_states();
+        assert_eq!(states.len(), 3);
+        assert_eq!(states["8.8.8.8"].streak(), Streak::Successes(4));
+        assert_eq!(states["1.1.1.1"].streak(), Streak::Successes(4));
+        assert_eq!(states["9.9.9.9"].streak(), Streak::None);
+        assert!(states["9.9.9.9"].last_result.is_none());
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+
+        // Re-adding a tracked target only updates its settings
+        assert!(!tracker.add_target(Target {
+            degraded_threshold: Some(5),
+            ..Target::new("Google DNS", "8.8.8.8")
+        }));
+        assert_eq!(
+            tracker.target_states()["8.8.8.8"].streak(),
+            Streak::Successes(4)
+        );
+
+        tracker.set_targets(&[
+            Target::new("Google DNS", "8.8.8.8"),
+            Target::new("Quad9", "9.9.9.9"),
+        ]);
+        assert!(!tracker.target_states().contains_key("1.1.1.1"));
+        assert_eq!(
+            tracker.target_states()["8.8.8.8"].streak(),
+            Streak::Successes(4)
+        );
+    }
+
+    #[test]
+    fn test_same_address_over_two_uplinks_tracked_apart() {
+        let primary = Target::new("Google DNS", "8.8.8.8");
+        let backup = Target {
+            source: Some("en1".to_string()),
+            ..Target::new("Google DNS via LTE", "8.8.8.8")
+        };
+        let mut tracker = ConnectivityTracker::new(&make_config(), &[primary, backup]);
+        assert_eq!(tracker.target_states().len(), 2);
+
+        tracker.process(&success_ping("8.8.8.8"));
+        tracker.process(&failure_ping("8.8.8.8@en1"));
+        let states = tracker.target_states();
+        assert_eq!(states["8.8.8.8"].streak(), Streak::Successes(1));
+        assert_eq!(states["8.8.8.8@en1"].streak(), Streak::Failures(1));
+        let failing: Vec<String> = tracker
+            .failing_targets()
+            .iter()
+            .map(|t| t.target.key())
+            .collect();
+        assert_eq!(failing, vec!["8.8.8.8@en1"]);
+    }
+
+    #[test]
+    fn test_removing_failing_target_ends_failure_run() {
+        let mut tracker = ConnectivityTracker::new(&make_config(), &make_targets());
+        prime(&mut tracker);
+
+        tracker.process(&failure_ping("1.1.1.1"));
+        tracker.process(&failure_ping("1.1.1.1"));
+        assert!(tracker.remove_target("1.1.1.1").is_some());
+        assert!(tracker.remove_target("1.1.1.1").is_none());
+
+        // The removed target's failures don't carry over into the threshold
+        let event = tracker.process(&failure_ping("8.8.8.8"));
+        assert!(matches!(event, StateEvent::NoChange));
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+
+        tracker.reset();
+        assert!(tracker
+            .target_states()
+            .values()
+            .all(|t| t.streak() == Streak::None && !t.is_failing()));
+    }
+
+    #[test]
+    fn test_removing_failing_target_clears_failure_window() {
+        let config = MonitorConfig {
+            window_samples: Some(10),
+            ..make_config()
+        };
+        let mut tracker = ConnectivityTracker::new(&config, &make_targets());
+        prime(&mut tracker);
+
+        tracker.process(&failure_ping("1.1.1.1"));
+        tracker.process(&failure_ping("1.1.1.1"));
+        tracker.remove_target("1.1.1.1");
+
+        // The window's failing samples went with the target
+        let event = tracker.process(&failure_ping("8.8.8.8"));
+        assert!(matches!(event, StateEvent::NoChange));
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+    }
+
+    #[test]
+    fn test_ignore_dns_failures() {
+        let dns_failure = || PingResult {
+            error: Some("DNS resolution failed".to_string()),
+            error_kind: Some(PingErrorKind::DnsFailure),
+            ..failure_ping("8.8.8.8")
+        };
+
+        let config = MonitorConfig {
+            ignore_dns_failures: true,
+            ..make_config()
+        };
+        let mut tracker = ConnectivityTracker::new(&config, &make_targets());
+        prime(&mut tracker);
+        for _ in 0..10 {
+            tracker.process(&dns_failure());
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+
+        // Without the option a resolution failure still counts
+        let mut tracker = ConnectivityTracker::new(&make_config(), &make_targets());
+        prime(&mut tracker);
+        for _ in 0..3 {
+            tracker.process(&dns_failure());
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+    }
+
+    #[test]
+    fn test_per_target_thresholds_escalate_independently() {
+        let config = make_config();
+        let targets = vec![
+            Target {
+                degraded_threshold: Some(10),
+                ..Target::new("Gateway", "192.168.1.1")
+            },
+            Target::new("Google DNS", "8.8.8.8"),
+        ];
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        tracker.process(&success_ping("192.168.1.1"));
+        tracker.process(&success_ping("8.8.8.8"));
+
+        // The tolerant gateway keeps failing without counting as failing
+        for _ in 0..6 {
+            let event = tracker.process(&failure_ping("192.168.1.1"));
+            assert!(matches!(event, StateEvent::NoChange));
+            tracker.process(&success_ping("8.8.8.8"));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+        assert!(tracker.failing_targets().is_empty());
+
+        // The sensitive target flips straight away and degrades on its own
+        tracker.process(&failure_ping("8.8.8.8"));
+        tracker.process(&failure_ping("192.168.1.1"));
+        let event = tracker.process(&failure_ping("8.8.8.8"));
+        match event {
+            StateEvent::Degraded { failing_targets } => {
+                assert_eq!(failing_targets, vec!["8.8.8.8".to_string()])
+            }
+            other => panic!("expected Degraded, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_per_target_recovery_threshold() {
+        let mut state = TargetState::new(Target {
+            recovery_threshold: Some(3),
+            ..Target::new("Gateway", "192.168.1.1")
+        });
+        state.update(&failure_ping("192.168.1.1"));
+        assert!(state.is_failing());
+
+        state.update(&success_ping("192.168.1.1"));
+        state.update(&success_ping("192.168.1.1"));
+        assert!(state.is_failing());
+        state.update(&success_ping("192.168.1.1"));
+        assert!(!state.is_failing());
+    }
+
+    #[test]
+    fn test_offline_min_targets_single_dead_host_stays_degraded() {
+        let config = MonitorConfig {
+            offline_min_targets: 2,
+            ..make_config()
+        };
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        prime(&mut tracker);
+
+        let events: Vec<StateEvent> = (0..20)
+            .map(|_| tracker.process(&failure_ping("8.8.8.8")))
+            .collect();
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+        assert!(tracker.current_outage().is_none());
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| matches!(e, StateEvent::TargetSpecific { .. }))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_offline_min_targets_two_failing_go_offline() {
+        let config = MonitorConfig {
+            offline_min_targets: 2,
+            ..make_config()
+        };
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        prime(&mut tracker);
+
+        let offline = (0..5).any(|_| {
+            ["8.8.8.8", "1.1.1.1"].iter().any(|ip| {
+                matches!(
+                    tracker.process(&failure_ping(ip)),
+                    StateEvent::Offline { .. }
+                )
+            })
+        });
+        assert!(offline);
+        assert_eq!(tracker.state(), ConnectivityState::Offline);
+        assert_eq!(tracker.current_outage().unwrap().affected_targets.len(), 2);
+    }
+
+    #[test]
+    fn test_degraded_min_targets_capped_at_target_count() {
+        let config = MonitorConfig {
+            degraded_min_targets: 5,
+            ..make_config()
+        };
+        let targets = vec![Target::new("Google DNS", "8.8.8.8")];
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+
+        for _ in 0..3 {
+            tracker.process(&failure_ping("8.8.8.8"));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+    }
+
+    #[test]
+    fn test_ipv6_only_loss_is_not_an_outage() {
+        let config = make_config();
+        let targets = vec![
+            Target::new("Google DNS", "8.8.8.8"),
+            Target::new("Google DNS v6", "2001:4860:4860::8888"),
+            Target::new("Cloudflare v6", "2606:4700:4700::1111"),
+        ];
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+
+        let mut events = Vec::new();
+        for _ in 0..10 {
+            tracker.process(&success_ping("8.8.8.8"));
+            events.push(tracker.process(&failure_ping("2001:4860:4860::8888")));
+            events.push(tracker.process(&failure_ping("2606:4700:4700::1111")));
+        }
+
+        let lost: Vec<&StateEvent> = events
+            .iter()
+            .filter(|e| matches!(e, StateEvent::FamilyLost { .. }))
+            .collect();
+        assert_eq!(lost.len(), 1, "reported once");
+        match lost[0] {
+            StateEvent::FamilyLost {
+                family,
+                failing_targets,
+            } => {
+                assert_eq!(*family, AddressFamily::V6);
+                assert_eq!(failing_targets.len(), 2);
+            }
+            _ => unreachable!(),
+        }
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, StateEvent::Offline { .. })));
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+        assert!(tracker.current_outage().is_none());
+    }
+
+    #[test]
+    fn test_target_specific_issue_does_not_hide_family_loss() {
+        let config = MonitorConfig {
+            offline_min_targets: 2,
+            ..make_config()
+        };
+        let targets = vec![
+            Target::new("Google DNS", "8.8.8.8"),
+            Target::new("Cloudflare", "1.1.1.1"),
+            Target::new("Google DNS v6", "2001:4860:4860::8888"),
+            Target::new("Cloudflare v6", "2606:4700:4700::1111"),
+        ];
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+
+        // One IPv6 target fails on its own first...
+        let mut events = Vec::new();
+        for _ in 0..10 {
+            events.push(tracker.process(&success_ping("8.8.8.8")));
+            events.push(tracker.process(&success_ping("1.1.1.1")));
+            events.push(tracker.process(&failure_ping("2001:4860:4860::8888")));
+            events.push(tracker.process(&success_ping("2606:4700:4700::1111")));
+        }
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, StateEvent::TargetSpecific { .. })));
+
+        // ...then the rest of IPv6 goes with it, still within the same period
+        let mut events = Vec::new();
+        for _ in 0..10 {
+            events.push(tracker.process(&success_ping("8.8.8.8")));
+            events.push(tracker.process(&success_ping("1.1.1.1")));
+            events.push(tracker.process(&failure_ping("2001:4860:4860::8888")));
+            events.push(tracker.process(&failure_ping("2606:4700:4700::1111")));
+        }
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, StateEvent::FamilyLost { .. })));
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+    }
+
+    #[test]
+    fn test_both_families_down_is_an_outage() {
+        let config = make_config();
+        let targets = vec![
+            Target::new("Google DNS", "8.8.8.8"),
+            Target::new("Google DNS v6", "2001:4860:4860::8888"),
+        ];
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+
+        let mut went_offline = false;
+        for _ in 0..5 {
+            for target in &targets {
+                went_offline |= matches!(
+                    tracker.process(&failure_ping(&target.ip)),
+                    StateEvent::Offline { .. }
+                );
+            }
+        }
+        assert!(went_offline);
+    }
+
+    fn make_canary_config() -> MonitorConfig {
+        MonitorConfig {
+            canary_targets: vec![Target::new("Quad9", "9.9.9.9")],
+            ..make_config()
+        }
+    }
+
+    #[test]
+    fn test_canary_up_prevents_outage() {
+        let config = make_canary_config();
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+
+        tracker.process(&success_ping("9.9.9.9"));
+
+        let mut events = Vec::new();
+        for _ in 0..8 {
+            events.push(tracker.process(&failure_ping("8.8.8.8")));
+        }
+
+        // Exactly one target-specific event, and no outage
+        let target_specific = events
+            .iter()
+            .filter(|e| matches!(e, StateEvent::TargetSpecific { .. }))
+            .count();
+        assert_eq!(target_specific, 1);
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, StateEvent::Offline { .. })));
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+        assert!(tracker.current_outage().is_none());
+    }
+
+    #[test]
+    fn test_canary_down_allows_outage() {
+        let config = make_canary_config();
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+
+        tracker.process(&failure_ping("9.9.9.9"));
+
+        let mut went_offline = false;
+        for _ in 0..5 {
+            if let StateEvent::Offline { .. } = tracker.process(&failure_ping("8.8.8.8")) {
+                went_offline = true;
+            }
+        }
+
+        assert!(went_offline);
+        assert_eq!(tracker.state(), ConnectivityState::Offline);
+    }
+
+    #[test]
+    fn test_canary_results_do_not_drive_state() {
+        let config = make_canary_config();
+        let targets = make_targets();
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        prime(&mut tracker);
+
+        for _ in 0..10 {
+            let event = tracker.process(&failure_ping("9.9.9.9"));
+            assert!(matches!(event, StateEvent::NoChange));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+        assert!(tracker.failing_targets().is_empty());
+    }
+
     #[test]
     fn test_target_state_tracking() {
         let config = make_config();
@@ -417,4 +1666,89 @@ mod tests {
         let cloudflare = states.get("1.1.1.1").unwrap();
         assert!(!cloudflare.is_failing());
     }
+
+    #[test]
+    fn test_sustained_jitter_degrades_and_recovers() {
+        let mut config = make_config();
+        config.jitter_threshold_ms = Some(5.0);
+        let targets = vec![Target::new("Google DNS", "8.8.8.8")];
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        prime(&mut tracker);
+
+        // Alternating 10ms/30ms after priming's 10ms: every reply arrives but
+        // the RTT swings by 20ms
+        let mut events = Vec::new();
+        for i in 1..JITTER_WINDOW {
+            let latency = if i % 2 == 0 { 10.0 } else { 30.0 };
+            events.push(tracker.process(&latency_ping("8.8.8.8", latency)));
+        }
+        assert!(matches!(
+            events.last(),
+            Some(StateEvent::Degraded { failing_targets }) if failing_targets == &["8.8.8.8"]
+        ));
+        assert!(events[..events.len() - 1]
+            .iter()
+            .all(|e| matches!(e, StateEvent::NoChange)));
+        assert_eq!(tracker.state(), ConnectivityState::Degraded);
+
+        // Steady replies recover once the swings have mostly left the window,
+        // not on the first steady reply
+        let steady = (1..=JITTER_WINDOW)
+            .find(|_| {
+                matches!(
+                    tracker.process(&latency_ping("8.8.8.8", 12.0)),
+                    StateEvent::DegradedRecovered { .. }
+                )
+            })
+            .expect("should recover within the window");
+        assert_eq!(steady, 8);
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+    }
+
+    #[test]
+    fn test_dead_target_is_quarantined() {
+        let mut config = make_config();
+        config.quarantine_after = Some(2);
+        let targets = vec![
+            Target::new("Decommissioned", "192.0.2.1"),
+            Target::new("Google DNS", "8.8.8.8"),
+        ];
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+        prime(&mut tracker);
+
+        // Fails forever while the other target works: never degrades the link
+        for _ in 0..50 {
+            assert!(matches!(
+                tracker.process(&failure_ping("192.0.2.1")),
+                StateEvent::NoChange
+            ));
+            assert!(matches!(
+                tracker.process(&success_ping("8.8.8.8")),
+                StateEvent::NoChange
+            ));
+        }
+        assert_eq!(tracker.state(), ConnectivityState::Online);
+        assert!(tracker.target_states()["192.0.2.1"].is_quarantined());
+        assert!(tracker.failing_targets().is_empty());
+
+        // One reply and it counts again
+        tracker.process(&success_ping("192.0.2.1"));
+        assert!(!tracker.target_states()["192.0.2.1"].is_quarantined());
+
+        // When everything fails nothing is quarantined: it's an outage
+        let mut went_offline = false;
+        for _ in 0..config.offline_threshold {
+            for ip in ["192.0.2.1", "8.8.8.8"] {
+                went_offline |= matches!(
+                    tracker.process(&failure_ping(ip)),
+                    StateEvent::Offline { .. }
+                );
+            }
+        }
+        assert!(went_offline);
+        assert!(tracker
+            .target_states()
+            .values()
+            .all(|t| !t.is_quarantined()));
+    }
 }
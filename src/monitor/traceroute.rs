@@ -1,12 +1,27 @@
-use crate::models::{TracerouteHop, TracerouteResult};
-use chrono::Utc;
+use crate::config::MonitorConfig;
+use crate::format::format_latency;
+use crate::models::{AddressFamily, Target, TracerouteHop, TracerouteResult};
+use crate::monitor::ping::{decode_output, HostOs};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::IpAddr;
+use std::process::Output;
+use std::sync::Mutex;
 use std::time::Duration;
 use tokio::process::Command;
 
+/// Hops allowed beyond a target's last known path length
+const HOP_MARGIN: u8 = 3;
+
 /// Hop analyzer for running traceroute and identifying failing hops
 pub struct HopAnalyzer {
     timeout_secs: u64,
     max_hops: u8,
+    /// IPv4 prefix length within which the last hop counts as the target
+    match_prefix: u8,
+    /// Path length of the last successful trace per target
+    path_lengths: Mutex<HashMap<String, u8>>,
 }
 
 impl Default for HopAnalyzer {
@@ -21,53 +36,75 @@ impl HopAnalyzer {
         Self {
             timeout_secs: timeout.as_secs().max(1),
             max_hops,
+            match_prefix: 24,
+            path_lengths: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Accept a last hop within the same IPv4 /`prefix_len` as the target
+    pub fn with_match_prefix(mut self, prefix_len: u8) -> Self {
+        self.match_prefix = prefix_len.min(32);
+        self
+    }
+
     /// Run traceroute to a target
     pub async fn trace(&self, target: &str) -> TracerouteResult {
         let timestamp = Utc::now();
+        let max_hops = self.max_hops_for(target);
+        let output = self.command(target, max_hops).output().await;
+        let addresses = resolve_target(target).await;
+        let result = build_result(target, &addresses, self.match_prefix, timestamp, output);
+        self.record_path(&result, max_hops);
+        result
+    }
 
-        // macOS traceroute: -n (numeric), -q 1 (1 query per hop), -w timeout, -m max_hops
-        let output = Command::new("traceroute")
-            .args([
-                "-n",
-                "-q",
-                "1",
-                "-w",
-                &self.timeout_secs.to_string(),
-                "-m",
-                &self.max_hops.to_string(),
-                target,
-            ])
-            .output()
-            .await;
-
-        match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let hops = parse_traceroute_output(&stdout);
-                let success = check_reached_target(&hops, target);
-
-                TracerouteResult {
-                    target: target.to_string(),
-                    timestamp,
-                    hops,
-                    success,
-                }
-            }
-            Err(e) => {
-                tracing::error!("Failed to execute traceroute: {}", e);
-                TracerouteResult {
-                    target: target.to_string(),
-                    timestamp,
-                    hops: vec![],
-                    success: false,
-                }
+    /// Run traceroute to a target, aborting (and killing the child process)
+    /// if `cancel` completes first. Returns `None` when canceled.
+    pub async fn trace_cancelable<F: Future>(
+        &self,
+        target: &str,
+        cancel: F,
+    ) -> Option<TracerouteResult> {
+        let timestamp = Utc::now();
+        let max_hops = self.max_hops_for(target);
+        let output = run_cancelable(self.command(target, max_hops), cancel).await?;
+        let addresses = resolve_target(target).await;
+        let result = build_result(target, &addresses, self.match_prefix, timestamp, output);
+        self.record_path(&result, max_hops);
+        Some(result)
+    }
+
+    /// Max hops to probe for a target, based on its last known path length
+    pub fn max_hops_for(&self, target: &str) -> u8 {
+        let learned = self.path_lengths.lock().unwrap().get(target).copied();
+        adaptive_max_hops(learned, self.max_hops)
+    }
+
+    /// Remember the path length after a successful trace. A trace that hit the
+    /// hop limit without reaching the target may mean the path grew, so the
+    /// learned length is dropped and the next trace uses the full default.
+    fn record_path(&self, result: &TracerouteResult, max_hops_used: u8) {
+        let last_hop = result.hops.iter().map(|h| h.hop_number).max();
+        let mut path_lengths = self.path_lengths.lock().unwrap();
+
+        if result.success {
+            if let Some(length) = last_hop {
+                path_lengths.insert(result.target.clone(), length);
             }
+        } else if last_hop.is_some_and(|hop| hop >= max_hops_used) {
+            path_lengths.remove(&result.target);
         }
     }
 
+    /// Build the traceroute command for a target
+    fn command(&self, target: &str, max_hops: u8) -> Command {
+        let (program, args) =
+            build_traceroute_args(HostOs::current(), target, self.timeout_secs, max_hops);
+        let mut command = Command::new(program);
+        command.args(args);
+        command
+    }
+
     /// Identify the failing hop from a traceroute result
     /// Returns the last responding hop (the one before the failure)
     pub fn identify_failing_hop(result: &TracerouteResult) -> Option<(u8, String)> {
@@ -86,6 +123,97 @@ impl HopAnalyzer {
     }
 }
 
+/// Run a command to completion unless `cancel` completes first, in which
+/// case the child process is killed and `None` is returned
+async fn run_cancelable<F: Future>(
+    mut command: Command,
+    cancel: F,
+) -> Option<std::io::Result<Output>> {
+    command.kill_on_drop(true);
+
+    tokio::select! {
+        output = command.output() => Some(output),
+        _ = cancel => None,
+    }
+}
+
+/// Addresses a target may answer from: the IP itself, or what a hostname resolves to
+pub async fn resolve_target(target: &str) -> Vec<IpAddr> {
+    if let Ok(ip) = target.parse() {
+        return vec![ip];
+    }
+
+    match tokio::net::lookup_host((target, 0)).await {
+        Ok(addrs) => addrs.map(|a| a.ip()).collect(),
+        Err(e) => {
+            tracing::debug!("Could not resolve traceroute target {}: {}", target, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Turn traceroute command output into a result
+fn build_result(
+    target: &str,
+    addresses: &[IpAddr],
+    match_prefix: u8,
+    timestamp: DateTime<Utc>,
+    output: std::io::Result<Output>,
+) -> TracerouteResult {
+    match output {
+        Ok(output) => {
+            let stdout = decode_output(&output.stdout, "traceroute");
+            let hops = parse_traceroute_output(&stdout);
+            let success = check_reached_target(&hops, addresses, match_prefix);
+
+            TracerouteResult {
+                target: target.to_string(),
+                timestamp,
+                hops,
+                success,
+            }
+        }
+        Err(e) => {
+            tracing::error!("Failed to execute traceroute: {}", e);
+            TracerouteResult {
+                target: target.to_string(),
+                timestamp,
+                hops: vec![],
+                success: false,
+            }
+        }
+    }
+}
+
+/// Program and arguments for one trace: -n (numeric), -q 1 (1 query per hop),
+/// -w timeout in seconds, -m max hops. The flags are the same on macOS and
+/// Linux; IPv6 needs traceroute6 on macOS and `traceroute -6` on Linux.
+fn build_traceroute_args(
+    os: HostOs,
+    target: &str,
+    timeout_secs: u64,
+    max_hops: u8,
+) -> (&'static str, Vec<String>) {
+    let ipv6 = AddressFamily::of(target) == Some(AddressFamily::V6);
+    let (program, mut args) = match os {
+        HostOs::MacOs if ipv6 => ("traceroute6", Vec::new()),
+        HostOs::Linux if ipv6 => ("traceroute", vec!["-6".to_string()]),
+        _ => ("traceroute", Vec::new()),
+    };
+
+    args.extend([
+        "-n".to_string(),
+        "-q".to_string(),
+        "1".to_string(),
+        "-w".to_string(),
+        timeout_secs.to_string(),
+        "-m".to_string(),
+        max_hops.to_string(),
+        target.to_string(),
+    ]);
+    (program, args)
+}
+
 /// Parse traceroute output into a list of hops
 fn parse_traceroute_output(output: &str) -> Vec<TracerouteHop> {
     let mut hops = Vec::new();
@@ -93,8 +221,8 @@ fn parse_traceroute_output(output: &str) -> Vec<TracerouteHop> {
     for line in output.lines() {
         let line = line.trim();
 
-        // Skip header line (starts with "traceroute to")
-        if line.starts_with("traceroute to") || line.is_empty() {
+        // Skip header line ("traceroute to ..." or "traceroute6 to ...")
+        if line.starts_with("traceroute") || line.is_empty() {
             continue;
         }
 
@@ -112,17 +240,15 @@ fn parse_traceroute_output(output: &str) -> Vec<TracerouteHop> {
 ///   " 2  * * *"
 ///   " 3  10.0.0.1  5.678 ms"
 fn parse_hop_line(line: &str) -> Option<TracerouteHop> {
-    let parts: Vec<&str> = line.split_whitespace().collect();
+    // Single pass over the fields; this runs for every line of every trace
+    let mut fields = line.split_whitespace();
 
-    if parts.is_empty() {
-        return None;
-    }
-
-    // First part should be hop number
-    let hop_number: u8 = parts[0].parse().ok()?;
+    // First field should be hop number
+    let hop_number: u8 = fields.next()?.parse().ok()?;
 
     // Check for timeout (asterisks)
-    if parts.len() >= 2 && parts[1] == "*" {
+    let address = fields.next()?;
+    if address == "*" {
         return Some(TracerouteHop {
             hop_number,
             ip: None,
@@ -132,43 +258,132 @@ fn parse_hop_line(line: &str) -> Option<TracerouteHop> {
         });
     }
 
-    // Parse IP and latency
-    if parts.len() >= 2 {
-        let ip = parts[1].to_string();
+    // Link-local IPv6 hops carry a zone ("fe80::1%en0")
+    let ip = address.split('%').next().unwrap_or(address).to_string();
 
-        // Look for latency (number followed by "ms")
-        let latency_ms = parts.iter().enumerate().find_map(|(i, &part)| {
-            if part == "ms" && i > 0 {
-                parts[i - 1].parse::<f64>().ok()
-            } else {
-                None
-            }
-        });
+    // Latency is the first number followed by "ms"
+    let mut previous = address;
+    let latency_ms = fields.find_map(|field| {
+        let latency = if field == "ms" {
+            previous.parse::<f64>().ok()
+        } else {
+            None
+        };
+        previous = field;
+        latency
+    });
+
+    Some(TracerouteHop {
+        hop_number,
+        ip: Some(ip),
+        hostname: None, // We use -n flag so no hostname
+        latency_ms,
+        timeout: false,
+    })
+}
 
-        return Some(TracerouteHop {
-            hop_number,
-            ip: Some(ip),
-            hostname: None, // We use -n flag so no hostname
-            latency_ms,
-            timeout: false,
-        });
-    }
+/// Check if the traceroute reached the target: the last hop is one of the
+/// target's addresses, or (IPv4) in the same /`match_prefix` as one of them
+fn check_reached_target(hops: &[TracerouteHop], addresses: &[IpAddr], match_prefix: u8) -> bool {
+    let Some(last_ip) = hops
+        .last()
+        .and_then(|h| h.ip.as_deref())
+        .and_then(|ip| ip.parse::<IpAddr>().ok())
+    else {
+        return false;
+    };
+
+    addresses.iter().any(|addr| match (addr, last_ip) {
+        (IpAddr::V4(addr), IpAddr::V4(hop)) => {
+            let mask = u32::MAX.checked_shl(32 - match_prefix as u32).unwrap_or(0);
+            u32::from(*addr) & mask == u32::from(hop) & mask
+        }
+        _ => *addr == last_ip,
+    })
+}
 
-    None
+/// Max hops for a trace: the learned path length plus a margin, capped at the default
+pub fn adaptive_max_hops(learned_path_length: Option<u8>, default_max_hops: u8) -> u8 {
+    match learned_path_length {
+        Some(length) => length.saturating_add(HOP_MARGIN).min(default_max_hops),
+        None => default_max_hops,
+    }
 }
 
-/// Check if the traceroute reached the target
-fn check_reached_target(hops: &[TracerouteHop], target: &str) -> bool {
-    if let Some(last_hop) = hops.last() {
-        if let Some(ref ip) = last_hop.ip {
-            return ip == target;
+/// Choose which addresses to trace when an outage starts: those of the
+/// outage's affected targets (by `Target::key`) in monitoring order, capped at
+/// `max` (at least one). Falls back to the first monitored target when none are known.
+pub fn select_trace_targets(affected: &[String], targets: &[Target], max: usize) -> Vec<String> {
+    let mut selected: Vec<String> = Vec::new();
+    for target in targets.iter().filter(|t| affected.contains(&t.key())) {
+        // The same address over two uplinks takes one trace
+        if !selected.contains(&target.ip) {
+            selected.push(target.ip.clone());
+        }
+    }
+    // Targets no longer configured are traced by what was recorded
+    for key in affected {
+        if !targets.iter().any(|t| t.key() == *key) && !selected.contains(key) {
+            selected.push(key.clone());
         }
     }
-    false
+
+    if selected.is_empty() {
+        selected.push(
+            targets
+                .first()
+                .map(|t| t.ip.clone())
+                .unwrap_or_else(|| "8.8.8.8".to_string()),
+        );
+    }
+
+    selected.truncate(max.max(1));
+    selected
+}
+
+/// Targets to trace for a new outage, or none when traceroutes are disabled.
+/// `trace_target` (or the first public target) goes first, then the failing
+/// targets, within the same `max_outage_traces` cap.
+pub fn outage_trace_targets(
+    config: &MonitorConfig,
+    affected: &[String],
+    targets: &[Target],
+) -> Vec<String> {
+    if !config.traceroute_enabled {
+        return Vec::new();
+    }
+    let max = config.max_outage_traces.max(1);
+    let mut selected = select_trace_targets(affected, targets, max);
+
+    let lead = config
+        .trace_target
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .or_else(|| first_public_target(targets));
+    if let Some(lead) = lead {
+        selected.retain(|ip| *ip != lead);
+        selected.insert(0, lead);
+        selected.truncate(max);
+    }
+    selected
+}
+
+/// The first target that isn't on the local network (a hostname counts as public)
+fn first_public_target(targets: &[Target]) -> Option<String> {
+    targets
+        .iter()
+        .find(|t| match t.ip.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => !(ip.is_private() || ip.is_loopback() || ip.is_link_local()),
+            Ok(IpAddr::V6(ip)) => {
+                !(ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local())
+            }
+            Err(_) => true,
+        })
+        .map(|t| t.ip.clone())
 }
 
-/// Format a traceroute result for display
-pub fn format_traceroute(result: &TracerouteResult) -> String {
+/// Format a traceroute result for display, with latencies at the given precision
+pub fn format_traceroute(result: &TracerouteResult, latency_precision: usize) -> String {
     let mut output = String::new();
 
     output.push_str(&format!("Traceroute to {}\n", result.target));
@@ -180,7 +395,7 @@ pub fn format_traceroute(result: &TracerouteResult) -> String {
         let ip_str = hop.ip.as_deref().unwrap_or("*");
         let latency_str = hop
             .latency_ms
-            .map(|l| format!("{:.2} ms", l))
+            .map(|l| format_latency(l, latency_precision))
             .unwrap_or_else(|| "*".to_string());
 
         output.push_str(&format!(
@@ -210,6 +425,185 @@ pub fn format_traceroute(result: &TracerouteResult) -> String {
 mod tests {
     use super::*;
 
+    fn trace_result(target: &str, hop_count: u8, success: bool) -> TracerouteResult {
+        TracerouteResult {
+            target: target.to_string(),
+            timestamp: Utc::now(),
+            hops: (1..=hop_count)
+                .map(|n| TracerouteHop {
+                    hop_number: n,
+                    ip: Some(format!("10.0.0.{}", n)),
+                    hostname: None,
+                    latency_ms: Some(1.0),
+                    timeout: false,
+                })
+                .collect(),
+            success,
+        }
+    }
+
+    #[test]
+    fn test_adaptive_max_hops() {
+        assert_eq!(adaptive_max_hops(None, 30), 30);
+        assert_eq!(adaptive_max_hops(Some(8), 30), 11);
+        assert_eq!(adaptive_max_hops(Some(29), 30), 30);
+        assert_eq!(adaptive_max_hops(Some(255), 30), 30);
+    }
+
+    #[test]
+    fn test_learned_path_length() {
+        let analyzer = HopAnalyzer::default();
+        assert_eq!(analyzer.max_hops_for("8.8.8.8"), 30);
+
+        analyzer.record_path(&trace_result("8.8.8.8", 8, true), 30);
+        assert_eq!(analyzer.max_hops_for("8.8.8.8"), 11);
+        assert_eq!(analyzer.max_hops_for("1.1.1.1"), 30);
+
+        // A failure short of the limit keeps the learned length
+        analyzer.record_path(&trace_result("8.8.8.8", 4, false), 11);
+        assert_eq!(analyzer.max_hops_for("8.8.8.8"), 11);
+
+        // Running into the limit means the path may have grown
+        analyzer.record_path(&trace_result("8.8.8.8", 11, false), 11);
+        assert_eq!(analyzer.max_hops_for("8.8.8.8"), 30);
+    }
+
+    #[test]
+    fn test_traceroute_disabled_records_outage_without_trace() {
+        use crate::db::Database;
+        use crate::models::PingResult;
+        use crate::monitor::{ConnectivityTracker, StateEvent};
+
+        let config = MonitorConfig {
+            traceroute_enabled: false,
+            ..MonitorConfig::default()
+        };
+        let targets = vec![Target::new("Google DNS", "8.8.8.8")];
+        let mut tracker = ConnectivityTracker::new(&config, &targets);
+
+        let failure = PingResult {
+            target: "8.8.8.8".to_string(),
+            target_name: "Google DNS".to_string(),
+            timestamp: Utc::now(),
+            success: false,
+            latency_ms: None,
+            jitter_ms: None,
+            loss: None,
+            error: Some("timeout".to_string()),
+            error_kind: None,
+            resolved_ip: None,
+        };
+        let outage = (0..config.offline_threshold)
+            .find_map(|_| match tracker.process(&failure) {
+                StateEvent::Offline { outage } => Some(outage),
+                _ => None,
+            })
+            .expect("outage should start");
+
+        assert!(outage_trace_targets(&config, &outage.affected_targets, &targets).is_empty());
+
+        let db = Database::in_memory().unwrap();
+        let id = db.insert_outage(&outage).unwrap();
+        let stored = db.get_outage(id).unwrap().unwrap();
+        assert!(stored.failing_hop.is_none());
+        assert!(stored.failing_hop_ip.is_none());
+
+        let enabled = MonitorConfig::default();
+        assert_eq!(
+            outage_trace_targets(&enabled, &outage.affected_targets, &targets),
+            vec!["8.8.8.8"]
+        );
+    }
+
+    #[test]
+    fn test_select_trace_targets_uses_failing_target() {
+        let targets = vec![
+            Target::new("Gateway", "192.168.1.1"),
+            Target::new("Google DNS", "8.8.8.8"),
+            Target::new("Cloudflare", "1.1.1.1"),
+        ];
+
+        // Only Cloudflare failed: trace it, not the gateway
+        let selected = select_trace_targets(&["1.1.1.1".to_string()], &targets, 2);
+        assert_eq!(selected, vec!["1.1.1.1"]);
+
+        // Monitoring order is kept and the cap applies
+        let affected = vec![
+            "1.1.1.1".to_string(),
+            "8.8.8.8".to_string(),
+            "192.168.1.1".to_string(),
+        ];
+        assert_eq!(
+            select_trace_targets(&affected, &targets, 2),
+            vec!["192.168.1.1", "8.8.8.8"]
+        );
+        assert_eq!(select_trace_targets(&affected, &targets, 0).len(), 1);
+
+        assert_eq!(select_trace_targets(&[], &targets, 2), vec!["192.168.1.1"]);
+
+        // Affected targets are keys; one address over two uplinks is traced once
+        let targets = vec![
+            Target::new("Google DNS", "8.8.8.8"),
+            Target {
+                source: Some("en1".to_string()),
+                ..Target::new("Google DNS via LTE", "8.8.8.8")
+            },
+        ];
+        let affected = vec!["8.8.8.8@en1".to_string(), "8.8.8.8".to_string()];
+        assert_eq!(
+            select_trace_targets(&affected, &targets, 2),
+            vec!["8.8.8.8"]
+        );
+    }
+
+    #[test]
+    fn test_trace_target_leads_outage_traces() {
+        let targets = vec![
+            Target::new("Gateway", "192.168.1.1"),
+            Target::new("Cloudflare", "1.1.1.1"),
+        ];
+        let affected = vec!["192.168.1.1".to_string(), "1.1.1.1".to_string()];
+
+        // The default 8.8.8.8 goes ahead of the gateway
+        let config = MonitorConfig::default();
+        assert_eq!(
+            outage_trace_targets(&config, &affected, &targets),
+            vec!["8.8.8.8", "192.168.1.1"]
+        );
+
+        // A configured target that is also monitored is traced once, first
+        let config = MonitorConfig {
+            trace_target: Some("1.1.1.1".to_string()),
+            ..MonitorConfig::default()
+        };
+        assert_eq!(
+            outage_trace_targets(&config, &affected, &targets),
+            vec!["1.1.1.1", "192.168.1.1"]
+        );
+
+        // Unset: the first public target rather than the gateway
+        let config = MonitorConfig {
+            trace_target: None,
+            max_outage_traces: 1,
+            ..MonitorConfig::default()
+        };
+        assert_eq!(
+            outage_trace_targets(&config, &affected, &targets),
+            vec!["1.1.1.1"]
+        );
+
+        // `trace_target = ""` is how the default is unset in TOML
+        let config = MonitorConfig {
+            trace_target: Some(" ".to_string()),
+            max_outage_traces: 1,
+            ..MonitorConfig::default()
+        };
+        assert_eq!(
+            outage_trace_targets(&config, &affected, &targets),
+            vec!["1.1.1.1"]
+        );
+    }
+
     #[test]
     fn test_parse_traceroute_success() {
         let output = r#"traceroute to 8.8.8.8 (8.8.8.8), 64 hops max, 52 byte packets
@@ -263,6 +657,78 @@ mod tests {
         assert!(hops.iter().all(|h| h.timeout));
     }
 
+    #[test]
+    fn test_build_traceroute_args() {
+        let (program, args) = build_traceroute_args(HostOs::MacOs, "8.8.8.8", 2, 30);
+        assert_eq!(program, "traceroute");
+        assert_eq!(
+            args,
+            vec!["-n", "-q", "1", "-w", "2", "-m", "30", "8.8.8.8"]
+        );
+        assert_eq!(
+            build_traceroute_args(HostOs::Linux, "8.8.8.8", 2, 30),
+            (program, args)
+        );
+
+        let (program, args) = build_traceroute_args(HostOs::MacOs, "2001:db8::1", 2, 30);
+        assert_eq!(program, "traceroute6");
+        assert_eq!(args[0], "-n");
+
+        let (program, args) = build_traceroute_args(HostOs::Linux, "2001:db8::1", 2, 30);
+        assert_eq!(program, "traceroute");
+        assert_eq!(args[..2], ["-6", "-n"]);
+    }
+
+    #[test]
+    fn test_parse_hop_line_whitespace() {
+        let hop = parse_hop_line("   4  10.0.0.1  5.678 ms").unwrap();
+        assert_eq!(hop.hop_number, 4);
+        assert_eq!(hop.ip.as_deref(), Some("10.0.0.1"));
+        assert_eq!(hop.latency_ms, Some(5.678));
+
+        let hop = parse_hop_line("5\t10.0.0.2\t6.5\tms").unwrap();
+        assert_eq!(hop.ip.as_deref(), Some("10.0.0.2"));
+        assert_eq!(hop.latency_ms, Some(6.5));
+
+        let hop = parse_hop_line(" 6 \t 10.0.0.3 \t 7.25 ms  8.5 ms\t9 ms").unwrap();
+        assert_eq!(hop.latency_ms, Some(7.25));
+    }
+
+    #[test]
+    fn test_parse_hop_line_edge_cases() {
+        assert!(parse_hop_line("").is_none());
+        assert!(parse_hop_line("7").is_none());
+        assert!(parse_hop_line("hop 10.0.0.1 1.0 ms").is_none());
+
+        // Address without a latency, and a latency that isn't a number
+        let hop = parse_hop_line("8  10.0.0.4").unwrap();
+        assert_eq!(hop.latency_ms, None);
+        let hop = parse_hop_line("9  10.0.0.5  !H ms").unwrap();
+        assert_eq!(hop.latency_ms, None);
+
+        // The first probe timing out marks the hop as a timeout
+        assert!(parse_hop_line("10  *  10.0.0.6  3.1 ms").unwrap().timeout);
+    }
+
+    #[test]
+    fn test_parse_traceroute6_output() {
+        let output = r#"traceroute6 to 2001:db8::1 (2001:db8::1) from fe80::2%en0, 64 hops max, 12 byte packets
+ 1  fe80::1%en0  1.204 ms
+ 2  *
+ 3  2001:db8::1  10.512 ms
+"#;
+
+        let hops = parse_traceroute_output(output);
+        assert_eq!(hops.len(), 3);
+        assert_eq!(hops[0].ip.as_deref(), Some("fe80::1"));
+        assert_eq!(hops[0].latency_ms, Some(1.204));
+        assert!(hops[1].timeout);
+        assert_eq!(hops[2].ip.as_deref(), Some("2001:db8::1"));
+
+        let target: Vec<IpAddr> = vec!["2001:db8::1".parse().unwrap()];
+        assert!(check_reached_target(&hops, &target, 24));
+    }
+
     #[test]
     fn test_check_reached_target() {
         let hops = vec![
@@ -282,8 +748,73 @@ mod tests {
             },
         ];
 
-        assert!(check_reached_target(&hops, "8.8.8.8"));
-        assert!(!check_reached_target(&hops, "1.1.1.1"));
+        let google: Vec<IpAddr> = vec!["8.8.8.8".parse().unwrap()];
+        let cloudflare: Vec<IpAddr> = vec!["1.1.1.1".parse().unwrap()];
+        assert!(check_reached_target(&hops, &google, 32));
+        assert!(!check_reached_target(&hops, &cloudflare, 32));
+        assert!(!check_reached_target(&hops, &[], 24));
+    }
+
+    fn hops_ending_at(ip: &str) -> Vec<TracerouteHop> {
+        vec![
+            TracerouteHop {
+                hop_number: 1,
+                ip: Some("192.168.1.1".to_string()),
+                hostname: None,
+                latency_ms: Some(1.0),
+                timeout: false,
+            },
+            TracerouteHop {
+                hop_number: 2,
+                ip: Some(ip.to_string()),
+                hostname: None,
+                latency_ms: Some(10.0),
+                timeout: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_check_reached_target_tolerates_anycast_neighbour() {
+        let hops = hops_ending_at("1.1.1.2");
+        let target: Vec<IpAddr> = vec!["1.1.1.1".parse().unwrap()];
+
+        assert!(check_reached_target(&hops, &target, 24));
+        assert!(!check_reached_target(&hops, &target, 32));
+        assert!(!check_reached_target(
+            &hops_ending_at("1.1.2.1"),
+            &target,
+            24
+        ));
+
+        // IPv6 last hops must match exactly
+        let v6: Vec<IpAddr> = vec!["2606:4700:4700::1111".parse().unwrap()];
+        assert!(check_reached_target(
+            &hops_ending_at("2606:4700:4700::1111"),
+            &v6,
+            24
+        ));
+        assert!(!check_reached_target(
+            &hops_ending_at("2606:4700:4700::1001"),
+            &v6,
+            24
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hostname_target_resolved_before_matching() {
+        let addresses = resolve_target("localhost").await;
+        assert!(addresses.contains(&"127.0.0.1".parse().unwrap()));
+        assert!(check_reached_target(
+            &hops_ending_at("127.0.0.1"),
+            &addresses,
+            32
+        ));
+
+        assert_eq!(
+            resolve_target("8.8.8.8").await,
+            vec!["8.8.8.8".parse::<IpAddr>().unwrap()]
+        );
     }
 
     #[test]
@@ -379,6 +910,30 @@ mod tests {
         assert!(result.success || !result.hops.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_run_cancelable_kills_long_command() {
+        let mut command = Command::new("sleep");
+        command.arg("30");
+
+        let started = std::time::Instant::now();
+        let output = run_cancelable(command, tokio::time::sleep(Duration::from_millis(50))).await;
+
+        assert!(output.is_none(), "Command should have been canceled");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_run_cancelable_completes() {
+        let mut command = Command::new("echo");
+        command.arg("done");
+
+        let output = run_cancelable(command, std::future::pending::<()>())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "done");
+    }
+
     #[test]
     fn test_format_traceroute() {
         let result = TracerouteResult {
@@ -403,8 +958,9 @@ mod tests {
             success: true,
         };
 
-        let output = format_traceroute(&result);
+        let output = format_traceroute(&result, 2);
         assert!(output.contains("192.168.1.1"));
+        assert!(output.contains("15.68ms"));
         assert!(output.contains("8.8.8.8"));
         assert!(output.contains("Target reached in 2 hops"));
     }
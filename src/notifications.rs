@@ -0,0 +1,741 @@
+use crate::config::{CircuitBreakerConfig, DisplayTimezone, EmailConfig, NotificationConfig};
+use crate::format::format_duration_secs;
+use crate::models::Outage;
+use crate::monitor::describe_ip;
+use chrono::{DateTime, Utc};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde_json::json;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Timeout for a single webhook request
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Timeout for SMTP connections and commands
+const SMTP_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Error, Debug)]
+pub enum NotifyError {
+    #[error("Webhook request failed: {0}")]
+    Webhook(String),
+    #[error("Email failed: {0}")]
+    Email(String),
+    #[error("Circuit open, skipping for another {0:?}")]
+    CircuitOpen(Duration),
+}
+
+/// Events that are sent to notification sinks
+#[derive(Debug, Clone)]
+pub enum NotificationEvent {
+    Offline {
+        outage: Outage,
+    },
+    /// Heartbeat while an outage is still going on at `at`
+    Ongoing {
+        outage: Outage,
+        at: DateTime<Utc>,
+    },
+    Recovered {
+        outage: Outage,
+    },
+    /// The targets failed at `at` while the canaries stayed reachable
+    TargetSpecific {
+        failing_targets: Vec<String>,
+        at: DateTime<Utc>,
+    },
+}
+
+impl NotificationEvent {
+    /// JSON payload sent to webhooks
+    pub fn payload(&self) -> serde_json::Value {
+        match self {
+            NotificationEvent::Offline { outage } => json!({
+                "event": "offline",
+                "timestamp": outage.start_time.to_rfc3339(),
+                "state": "offline",
+                "failing_hop": outage.failing_hop,
+                "failing_hop_ip": outage.failing_hop_ip,
+                "affected_targets": outage.affected_targets,
+            }),
+            NotificationEvent::Ongoing { outage, at } => json!({
+                "event": "ongoing",
+                "timestamp": at.to_rfc3339(),
+                "state": "offline",
+                "outage_start": outage.start_time.to_rfc3339(),
+                "elapsed_secs": outage.elapsed_secs(*at),
+                "failing_hop": outage.failing_hop,
+                "failing_hop_ip": outage.failing_hop_ip,
+                "affected_targets": outage.affected_targets,
+            }),
+            NotificationEvent::Recovered { outage } => json!({
+                "event": "recovered",
+                "timestamp": outage.end_time.map(|t| t.to_rfc3339()),
+                "state": "online",
+                "previous_state": "offline",
+                "outage_duration_secs": outage.duration_secs,
+                "failing_hop": outage.failing_hop,
+                "failing_hop_ip": outage.failing_hop_ip,
+            }),
+            NotificationEvent::TargetSpecific {
+                failing_targets,
+                at,
+            } => json!({
+                "event": "target_specific",
+                "timestamp": at.to_rfc3339(),
+                "state": "degraded",
+                "failing_targets": failing_targets,
+            }),
+        }
+    }
+}
+
+/// A notification sink
+pub trait Notifier {
+    /// Short name used in logs
+    fn name(&self) -> &str;
+
+    fn notify(&mut self, event: &NotificationEvent) -> Result<(), NotifyError>;
+}
+
+impl<N: Notifier + ?Sized> Notifier for Box<N> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn notify(&mut self, event: &NotificationEvent) -> Result<(), NotifyError> {
+        (**self).notify(event)
+    }
+}
+
+/// POSTs event JSON to a URL
+pub struct WebhookNotifier {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            agent: ureq::AgentBuilder::new().timeout(WEBHOOK_TIMEOUT).build(),
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn notify(&mut self, event: &NotificationEvent) -> Result<(), NotifyError> {
+        self.agent
+            .post(&self.url)
+            .send_json(event.payload())
+            .map(|_| ())
+            .map_err(|e| NotifyError::Webhook(e.to_string()))
+    }
+}
+
+/// Emails an outage summary when connectivity comes back
+pub struct EmailNotifier {
+    config: EmailConfig,
+    timezone: DisplayTimezone,
+}
+
+impl EmailNotifier {
+    pub fn new(config: &EmailConfig, timezone: DisplayTimezone) -> Self {
+        Self {
+            config: config.clone(),
+            timezone,
+        }
+    }
+
+    /// Subject and body for an event; only recoveries are emailed
+    pub fn compose(&self, event: &NotificationEvent) -> Option<(String, String)> {
+        let NotificationEvent::Recovered { outage } = event else {
+            return None;
+        };
+
+        let fmt = "%Y-%m-%d %H:%M:%S %Z";
+        let duration = outage
+            .duration_secs
+            .map(format_duration_secs)
+            .unwrap_or_else(|| "unknown".to_string());
+        let failing_hop = match (outage.failing_hop, &outage.failing_hop_ip) {
+            (Some(hop), Some(ip)) => format!("{} — {}", hop, describe_ip(ip, false)),
+            (Some(hop), None) => hop.to_string(),
+            (None, _) => "not identified".to_string(),
+        };
+
+        let subject = format!("Vigil: internet back after {}", duration);
+        let body = format!(
+            "Your internet connection is back.\n\n\
+             Started:          {}\n\
+             Ended:            {}\n\
+             Duration:         {}\n\
+             Failing hop:      {}\n\
+             Affected targets: {}\n",
+            self.timezone.format(outage.start_time, fmt),
+            outage
+                .end_time
+                .map(|t| self.timezone.format(t, fmt))
+                .unwrap_or_else(|| "-".to_string()),
+            duration,
+            failing_hop,
+            outage.affected_targets.join(", "),
+        );
+
+        Some((subject, body))
+    }
+
+    /// A blocking transport: `NotificationQueue` sends from its own thread,
+    /// so a slow SMTP handshake never holds up the monitor
+    fn transport(&self) -> Result<SmtpTransport, NotifyError> {
+        let builder = if self.config.starttls {
+            SmtpTransport::starttls_relay(&self.config.smtp_host)
+                .map_err(|e| NotifyError::Email(e.to_string()))?
+        } else {
+            SmtpTransport::builder_dangerous(&self.config.smtp_host)
+        };
+
+        let mut builder = builder
+            .port(self.config.smtp_port)
+            .timeout(Some(SMTP_TIMEOUT));
+        if let (Some(user), Some(password)) = (&self.config.username, &self.config.password) {
+            builder = builder.credentials(Credentials::new(user.clone(), password.clone()));
+        }
+
+        Ok(builder.build())
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn notify(&mut self, event: &NotificationEvent) -> Result<(), NotifyError> {
+        let Some((subject, body)) = self.compose(event) else {
+            return Ok(());
+        };
+
+        let parse = |addr: &str| {
+            addr.parse()
+                .map_err(|e| NotifyError::Email(format!("invalid address '{}': {}", addr, e)))
+        };
+        let message = Message::builder()
+            .from(parse(&self.config.from)?)
+            .to(parse(&self.config.to)?)
+            .subject(subject)
+            .body(body)
+            .map_err(|e| NotifyError::Email(e.to_string()))?;
+
+        self.transport()?
+            .send(&message)
+            .map(|_| ())
+            .map_err(|e| NotifyError::Email(e.to_string()))
+    }
+}
+
+/// Native macOS notification through `osascript`; does nothing elsewhere
+pub struct DesktopNotifier {
+    timezone: DisplayTimezone,
+}
+
+impl DesktopNotifier {
+    pub fn new(timezone: DisplayTimezone) -> Self {
+        Self { timezone }
+    }
+
+    /// Title and message for an event
+    pub fn compose(&self, event: &NotificationEvent) -> (String, String) {
+        match event {
+            NotificationEvent::Offline { outage } => {
+                let mut message = format!(
+                    "Outage started at {}",
+                    self.timezone.format(outage.start_time, "%H:%M:%S")
+                );
+                if let Some(hop) = outage.failing_hop {
+                    let ip = outage.failing_hop_ip.as_deref().unwrap_or("unknown");
+                    message.push_str(&format!(
+                        " - failing hop {} ({})",
+                        hop,
+                        describe_ip(ip, false)
+                    ));
+                }
+                ("Vigil: internet down".to_string(), message)
+            }
+            NotificationEvent::Ongoing { outage, at } => {
+                let mut message = format!(
+                    "Down for {}",
+                    format_duration_secs(outage.elapsed_secs(*at))
+                );
+                if let Some(hop) = outage.failing_hop {
+                    let ip = outage.failing_hop_ip.as_deref().unwrap_or("unknown");
+                    message.push_str(&format!(
+                        " - failing hop {} ({})",
+                        hop,
+                        describe_ip(ip, false)
+                    ));
+                }
+                ("Vigil: internet still down".to_string(), message)
+            }
+            NotificationEvent::Recovered { outage } => {
+                let duration = outage
+                    .duration_secs
+                    .map(format_duration_secs)
+                    .unwrap_or_else(|| "unknown".to_string());
+                (
+                    "Vigil: internet back".to_string(),
+                    format!("Outage lasted {}", duration),
+                )
+            }
+            NotificationEvent::TargetSpecific {
+                failing_targets, ..
+            } => (
+                "Vigil: targets unreachable".to_string(),
+                format!(
+                    "Internet is up, but these are failing: {}",
+                    failing_targets.join(", ")
+                ),
+            ),
+        }
+    }
+}
+
+/// `display notification` script with both strings quoted for AppleScript
+fn applescript_notification(title: &str, message: &str) -> String {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    format!(
+        "display notification {} with title {}",
+        quote(message),
+        quote(title)
+    )
+}
+
+impl Notifier for DesktopNotifier {
+    fn name(&self) -> &str {
+        "desktop"
+    }
+
+    fn notify(&mut self, event: &NotificationEvent) -> Result<(), NotifyError> {
+        if !cfg!(target_os = "macos") {
+            return Ok(());
+        }
+
+        let (title, message) = self.compose(event);
+        let script = applescript_notification(&title, &message);
+        // Fire and forget: the ping loop never waits on Notification Center
+        std::thread::spawn(move || {
+            match std::process::Command::new("osascript")
+                .args(["-e", &script])
+                .output()
+            {
+                Ok(output) if !output.status.success() => tracing::warn!(
+                    "desktop notification failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("desktop notification failed: {}", e),
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Calls go through; counts consecutive failures
+    Closed { failures: u32 },
+    /// Calls are skipped until the cooldown has passed
+    Open { until: Instant },
+    /// The next call is a trial: success closes, failure re-opens
+    HalfOpen,
+}
+
+/// Wraps a notifier and stops calling it after repeated failures
+pub struct CircuitBreaker<N> {
+    inner: N,
+    state: BreakerState,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+impl<N: Notifier> CircuitBreaker<N> {
+    pub fn new(inner: N, config: &CircuitBreakerConfig) -> Self {
+        Self {
+            inner,
+            state: BreakerState::Closed { failures: 0 },
+            failure_threshold: config.failure_threshold.max(1),
+            cooldown: Duration::from_secs(config.cooldown_secs),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state
+    }
+
+    /// Send an event, using `now` to evaluate the cooldown
+    pub fn notify_at(
+        &mut self,
+        event: &NotificationEvent,
+        now: Instant,
+    ) -> Result<(), NotifyError> {
+        if let BreakerState::Open { until } = self.state {
+            if now < until {
+                return Err(NotifyError::CircuitOpen(until - now));
+            }
+            self.state = BreakerState::HalfOpen;
+        }
+
+        match self.inner.notify(event) {
+            Ok(()) => {
+                if self.state == BreakerState::HalfOpen {
+                    tracing::info!("{} notifier recovered, circuit closed", self.inner.name());
+                }
+                self.state = BreakerState::Closed { failures: 0 };
+                Ok(())
+            }
+            Err(e) => {
+                self.state = match self.state {
+                    BreakerState::Closed { failures } if failures + 1 < self.failure_threshold => {
+                        BreakerState::Closed {
+                            failures: failures + 1,
+                        }
+                    }
+                    _ => {
+                        tracing::warn!(
+                            "{} notifier failing, pausing for {}s",
+                            self.inner.name(),
+                            self.cooldown.as_secs()
+                        );
+                        BreakerState::Open {
+                            until: now + self.cooldown,
+                        }
+                    }
+                };
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<N: Notifier> Notifier for CircuitBreaker<N> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn notify(&mut self, event: &NotificationEvent) -> Result<(), NotifyError> {
+        self.notify_at(event, Instant::now())
+    }
+}
+
+/// Build the configured notifiers, each behind its own circuit breaker
+pub fn from_config(
+    config: &NotificationConfig,
+    timezone: DisplayTimezone,
+) -> Vec<Box<dyn Notifier + Send>> {
+    let mut notifiers: Vec<Box<dyn Notifier + Send>> = Vec::new();
+
+    if let Some(url) = &config.webhook_url {
+        notifiers.push(Box::new(CircuitBreaker::new(
+            WebhookNotifier::new(url),
+            &config.circuit_breaker,
+        )));
+    }
+
+    if let Some(email) = &config.email {
+        notifiers.push(Box::new(CircuitBreaker::new(
+            EmailNotifier::new(email, timezone),
+            &config.circuit_breaker,
+        )));
+    }
+
+    if config.desktop {
+        notifiers.push(Box::new(DesktopNotifier::new(timezone)));
+    }
+
+    notifiers
+}
+
+/// Send an event to every notifier. Failures are logged and never propagated.
+pub fn notify_all(notifiers: &mut [Box<dyn Notifier + Send>], event: &NotificationEvent) {
+    for notifier in notifiers.iter_mut() {
+        match notifier.notify(event) {
+            Ok(()) => {}
+            Err(NotifyError::CircuitOpen(_)) => {
+                tracing::debug!("Skipping {} notification, circuit open", notifier.name());
+            }
+            Err(e) => tracing::warn!("{} notification failed: {}", notifier.name(), e),
+        }
+    }
+}
+
+/// Delivers events on a blocking thread, one after another in the order they
+/// were sent, so a slow webhook or SMTP server never holds up monitoring
+pub struct NotificationQueue {
+    tx: tokio::sync::mpsc::UnboundedSender<NotificationEvent>,
+    worker: tokio::task::JoinHandle<()>,
+}
+
+impl NotificationQueue {
+    /// Start delivering to `notifiers` (must be called inside a tokio runtime)
+    pub fn spawn(mut notifiers: Vec<Box<dyn Notifier + Send>>) -> Self {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let worker = tokio::task::spawn_blocking(move || {
+            while let Some(event) = rx.blocking_recv() {
+                notify_all(&mut notifiers, &event);
+            }
+        });
+        Self { tx, worker }
+    }
+
+    /// Queue an event for delivery; returns straight away
+    pub fn send(&self, event: NotificationEvent) {
+        if self.tx.send(event).is_err() {
+            tracing::warn!("Notification dropped, delivery thread stopped");
+        }
+    }
+
+    /// Deliver whatever is still queued, then stop
+    pub async fn close(self) {
+        drop(self.tx);
+        if let Err(e) = self.worker.await {
+            tracing::warn!("Notification delivery thread failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Notifier that fails while `failing` is set and counts attempts
+    struct MockNotifier {
+        failing: bool,
+        attempts: u32,
+    }
+
+    impl Notifier for MockNotifier {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn notify(&mut self, _event: &NotificationEvent) -> Result<(), NotifyError> {
+            self.attempts += 1;
+            if self.failing {
+                Err(NotifyError::Webhook("connection refused".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn event() -> NotificationEvent {
+        NotificationEvent::Offline {
+            outage: Outage::new(vec!["8.8.8.8".to_string()]),
+        }
+    }
+
+    fn breaker() -> CircuitBreaker<MockNotifier> {
+        CircuitBreaker::new(
+            MockNotifier {
+                failing: true,
+                attempts: 0,
+            },
+            &CircuitBreakerConfig {
+                failure_threshold: 3,
+                cooldown_secs: 60,
+            },
+        )
+    }
+
+    #[test]
+    fn test_email_body_contains_outage_summary() {
+        let notifier = EmailNotifier::new(
+            &EmailConfig {
+                smtp_host: "smtp.example.com".to_string(),
+                smtp_port: 587,
+                starttls: true,
+                username: None,
+                password: None,
+                from: "vigil@example.com".to_string(),
+                to: "me@example.com".to_string(),
+            },
+            DisplayTimezone::Utc,
+        );
+
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string(), "1.1.1.1".to_string()]);
+        outage.start_time = "2024-01-15T14:23:05Z".parse().unwrap();
+        outage.end_time = Some("2024-01-15T14:25:47Z".parse().unwrap());
+        outage.duration_secs = Some(162.0);
+        outage.failing_hop = Some(3);
+        outage.failing_hop_ip = Some("10.0.0.1".to_string());
+
+        let (subject, body) = notifier
+            .compose(&NotificationEvent::Recovered {
+                outage: outage.clone(),
+            })
+            .unwrap();
+        assert_eq!(subject, "Vigil: internet back after 2m 42s");
+        assert!(body.contains("Started:          2024-01-15 14:23:05 UTC"));
+        assert!(body.contains("Ended:            2024-01-15 14:25:47 UTC"));
+        assert!(body.contains("Duration:         2m 42s"));
+        assert!(body.contains("Failing hop:      3 — 10.0.0.1"));
+        assert!(body.contains("Affected targets: 8.8.8.8, 1.1.1.1"));
+
+        // Nothing is sent when the outage starts
+        assert!(notifier
+            .compose(&NotificationEvent::Offline { outage })
+            .is_none());
+    }
+
+    #[test]
+    fn test_desktop_notification_text() {
+        let notifier = DesktopNotifier::new(DisplayTimezone::Utc);
+        let mut outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        outage.start_time = "2024-01-15T14:23:05Z".parse().unwrap();
+        outage.failing_hop = Some(3);
+        outage.failing_hop_ip = Some("10.0.0.1".to_string());
+
+        let (title, message) = notifier.compose(&NotificationEvent::Offline {
+            outage: outage.clone(),
+        });
+        assert_eq!(title, "Vigil: internet down");
+        assert_eq!(
+            message,
+            "Outage started at 14:23:05 - failing hop 3 (10.0.0.1)"
+        );
+
+        let (title, message) = notifier.compose(&NotificationEvent::Ongoing {
+            outage: outage.clone(),
+            at: "2024-01-15T14:35:05Z".parse().unwrap(),
+        });
+        assert_eq!(title, "Vigil: internet still down");
+        assert_eq!(message, "Down for 12m - failing hop 3 (10.0.0.1)");
+
+        outage.duration_secs = Some(162.0);
+        let (title, message) = notifier.compose(&NotificationEvent::Recovered { outage });
+        assert_eq!(title, "Vigil: internet back");
+        assert_eq!(message, "Outage lasted 2m 42s");
+
+        let (title, message) = notifier.compose(&NotificationEvent::TargetSpecific {
+            failing_targets: vec!["10.0.0.1".to_string()],
+            at: "2024-01-15T14:35:05Z".parse().unwrap(),
+        });
+        assert_eq!(title, "Vigil: targets unreachable");
+        assert_eq!(message, "Internet is up, but these are failing: 10.0.0.1");
+
+        assert_eq!(
+            applescript_notification("Vigil", r#"say "hi" \ bye"#),
+            r#"display notification "say \"hi\" \\ bye" with title "Vigil""#
+        );
+    }
+
+    #[test]
+    fn test_breaker_opens_after_consecutive_failures() {
+        let mut breaker = breaker();
+        let now = Instant::now();
+
+        assert!(breaker.notify_at(&event(), now).is_err());
+        assert!(breaker.notify_at(&event(), now).is_err());
+        assert_eq!(breaker.state(), BreakerState::Closed { failures: 2 });
+
+        assert!(breaker.notify_at(&event(), now).is_err());
+        assert!(matches!(breaker.state(), BreakerState::Open { .. }));
+
+        // While open, the endpoint is not called
+        let result = breaker.notify_at(&event(), now + Duration::from_secs(30));
+        assert!(matches!(result, Err(NotifyError::CircuitOpen(_))));
+        assert_eq!(breaker.inner.attempts, 3);
+    }
+
+    #[test]
+    fn test_breaker_half_open_success_closes() {
+        let mut breaker = breaker();
+        let now = Instant::now();
+        for _ in 0..3 {
+            let _ = breaker.notify_at(&event(), now);
+        }
+
+        breaker.inner.failing = false;
+        let later = now + Duration::from_secs(61);
+        assert!(breaker.notify_at(&event(), later).is_ok());
+        assert_eq!(breaker.state(), BreakerState::Closed { failures: 0 });
+        assert_eq!(breaker.inner.attempts, 4);
+    }
+
+    #[test]
+    fn test_breaker_half_open_failure_reopens() {
+        let mut breaker = breaker();
+        let now = Instant::now();
+        for _ in 0..3 {
+            let _ = breaker.notify_at(&event(), now);
+        }
+
+        // A single failed trial re-opens for a fresh cooldown
+        let later = now + Duration::from_secs(61);
+        assert!(matches!(
+            breaker.notify_at(&event(), later),
+            Err(NotifyError::Webhook(_))
+        ));
+        assert_eq!(
+            breaker.state(),
+            BreakerState::Open {
+                until: later + Duration::from_secs(60)
+            }
+        );
+        assert_eq!(breaker.inner.attempts, 4);
+    }
+
+    #[test]
+    fn test_success_resets_failure_count() {
+        let mut breaker = breaker();
+        let now = Instant::now();
+        let _ = breaker.notify_at(&event(), now);
+        let _ = breaker.notify_at(&event(), now);
+
+        breaker.inner.failing = false;
+        assert!(breaker.notify_at(&event(), now).is_ok());
+        assert_eq!(breaker.state(), BreakerState::Closed { failures: 0 });
+    }
+
+    /// Takes a while per event and records what it was sent
+    struct SlowNotifier {
+        delivered: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl Notifier for SlowNotifier {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn notify(&mut self, event: &NotificationEvent) -> Result<(), NotifyError> {
+            std::thread::sleep(Duration::from_millis(100));
+            let name = event.payload()["event"].as_str().unwrap().to_string();
+            self.delivered.lock().unwrap().push(name);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_delivers_in_order_without_blocking() {
+        let delivered = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let queue = NotificationQueue::spawn(vec![Box::new(SlowNotifier {
+            delivered: delivered.clone(),
+        })]);
+
+        let started = Instant::now();
+        let outage = Outage::new(vec!["8.8.8.8".to_string()]);
+        queue.send(NotificationEvent::Offline {
+            outage: outage.clone(),
+        });
+        queue.send(NotificationEvent::Recovered { outage });
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        queue.close().await;
+        assert_eq!(*delivered.lock().unwrap(), vec!["offline", "recovered"]);
+    }
+}